@@ -67,22 +67,21 @@ impl<'a> OpEmitter<'a> {
     /// Load a value corresponding to the pointee type of a pointer operand on the stack.
     ///
     /// The type of the pointer determines what address space the pointer value represents;
-    /// either the Miden-native address space (word-addressable), or the IR's byte-addressable
-    /// address space.
+    /// either the Miden-native address space (word-addressable), the IR's byte-addressable
+    /// address space, or a statically allocated byte-addressable address space.
     pub fn load(&mut self, ty: Type, span: SourceSpan) {
         let ptr = self.stack.pop().expect("operand stack is empty");
         match ptr.ty() {
-            Type::Ptr(_) => {
+            ptr_ty @ (Type::Ptr(_) | Type::NativePtr(_, hir::AddressSpace::Id(_))) => {
                 // Convert the pointer to a native pointer representation
-                self.emit_native_ptr(span);
+                self.emit_native_ptr(&ptr_ty, span);
                 match &ty {
-                    Type::I128 => self.load_quad_word(None, span),
-                    Type::I64 | Type::U64 => self.load_double_word(None, span),
+                    Type::I128 | Type::U128 => self.load_quad_word(None, span),
+                    Type::I64 | Type::U64 | Type::F64 => self.load_double_word(None, span),
                     Type::Felt => self.load_felt(None, span),
                     Type::I32 | Type::U32 => self.load_word(None, span),
                     ty @ (Type::I16 | Type::U16 | Type::U8 | Type::I8 | Type::I1) => {
-                        self.load_word(None, span);
-                        self.trunc_int32(ty.size_in_bits() as u32, span);
+                        self.load_small(ty, None, span);
                     }
                     ty => todo!("support for loading {ty} is not yet implemented"),
                 }
@@ -101,13 +100,12 @@ impl<'a> OpEmitter<'a> {
     pub fn load_imm(&mut self, addr: u32, ty: Type, span: SourceSpan) {
         let ptr = NativePtr::from_ptr(addr);
         match &ty {
-            Type::I128 => self.load_quad_word(Some(ptr), span),
-            Type::I64 | Type::U64 => self.load_double_word(Some(ptr), span),
+            Type::I128 | Type::U128 => self.load_quad_word(Some(ptr), span),
+            Type::I64 | Type::U64 | Type::F64 => self.load_double_word(Some(ptr), span),
             Type::Felt => self.load_felt(Some(ptr), span),
             Type::I32 | Type::U32 => self.load_word(Some(ptr), span),
             Type::I16 | Type::U16 | Type::U8 | Type::I8 | Type::I1 => {
-                self.load_word(Some(ptr), span);
-                self.trunc_int32(ty.size_in_bits() as u32, span);
+                self.load_small(&ty, Some(ptr), span);
             }
             ty => todo!("support for loading {ty} is not yet implemented"),
         }
@@ -121,7 +119,10 @@ impl<'a> OpEmitter<'a> {
     /// Instructions which must act on a pointer will expect the stack to have
     /// these values in that order so that they can perform any necessary
     /// re-alignment.
-    fn emit_native_ptr(&mut self, span: SourceSpan) {
+    ///
+    /// If `ptr` is a pointer into a statically allocated address space, the word address is
+    /// offset to the region of Miden's address space assigned to it.
+    fn emit_native_ptr(&mut self, ptr: &Type, span: SourceSpan) {
         self.emit_all(
             &[
                 // Copy the address
@@ -153,6 +154,12 @@ impl<'a> OpEmitter<'a> {
             ],
             span,
         );
+        if let Type::NativePtr(_, addrspace) = ptr {
+            let base = NativePtr::address_space_base(*addrspace);
+            if base > 0 {
+                self.emit(Op::U32WrappingAddImm(base), span);
+            }
+        }
     }
 
     /// Load a field element from a naturally aligned address, either immediate or dynamic
@@ -228,7 +235,8 @@ impl<'a> OpEmitter<'a> {
     /// Loads a single 32-bit machine word from the given immediate address.
     fn load_word_imm(&mut self, ptr: NativePtr, span: SourceSpan) {
         let is_aligned = ptr.is_element_aligned();
-        let rshift = 32 - ptr.offset as u32;
+        let lshift = ptr.offset as u32 * 8;
+        let rshift = 32 - lshift;
         match ptr.index {
             0 if is_aligned => self.emit(Op::MemLoadImm(ptr.waddr), span),
             0 => {
@@ -243,7 +251,7 @@ impl<'a> OpEmitter<'a> {
                         Op::U32ShrImm(rshift),
                         // shift high bits left by the offset
                         Op::Swap(1),
-                        Op::U32ShlImm(ptr.offset as u32),
+                        Op::U32ShlImm(lshift),
                         // OR the high and low bits together
                         Op::U32Or,
                     ],
@@ -278,7 +286,7 @@ impl<'a> OpEmitter<'a> {
                         Op::U32ShrImm(rshift),
                         // Shift the high bits
                         Op::Swap(1),
-                        Op::U32ShlImm(ptr.offset as u32),
+                        Op::U32ShlImm(lshift),
                         // OR the high and low bits together
                         Op::U32Or,
                     ],
@@ -314,7 +322,8 @@ impl<'a> OpEmitter<'a> {
                         // Shift low bits
                         Op::U32ShrImm(rshift),
                         // Shift high bits
-                        Op::U32ShlImm(ptr.offset as u32),
+                        Op::Swap(1),
+                        Op::U32ShlImm(lshift),
                         // OR the high and low bits together
                         Op::U32Or,
                     ],
@@ -351,7 +360,7 @@ impl<'a> OpEmitter<'a> {
                         Op::Drop,
                         Op::Drop,
                         // Shift the high bits
-                        Op::U32ShlImm(ptr.offset as u32),
+                        Op::U32ShlImm(lshift),
                         // OR the high and low bits together
                         Op::U32Or,
                     ],
@@ -362,6 +371,19 @@ impl<'a> OpEmitter<'a> {
         }
     }
 
+    /// Loads a value smaller than 32 bits, i.e. one of the 8 or 16-bit integer types, or a boolean
+    ///
+    /// Such values are not required to be element-aligned, and occupy the most significant bits
+    /// of the 32-bit chunk starting at the pointer, so we load that chunk, and then shift the
+    /// bits of the value into place.
+    ///
+    /// Expects a native pointer triplet on the stack if an immediate address is not given.
+    fn load_small(&mut self, ty: &Type, ptr: Option<NativePtr>, span: SourceSpan) {
+        let type_size = ty.size_in_bytes() as u32 * 8;
+        self.load_word(ptr, span);
+        self.emit(Op::U32ShrImm(32 - type_size), span);
+    }
+
     /// Load a pair of machine words (32-bit elements) to the operand stack
     fn load_double_word(&mut self, ptr: Option<NativePtr>, span: SourceSpan) {
         if let Some(imm) = ptr {
@@ -379,119 +401,66 @@ impl<'a> OpEmitter<'a> {
                     &[
                         // Load quad-word
                         Op::Padw,
+                        // [w3, w2, w1, w0]
                         Op::MemLoadwImm(ptr.waddr),
-                        // Move the two elements we need to the bottom temporarily
-                        Op::Movdn(4),
-                        Op::Movdn(4),
                         // Drop the unused elements
                         Op::Drop,
                         Op::Drop,
+                        // [w0, w1]
+                        Op::Swap(1),
                     ],
                     span,
                 );
             }
-            0 => {
-                // An unaligned double-word load spans three elements
-                self.emit_all(
-                    &[
-                        // Load quad-word
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr),
-                        // Move the unused element to the top and drop it
-                        Op::Movup(4),
-                        Op::Drop,
-                        // Move into stack order for realign_dw
-                        Op::Swap(2),
-                    ],
-                    span,
-                );
-                self.realign_double_word(ptr, span);
-            }
             1 if aligned => {
                 self.emit_all(
                     &[
                         // Load quad-word
                         Op::Padw,
+                        // [w3, w2, w1, w0]
                         Op::MemLoadwImm(ptr.waddr),
-                        // Drop the first word, its unused
+                        // Drop the last element, it is unused
                         Op::Drop,
-                        // Move the last word up and drop it, also unused
-                        Op::Movup(3),
-                        Op::Drop,
-                    ],
-                    span,
-                );
-            }
-            1 => {
-                // An unaligned double-word load spans three elements
-                self.emit_all(
-                    &[
-                        // Load a quad-word
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr),
-                        // Drop the unused element
+                        // Move the first element up and drop it, also unused
+                        Op::Movup(2),
                         Op::Drop,
-                        // Move into stack order for realign_dw
-                        Op::Swap(2),
+                        // [w1, w2]
+                        Op::Swap(1),
                     ],
                     span,
                 );
-                self.realign_double_word(ptr, span);
             }
             2 if aligned => {
                 self.emit_all(
                     &[
                         // Load quad-word
                         Op::Padw,
+                        // [w3, w2, w1, w0]
                         Op::MemLoadwImm(ptr.waddr),
-                        // Drop unused words
-                        Op::Drop,
-                        Op::Drop,
-                    ],
-                    span,
-                );
-            }
-            2 => {
-                // An unaligned double-word load spans three elements,
-                // and in this case, two quad-words, because the last
-                // element is across a quad-word boundary
-                self.emit_all(
-                    &[
-                        // Load the second quad-word first
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr + 1),
-                        // Move the element we need to the bottom temporarily
-                        Op::Movdn(4),
-                        // Drop the three unused elements of this word
-                        Op::Drop,
-                        Op::Drop,
-                        Op::Drop,
-                        // Load the first quad-word
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr),
-                        // Drop the two unused elements
+                        // Move the unused elements to the top and drop them
+                        Op::Movup(3),
+                        Op::Movup(3),
                         Op::Drop,
                         Op::Drop,
-                        // Move into stack order for realign_dw
-                        Op::Swap(2),
+                        // [w2, w3]
+                        Op::Swap(1),
                     ],
                     span,
                 );
-                self.realign_double_word(ptr, span);
             }
             3 if aligned => {
                 self.emit_all(
                     &[
-                        // Load second word, drop unused elements
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr + 1),
-                        Op::Movup(4),
-                        Op::Drop,
-                        Op::Movup(3),
-                        Op::Drop,
-                        // Load first word, drop unused elements
+                        // Load the first element of the second quad-word
+                        // [lo]
+                        Op::MemLoadImm(ptr.waddr + 1),
+                        // Load the first quad-word
                         Op::Padw,
+                        // [w3, w2, w1, w0, lo]
                         Op::MemLoadwImm(ptr.waddr),
+                        // Drop the unused elements
+                        // [w3, lo]
+                        Op::Movdn(3),
                         Op::Drop,
                         Op::Drop,
                         Op::Drop,
@@ -499,28 +468,12 @@ impl<'a> OpEmitter<'a> {
                     span,
                 );
             }
-            3 => {
-                self.emit_all(
-                    &[
-                        // Load second word, drop unused element
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr + 1),
-                        Op::Movup(4),
-                        Op::Drop,
-                        // Load first word, drop unused elements
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr),
-                        Op::Drop,
-                        Op::Drop,
-                        Op::Drop,
-                        // Move into stack order for realign_dw
-                        Op::Swap(2),
-                    ],
-                    span,
-                );
-                self.realign_double_word(ptr, span);
+            // An unaligned double-word spans three elements, and possibly two quad-words, so
+            // we delegate to the intrinsic, which handles re-aligning the value for us
+            _ => {
+                self.push_native_ptr(ptr, span);
+                self.emit(Op::Exec("intrinsics::mem::load_dw".parse().unwrap()), span);
             }
-            _ => unimplemented!("unaligned loads are not yet implemented: {ptr:#?}"),
         }
     }
 
@@ -555,28 +508,6 @@ impl<'a> OpEmitter<'a> {
                 ],
                 span,
             ),
-            0 => {
-                // An unaligned quad-word load spans five elements
-                self.emit_all(
-                    &[
-                        // Load first element of second quad-word
-                        // [e]
-                        Op::MemLoadImm(ptr.waddr + 1),
-                        // Load first quad-word
-                        Op::Padw,
-                        // [d, c, b, a, e]
-                        Op::MemLoadwImm(ptr.waddr),
-                        // [a, c, b, d, e]
-                        Op::Swap(3),
-                        // [c, a, b, d, e]
-                        Op::Swap(1),
-                        // [a, b, c, d, e]
-                        Op::Movdn(2),
-                    ],
-                    span,
-                );
-                self.realign_quad_word(ptr, span);
-            }
             1 if aligned => {
                 self.emit_all(
                     &[
@@ -596,44 +527,13 @@ impl<'a> OpEmitter<'a> {
                     span,
                 );
             }
-            1 => {
-                // An unaligned double-word load spans five elements
-                self.emit_all(
-                    &[
-                        // Load first two elements of second quad-word
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr + 1),
-                        Op::Drop,
-                        // [e, d]
-                        Op::Drop,
-                        // Load last three elements of first quad-word
-                        Op::Padw,
-                        // [c, b, a, _, e, d]
-                        Op::MemLoadwImm(ptr.waddr),
-                        // [_, b, a, c, e, d]
-                        Op::Swap(3),
-                        // [b, a, c, e, d]
-                        Op::Drop,
-                        // [e, a, c, b, d]
-                        Op::Swap(3),
-                        // [d, a, c, b, e]
-                        Op::Swap(4),
-                        // [b, a, c, d, e]
-                        Op::Swap(3),
-                        // [a, b, c, d, e]
-                        Op::Swap(1),
-                    ],
-                    span,
-                );
-                self.realign_quad_word(ptr, span);
-            }
             2 if aligned => {
                 self.emit_all(
                     &[
                         // Load first two elements of second quad-word
                         Op::Padw,
                         // [_, _, d, c]
-                        Op::MemLoadwImm(ptr.waddr),
+                        Op::MemLoadwImm(ptr.waddr + 1),
                         // Drop last two elements
                         Op::Drop,
                         // [d, c]
@@ -654,271 +554,48 @@ impl<'a> OpEmitter<'a> {
                     span,
                 );
             }
-            2 => {
-                // An unaligned double-word load spans five elements
-                self.emit_all(
-                    &[
-                        // Load the first three elements of the second quad-word
-                        Op::Padw,
-                        Op::MemLoadwImm(ptr.waddr + 1),
-                        // [e, d, c]
-                        Op::Drop,
-                        // Load the last two elements of the first quad-word
-                        Op::Padw,
-                        // [b, a, _, _, e, d, c]
-                        Op::MemLoadwImm(ptr.waddr),
-                        // [a, _, _, b, e, d, c]
-                        Op::Movdn(3),
-                        // [_, _, a, b, e, d, c]
-                        Op::Movdn(2),
-                        // [c, _, a, b, e, d, _]
-                        Op::Swap(6),
-                        // [e, _, a, b, c, d, _]
-                        Op::Swap(4),
-                        // [_, _, a, b, c, d, e]
-                        Op::Swap(6),
-                        Op::Drop,
-                        // [a, b, c, d, e]
-                        Op::Drop,
-                    ],
-                    span,
-                );
-                self.realign_quad_word(ptr, span);
-            }
             3 if aligned => {
                 self.emit_all(
                     &[
                         // Load first three elements of second quad-word
                         Op::Padw,
+                        // [_, d, c, b]
                         Op::MemLoadwImm(ptr.waddr + 1),
+                        // [d, c, b]
                         Op::Drop,
                         // Load last element of first quad-word
                         Op::Padw,
+                        // [a, _, _, _, d, c, b]
                         Op::MemLoadwImm(ptr.waddr),
+                        // [a, d, c, b]
                         Op::Movdn(3),
                         Op::Drop,
                         Op::Drop,
                         Op::Drop,
-                    ],
-                    span,
-                );
-            }
-            3 => {
-                // An unaligned quad-word load spans five elements,
-                self.emit_all(
-                    &[
-                        // Load second quad-word
-                        Op::Padw,
-                        // [e, d, c, b]
-                        Op::MemLoadwImm(ptr.waddr + 1),
-                        // Load last element of first quad-word
-                        Op::Padw,
-                        // [a, _, _, _, e, d, c, b]
-                        Op::MemLoadwImm(ptr.waddr),
-                        // [_, _, _, a, e, d, c, b]
-                        Op::Movdn(3),
-                        Op::Drop,
-                        Op::Drop,
-                        // [a, e, d, c, b]
-                        Op::Drop,
-                        // [e, a, d, c, b]
+                        // [d, a, c, b]
+                        Op::Swap(1),
+                        // [b, a, c, d]
+                        Op::Swap(3),
+                        // [a, b, c, d]
                         Op::Swap(1),
-                        // [b, a, d, c, e]
-                        Op::Swap(4),
-                        // [d, a, b, c, e]
-                        Op::Swap(2),
-                        // [a, b, c, d, e]
-                        Op::Movdn(3),
                     ],
                     span,
                 );
-                self.realign_quad_word(ptr, span);
             }
-            _ => unimplemented!("unaligned loads are not yet implemented: {ptr:#?}"),
+            // An unaligned quad-word spans five elements across two quad-words, so we delegate
+            // to the intrinsic, which loads it as a pair of unaligned double-words
+            _ => {
+                self.push_native_ptr(ptr, span);
+                self.emit(Op::Exec("intrinsics::mem::load_qw".parse().unwrap()), span);
+            }
         }
     }
 
-    /// This handles emitting code that handles aligning an unaligned double machine-word value
-    /// which is split across three machine words (field elements).
-    ///
-    /// To recap:
-    ///
-    /// * A machine word is a 32-bit chunk stored in a single field element
-    /// * A double word is a pair of 32-bit chunks
-    /// * A quad word is a quartet of 32-bit chunks (i.e. a Miden "word")
-    /// * An unaligned double-word requires three 32-bit chunks to represent, since the first chunk
-    ///   does not contain a full 32-bits, so an extra is needed to hold those bits.
-    ///
-    /// As an example, assume the pointer we are dereferencing is a u64 value,
-    /// which has 8-byte alignment, and the value is stored 40 bytes from the
-    /// nearest quad-word-aligned boundary. To load the value, we must fetch
-    /// the full quad-word from the aligned address, drop the first word, as
-    /// it is unused, and then recombine the 64 bits we need spread across
-    /// the remaining three words to obtain the double-word value we actually want.
-    ///
-    /// The data, on the stack, is shown below:
-    ///
-    /// ```text,ignore
-    /// # If we visualize which bytes are contained in each 32-bit chunk on the stack, we get:
-    /// [0..=4, 5..=8, 9..=12]
-    ///
-    /// # These byte indices are relative to the nearest word-aligned address, in the same order
-    /// # as they would occur in a byte-addressable address space. The significance of each byte
-    /// # depends on the value being dereferenced, but Miden is a little-endian machine, so typically
-    /// # the most significant bytes come first (i.e. also commonly referred to as "high" vs "low" bits).
-    /// #
-    /// # If we visualize the layout of the bits of our u64 value spread across the three chunks, we get:
-    /// [00000000111111111111111111111111, 111111111111111111111111111111, 11111111111111111111111100000000]
-    /// ```
-    ///
-    /// As illustrated above, what should be a double-word value is occupying three words. To
-    /// "realign" the value, i.e. ensure that it is naturally aligned and fits in two words, we
-    /// have to perform a sequence of shifts and masks to get the bits where they belong. This
-    /// function performs those steps, with the assumption that the caller has three values on
-    /// the operand stack representing any unaligned double-word value
-    fn realign_double_word(&mut self, _ptr: NativePtr, span: SourceSpan) {
-        self.emit(Op::Exec("intrinsics::mem::realign_dw".parse().unwrap()), span);
-    }
-
-    /// This handles emitting code that handles aligning an unaligned quad machine-word value
-    /// which is split across five machine words (field elements).
-    ///
-    /// To recap:
-    ///
-    /// * A machine word is a 32-bit chunk stored in a single field element
-    /// * A double word is a pair of 32-bit chunks
-    /// * A quad word is a quartet of 32-bit chunks (i.e. a Miden "word")
-    /// * An unaligned quad-word requires five 32-bit chunks to represent, since the first chunk
-    ///   does not contain a full 32-bits, so an extra is needed to hold those bits.
-    ///
-    /// See the example in [OpEmitter::realign_quad_word] for more details on how bits are
-    /// laid out in each word, and what is required to realign unaligned words.
-    fn realign_quad_word(&mut self, ptr: NativePtr, span: SourceSpan) {
-        // The stack starts as: [chunk_hi, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo]
-        //
-        // We will refer to the parts of our desired quad-word value
-        // as four parts, `x_hi2`, `x_hi1`, `x_lo2`, and `x_lo1`, where
-        // the integer suffix should appear in decreasing order on the
-        // stack when we're done.
+    /// Push the components of an immediate native pointer on the operand stack, as a triple of
+    /// `(waddr, index, offset)`, in that order, as expected by the memory intrinsics.
+    fn push_native_ptr(&mut self, ptr: NativePtr, span: SourceSpan) {
         self.emit_all(
-            &[
-                // Re-align the high bits by shifting out the offset
-                //
-                // This gives us the first half of `x_hi2`.
-                //
-                // [x_hi2_hi, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk__lo]
-                Op::U32ShlImm(ptr.offset as u32),
-                // Move the value below the other chunks temporarily
-                //
-                // [chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk__lo, x_hi2_hi]
-                Op::Movdn(5),
-                // We must split the `chunk_mid_hi` chunk into two parts,
-                // one containing the bits to be combined with `x_hi2_hi`;
-                // the other to be combined with `x_hi1_hi`.
-                //
-                // First, we duplicate the chunk, since we need two
-                // copies of it:
-                //
-                // [chunk_mid_hi, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2_hi]
-                Op::Dup(0),
-                // Then, we shift the chunk right by 32 - offset bits,
-                // re-aligning the low bits of `x_hi2`, and isolating them.
-                //
-                // [x_hi2_lo, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2_hi]
-                Op::U32ShrImm(32 - ptr.offset as u32),
-                // Move the high bits of `x_hi2` back to the top
-                //
-                // [x_hi2_hi, x_hi2_lo, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo]
-                Op::Movup(3),
-                // OR the two parts of the `x_hi2` chunk together
-                //
-                // [x_hi2, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo]
-                Op::U32Or,
-                // Move `x_hi2` to the bottom for later
-                //
-                // [chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2]
-                Op::Movdn(5),
-                // Now, we need to re-align the high bits of `x_hi1` by shifting
-                // the remaining copy of `chunk_mid_hi`, similar to what we did for `x_hi2`
-                //
-                // This gives us the first half of `x_hi1`
-                //
-                // [x_hi1_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2]
-                Op::U32ShlImm(ptr.offset as u32),
-                // Next, move the chunk containing the low bits of `x_hi1` to the top temporarily
-                //
-                // [chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1_hi]
-                Op::Movdn(5),
-                // Duplicate it, as we need two copies
-                //
-                // [chunk_mid_mid, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1_hi]
-                Op::Dup(0),
-                // Shift the value right, as done previously for the low bits of `x_hi2`
-                //
-                // [x_hi1_lo, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1_hi]
-                Op::U32ShrImm(32 - ptr.offset as u32),
-                // Move the high bits of `x_hi1` to the top
-                Op::Movup(5),
-                // OR the two halves together, giving us our second word, `x_hi1`
-                //
-                // [x_hi1, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2]
-                Op::U32Or,
-                // Move the word to the bottom of the stack
-                //
-                // [chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-                Op::Movdn(5),
-                // Now, we need to re-align the high bits of `x_lo2` by shifting
-                // the remaining copy of `chunk_mid_mid`, as done previously.
-                //
-                // [x_lo2_hi, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-                Op::U32ShlImm(ptr.offset as u32),
-                // Next, move the chunk containing the low bits of `x_lo2` to the top temporarily
-                //
-                // [chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2_hi]
-                Op::Movdn(5),
-                // Duplicate it, as done previously
-                //
-                // [chunk_mid_lo, chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2_hi]
-                Op::Dup(0),
-                // Shift the value right to get the low bits of `x_lo2`
-                //
-                // [x_lo2_lo, chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2_hi]
-                Op::U32ShrImm(32 - ptr.offset as u32),
-                // Move the high bits of `x_lo2` to the top
-                //
-                // [x_lo2_hi, x_lo2_lo, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-                Op::Movup(6),
-                // OR the two halves together, giving us our third word, `x_lo2`
-                //
-                // [x_lo2, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-                Op::U32Or,
-                // Move to the bottom of the stack
-                //
-                // [chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2]
-                Op::Movdn(5),
-                // Re-align the high bits of `x_lo1`
-                //
-                // [x_lo1_hi, chunk_lo, x_hi2, x_hi1, x_lo2]
-                Op::U32ShlImm(ptr.offset as u32),
-                // Move the chunk containing the low bits to the top
-                //
-                // [chunk_lo, x_hi2, x_hi1, x_lo2, x_lo1_hi]
-                Op::Movdn(5),
-                // Shift the value right to get the low bits of `x_lo1`
-                Op::U32ShrImm(32 - ptr.offset as u32),
-                // Move the high bits of `x_lo1` to the top
-                //
-                // [x_lo1_hi, x_lo1_lo, x_hi2, x_hi1, x_lo2]
-                Op::Movup(5),
-                // OR the two halves together, giving us our fourth word, `x_lo1`
-                //
-                // [x_lo1, x_hi2, x_hi1, x_lo2]
-                Op::U32Or,
-                // Move to the bottom
-                //
-                // [x_hi2, x_hi1, x_lo2, x_lo1]
-                Op::Movdn(5),
-            ],
+            &[Op::PushU8(ptr.offset), Op::PushU8(ptr.index), Op::PushU32(ptr.waddr)],
             span,
         );
     }
@@ -951,12 +628,12 @@ impl<'a> OpEmitter<'a> {
         let value_ty = value.ty();
         assert!(!value_ty.is_zst(), "cannot store a zero-sized type in memory");
         match ptr_ty {
-            Type::Ptr(_) => {
+            Type::Ptr(_) | Type::NativePtr(_, hir::AddressSpace::Id(_)) => {
                 // Convert the pointer to a native pointer representation
-                self.emit_native_ptr(span);
+                self.emit_native_ptr(&ptr_ty, span);
                 match value_ty {
-                    Type::I128 => self.store_quad_word(None, span),
-                    Type::I64 | Type::U64 => self.store_double_word(None, span),
+                    Type::I128 | Type::U128 => self.store_quad_word(None, span),
+                    Type::I64 | Type::U64 | Type::F64 => self.store_double_word(None, span),
                     Type::Felt => self.store_felt(None, span),
                     Type::I32 | Type::U32 => self.store_word(None, span),
                     ref ty if ty.size_in_bytes() <= 4 => self.store_small(ty, None, span),
@@ -983,8 +660,8 @@ impl<'a> OpEmitter<'a> {
         assert!(!value_ty.is_zst(), "cannot store a zero-sized type in memory");
         let ptr = NativePtr::from_ptr(addr);
         match value_ty {
-            Type::I128 => self.store_quad_word(Some(ptr), span),
-            Type::I64 | Type::U64 => self.store_double_word(Some(ptr), span),
+            Type::I128 | Type::U128 => self.store_quad_word(Some(ptr), span),
+            Type::I64 | Type::U64 | Type::F64 => self.store_double_word(Some(ptr), span),
             Type::Felt => self.store_felt(Some(ptr), span),
             Type::I32 | Type::U32 => self.store_word(Some(ptr), span),
             ref ty if ty.size_in_bytes() <= 4 => self.store_small(ty, Some(ptr), span),
@@ -1083,12 +760,21 @@ impl<'a> OpEmitter<'a> {
         assert_eq!(count.ty(), Type::U32, "expected count operand to be a u32");
         let ty = src.ty();
         assert!(ty.is_pointer());
-        assert_eq!(ty, dst.ty(), "expected src and dst operands to have the same type");
+        assert_eq!(
+            ty.pointee(),
+            dst.ty().pointee(),
+            "expected src and dst operands to have the same pointee type"
+        );
         let value_ty = ty.pointee().unwrap();
         let value_size = u32::try_from(value_ty.size_in_bytes()).expect("invalid value size");
 
+        // Copies between different address spaces, or within a statically allocated one, must
+        // translate each address individually, so they always use the default implementation
+        let is_default_addrspace = matches!((&ty, dst.ty()), (Type::Ptr(_), Type::Ptr(_)));
+
         // Use optimized intrinsics when available
         match value_size {
+            _ if !is_default_addrspace => (),
             // Word-sized values have an optimized intrinsic we can lean on
             16 => {
                 self.emit_all(
@@ -1215,8 +901,11 @@ impl<'a> OpEmitter<'a> {
                 ],
                 span,
             ),
+            // All other cases span more than one quad-word, and/or require splitting the value
+            // across unaligned elements, so we delegate to the intrinsic
             _ => {
-                todo!("quad-word stores currently require 32-byte alignment")
+                self.push_native_ptr(ptr, span);
+                self.emit(Op::Exec("intrinsics::mem::store_qw".parse().unwrap()), span);
             }
         }
     }
@@ -1259,10 +948,7 @@ impl<'a> OpEmitter<'a> {
             ),
             _ => {
                 // TODO: Optimize double-word stores when pointer is contant
-                self.emit_all(
-                    &[Op::PushU8(ptr.offset), Op::PushU8(ptr.index), Op::PushU32(ptr.waddr)],
-                    span,
-                );
+                self.push_native_ptr(ptr, span);
                 self.emit(Op::Exec("intrinsics::mem::store_dw".parse().unwrap()), span);
             }
         }
@@ -1283,12 +969,13 @@ impl<'a> OpEmitter<'a> {
     /// Stores a single 32-bit machine word to the given immediate address.
     fn store_word_imm(&mut self, ptr: NativePtr, span: SourceSpan) {
         let is_aligned = ptr.is_element_aligned();
-        let rshift = 32 - ptr.offset as u32;
+        let lshift = ptr.offset as u32 * 8;
+        let rshift = 32 - lshift;
         match ptr.index {
             0 if is_aligned => self.emit(Op::MemStoreImm(ptr.waddr), span),
             0 => {
                 let mask_hi = u32::MAX << rshift;
-                let mask_lo = u32::MAX >> (ptr.offset as u32);
+                let mask_lo = u32::MAX >> lshift;
                 self.emit_all(
                     &[
                         // Load the word
@@ -1308,7 +995,7 @@ impl<'a> OpEmitter<'a> {
                         // [value, w0_masked, w1_masked, w3, w2, value]
                         Op::Dup(4),
                         // [value, w0_masked, w1_masked, w3, w2, value]
-                        Op::U32ShrImm(ptr.offset as u32),
+                        Op::U32ShrImm(lshift),
                         // [w0', w1_masked, w3, w2, value]
                         Op::U32Or,
                         // [w1_masked, w0', w3, w2, value]
@@ -1347,7 +1034,7 @@ impl<'a> OpEmitter<'a> {
             ),
             1 => {
                 let mask_hi = u32::MAX << rshift;
-                let mask_lo = u32::MAX >> (ptr.offset as u32);
+                let mask_lo = u32::MAX >> lshift;
                 self.emit_all(
                     &[
                         Op::Padw,
@@ -1366,7 +1053,7 @@ impl<'a> OpEmitter<'a> {
                         Op::U32And,
                         // [value, w1_masked, w2_masked, w3, w0, value]
                         Op::Dup(4),
-                        Op::U32ShrImm(ptr.offset as u32),
+                        Op::U32ShrImm(lshift),
                         // [w1', w2_masked, w3, w0, value]
                         Op::U32Or,
                         // [w2_masked, w1', w3, w0, value]
@@ -1406,7 +1093,7 @@ impl<'a> OpEmitter<'a> {
             ),
             2 => {
                 let mask_hi = u32::MAX << rshift;
-                let mask_lo = u32::MAX >> (ptr.offset as u32);
+                let mask_lo = u32::MAX >> lshift;
                 self.emit_all(
                     &[
                         // the load is across both the third and fourth elements
@@ -1423,7 +1110,7 @@ impl<'a> OpEmitter<'a> {
                         Op::U32And,
                         // [value, w2_masked, w3_masked, w1, w0, value]
                         Op::Dup(4),
-                        Op::U32ShrImm(ptr.offset as u32),
+                        Op::U32ShrImm(lshift),
                         // [w2', w3_masked, w1, w0, value]
                         Op::U32Or,
                         // [w3_masked, w2', w1, w0, value]
@@ -1462,7 +1149,7 @@ impl<'a> OpEmitter<'a> {
                 // the end of the first word, and then handle the "lo" bits in a simpler
                 // fashion
                 let mask_hi = u32::MAX << rshift;
-                let mask_lo = u32::MAX >> (ptr.offset as u32);
+                let mask_lo = u32::MAX >> lshift;
                 self.emit_all(
                     &[
                         // the load crosses a word boundary, start with the element containing
@@ -1489,7 +1176,7 @@ impl<'a> OpEmitter<'a> {
                         Op::U32And,
                         // [value, w3_masked, w2, w1, w0]
                         Op::Movup(4),
-                        Op::U32ShrImm(ptr.offset as u32),
+                        Op::U32ShrImm(lshift),
                         // [w3', w2, w1, w0]
                         Op::U32Or,
                         Op::MemStorewImm(ptr.waddr),
@@ -1572,12 +1259,19 @@ impl<'a> OpEmitter<'a> {
         }
     }
 
+    /// Stores a value smaller than 32 bits, i.e. one of the 8 or 16-bit integer types, or a
+    /// boolean.
+    ///
+    /// The value is written to the most significant bits of the 32-bit chunk starting at the
+    /// pointer, preserving the remaining bits of that chunk, which may belong to other values.
+    ///
+    /// Expects a native pointer triplet on the stack if an immediate address is not given.
     fn store_small(&mut self, ty: &Type, ptr: Option<NativePtr>, span: SourceSpan) {
         if let Some(imm) = ptr {
             return self.store_small_imm(ty, imm, span);
         }
 
-        let type_size = ty.size_in_bits();
+        let type_size = ty.size_in_bytes() as u32 * 8;
         if type_size == 32 {
             self.store_word(ptr, span);
             return;
@@ -1590,22 +1284,20 @@ impl<'a> OpEmitter<'a> {
         self.load_word(ptr, span);
 
         // Mask out the bits we're going to be writing from the loaded value
-        let mask = u32::MAX << type_size;
+        let mask = u32::MAX >> type_size;
         self.const_mask_u32(mask, span);
 
-        // Mix in the bits we want to write: [masked, addr1, addr2, addr3, value]
-        self.emit(Op::Movup(5), span);
+        // Mix in the bits we want to write: [masked, waddr, index, offset, value]
+        self.emit_all(&[Op::Movup(4), Op::U32ShlImm(32 - type_size)], span);
         self.bor_u32(span);
 
-        // Store the combined bits: [value, addr1, addr2, addr3]
-        self.emit(Op::Movdn(4), span);
+        // Store the combined bits: [waddr, index, offset, value]
+        self.emit(Op::Movdn(3), span);
         self.store_word(ptr, span);
     }
 
     fn store_small_imm(&mut self, ty: &Type, ptr: NativePtr, span: SourceSpan) {
-        assert!(ptr.alignment() as usize >= ty.min_alignment());
-
-        let type_size = ty.size_in_bits();
+        let type_size = ty.size_in_bytes() as u32 * 8;
         if type_size == 32 {
             self.store_word_imm(ptr, span);
             return;
@@ -1615,11 +1307,11 @@ impl<'a> OpEmitter<'a> {
         self.load_word_imm(ptr, span);
 
         // Mask out the bits we're going to be writing from the loaded value
-        let mask = u32::MAX << type_size;
+        let mask = u32::MAX >> type_size;
         self.const_mask_u32(mask, span);
 
         // Mix in the bits we want to write
-        self.emit(Op::Movup(4), span);
+        self.emit_all(&[Op::Swap(1), Op::U32ShlImm(32 - type_size)], span);
         self.bor_u32(span);
 
         // Store the combined bits
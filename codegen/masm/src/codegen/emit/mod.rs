@@ -125,6 +125,11 @@ impl<'a> InstOpEmitter<'a> {
         self.emitter.exec(import, span);
     }
 
+    pub fn exec_dynamic(&mut self, callee: hir::FunctionIdent, root_addr: u32, span: SourceSpan) {
+        let import = self.dfg.get_import(&callee).unwrap();
+        self.emitter.exec_dynamic(import, root_addr, span);
+    }
+
     pub fn call(&mut self, callee: hir::FunctionIdent, span: SourceSpan) {
         let import = self.dfg.get_import(&callee).unwrap();
         self.emitter.call(import, span);
@@ -135,6 +140,31 @@ impl<'a> InstOpEmitter<'a> {
         self.emitter.syscall(import, span);
     }
 
+    /// Emit an indirect call through `table`.
+    ///
+    /// The `dynamic` callback returns the address of the stub root for entries which must be
+    /// executed dynamically, see [OpEmitter::exec_dynamic].
+    pub fn call_indirect<F>(
+        &mut self,
+        signature: &hir::Signature,
+        table: &[Option<hir::FunctionIdent>],
+        dynamic: F,
+        span: SourceSpan,
+    ) where
+        F: Fn(hir::FunctionIdent) -> Option<u32>,
+    {
+        let callees = table
+            .iter()
+            .enumerate()
+            .filter_map(|(index, callee)| {
+                let callee = callee.as_ref()?;
+                let index = u32::try_from(index).expect("function table is too large");
+                Some((index, *callee, dynamic(*callee)))
+            })
+            .collect::<Vec<_>>();
+        self.emitter.call_indirect(signature, &callees, span);
+    }
+
     #[inline(always)]
     pub fn value_type(&self, value: hir::Value) -> &Type {
         self.dfg.value_type(value)
@@ -219,6 +249,9 @@ impl<'a> OpEmitter<'a> {
             Op::Syscall(id) => {
                 self.function.register_absolute_invocation_target(InvokeKind::SysCall, *id)
             }
+            Op::ProcRef(id) => {
+                self.function.register_absolute_invocation_target(InvokeKind::ProcRef, *id)
+            }
             _ => (),
         }
     }
@@ -290,7 +323,7 @@ impl<'a> OpEmitter<'a> {
             Immediate::U128(i) => self.push_u128(i, span),
             Immediate::I128(i) => self.push_i128(i, span),
             Immediate::Felt(i) => self.emit(Op::Push(i), span),
-            Immediate::F64(_) => unimplemented!("floating-point immediates are not supported"),
+            Immediate::F64(f) => self.push_u64(f.to_bits(), span),
         }
     }
 
@@ -1947,6 +1980,56 @@ mod tests {
         assert_eq!(emitter.stack()[1], Type::U32);
     }
 
+    #[test]
+    fn op_emitter_addrspace_load_store_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        let addrspace = hir::AddressSpace::Id(core::num::NonZeroU16::new(2).unwrap());
+        let addr = Type::NativePtr(Box::new(Type::U32), addrspace);
+
+        emitter.push(addr.clone());
+        emitter.load(Type::U32, SourceSpan::default());
+        assert_eq!(emitter.stack_len(), 1);
+        assert_eq!(emitter.stack()[0], Type::U32);
+
+        emitter.push(addr);
+        emitter.store(SourceSpan::default());
+        assert_eq!(emitter.stack_len(), 0);
+
+        // Both the load and the store are offset to the region for address space 2
+        let offsets = emitter
+            .current_block()
+            .ops
+            .iter()
+            .filter(|op| op.into_inner() == Op::U32WrappingAddImm(2 << 28))
+            .count();
+        assert_eq!(offsets, 2);
+    }
+
+    #[test]
+    fn op_emitter_unaligned_load_store_imm_test() {
+        let mut function = setup();
+        let entry = function.body.id();
+        let mut stack = OperandStack::default();
+        let mut emitter = OpEmitter::new(&mut function, entry, &mut stack);
+
+        let types = [Type::U8, Type::I16, Type::U32, Type::U64, Type::I128, Type::U128];
+        for ty in types {
+            // Cover every element index and byte offset, including those which cross a word
+            for addr in 0..32 {
+                emitter.load_imm(addr, ty.clone(), SourceSpan::default());
+                assert_eq!(emitter.stack_len(), 1);
+                assert_eq!(emitter.stack()[0], ty);
+
+                emitter.store_imm(addr + 1, SourceSpan::default());
+                assert_eq!(emitter.stack_len(), 0);
+            }
+        }
+    }
+
     #[inline]
     fn setup() -> Function {
         Function::new(
@@ -4,7 +4,7 @@ use midenc_hir::{
 };
 
 use super::{int64, OpEmitter};
-use crate::masm::Op;
+use crate::masm::{NativePtr, Op};
 
 impl<'a> OpEmitter<'a> {
     /// Assert that an integer value on the stack has the value 1
@@ -218,6 +218,48 @@ impl<'a> OpEmitter<'a> {
     ///
     /// A function called using this operation is invoked in the same memory context as the caller.
     pub fn exec(&mut self, callee: &hir::ExternalFunction, span: SourceSpan) {
+        self.prepare_exec(callee, span);
+        self.emit(Op::Exec(callee.id), span);
+    }
+
+    /// Execute the given procedure dynamically, via `dynexec` of the stub whose MAST root is
+    /// stored at `root_addr`.
+    ///
+    /// This is how recursive calls are emitted, see [hir::RecursiveCalls] for details. Otherwise,
+    /// this behaves just like [Self::exec].
+    pub fn exec_dynamic(
+        &mut self,
+        callee: &hir::ExternalFunction,
+        root_addr: u32,
+        span: SourceSpan,
+    ) {
+        self.prepare_exec(callee, span);
+        self.load_dynexec_root(root_addr, span);
+        self.emit(Op::DynExec, span);
+    }
+
+    /// Store the MAST root of `stub` at `root_addr`, so that it can be used by [Self::exec_dynamic]
+    pub fn store_dynexec_root(
+        &mut self,
+        stub: hir::FunctionIdent,
+        root_addr: u32,
+        span: SourceSpan,
+    ) {
+        let ptr = NativePtr::from_ptr(root_addr);
+        assert!(ptr.is_word_aligned(), "expected stub root to be word-aligned");
+        self.emit_all(&[Op::ProcRef(stub), Op::MemStorewImm(ptr.waddr), Op::Dropw], span);
+    }
+
+    /// Push the MAST root stored at `root_addr` by [Self::store_dynexec_root] on the stack
+    fn load_dynexec_root(&mut self, root_addr: u32, span: SourceSpan) {
+        let ptr = NativePtr::from_ptr(root_addr);
+        assert!(ptr.is_word_aligned(), "expected stub root to be word-aligned");
+        self.emit_all(&[Op::Padw, Op::MemLoadwImm(ptr.waddr)], span);
+    }
+
+    /// Validate the arguments on the operand stack for an `exec` of `callee`, extending them as
+    /// needed, and replace them with the results of the call.
+    fn prepare_exec(&mut self, callee: &hir::ExternalFunction, span: SourceSpan) {
         let import = callee;
         let callee = import.id;
         let signature = &import.signature;
@@ -330,8 +372,60 @@ impl<'a> OpEmitter<'a> {
         for result in signature.results.iter().rev() {
             self.push(result.ty.clone());
         }
+    }
 
-        self.emit(Op::Exec(callee), span);
+    /// Execute the procedure selected by the `u32` index on top of the stack from `callees`.
+    ///
+    /// Each entry of `callees` is a table index, and the procedure at that index, which must have
+    /// the given signature. The call is lowered to a dispatch which compares the index against
+    /// each entry in turn, and executes the matching procedure via `exec`, trapping if there is
+    /// no match, i.e. the index is out of bounds, or refers to an empty or mismatched entry.
+    ///
+    /// Entries which have the address of a stub root are executed via `dynexec` instead, see
+    /// [Self::exec_dynamic].
+    pub fn call_indirect(
+        &mut self,
+        signature: &hir::Signature,
+        callees: &[(u32, hir::FunctionIdent, Option<u32>)],
+        span: SourceSpan,
+    ) {
+        let index = self.stack.pop().expect("operand stack is empty");
+        assert_eq!(index.ty(), Type::U32, "expected callee index to be a u32");
+        for (i, param) in signature.params.iter().enumerate() {
+            let arg = self.stack.pop().expect("operand stack is empty");
+            assert_eq!(
+                arg.ty(),
+                param.ty,
+                "invalid indirect call: invalid argument type for parameter at index {i}"
+            );
+        }
+        for result in signature.results.iter().rev() {
+            self.push(result.ty.clone());
+        }
+
+        // [index, args..]
+        let current_block = self.current_block;
+        for (index, callee, root_addr) in callees.iter().copied() {
+            let then_blk = self.function.create_block();
+            let else_blk = self.function.create_block();
+            self.emit_all(
+                &[Op::Dup(0), Op::EqImm(Felt::new(index as u64)), Op::If(then_blk, else_blk)],
+                span,
+            );
+            self.switch_to_block(then_blk);
+            match root_addr {
+                Some(root_addr) => {
+                    self.emit(Op::Drop, span);
+                    self.load_dynexec_root(root_addr, span);
+                    self.emit(Op::DynExec, span);
+                }
+                None => self.emit_all(&[Op::Drop, Op::Exec(callee)], span),
+            }
+            self.switch_to_block(else_blk);
+        }
+        // None of the entries matched, so trap
+        self.emit_all(&[Op::PushU32(0), Op::Assert], span);
+        self.switch_to_block(current_block);
     }
 
     /// Calls the given procedure via a `call` instruction.
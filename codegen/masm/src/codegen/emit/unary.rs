@@ -185,9 +185,15 @@ impl<'a> OpEmitter<'a> {
     pub fn bitcast(&mut self, dst: &Type, _span: SourceSpan) {
         let arg = self.stack.pop().expect("operand stack is empty");
         let src = arg.ty();
+        // A 64-bit float is represented the same way as a 64-bit integer of the same bits
+        let is_float_bitcast = matches!(
+            (&src, dst),
+            (Type::F64, Type::I64 | Type::U64) | (Type::I64 | Type::U64, Type::F64)
+        );
         assert!(
-            src.is_integer() && dst.is_integer(),
-            "invalid cast of {src} to {dst}: only integer-to-integer bitcasts are supported"
+            (src.is_integer() && dst.is_integer()) || is_float_bitcast,
+            "invalid cast of {src} to {dst}: only integer-to-integer bitcasts, or bitcasts \
+             between f64 and 64-bit integers, are supported"
         );
         self.push(dst.clone());
     }
@@ -1,4 +1,7 @@
-use std::{collections::BTreeMap, rc::Rc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
 
 use cranelift_entity::SecondaryMap;
 use midenc_hir::{
@@ -28,6 +31,7 @@ pub struct FunctionEmitter<'a> {
     liveness: &'a LivenessAnalysis,
     locals: BTreeMap<hir::LocalId, masm::LocalId>,
     globals: &'a GlobalVariableLayout,
+    recursion: &'a hir::RecursiveCalls,
     visited: SecondaryMap<hir::Block, bool>,
 }
 
@@ -100,6 +104,7 @@ impl<'a> FunctionEmitter<'a> {
         loops: &'a LoopAnalysis,
         liveness: &'a LivenessAnalysis,
         globals: &'a GlobalVariableLayout,
+        recursion: &'a hir::RecursiveCalls,
     ) -> Self {
         // Allocate procedure locals for each local variable
         let locals = BTreeMap::from_iter(
@@ -114,6 +119,7 @@ impl<'a> FunctionEmitter<'a> {
             liveness,
             locals,
             globals,
+            recursion,
             visited: SecondaryMap::new(),
         }
     }
@@ -229,6 +235,9 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
 
         match self.function.f.dfg.inst(inst_info.inst) {
             ix @ (Instruction::RetImm(_) | Instruction::Ret(_)) => self.emit_ret(inst_info, ix),
+            Instruction::Call(op) if op.op == hir::Opcode::TailExec => {
+                self.emit_tail_exec(inst_info, op)
+            }
             Instruction::Br(ref op) => self.emit_br(inst_info, op, tasks),
             Instruction::CondBr(ref op) => self.emit_cond_br(inst_info, op, tasks),
             Instruction::GlobalValue(op) => self.emit_global_value(inst_info, op),
@@ -241,6 +250,7 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
             Instruction::PrimOp(op) => self.emit_primop(inst_info, op),
             Instruction::PrimOpImm(op) => self.emit_primop_imm(inst_info, op),
             Instruction::Call(op) => self.emit_call_op(inst_info, op),
+            Instruction::CallIndirect(op) => self.emit_call_indirect_op(inst_info, op),
             Instruction::InlineAsm(op) => self.emit_inline_asm(inst_info, op),
             Instruction::Switch(_) => {
                 panic!("expected switch instructions to have been rewritten before stackification")
@@ -292,6 +302,36 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         }
     }
 
+    /// Lower a call in tail position.
+    ///
+    /// Miden Assembly has no means of transferring control to another procedure other than by
+    /// calling it, so this is emitted as an `exec` followed by a return. However, everything on the
+    /// operand stack other than the call arguments is dropped before the call is made, rather than
+    /// after it returns, so that the operand stack does not grow with each call in a chain of tail
+    /// calls.
+    ///
+    /// Self-recursive tail calls are expected to have been rewritten as loops prior to codegen.
+    fn emit_tail_exec(&mut self, inst_info: &InstInfo, op: &hir::Call) {
+        assert!(
+            !self.visited,
+            "invalid control flow graph: unexpected tail call in loop in {}",
+            self.function.f.dfg.inst_block(inst_info.inst).unwrap(),
+        );
+
+        let span = self.function.f.dfg.inst_span(inst_info.inst);
+        let num_args = op.args.len(&self.function.f.dfg.value_lists);
+        let level = self.controlling_loop_level().unwrap_or(0);
+
+        self.emitter().truncate_stack(num_args, span);
+        self.emit_call_op(inst_info, op);
+
+        // If we're in a loop, push N zeroes on the stack, where N is the current loop depth
+        let mut emitter = self.emitter();
+        for _ in 0..level {
+            emitter.literal(false, span);
+        }
+    }
+
     /// Lower an unconditional branch instruction.
     ///
     /// There are two ways in which code generation lowers these instructions, depending on
@@ -812,18 +852,74 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
     }
 
     fn emit_call_op(&mut self, inst_info: &InstInfo, op: &hir::Call) {
-        assert_ne!(op.callee, self.function.f.id, "unexpected recursive call");
+        let caller = self.function.f.id;
+        let recursion = self.function.recursion;
+        let globals = self.function.globals;
 
         let span = self.function.f.dfg.inst_span(inst_info.inst);
         let mut emitter = self.inst_emitter(inst_info.inst);
+
+        // Recursive calls cannot be emitted as a static `exec`, see [hir::RecursiveCalls]
+        if recursion.is_back_edge(caller, op.callee) {
+            let target = recursion.get(op.callee).expect("expected back edge to have a target");
+            match op.op {
+                hir::Opcode::Exec | hir::Opcode::TailExec => {
+                    emitter.exec_dynamic(op.callee, dynexec_root_addr(globals, target), span)
+                }
+                // The linker rejects recursion across contexts
+                opcode => unreachable!("unexpected recursive call via '{opcode}'"),
+            }
+            return;
+        }
+
+        // Entering a recursive component of the call graph requires making the roots of the stubs
+        // used within it available first.
+        if matches!(op.op, hir::Opcode::Exec | hir::Opcode::TailExec) {
+            for target in recursion.entering(caller, op.callee) {
+                emitter.store_dynexec_root(target.stub, dynexec_root_addr(globals, target), span);
+            }
+        }
+
         match op.op {
             hir::Opcode::Syscall => emitter.syscall(op.callee, span),
-            hir::Opcode::Exec => emitter.exec(op.callee, span),
+            hir::Opcode::Exec | hir::Opcode::TailExec => emitter.exec(op.callee, span),
             hir::Opcode::Call => emitter.call(op.callee, span),
             opcode => unimplemented!("unrecognized procedure call opcode: '{opcode}'"),
         }
     }
 
+    fn emit_call_indirect_op(&mut self, inst_info: &InstInfo, op: &hir::CallIndirect) {
+        let caller = self.function.f.id;
+        let recursion = self.function.recursion;
+        let globals = self.function.globals;
+
+        let span = self.function.f.dfg.inst_span(inst_info.inst);
+        let mut emitter = self.inst_emitter(inst_info.inst);
+
+        // Each entry of the table is handled as if it were called directly, see `emit_call_op`
+        let mut stored = BTreeSet::default();
+        for callee in op.table.iter().flatten().copied() {
+            for target in recursion.entering(caller, callee) {
+                if stored.insert(target.stub) {
+                    emitter.store_dynexec_root(
+                        target.stub,
+                        dynexec_root_addr(globals, target),
+                        span,
+                    );
+                }
+            }
+        }
+        let dynamic = |callee| {
+            if recursion.is_back_edge(caller, callee) {
+                let target = recursion.get(callee).expect("expected back edge to have a target");
+                Some(dynexec_root_addr(globals, target))
+            } else {
+                None
+            }
+        };
+        emitter.call_indirect(&op.signature, &op.table, dynamic, span);
+    }
+
     fn emit_inline_asm(&mut self, inst_info: &InstInfo, op: &hir::InlineAsm) {
         use super::TypedValue;
 
@@ -1255,3 +1351,10 @@ impl<'b, 'f: 'b> BlockEmitter<'b, 'f> {
         OpEmitter::new(self.function.f_prime, self.target, &mut self.stack)
     }
 }
+
+/// Get the address at which the MAST root of the stub for `target` is stored
+fn dynexec_root_addr(globals: &GlobalVariableLayout, target: &hir::DynamicCallee) -> u32 {
+    globals
+        .get_symbol_addr(target.root)
+        .expect("expected global to be allocated for recursive call target")
+}
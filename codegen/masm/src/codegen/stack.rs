@@ -454,14 +454,15 @@ impl Operand {
                         }
                     }
                     ConstantValue::Imm(Immediate::F64(f)) => {
-                        let bytes = f.to_le_bytes();
-                        let hi = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-                        let lo = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
-                        *imm = ConstantValue::Imm(Immediate::U32(lo));
+                        // Floats are represented the same way as a u64 of the same bits
+                        let bits = f.to_bits();
+                        let lo = bits & (u32::MAX as u64);
+                        let hi = bits & !(u32::MAX as u64);
+                        *imm = ConstantValue::Imm(Immediate::U32(lo as u32));
                         let ty = self.word.pop().unwrap();
                         Self {
                             word: smallvec![ty],
-                            operand: Immediate::U32(hi).into(),
+                            operand: Immediate::U32((hi >> 32) as u32).into(),
                         }
                     }
                     ConstantValue::Imm(Immediate::I128(i)) => {
@@ -0,0 +1,36 @@
+use midenc_hir::{self as hir, pass::AnalysisManager};
+use midenc_hir_analysis::GlobalVariableLayout;
+use midenc_hir_transform::InlineCostModel;
+use midenc_session::Session;
+
+use crate::convert::emit_function;
+
+/// An [InlineCostModel] which measures the cost of a function by the number of Miden Assembly
+/// instructions emitted for it by the code generator.
+///
+/// Functions for which code cannot be generated are treated as infinitely expensive.
+pub(super) struct EmitterCostModel;
+impl InlineCostModel for EmitterCostModel {
+    fn function_cost(
+        &mut self,
+        function: &hir::Function,
+        program: &hir::Program,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> usize {
+        let globals = GlobalVariableLayout::for_function(program, function);
+        match emit_function(function, &globals, program.recursive_calls(), analyses, session) {
+            Ok(emitted) => emitted.body.blocks.values().map(|block| block.ops.len()).sum(),
+            Err(_) => usize::MAX,
+        }
+    }
+
+    /// A call is emitted as a single `exec`, but in general the arguments must first be moved
+    /// into place on the operand stack, and the results moved to wherever they are needed once
+    /// the callee returns, which costs an instruction per field element moved.
+    fn call_cost(&mut self, signature: &hir::Signature) -> usize {
+        let params = signature.params().iter().map(|param| param.ty.size_in_felts());
+        let results = signature.results().iter().map(|result| result.ty.size_in_felts());
+        1 + params.chain(results).sum::<usize>()
+    }
+}
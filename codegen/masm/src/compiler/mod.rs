@@ -1,11 +1,13 @@
+mod cost;
 mod masm;
 
 use midenc_hir::{
     self as hir,
     pass::{RewritePass, RewriteSet},
 };
-use midenc_session::{diagnostics::Report, Session};
+use midenc_session::{diagnostics::Report, OptLevel, Session};
 
+use self::cost::EmitterCostModel;
 pub use self::masm::MasmArtifact;
 use crate::{intrinsics, ConvertHirToMasm, Program};
 
@@ -43,6 +45,8 @@ impl<'a> MasmCompiler<'a> {
             rewrites.apply(&mut module, &mut self.analyses, self.session)?;
             input.modules_mut().insert(module);
         }
+        let mut program_rewrites = default_program_rewrites(self.session);
+        program_rewrites.apply(&mut input, &mut self.analyses, self.session)?;
 
         let mut convert_to_masm = ConvertHirToMasm::<hir::Program>::default();
         let mut artifact = convert_to_masm.convert(input, &mut self.analyses, self.session)?;
@@ -60,6 +64,12 @@ impl<'a> MasmCompiler<'a> {
             intrinsics::load("intrinsics::i64", &self.session.source_manager)
                 .expect("undefined intrinsics module"),
         ));
+        if self.session.options.soft_float {
+            artifact.insert(Box::new(
+                intrinsics::load("intrinsics::f64", &self.session.source_manager)
+                    .expect("undefined intrinsics module"),
+            ));
+        }
 
         Ok(artifact)
     }
@@ -122,6 +132,11 @@ where
     let mut rewrites = RewriteSet::default();
     if registered.len() == 0 {
         if session.should_codegen() {
+            // Self-recursive tail calls are rewritten as loops first, so that the functions
+            // containing them are not treated as recursive by the passes which follow
+            rewrites
+                .push(ModuleRewritePassAdapter::new(midenc_hir_transform::EliminateTailRecursion));
+            rewrites.append(&mut default_optimizations(session));
             let fn_rewrites = default_function_rewrites(session);
             for rewrite in fn_rewrites {
                 rewrites.push(ModuleRewritePassAdapter::new(rewrite));
@@ -134,6 +149,126 @@ where
     rewrites
 }
 
+/// The optimization passes which may be selected by [optimization_pipeline]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Optimization {
+    /// See [midenc_hir_transform::InlineFunctions]
+    InlineFunctions,
+    /// See [midenc_hir_transform::InlineBlocks]
+    InlineBlocks,
+    /// See [midenc_hir_transform::Sccp]
+    Sccp,
+    /// See [midenc_hir_transform::GlobalValueNumbering]
+    GlobalValueNumbering,
+    /// See [midenc_hir_transform::LoopInvariantCodeMotion]
+    LoopInvariantCodeMotion,
+    /// See [midenc_hir_transform::EliminateDeadCode]
+    EliminateDeadCode,
+}
+
+/// Returns the ordered sequence of optimization passes to run at `level`.
+///
+/// Passes are ordered such that each tends to expose opportunities for the passes which follow it:
+///
+/// * `basic` only propagates constants and cleans up after itself
+/// * `balanced` additionally inlines functions, eliminates redundant computations and hoists
+///   loop-invariant code
+/// * `max` is the same as `balanced`, but repeats constant propagation and value numbering once
+///   invariant code has been hoisted out of loops, and inlines more aggressively
+/// * `size` is the same as `balanced`, but does not hoist code out of loops, and only inlines
+///   functions when doing so does not grow the code
+/// * `size-min` only runs passes which never grow the code, so nothing is inlined
+pub fn optimization_pipeline(level: OptLevel) -> &'static [Optimization] {
+    use Optimization::*;
+
+    match level {
+        OptLevel::None => &[],
+        OptLevel::Basic => &[InlineBlocks, Sccp, EliminateDeadCode],
+        OptLevel::Balanced => &[
+            InlineFunctions,
+            InlineBlocks,
+            Sccp,
+            GlobalValueNumbering,
+            LoopInvariantCodeMotion,
+            EliminateDeadCode,
+        ],
+        OptLevel::Max => &[
+            InlineFunctions,
+            InlineBlocks,
+            Sccp,
+            GlobalValueNumbering,
+            LoopInvariantCodeMotion,
+            Sccp,
+            GlobalValueNumbering,
+            EliminateDeadCode,
+        ],
+        OptLevel::Size => {
+            &[InlineFunctions, InlineBlocks, Sccp, GlobalValueNumbering, EliminateDeadCode]
+        }
+        OptLevel::SizeMin => &[InlineBlocks, Sccp, GlobalValueNumbering, EliminateDeadCode],
+    }
+}
+
+/// Returns the set of optimization passes to run for the optimization level selected in `session`.
+///
+/// These run before [default_function_rewrites], which are required for codegen. See
+/// [optimization_pipeline] for the passes selected at each level, with the exception of
+/// [Optimization::InlineFunctions], which is applied to the linked program by
+/// [default_program_rewrites]. When optimizations are disabled, the resulting set is empty.
+pub fn default_optimizations(session: &Session) -> RewriteSet<hir::Module> {
+    use midenc_hir::pass::ModuleRewritePassAdapter;
+
+    let mut rewrites = RewriteSet::default();
+    for rewrite in function_optimizations(session.options.optimize) {
+        rewrites.push(ModuleRewritePassAdapter::new(rewrite));
+    }
+
+    rewrites
+}
+
+/// Returns the set of rewrites to apply to a linked program before it is converted to Miden
+/// Assembly, for the optimization level selected in `session`.
+///
+/// This is where functions are inlined, as that may happen across module boundaries. Every
+/// function into which a call is inlined is then optimized again, and has the rewrites required
+/// for codegen reapplied, see [default_function_rewrites].
+pub fn default_program_rewrites(session: &Session) -> RewriteSet<hir::Program> {
+    use midenc_hir_transform as transforms;
+
+    let mut rewrites = RewriteSet::default();
+    let level = session.options.optimize;
+    if session.should_codegen()
+        && optimization_pipeline(level).contains(&Optimization::InlineFunctions)
+    {
+        let mut cleanup = function_optimizations(level);
+        cleanup.append(&mut default_function_rewrites(session));
+        rewrites.push(transforms::InlineFunctions::new(EmitterCostModel, cleanup));
+    }
+
+    rewrites
+}
+
+/// Returns the function-level passes of the optimization pipeline for `level`
+fn function_optimizations(level: OptLevel) -> RewriteSet<hir::Function> {
+    use midenc_hir_transform as transforms;
+
+    let mut rewrites = RewriteSet::default();
+    for optimization in optimization_pipeline(level) {
+        match optimization {
+            Optimization::InlineFunctions => continue,
+            Optimization::InlineBlocks => rewrites.push(transforms::InlineBlocks),
+            Optimization::Sccp => rewrites.push(transforms::Sccp),
+            Optimization::GlobalValueNumbering => rewrites.push(transforms::GlobalValueNumbering),
+            Optimization::LoopInvariantCodeMotion => {
+                rewrites.push(transforms::LoopInvariantCodeMotion)
+            }
+            Optimization::EliminateDeadCode => rewrites.push(transforms::EliminateDeadCode),
+        }
+    }
+
+    rewrites
+}
+
 pub fn default_function_rewrites(session: &Session) -> RewriteSet<hir::Function> {
     use midenc_hir_transform as transforms;
 
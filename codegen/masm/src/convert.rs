@@ -2,7 +2,7 @@ use miden_assembly::{ast, LibraryNamespace, LibraryPath, Span};
 use midenc_hir::{
     self as hir,
     pass::{AnalysisManager, ConversionPass, ConversionResult},
-    ConversionPassRegistration, PassInfo,
+    ConversionPassRegistration, PassInfo, SourceSpan,
 };
 use midenc_hir_analysis as analysis;
 use midenc_session::Session;
@@ -59,6 +59,8 @@ impl ConversionPass for ConvertHirToMasm<hir::Program> {
         // Ensure global variable analysis is computed
         let globals =
             analyses.get_or_compute::<ProgramGlobalVariableAnalysis>(&program, session)?;
+        // Ensure the recursive calls of the program are known when converting its functions
+        analyses.get_or_compute::<analysis::RecursionAnalysis>(&program, session)?;
 
         let mut artifact = if program.has_entrypoint() {
             masm::Program::from_hir(&program, &globals)
@@ -133,10 +135,17 @@ impl ConversionPass for ConvertHirToMasm<hir::Module> {
         // the next function in the module. Once the end of the module
         // is reached, the cursor will point to the null object, and
         // `remove` will return `None`.
+        let recursion = analyses.get::<analysis::RecursionAnalysis>(&ProgramAnalysisKey);
         while let Some(function) = module.pop_front() {
             let mut convert_to_masm = ConvertHirToMasm::<&hir::Function>::default();
             let masm_function = convert_to_masm.convert(&function, analyses, session)?;
             masm_module.push_back(Box::new(masm_function));
+
+            // If this function is called recursively, emit the stub used as the target of
+            // `dynexec` for those calls
+            if let Some(target) = recursion.as_ref().and_then(|r| r.calls().get(function.id)) {
+                masm_module.push_back(Box::new(dynexec_stub(target, &function.signature)));
+            }
         }
 
         Ok(masm_module)
@@ -155,46 +164,73 @@ impl<'a> ConversionPass for ConvertHirToMasm<&'a hir::Function> {
     ) -> ConversionResult<Self::To> {
         use midenc_hir::ProgramAnalysisKey;
 
-        let mut f_prime = masm::Function::new(f.id, f.signature.clone());
-
-        // Start at the function entry
-        {
-            let entry = f.dfg.entry_block();
-
-            let globals = analyses
-                .get::<ProgramGlobalVariableAnalysis>(&ProgramAnalysisKey)
-                .map(|result| result.layout().clone())
-                .unwrap_or_else(|| {
-                    let result = analyses.expect::<ModuleGlobalVariableAnalysis>(
-                        &f.id.module,
-                        "expected global variable analysis to be available",
-                    );
-                    result.layout().clone()
-                });
-
-            let domtree = analyses.get_or_compute::<analysis::DominatorTree>(f, session)?;
-            let loops = analyses.get_or_compute::<analysis::LoopAnalysis>(f, session)?;
-            let liveness = analyses.get_or_compute::<analysis::LivenessAnalysis>(f, session)?;
-
-            let mut stack = OperandStack::default();
-            for arg in f.dfg.block_args(entry).iter().rev().copied() {
-                let ty = f.dfg.value_type(arg).clone();
-                stack.push(TypedValue { value: arg, ty });
-            }
-
-            let scheduler = Scheduler::new(f, &mut f_prime, &domtree, &loops, &liveness);
-            let schedule = scheduler.build();
-
-            /*
-                       if f.id.function.as_str().contains("get_inputs") {
-                           dbg!(&schedule);
-                       }
-            */
-            let emitter =
-                FunctionEmitter::new(f, &mut f_prime, &domtree, &loops, &liveness, &globals);
-            emitter.emit(schedule, stack);
-        }
+        let globals = analyses
+            .get::<ProgramGlobalVariableAnalysis>(&ProgramAnalysisKey)
+            .map(|result| result.layout().clone())
+            .unwrap_or_else(|| {
+                let result = analyses.expect::<ModuleGlobalVariableAnalysis>(
+                    &f.id.module,
+                    "expected global variable analysis to be available",
+                );
+                result.layout().clone()
+            });
+        let recursion = analyses
+            .get::<analysis::RecursionAnalysis>(&ProgramAnalysisKey)
+            .map(|result| result.calls().clone())
+            .unwrap_or_default();
+
+        emit_function(f, &globals, &recursion, analyses, session)
+    }
+}
 
-        Ok(f_prime)
+/// Emit Miden Assembly for `f`, given the layout of global variables in linear memory, and the
+/// set of recursive calls in the program.
+pub(crate) fn emit_function(
+    f: &hir::Function,
+    globals: &analysis::GlobalVariableLayout,
+    recursion: &hir::RecursiveCalls,
+    analyses: &mut AnalysisManager,
+    session: &Session,
+) -> ConversionResult<masm::Function> {
+    let mut f_prime = masm::Function::new(f.id, f.signature.clone());
+    f_prime.debug_variables = f.dfg.debug_variables.clone();
+
+    // Start at the function entry
+    let entry = f.dfg.entry_block();
+
+    let domtree = analyses.get_or_compute::<analysis::DominatorTree>(f, session)?;
+    let loops = analyses.get_or_compute::<analysis::LoopAnalysis>(f, session)?;
+    let liveness = analyses.get_or_compute::<analysis::LivenessAnalysis>(f, session)?;
+
+    let mut stack = OperandStack::default();
+    for arg in f.dfg.block_args(entry).iter().rev().copied() {
+        let ty = f.dfg.value_type(arg).clone();
+        stack.push(TypedValue { value: arg, ty });
     }
+
+    let scheduler = Scheduler::new(f, &mut f_prime, &domtree, &loops, &liveness);
+    let schedule = scheduler.build();
+
+    let emitter =
+        FunctionEmitter::new(f, &mut f_prime, &domtree, &loops, &liveness, globals, recursion);
+    emitter.emit(schedule, stack);
+
+    Ok(f_prime)
+}
+
+/// Generate the stub procedure which is the target of `dynexec` for recursive calls to `target`.
+///
+/// `dynexec` leaves the MAST root of the callee on the operand stack when entering it, so the stub
+/// drops it before executing the actual callee, whose signature is `signature`.
+fn dynexec_stub(target: &hir::DynamicCallee, signature: &hir::Signature) -> masm::Function {
+    use miden_assembly::ast::InvokeKind;
+
+    let mut signature = signature.clone();
+    signature.linkage = hir::Linkage::External;
+    let mut stub = masm::Function::new(target.stub, signature);
+    let span = SourceSpan::default();
+    stub.body_mut().push(masm::Op::Dropw, span);
+    stub.body_mut().push(masm::Op::Exec(target.callee), span);
+    stub.register_absolute_invocation_target(InvokeKind::Exec, target.callee);
+    stub
 }
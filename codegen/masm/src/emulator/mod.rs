@@ -34,6 +34,9 @@ pub enum EmulationError {
     /// The given function cannot be found
     #[error("unable to invoke function: '{0}' is not defined")]
     UndefinedFunction(FunctionIdent),
+    /// A dynamic call was made to a MAST root which does not belong to any known function
+    #[error("unable to invoke function: no function has the MAST root {0:?}")]
+    UndefinedRoot([Felt; 4]),
     /// The emulator ran out of available memory
     #[error("system limit: out of memory")]
     OutOfMemory,
@@ -117,6 +120,11 @@ enum Status {
 pub struct Emulator {
     status: Status,
     functions: FxHashMap<FunctionIdent, Stub>,
+    /// The functions whose MAST roots have been referenced, see [Op::ProcRef]
+    ///
+    /// The emulator does not compute MAST roots, so the root of a function is simulated by the
+    /// index at which it is found in this list.
+    roots: Vec<FunctionIdent>,
     locals: FxHashMap<FunctionIdent, Addr>,
     modules_loaded: FxHashMap<Ident, Arc<Module>>,
     modules_pending: FxHashSet<Ident>,
@@ -161,6 +169,7 @@ impl Emulator {
         Self {
             status: Status::Init,
             functions: Default::default(),
+            roots: Default::default(),
             locals: Default::default(),
             modules_loaded: Default::default(),
             modules_pending: Default::default(),
@@ -596,6 +605,7 @@ impl Emulator {
     pub fn reset(&mut self) {
         self.stop();
         self.functions.clear();
+        self.roots.clear();
         self.locals.clear();
         self.modules_loaded.clear();
         self.modules_pending.clear();
@@ -1210,6 +1220,31 @@ impl Emulator {
     }
 
     #[inline(never)]
+    /// Suspend the activation of the caller, `state`, and schedule `callee` to run next
+    fn exec(
+        &mut self,
+        state: Activation,
+        callee: FunctionIdent,
+    ) -> Result<EmulatorEvent, EmulationError> {
+        let fun = self
+            .functions
+            .get(&callee)
+            .cloned()
+            .ok_or(EmulationError::UndefinedFunction(callee))?;
+        self.step_over = Some(state.ip());
+        match fun {
+            Stub::Asm(ref function) => {
+                let fp = self.locals[&function.name];
+                let callee_state = Activation::new(function.clone(), fp);
+                // Suspend caller and scheduled callee next
+                self.callstack.push(state);
+                self.callstack.push(callee_state);
+                Ok(EmulatorEvent::EnterFunction(function.name))
+            }
+            Stub::Native(_function) => unimplemented!(),
+        }
+    }
+
     fn run_once(&mut self) -> Result<EmulatorEvent, EmulationError> {
         const U32_P: u64 = 2u64.pow(32);
 
@@ -1439,6 +1474,29 @@ impl Emulator {
                     let a = popw!(self);
                     assert_eq!(a, b, "equality assertion failed");
                 }
+                Op::AssertWithError(code) => {
+                    let cond = pop_bool!(self);
+                    assert!(
+                        cond,
+                        "assertion failed with error code {code}: expected true, got false"
+                    );
+                }
+                Op::AssertzWithError(code) => {
+                    let cond = pop_bool!(self);
+                    assert!(
+                        !cond,
+                        "assertion failed with error code {code}: expected false, got true"
+                    );
+                }
+                Op::AssertEqWithError(code) => {
+                    let (b, a) = pop2!(self);
+                    assert_eq!(a, b, "equality assertion failed with error code {code}");
+                }
+                Op::AssertEqwWithError(code) => {
+                    let b = popw!(self);
+                    let a = popw!(self);
+                    assert_eq!(a, b, "equality assertion failed with error code {code}");
+                }
                 Op::LocAddr(id) => {
                     let addr = state.fp() + id.as_usize() as u32;
                     debug_assert!(addr < self.memory.len() as u32);
@@ -1574,24 +1632,28 @@ impl Emulator {
                     self.callstack.push(state);
                     return Ok(EmulatorEvent::EnterLoop(body_blk));
                 }
-                Op::Exec(callee) => {
-                    let fun = self
-                        .functions
-                        .get(&callee)
-                        .cloned()
-                        .ok_or(EmulationError::UndefinedFunction(callee))?;
-                    self.step_over = Some(state.ip());
-                    match fun {
-                        Stub::Asm(ref function) => {
-                            let fp = self.locals[&function.name];
-                            let callee_state = Activation::new(function.clone(), fp);
-                            // Suspend caller and scheduled callee next
-                            self.callstack.push(state);
-                            self.callstack.push(callee_state);
-                            return Ok(EmulatorEvent::EnterFunction(function.name));
+                Op::Exec(callee) => return self.exec(state, callee),
+                Op::DynExec => {
+                    // The MAST root is left on the operand stack for the callee to consume
+                    let root =
+                        self.stack.peekw().expect("operand stack does not contain a full word");
+                    let callee = if root[1..] == [Felt::ZERO; 3] {
+                        self.roots.get(root[0].as_int() as usize).copied()
+                    } else {
+                        None
+                    };
+                    let callee = callee.ok_or(EmulationError::UndefinedRoot(root))?;
+                    return self.exec(state, callee);
+                }
+                Op::ProcRef(callee) => {
+                    let index = match self.roots.iter().position(|root| root == &callee) {
+                        Some(index) => index,
+                        None => {
+                            self.roots.push(callee);
+                            self.roots.len() - 1
                         }
-                        Stub::Native(_function) => unimplemented!(),
-                    }
+                    };
+                    self.stack.pushw([Felt::new(index as u64), Felt::ZERO, Felt::ZERO, Felt::ZERO]);
                 }
                 Op::Call(_callee) | Op::Syscall(_callee) => unimplemented!(),
                 Op::Add => binop!(self, add),
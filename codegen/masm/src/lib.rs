@@ -14,7 +14,9 @@ mod tests;
 
 pub use self::{
     compiler::{
-        default_function_rewrites, default_rewrites, CompilerResult, MasmArtifact, MasmCompiler,
+        default_function_rewrites, default_optimizations, default_program_rewrites,
+        default_rewrites, optimization_pipeline, CompilerResult, MasmArtifact, MasmCompiler,
+        Optimization,
     },
     convert::ConvertHirToMasm,
     emulator::{
@@ -9,7 +9,7 @@ use miden_assembly::{
 use midenc_hir::{
     diagnostics::{SourceSpan, Span, Spanned},
     formatter::PrettyPrint,
-    AttributeSet, FunctionIdent, Ident, Signature, Type,
+    AttributeSet, DebugVariable, FunctionIdent, Ident, Signature, Type,
 };
 use smallvec::SmallVec;
 
@@ -38,6 +38,8 @@ pub struct Function {
     locals: SmallVec<[Local; 1]>,
     /// The next available local index
     next_local_id: usize,
+    /// The source-level variables of this function, if debug information is available
+    pub debug_variables: Vec<DebugVariable>,
 }
 impl Function {
     pub fn new(name: FunctionIdent, signature: Signature) -> Self {
@@ -51,6 +53,7 @@ impl Function {
             invoked: Default::default(),
             locals: Default::default(),
             next_local_id: 0,
+            debug_variables: vec![],
         }
     }
 
@@ -7,11 +7,13 @@ const I32_INTRINSICS: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/intrinsics/i32.masm"));
 const I64_INTRINSICS: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/intrinsics/i64.masm"));
+const F64_INTRINSICS: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/intrinsics/f64.masm"));
 const MEM_INTRINSICS: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/intrinsics/mem.masm"));
 
 /// This is a mapping of intrinsics module name to the raw MASM source for that module
-const INTRINSICS: [(&str, &str, &str); 3] = [
+const INTRINSICS: [(&str, &str, &str); 4] = [
     (
         "intrinsics::i32",
         I32_INTRINSICS,
@@ -22,6 +24,11 @@ const INTRINSICS: [(&str, &str, &str); 3] = [
         I64_INTRINSICS,
         concat!(env!("CARGO_MANIFEST_DIR"), "/intrinsics/i64.masm"),
     ),
+    (
+        "intrinsics::f64",
+        F64_INTRINSICS,
+        concat!(env!("CARGO_MANIFEST_DIR"), "/intrinsics/f64.masm"),
+    ),
     (
         "intrinsics::mem",
         MEM_INTRINSICS,
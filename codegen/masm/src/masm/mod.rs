@@ -81,6 +81,24 @@ impl NativePtr {
         }
     }
 
+    /// Returns the word address at which the region of Miden's address space assigned to
+    /// `addrspace` starts.
+    ///
+    /// The IR's byte-addressable address space is translated to the first 2^28 words, i.e. 2^32
+    /// bytes, of Miden's address space. Each statically allocated address space is assigned a
+    /// region of the same size following it. Procedure locals are allocated starting from word
+    /// address 2^30, so only address spaces 1 to 3 are available.
+    pub fn address_space_base(addrspace: midenc_hir::AddressSpace) -> u32 {
+        match addrspace {
+            midenc_hir::AddressSpace::Id(id) => {
+                let id = u32::from(id.get());
+                assert!(id <= 3, "unsupported address space {id}: expected an id from 1 to 3");
+                id << 28
+            }
+            midenc_hir::AddressSpace::Root | midenc_hir::AddressSpace::Unknown => 0,
+        }
+    }
+
     /// Returns true if this pointer is aligned to a word boundary
     pub const fn is_word_aligned(&self) -> bool {
         self.index == 0 && self.offset == 0
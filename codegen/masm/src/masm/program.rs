@@ -37,6 +37,11 @@ pub struct Program {
     /// or, if available, the next byte following the both the reserved linear memory region as
     /// declared in HIR, and the global variables of the program.
     heap_base: u32,
+    /// The stubs used to recursively call functions in the same recursive component of the call
+    /// graph as the entrypoint, along with the address at which the MAST root of each is stored.
+    ///
+    /// These roots are written before the entrypoint is invoked, see [hir::RecursiveCalls].
+    dynamic_roots: Vec<(FunctionIdent, u32)>,
 }
 impl Program {
     /// Create a new [Program] initialized from an [hir::Program].
@@ -59,16 +64,38 @@ impl Program {
         let library = Library::from_hir(program, globals);
 
         // Compute the first page boundary after the end of the globals table to use as the start
-        // of the dynamic heap when the program is executed
-        let heap_base = program.reserved_memory_bytes()
-            + u32::try_from(
-                program.globals().size_in_bytes().next_multiple_of(program.page_size() as usize),
-            )
-            .expect("unable to allocate dynamic heap: global table too large");
+        // of the dynamic heap when the program is executed. The globals table follows any data
+        // segments placed beyond the reserved memory region.
+        let heap_base = u32::try_from(
+            (globals.layout().global_table_offset() as usize + program.globals().size_in_bytes())
+                .next_multiple_of(program.page_size() as usize),
+        )
+        .expect("unable to allocate dynamic heap: global table too large");
+
+        // The program entrypoint is not part of any recursive component, so if the entrypoint
+        // itself is, it is responsible for providing the roots used within that component
+        let main = FunctionIdent {
+            module: Ident::with_empty_span(Symbol::intern(LibraryNamespace::EXEC_PATH)),
+            function: Ident::with_empty_span(Symbol::intern(ProcedureName::MAIN_PROC_NAME)),
+        };
+        let dynamic_roots = program
+            .recursive_calls()
+            .entering(main, entrypoint)
+            .iter()
+            .map(|target| {
+                let addr = globals
+                    .layout()
+                    .get_symbol_addr(target.root)
+                    .expect("expected global to be allocated for recursive call target");
+                (target.stub, addr)
+            })
+            .collect();
+
         Ok(Self {
             library,
             entrypoint,
             heap_base,
+            dynamic_roots,
         })
     }
 
@@ -117,6 +144,14 @@ impl Program {
             if emit_test_harness {
                 self.emit_test_harness(body);
             }
+            // Store the roots of the stubs needed by recursive calls reachable from the entrypoint
+            for (stub, addr) in self.dynamic_roots.iter().copied() {
+                let ptr = NativePtr::from_ptr(addr);
+                assert!(ptr.is_word_aligned(), "expected stub root to be word-aligned");
+                body.push(Op::ProcRef(stub), SourceSpan::default());
+                body.push(Op::MemStorewImm(ptr.waddr), SourceSpan::default());
+                body.push(Op::Dropw, SourceSpan::default());
+            }
             // Invoke the program entrypoint
             body.push(Op::Exec(entrypoint), SourceSpan::default());
         }
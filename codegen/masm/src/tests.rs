@@ -272,6 +272,48 @@ fn issue56() {
     compiler.compile(program).expect("compilation failed");
 }
 
+/// Each optimization level selects its own, ordered, set of passes
+#[test]
+fn optimization_pipeline_per_level() {
+    use midenc_session::OptLevel;
+    use Optimization::*;
+
+    assert!(optimization_pipeline(OptLevel::None).is_empty());
+    assert_eq!(optimization_pipeline(OptLevel::Basic), &[InlineBlocks, Sccp, EliminateDeadCode]);
+    assert_eq!(
+        optimization_pipeline(OptLevel::Balanced),
+        &[
+            InlineFunctions,
+            InlineBlocks,
+            Sccp,
+            GlobalValueNumbering,
+            LoopInvariantCodeMotion,
+            EliminateDeadCode
+        ]
+    );
+    assert_eq!(
+        optimization_pipeline(OptLevel::Max),
+        &[
+            InlineFunctions,
+            InlineBlocks,
+            Sccp,
+            GlobalValueNumbering,
+            LoopInvariantCodeMotion,
+            Sccp,
+            GlobalValueNumbering,
+            EliminateDeadCode
+        ]
+    );
+    assert_eq!(
+        optimization_pipeline(OptLevel::Size),
+        &[InlineFunctions, InlineBlocks, Sccp, GlobalValueNumbering, EliminateDeadCode]
+    );
+    assert_eq!(
+        optimization_pipeline(OptLevel::SizeMin),
+        &[InlineBlocks, Sccp, GlobalValueNumbering, EliminateDeadCode]
+    );
+}
+
 /// Test the emulator on the fibonacci function
 #[test]
 fn fib_emulator() {
@@ -303,6 +345,47 @@ fn fib_emulator() {
     assert_eq!(stack.pop().map(|e| e.as_int()), Some(55));
 }
 
+/// Test the emulator on the recursive factorial function, called via the stub for `dynexec`
+#[test]
+fn fact_emulator() {
+    let mut harness = TestByEmulationHarness::default();
+
+    let mut builder = ProgramBuilder::new(&harness.context.session.diagnostics);
+
+    // Build test module with the fact function, and a main function which enters it, so that the
+    // root of the stub for the recursive call is written before it is used
+    let mut mb = builder.module("test");
+    let fact = testing::fact(mb.as_mut(), &harness.context);
+    let main = {
+        let sig = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+        let mut fb = mb.function("main", sig.clone()).expect("unexpected symbol conflict");
+        let fact = fb.import_function(fact.module, fact.function, sig).unwrap();
+        let entry = fb.current_block();
+        let n = fb.block_params(entry)[0];
+        let call = fb.ins().exec(fact, &[n], SourceSpan::default());
+        let result = fb.first_result(call);
+        fb.ins().ret(Some(result), SourceSpan::default());
+        fb.build(&harness.context.session.diagnostics)
+            .expect("unexpected validation error, see diagnostics output")
+    };
+    mb.build().expect("unexpected error constructing test module");
+
+    let program = builder.with_entrypoint(main).link().expect("failed to link program");
+    assert!(program.recursive_calls().is_back_edge(fact, fact));
+
+    let mut compiler = MasmCompiler::new(&harness.context.session);
+    let program = compiler.compile(program).expect("compilation failed").unwrap_executable();
+    let program = program.freeze();
+
+    for (n, expected) in [(0, 1), (1, 1), (5, 120), (10, 3628800)] {
+        let mut stack = harness
+            .execute_program(program.clone(), &[Felt::new(n)])
+            .expect("execution failed");
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected), "fact({n})");
+    }
+}
+
 /// Test the code generator on a very simple program with a conditional as a sanity check
 #[test]
 fn codegen_fundamental_if() {
@@ -358,6 +441,116 @@ fn codegen_fundamental_if() {
     assert_eq!(stack.pop().map(|e| e.as_int()), Some(12));
 }
 
+/// Build a program whose entrypoint, `main(x, index)`, applies the function at `index` of the table
+/// `[null, inc, id64, dbl]` to `x` twice, via indirect calls through a shared dispatch function, as
+/// the Wasm frontend does for `call_indirect`. Since `id64` has a different signature, it is a null
+/// entry of the dispatch function.
+fn call_indirect_program(context: &TestContext) -> Arc<Program> {
+    let span = SourceSpan::UNKNOWN;
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    let mut mb = builder.module("test");
+
+    let callee_sig = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+    for (name, factor) in [("inc", None), ("dbl", Some(2))] {
+        let mut fb = mb.function(name, callee_sig.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let x = fb.block_params(entry)[0];
+        let y = match factor {
+            None => fb.ins().incr_wrapping(x, span),
+            Some(factor) => fb.ins().mul_imm_wrapping(x, Immediate::U32(factor), span),
+        };
+        fb.ins().ret(Some(y), span);
+        fb.build().expect("unexpected error building function");
+    }
+
+    let mut dispatch_sig = callee_sig.clone();
+    dispatch_sig.params.push(AbiParam::new(Type::U32));
+    {
+        let mut fb = mb
+            .function("dispatch", dispatch_sig.clone())
+            .expect("unexpected symbol conflict");
+        let inc = fb.import_function("test", "inc", callee_sig.clone()).unwrap();
+        let dbl = fb.import_function("test", "dbl", callee_sig.clone()).unwrap();
+        let entry = fb.current_block();
+        let (x, index) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let table = vec![None, Some(inc), None, Some(dbl)];
+        let call = fb.ins().call_indirect(index, callee_sig, table, &[x], span);
+        let y = fb.first_result(call);
+        fb.ins().ret(Some(y), span);
+        fb.build().expect("unexpected error building function");
+    }
+
+    let main = {
+        let mut fb = mb.function("main", dispatch_sig.clone()).expect("unexpected symbol conflict");
+        let dispatch = fb.import_function("test", "dispatch", dispatch_sig).unwrap();
+        let entry = fb.current_block();
+        let (x, index) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let call = fb.ins().exec(dispatch, &[x, index], span);
+        let y = fb.first_result(call);
+        let call = fb.ins().exec(dispatch, &[y, index], span);
+        let z = fb.first_result(call);
+        fb.ins().ret(Some(z), span);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    let program = builder.with_entrypoint(main).link().expect("failed to link program");
+    let mut compiler = MasmCompiler::new(&context.session);
+    compiler
+        .compile(program)
+        .expect("compilation failed")
+        .unwrap_executable()
+        .freeze()
+}
+
+/// Test that an indirect call executes the entry of the table at the given index
+#[test]
+fn codegen_call_indirect_dispatch() {
+    let context = TestContext::default();
+    let program = call_indirect_program(&context);
+
+    for (index, expected) in [(1, 7 + 1 + 1), (3, 7 * 2 * 2)] {
+        let mut harness = TestByEmulationHarness::default();
+        let mut stack = harness
+            .execute_program(program.clone(), &[Felt::new(7), Felt::new(index)])
+            .expect("execution failed");
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected), "calling entry {index}");
+    }
+}
+
+/// Test that an indirect call traps when the index refers to a null entry, to an entry with a
+/// different signature, or is out of bounds
+#[test]
+fn codegen_call_indirect_traps() {
+    let context = TestContext::default();
+    let program = call_indirect_program(&context);
+
+    for index in [0, 2, 4, u32::MAX as u64] {
+        let program = program.clone();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let mut harness = TestByEmulationHarness::default();
+            harness.execute_program(program, &[Felt::new(7), Felt::new(index)])
+        }));
+        let Err(err) = result else {
+            panic!("expected calling entry {index} to trap");
+        };
+        let message = err
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| err.downcast_ref::<&str>().copied())
+            .unwrap_or_default();
+        assert!(message.contains("assertion failed"), "unexpected failure: {message}");
+    }
+}
+
 /// Test the code generator on a very simple program with a loop as a sanity check
 #[test]
 fn codegen_fundamental_loops() {
@@ -722,6 +915,225 @@ fn codegen_mem_store_felt_load_felt() {
         .unwrap();
 }
 
+#[test]
+fn codegen_mem_store_qw_load_qw() {
+    let context = TestContext::default();
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "store_load_qw",
+                Signature::new(
+                    [AbiParam::new(Type::U32), AbiParam::new(Type::I128)],
+                    [AbiParam::new(Type::I128)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (ptr_u32, value) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1])
+        };
+        let ptr = fb.ins().inttoptr(ptr_u32, Type::Ptr(Type::I128.into()), SourceSpan::UNKNOWN);
+        fb.ins().store(ptr, value, SourceSpan::UNKNOWN);
+        let loaded_value = fb.ins().load(ptr, SourceSpan::UNKNOWN);
+        fb.ins().ret(Some(loaded_value), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler
+        .compile(program)
+        .expect("compilation failed")
+        .unwrap_executable()
+        .freeze();
+
+    fn roundtrip(program: Arc<Program>, ptr: u32, value: i128) -> i128 {
+        eprintln!("---------------------------------");
+        eprintln!("testing store_qw/load_qw ptr: {ptr}, value: {value}");
+        eprintln!("---------------------------------");
+        let mut harness = TestByEmulationHarness::with_emulator_config(
+            MEMORY_SIZE_VM_WORDS as usize,
+            Emulator::DEFAULT_HEAP_START as usize,
+            Emulator::DEFAULT_LOCALS_START as usize,
+            true,
+        );
+        let mut args: SmallVec<[Felt; 4]> = smallvec!(Felt::new(ptr as u64));
+        args.extend(value.canonicalize());
+        let mut stack = harness.execute_program(program.clone(), &args).expect("execution failed");
+        i128::from_stack(&mut stack)
+    }
+
+    TestRunner::new(Config::with_cases(1024))
+        .run(&(0u32..MEMORY_SIZE_BYTES - 16, any::<i128>()), move |(ptr, value)| {
+            let out = roundtrip(program.clone(), ptr, value);
+            prop_assert_eq!(out, value);
+            Ok(())
+        })
+        .unwrap();
+}
+
+/// This test writes the fields of a packed struct, i.e. `#[repr(packed)] struct { a: u8, b: u64,
+/// c: u16 }`, and reads them back, ensuring that unaligned stores of each field preserve the bytes
+/// belonging to their neighbors.
+#[test]
+fn codegen_mem_store_load_packed_struct() {
+    let context = TestContext::default();
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "store_load_packed",
+                Signature::new(
+                    [
+                        AbiParam::new(Type::U32),
+                        AbiParam::new(Type::U8),
+                        AbiParam::new(Type::U64),
+                        AbiParam::new(Type::U16),
+                    ],
+                    [AbiParam::new(Type::U64)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (addr, a, b, c) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2], args[3])
+        };
+        let span = SourceSpan::UNKNOWN;
+        let a_ptr = fb.ins().inttoptr(addr, Type::Ptr(Type::U8.into()), span);
+        let b_addr = fb.ins().add_imm_checked(addr, Immediate::U32(1), span);
+        let b_ptr = fb.ins().inttoptr(b_addr, Type::Ptr(Type::U64.into()), span);
+        let c_addr = fb.ins().add_imm_checked(addr, Immediate::U32(9), span);
+        let c_ptr = fb.ins().inttoptr(c_addr, Type::Ptr(Type::U16.into()), span);
+        fb.ins().store(a_ptr, a, span);
+        fb.ins().store(b_ptr, b, span);
+        fb.ins().store(c_ptr, c, span);
+        let loaded_a = fb.ins().load(a_ptr, span);
+        fb.ins().assert_eq(loaded_a, a, span);
+        let loaded_c = fb.ins().load(c_ptr, span);
+        fb.ins().assert_eq(loaded_c, c, span);
+        let loaded_b = fb.ins().load(b_ptr, span);
+        fb.ins().ret(Some(loaded_b), span);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler
+        .compile(program)
+        .expect("compilation failed")
+        .unwrap_executable()
+        .freeze();
+
+    fn roundtrip(program: Arc<Program>, ptr: u32, a: u8, b: u64, c: u16) -> u64 {
+        eprintln!("---------------------------------");
+        eprintln!("testing packed struct ptr: {ptr}, a: {a}, b: {b}, c: {c}");
+        eprintln!("---------------------------------");
+        let mut harness = TestByEmulationHarness::with_emulator_config(
+            MEMORY_SIZE_VM_WORDS as usize,
+            Emulator::DEFAULT_HEAP_START as usize,
+            Emulator::DEFAULT_LOCALS_START as usize,
+            true,
+        );
+        let mut args: SmallVec<[Felt; 4]> = smallvec!(Felt::new(ptr as u64));
+        args.extend(a.canonicalize());
+        args.extend(b.canonicalize());
+        args.extend(c.canonicalize());
+        let mut stack = harness.execute_program(program.clone(), &args).expect("execution failed");
+        u64::from_stack(&mut stack)
+    }
+
+    TestRunner::new(Config::with_cases(1024))
+        .run(
+            &(0u32..MEMORY_SIZE_BYTES - 16, any::<u8>(), any::<u64>(), any::<u16>()),
+            move |(ptr, a, b, c)| {
+                let out = roundtrip(program.clone(), ptr, a, b, c);
+                prop_assert_eq!(out, b);
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn codegen_multiple_results() {
+    let context = TestContext::default();
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    let mut mb = builder.module("test");
+    let signature = Signature::new(
+        [AbiParam::new(Type::I32), AbiParam::new(Type::I64), AbiParam::new(Type::I32)],
+        [AbiParam::new(Type::I32), AbiParam::new(Type::I64), AbiParam::new(Type::I32)],
+    );
+    // Returns its arguments in reverse order
+    let reverse = {
+        let mut fb = mb.function("reverse", signature.clone()).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b, c) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2])
+        };
+        fb.ins().ret_values(&[c, b, a], SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+    // Forwards the results of `reverse` to the caller unchanged
+    let id = {
+        let mut fb = mb.function("entrypoint", signature).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let (a, b, c) = {
+            let args = fb.block_params(entry);
+            (args[0], args[1], args[2])
+        };
+        let span = SourceSpan::UNKNOWN;
+        let call = fb.ins().exec(reverse, &[a, b, c], span);
+        let results = fb.inst_results(call).to_vec();
+        assert_eq!(results.len(), 3);
+        fb.ins().ret_values(&results, span);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build().expect("unexpected error constructing test module");
+
+    let program = builder.with_entrypoint(id).link().expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&context.session);
+    let program = compiler
+        .compile(program)
+        .expect("compilation failed")
+        .unwrap_executable()
+        .freeze();
+
+    TestRunner::new(Config::with_cases(256))
+        .run(&(any::<i32>(), any::<i64>(), any::<i32>()), move |(a, b, c)| {
+            let mut harness = TestByEmulationHarness::with_emulator_config(
+                MEMORY_SIZE_VM_WORDS as usize,
+                Emulator::DEFAULT_HEAP_START as usize,
+                Emulator::DEFAULT_LOCALS_START as usize,
+                false,
+            );
+            let mut args: SmallVec<[Felt; 4]> = a.canonicalize();
+            args.extend(b.canonicalize());
+            args.extend(c.canonicalize());
+            let mut stack =
+                harness.execute_program(program.clone(), &args).expect("execution failed");
+            // The first result is on top of the stack, followed by the rest in order
+            prop_assert_eq!(i32::from_stack(&mut stack), c);
+            prop_assert_eq!(i64::from_stack(&mut stack), b);
+            prop_assert_eq!(i32::from_stack(&mut stack), a);
+            Ok(())
+        })
+        .unwrap();
+}
+
 #[allow(unused)]
 macro_rules! proptest_unary_numeric_op {
     ($ty_name:ident :: $op:ident, $ty:ty => $ret:ty, $rust_op:ident) => {
@@ -13,24 +13,31 @@
 //!
 //! Based on Cranelift's Wasm -> CLIF translator v11.0.0
 
+use core::num::NonZeroU16;
+
 use midenc_hir::{
     cranelift_entity::packed_option::ReservedValue,
     diagnostics::{DiagnosticsHandler, IntoDiagnostic, Report, Severity, SourceSpan},
-    Block, FieldElement, Immediate, Inst, InstBuilder, Type,
+    AddressSpace, Block, FieldElement, Immediate, Inst, InstBuilder, Type,
     Type::*,
     Value,
 };
 use wasmparser::{MemArg, Operator};
 
 use crate::{
+    config::WasmTranslationConfig,
     error::WasmResult,
-    intrinsics::{convert_intrinsics_call, is_miden_intrinsics_module},
+    intrinsics::{self, convert_intrinsics_call, is_miden_intrinsics_module},
     miden_abi::{is_miden_abi_module, transform::transform_miden_abi_call},
     module::{
+        debug_variables::Storage,
         func_translation_state::{ControlStackFrame, ElseData, FuncTranslationState},
         function_builder_ext::FunctionBuilderExt,
         module_translation_state::ModuleTranslationState,
-        types::{ir_type, BlockType, FuncIndex, GlobalIndex, ModuleTypes},
+        types::{
+            ir_type, BlockType, DataIndex, FuncIndex, GlobalIndex, MemoryIndex, ModuleTypes,
+            TableIndex, TagIndex, TypeIndex, NULL_FUNCREF,
+        },
         Module,
     },
     ssa::Variable,
@@ -52,11 +59,12 @@ pub fn translate_operator(
     module_state: &mut ModuleTranslationState,
     module: &Module,
     mod_types: &ModuleTypes,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
     span: SourceSpan,
 ) -> WasmResult<()> {
     if !state.reachable {
-        translate_unreachable_operator(op, builder, state, mod_types, diagnostics, span)?;
+        translate_unreachable_operator(op, builder, state, mod_types, config, diagnostics, span)?;
         return Ok(());
     }
 
@@ -90,25 +98,31 @@ pub fn translate_operator(
                 val
             };
             builder.def_var(var, val);
+            let storage = Storage::Local(*local_index);
+            state.debug_variables.emit_assignment(storage, val, builder, span);
         }
         Operator::LocalTee { local_index } => {
             let val = state.peek1();
             builder.def_var(Variable::from_u32(*local_index), val);
+            let storage = Storage::Local(*local_index);
+            state.debug_variables.emit_assignment(storage, val, builder, span);
         }
         /********************************** Globals ****************************************/
         Operator::GlobalGet { global_index } => {
             let global_index = GlobalIndex::from_u32(*global_index);
             let name = module.global_name(global_index);
-            let ty = ir_type(module.globals[global_index].ty, diagnostics)?;
+            let ty = ir_type(module.globals[global_index].ty, config, diagnostics)?;
             state.push1(builder.ins().load_symbol(name.as_str(), ty, span));
         }
         Operator::GlobalSet { global_index } => {
             let global_index = GlobalIndex::from_u32(*global_index);
             let name = module.global_name(global_index);
-            let ty = ir_type(module.globals[global_index].ty, diagnostics)?;
+            let ty = ir_type(module.globals[global_index].ty, config, diagnostics)?;
             let ptr = builder.ins().symbol_addr(name.as_str(), Ptr(ty.clone().into()), span);
             let val = state.pop1();
             builder.ins().store(ptr, val, span);
+            let storage = Storage::Global(global_index.as_u32());
+            state.debug_variables.emit_assignment(storage, val, builder, span);
         }
         /********************************* Stack misc **************************************/
         Operator::Drop => _ = state.pop1(),
@@ -128,7 +142,7 @@ pub fn translate_operator(
                         builder.ins().gt_imm(cond, Immediate::Felt(midenc_hir::Felt::ZERO), span);
                     state.push1(builder.ins().select(cond, arg1, arg2, span));
                 }
-                wasmparser::ValType::I32 => {
+                wasmparser::ValType::I32 | wasmparser::ValType::Ref(_) => {
                     let cond = builder.ins().neq_imm(cond, Immediate::I32(0), span);
                     state.push1(builder.ins().select(cond, arg1, arg2, span));
                 }
@@ -146,16 +160,37 @@ pub fn translate_operator(
         Operator::Nop => {}
         /***************************** Control flow blocks *********************************/
         Operator::Block { blockty } => {
-            translate_block(blockty, builder, state, mod_types, diagnostics, span)?
+            translate_block(blockty, builder, state, mod_types, config, diagnostics, span)?
         }
         Operator::Loop { blockty } => {
-            translate_loop(blockty, builder, state, mod_types, diagnostics, span)?
+            translate_loop(blockty, builder, state, mod_types, config, diagnostics, span)?
         }
         Operator::If { blockty } => {
-            translate_if(blockty, state, builder, mod_types, diagnostics, span)?
+            translate_if(blockty, state, builder, mod_types, config, diagnostics, span)?
         }
         Operator::Else => translate_else(state, builder, span)?,
         Operator::End => translate_end(state, builder, span),
+        /***************************** Exception handling **********************************/
+        // Exceptions are never caught: throwing one aborts the program. As a result, the body of
+        // a `try_table` or `try` block is translated like any other block, and its handlers, which
+        // could only be entered by catching an exception, are unreachable.
+        Operator::TryTable { try_table } => {
+            translate_block(&try_table.ty, builder, state, mod_types, config, diagnostics, span)?
+        }
+        Operator::Try { blockty } => {
+            translate_block(blockty, builder, state, mod_types, config, diagnostics, span)?
+        }
+        Operator::Catch { .. } | Operator::CatchAll => translate_br(state, &0, builder, span),
+        Operator::Delegate { .. } => translate_end(state, builder, span),
+        Operator::Throw { tag_index } => {
+            translate_throw(*tag_index, builder, state, module, mod_types, span)
+        }
+        // An exception reference is always null, as no exception is ever caught, so rethrowing
+        // it traps
+        Operator::ThrowRef | Operator::Rethrow { .. } => {
+            builder.ins().unreachable(span);
+            state.reachable = false;
+        }
 
         /**************************** Branch instructions *********************************/
         Operator::Br { relative_depth } => translate_br(state, relative_depth, builder, span),
@@ -163,7 +198,7 @@ pub fn translate_operator(
             translate_br_if(*relative_depth, builder, state, span)?
         }
         Operator::BrTable { targets } => translate_br_table(targets, state, builder, span)?,
-        Operator::Return => translate_return(state, builder, diagnostics, span)?,
+        Operator::Return => translate_return(state, builder, span),
         /************************************ Calls ****************************************/
         Operator::Call { function_index } => {
             translate_call(
@@ -176,48 +211,204 @@ pub fn translate_operator(
             )?;
         }
         Operator::CallIndirect {
-            type_index: _,
-            table_index: _,
+            type_index,
+            table_index,
         } => {
-            // TODO:
+            translate_call_indirect(
+                state,
+                module_state,
+                builder,
+                module,
+                mod_types,
+                TypeIndex::from_u32(*type_index),
+                TableIndex::from_u32(*table_index),
+                span,
+                config,
+                diagnostics,
+            )?;
+        }
+        Operator::ReturnCall { function_index } => {
+            translate_return_call(
+                state,
+                module_state,
+                builder,
+                FuncIndex::from_u32(*function_index),
+                span,
+                diagnostics,
+            )?;
+        }
+        Operator::ReturnCallIndirect {
+            type_index,
+            table_index,
+        } => {
+            // Indirect calls are lowered to a dispatch over the function table, which is not
+            // itself a call, so the results of the dispatch are returned as usual
+            translate_call_indirect(
+                state,
+                module_state,
+                builder,
+                module,
+                mod_types,
+                TypeIndex::from_u32(*type_index),
+                TableIndex::from_u32(*table_index),
+                span,
+                config,
+                diagnostics,
+            )?;
+            translate_return(state, builder, span);
+        }
+        /******************************* Table operations **********************************/
+        Operator::TableSize { table } => {
+            let elements =
+                module_state.table_elements(module, TableIndex::from_u32(*table), diagnostics)?;
+            let size = i32::try_from(elements.len()).expect("table size exceeds i32::MAX");
+            state.push1(builder.ins().i32(size, span));
+        }
+        Operator::TableGet { table } => {
+            // Function references are represented by their index in the table they were read
+            // from, so all that remains is to trap if the index is out of bounds
+            let elements =
+                module_state.table_elements(module, TableIndex::from_u32(*table), diagnostics)?;
+            let size = u32::try_from(elements.len()).expect("table size exceeds u32::MAX");
+            let index = state.pop1_bitcasted(U32, builder, span);
+            let in_bounds = builder.ins().lt_imm(index, Immediate::U32(size), span);
+            builder.ins().assert(in_bounds, span);
+            state.push1(builder.ins().bitcast(index, I32, span));
+        }
+        Operator::TableGrow { .. } => {
+            // The contents of tables are fixed at compile time, so growing a table always fails,
+            // which is permitted by the spec
+            state.popn(2);
+            state.push1(builder.ins().i32(-1, span));
+        }
+        Operator::ElemDrop { .. } => {
+            // Element segments are only used at compile time, so there is nothing to release
+        }
+        Operator::TableSet { .. }
+        | Operator::TableFill { .. }
+        | Operator::TableCopy { .. }
+        | Operator::TableInit { .. } => {
+            unsupported_diag!(
+                diagnostics,
+                "Wasm op {:?} is not supported: the contents of tables are fixed at compile time",
+                op
+            );
+        }
+        /******************************* Reference types ***********************************/
+        Operator::RefNull { .. } => state.push1(builder.ins().i32(NULL_FUNCREF, span)),
+        Operator::RefIsNull => {
+            let arg = state.pop1();
+            let val = builder.ins().eq_imm(arg, Immediate::I32(NULL_FUNCREF), span);
+            state.push1(builder.ins().zext(val, I32, span));
+        }
+        Operator::RefFunc { function_index } => {
+            // A function reference is the index of the function in table 0, so that it can be
+            // called via `call_indirect`
+            let func_index = FuncIndex::from_u32(*function_index);
+            let table = TableIndex::from_u32(0);
+            let position = match module.tables.get(table) {
+                Some(_) => module_state
+                    .table_elements(module, table, diagnostics)?
+                    .iter()
+                    .position(|element| *element == Some(func_index)),
+                None => None,
+            };
+            let Some(position) = position else {
+                unsupported_diag!(
+                    diagnostics,
+                    "RefFunc: function {function_index} must be an element of table 0"
+                );
+            };
+            let position = i32::try_from(position).expect("table size exceeds i32::MAX");
+            state.push1(builder.ins().i32(position, span));
         }
         /******************************* Memory management *********************************/
-        Operator::MemoryGrow { .. } => {
-            let arg = state.pop1_bitcasted(U32, builder, span);
-            state.push1(builder.ins().mem_grow(arg, span));
+        Operator::MemoryGrow { mem } => {
+            let memory = &module.memories[MemoryIndex::from_u32(*mem)];
+            let num_pages = prepare_len(state.pop1(), builder, span);
+            // Only memory 0 is backed by the heap, the size of other memories is fixed, so growing
+            // them always fails, which is permitted by the spec
+            let result = match *mem {
+                0 => builder.ins().mem_grow(num_pages, span),
+                _ => builder.ins().i32(-1, span),
+            };
+            match memory.memory64 {
+                true => state.push1(builder.ins().sext(result, I64, span)),
+                false => state.push1(result),
+            }
         }
-        Operator::MemorySize { .. } => {
-            // Return total Miden memory size
-            state.push1(builder.ins().mem_size(span));
+        Operator::MemorySize { mem } => {
+            let memory = &module.memories[MemoryIndex::from_u32(*mem)];
+            let size = match *mem {
+                // Return total Miden memory size
+                0 => builder.ins().mem_size(span),
+                _ => builder.ins().u32(memory.minimum as u32, span),
+            };
+            match memory.memory64 {
+                true => state.push1(builder.ins().zext(size, I64, span)),
+                false => state.push1(size),
+            }
         }
         /******************************* Bulk memory operations *********************************/
         Operator::MemoryCopy { dst_mem, src_mem } => {
             // See semantics at https://github.com/WebAssembly/bulk-memory-operations/blob/master/proposals/bulk-memory-operations/Overview.md#memorycopy-instruction
-            if *src_mem == 0 && src_mem == dst_mem {
-                let count_i32 = state.pop1();
-                let src_i32 = state.pop1();
-                let dst_i32 = state.pop1();
-                let count = builder.ins().bitcast(count_i32, Type::U32, span);
-                let dst = prepare_addr(dst_i32, &U8, None, builder, span);
-                let src = prepare_addr(src_i32, &U8, None, builder, span);
-                builder.ins().memcpy(src, dst, count, span);
-            } else {
-                unsupported_diag!(diagnostics, "MemoryCopy: only single memory is supported");
-            }
+            let count = state.pop1();
+            let src_i32 = state.pop1();
+            let dst_i32 = state.pop1();
+            let count = prepare_len(count, builder, span);
+            let dst = prepare_addr(dst_i32, &U8, *dst_mem, None, builder, span);
+            let src = prepare_addr(src_i32, &U8, *src_mem, None, builder, span);
+            builder.ins().memcpy(src, dst, count, span);
         }
         Operator::MemoryFill { mem } => {
             // See semantics at https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-fill
-            if *mem != 0 {
-                unsupported_diag!(diagnostics, "MemoryFill: only single memory is supported");
-            }
             let num_bytes = state.pop1();
             let value = state.pop1();
             let dst_i32 = state.pop1();
             let value = builder.ins().trunc(value, Type::U8, span);
-            let num_bytes = builder.ins().bitcast(num_bytes, Type::U32, span);
-            let dst = prepare_addr(dst_i32, &U8, None, builder, span);
+            let num_bytes = prepare_len(num_bytes, builder, span);
+            let dst = prepare_addr(dst_i32, &U8, *mem, None, builder, span);
             builder.ins().memset(dst, num_bytes, value, span);
         }
+        Operator::MemoryInit { data_index, mem } => {
+            // See semantics at https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-init
+            let data_index = DataIndex::from_u32(*data_index);
+            let count = state.pop1_bitcasted(U32, builder, span);
+            let src_offset = state.pop1_bitcasted(U32, builder, span);
+            let dst_i32 = state.pop1();
+            // A dropped segment is treated the same as an empty segment. Active segments are
+            // dropped once they are copied into memory during instantiation.
+            let (segment_start, segment_len) = match module.passive_data_map.get(&data_index) {
+                Some(segment) => {
+                    let name = module.data_dropped_name(data_index);
+                    let dropped = builder.ins().load_symbol(name.as_str(), I32, span);
+                    let is_dropped = builder.ins().neq_imm(dropped, Immediate::I32(0), span);
+                    let empty = builder.ins().u32(0, span);
+                    let len = builder.ins().u32(segment.len() as u32, span);
+                    (segment.start, builder.ins().select(is_dropped, empty, len, span))
+                }
+                None => (0, builder.ins().u32(0, span)),
+            };
+            // Trap if the range to copy is not entirely within the segment
+            let src_end = builder.ins().add_checked(src_offset, count, span);
+            let in_bounds = builder.ins().lte(src_end, segment_len, span);
+            builder.ins().assert(in_bounds, span);
+            let src_addr =
+                builder.ins().add_imm_checked(src_offset, Immediate::U32(segment_start), span);
+            let dst = prepare_addr(dst_i32, &U8, *mem, None, builder, span);
+            let src = prepare_addr(src_addr, &U8, 0, None, builder, span);
+            builder.ins().memcpy(src, dst, count, span);
+        }
+        Operator::DataDrop { data_index } => {
+            // Active segments are already dropped, so only passive segments need to be recorded
+            let data_index = DataIndex::from_u32(*data_index);
+            if module.passive_data_map.contains_key(&data_index) {
+                let name = module.data_dropped_name(data_index);
+                let ptr = builder.ins().symbol_addr(name.as_str(), Ptr(I32.into()), span);
+                let dropped = builder.ins().i32(1, span);
+                builder.ins().store(ptr, dropped, span);
+            }
+        }
         /******************************* Load instructions ***********************************/
         Operator::I32Load8U { memarg } => {
             translate_load_zext(U8, I32, memarg, state, builder, span)
@@ -541,6 +732,158 @@ pub fn translate_operator(
             let val = builder.ins().neq(arg0, arg1, span);
             state.push1(builder.ins().zext(val, I32, span));
         }
+        /************************** Soft-float f64 Operators *********************************
+         *  When enabled, 64-bit floating point operations are lowered to calls into the
+         *  `intrinsics::f64` module, which implements them in software. Floats are represented
+         *  by their raw bits, so loads, stores and reinterpretations are simply 64-bit moves.
+         ***********************************************************************************/
+        Operator::F64Load { memarg } if config.soft_float => {
+            translate_load(F64, memarg, state, builder, span)
+        }
+        Operator::F64Store { memarg } if config.soft_float => {
+            translate_store(F64, memarg, state, builder, span)
+        }
+        Operator::F64Const { value } if config.soft_float => {
+            state.push1(builder.ins().f64(f64::from_bits(value.bits()), span));
+        }
+        Operator::F64ReinterpretI64 if config.soft_float => {
+            let val = state.pop1();
+            state.push1(builder.ins().bitcast(val, F64, span));
+        }
+        Operator::I64ReinterpretF64 if config.soft_float => {
+            let val = state.pop1();
+            state.push1(builder.ins().bitcast(val, I64, span));
+        }
+        Operator::F64Neg | Operator::F64Abs if config.soft_float => {
+            let function = match op {
+                Operator::F64Neg => intrinsics::f64::NEG,
+                _ => intrinsics::f64::ABS,
+            };
+            let arg = state.pop1();
+            state.push1(intrinsics::f64::call(function, &[arg], builder, span));
+        }
+        Operator::F64Add
+        | Operator::F64Sub
+        | Operator::F64Mul
+        | Operator::F64Div
+        | Operator::F64Min
+        | Operator::F64Max
+        | Operator::F64Copysign
+            if config.soft_float =>
+        {
+            let function = match op {
+                Operator::F64Add => intrinsics::f64::ADD,
+                Operator::F64Sub => intrinsics::f64::SUB,
+                Operator::F64Mul => intrinsics::f64::MUL,
+                Operator::F64Div => intrinsics::f64::DIV,
+                Operator::F64Min => intrinsics::f64::MIN,
+                Operator::F64Max => intrinsics::f64::MAX,
+                _ => intrinsics::f64::COPYSIGN,
+            };
+            let (arg1, arg2) = state.pop2();
+            state.push1(intrinsics::f64::call(function, &[arg2, arg1], builder, span));
+        }
+        Operator::F64Eq
+        | Operator::F64Ne
+        | Operator::F64Lt
+        | Operator::F64Gt
+        | Operator::F64Le
+        | Operator::F64Ge
+            if config.soft_float =>
+        {
+            let function = match op {
+                Operator::F64Eq => intrinsics::f64::EQ,
+                Operator::F64Ne => intrinsics::f64::NE,
+                Operator::F64Lt => intrinsics::f64::LT,
+                Operator::F64Gt => intrinsics::f64::GT,
+                Operator::F64Le => intrinsics::f64::LE,
+                _ => intrinsics::f64::GE,
+            };
+            let (arg0, arg1) = state.pop2();
+            let val = intrinsics::f64::call(function, &[arg1, arg0], builder, span);
+            state.push1(builder.ins().zext(val, I32, span));
+        }
+        Operator::F64ConvertI32S if config.soft_float => {
+            let arg = state.pop1();
+            state.push1(intrinsics::f64::call(intrinsics::f64::FROM_I32, &[arg], builder, span));
+        }
+        Operator::F64ConvertI32U if config.soft_float => {
+            let arg = state.pop1();
+            let arg = builder.ins().bitcast(arg, U32, span);
+            state.push1(intrinsics::f64::call(intrinsics::f64::FROM_U32, &[arg], builder, span));
+        }
+        Operator::F64ConvertI64S if config.soft_float => {
+            let arg = state.pop1();
+            state.push1(intrinsics::f64::call(intrinsics::f64::FROM_I64, &[arg], builder, span));
+        }
+        Operator::F64ConvertI64U if config.soft_float => {
+            let arg = state.pop1();
+            let arg = builder.ins().bitcast(arg, U64, span);
+            state.push1(intrinsics::f64::call(intrinsics::f64::FROM_U64, &[arg], builder, span));
+        }
+        Operator::I32TruncF64S | Operator::I32TruncSatF64S if config.soft_float => {
+            let function = match op {
+                Operator::I32TruncF64S => intrinsics::f64::TO_I32,
+                _ => intrinsics::f64::TO_I32_SAT,
+            };
+            let arg = state.pop1();
+            state.push1(intrinsics::f64::call(function, &[arg], builder, span));
+        }
+        Operator::I64TruncF64S | Operator::I64TruncSatF64S if config.soft_float => {
+            let function = match op {
+                Operator::I64TruncF64S => intrinsics::f64::TO_I64,
+                _ => intrinsics::f64::TO_I64_SAT,
+            };
+            let arg = state.pop1();
+            state.push1(intrinsics::f64::call(function, &[arg], builder, span));
+        }
+        Operator::I32TruncF64U | Operator::I32TruncSatF64U if config.soft_float => {
+            let function = match op {
+                Operator::I32TruncF64U => intrinsics::f64::TO_U32,
+                _ => intrinsics::f64::TO_U32_SAT,
+            };
+            let arg = state.pop1();
+            let val = intrinsics::f64::call(function, &[arg], builder, span);
+            state.push1(builder.ins().bitcast(val, I32, span));
+        }
+        Operator::I64TruncF64U | Operator::I64TruncSatF64U if config.soft_float => {
+            let function = match op {
+                Operator::I64TruncF64U => intrinsics::f64::TO_U64,
+                _ => intrinsics::f64::TO_U64_SAT,
+            };
+            let arg = state.pop1();
+            let val = intrinsics::f64::call(function, &[arg], builder, span);
+            state.push1(builder.ins().bitcast(val, I64, span));
+        }
+        Operator::F64Ceil | Operator::F64Floor | Operator::F64Trunc | Operator::F64Nearest
+            if config.soft_float =>
+        {
+            let function = match op {
+                Operator::F64Ceil => intrinsics::f64::CEIL,
+                Operator::F64Floor => intrinsics::f64::FLOOR,
+                Operator::F64Trunc => intrinsics::f64::TRUNC,
+                _ => intrinsics::f64::NEAREST,
+            };
+            let arg = state.pop1();
+            state.push1(intrinsics::f64::call(function, &[arg], builder, span));
+        }
+        // `sqrt` has no software implementation yet, and 32-bit floats are represented as field
+        // elements, so there is no meaningful conversion between them and 64-bit floats. These
+        // trap when executed, so that code which never executes them, e.g. the float formatting
+        // of `core::fmt`, can still be compiled.
+        Operator::F64Sqrt | Operator::F64PromoteF32 | Operator::F32DemoteF64
+            if config.soft_float =>
+        {
+            state.pop1();
+            let supported = builder.ins().i1(false, span);
+            builder.ins().assert_with_error(
+                supported,
+                midenc_hir::ASSERT_FAILED_UNSUPPORTED_F64,
+                span,
+            );
+            builder.ins().unreachable(span);
+            state.reachable = false;
+        }
         op => {
             unsupported_diag!(diagnostics, "Wasm op {:?} is not supported", op);
         }
@@ -556,7 +899,7 @@ fn translate_load(
     span: SourceSpan,
 ) {
     let addr_int = state.pop1();
-    let addr = prepare_addr(addr_int, &ptr_ty, Some(memarg), builder, span);
+    let addr = prepare_addr(addr_int, &ptr_ty, memarg.memory, Some(memarg), builder, span);
     state.push1(builder.ins().load(addr, span));
 }
 
@@ -569,7 +912,7 @@ fn translate_load_sext(
     span: SourceSpan,
 ) {
     let addr_int = state.pop1();
-    let addr = prepare_addr(addr_int, &ptr_ty, Some(memarg), builder, span);
+    let addr = prepare_addr(addr_int, &ptr_ty, memarg.memory, Some(memarg), builder, span);
     let val = builder.ins().load(addr, span);
     let sext_val = builder.ins().sext(val, sext_ty, span);
     state.push1(sext_val);
@@ -585,7 +928,7 @@ fn translate_load_zext(
 ) {
     assert!(ptr_ty.is_unsigned_integer());
     let addr_int = state.pop1();
-    let addr = prepare_addr(addr_int, &ptr_ty, Some(memarg), builder, span);
+    let addr = prepare_addr(addr_int, &ptr_ty, memarg.memory, Some(memarg), builder, span);
     let val = builder.ins().load(addr, span);
     let sext_val = builder.ins().zext(val, zext_ty, span);
     state.push1(sext_val);
@@ -613,52 +956,83 @@ fn translate_store(
     } else {
         val
     };
-    let addr = prepare_addr(addr_int, &ptr_ty, Some(memarg), builder, span);
+    let addr = prepare_addr(addr_int, &ptr_ty, memarg.memory, Some(memarg), builder, span);
     builder.ins().store(addr, arg, span);
 }
 
+/// Converts the length operand of a bulk memory operation to `u32`.
+///
+/// In 64-bit memories, lengths are `i64`, and trap if they do not fit in 32 bits.
+fn prepare_len(len: Value, builder: &mut FunctionBuilderExt, span: SourceSpan) -> Value {
+    let len_ty = builder.data_flow_graph().value_type(len).clone();
+    match len_ty {
+        I64 => builder.ins().cast(len, U32, span),
+        _ => builder.ins().bitcast(len, U32, span),
+    }
+}
+
+/// Returns the type of a pointer to `pointee` in the linear memory with index `memory`.
+///
+/// Memory 0 is the IR's default byte-addressable address space, while each additional memory is
+/// placed in a separate, statically allocated, address space.
+fn memory_ptr_type(pointee: &Type, memory: u32) -> Type {
+    let pointee = Box::new(pointee.clone());
+    match u16::try_from(memory).ok().and_then(NonZeroU16::new) {
+        None => Type::Ptr(pointee),
+        Some(id) => Type::NativePtr(pointee, AddressSpace::Id(id)),
+    }
+}
+
 fn prepare_addr(
     addr_int: Value,
     ptr_ty: &Type,
+    memory: u32,
     memarg: Option<&MemArg>,
     builder: &mut FunctionBuilderExt,
     span: SourceSpan,
 ) -> Value {
+    let offset = memarg.map(|memarg| memarg.offset).unwrap_or(0);
     let addr_int_ty = builder.data_flow_graph().value_type(addr_int);
-    let addr_u32 = if addr_int_ty == &U32 {
-        addr_int
-    } else if addr_int_ty == &I32 {
-        builder.ins().bitcast(addr_int, U32, span)
-    } else if matches!(addr_int_ty, Ptr(_)) {
-        builder.ins().ptrtoint(addr_int, U32, span)
+    let full_addr_int = if addr_int_ty == &I64 {
+        // Addresses in 64-bit memories are truncated to 32 bits, trapping if the address,
+        // including the static offset, does not fit
+        let addr_u64 = builder.ins().bitcast(addr_int, U64, span);
+        let addr_u64 = if offset != 0 {
+            builder.ins().add_imm_checked(addr_u64, Immediate::U64(offset), span)
+        } else {
+            addr_u64
+        };
+        builder.ins().cast(addr_u64, U32, span)
     } else {
-        panic!("unexpected type used as pointer value: {addr_int_ty}");
-    };
-    let mut full_addr_int = addr_u32;
-    if let Some(memarg) = memarg {
-        if memarg.offset != 0 {
-            full_addr_int =
-                builder
-                    .ins()
-                    .add_imm_checked(addr_u32, Immediate::U32(memarg.offset as u32), span);
-        }
-        // TODO(pauls): For now, asserting alignment helps us catch mistakes/bugs, but we should
-        // probably make this something that can be disabled to avoid the overhead in release builds
-        if memarg.align > 0 {
-            // Generate alignment assertion - aligned addresses should always produce 0 here
-            let align_offset = builder.ins().mod_imm_unchecked(
-                full_addr_int,
-                Immediate::U32(2u32.pow(memarg.align as u32)),
-                span,
-            );
-            builder.ins().assertz_with_error(
-                align_offset,
-                midenc_hir::ASSERT_FAILED_ALIGNMENT,
-                span,
-            );
+        let addr_u32 = if addr_int_ty == &U32 {
+            addr_int
+        } else if addr_int_ty == &I32 {
+            builder.ins().bitcast(addr_int, U32, span)
+        } else if matches!(addr_int_ty, Ptr(_)) {
+            builder.ins().ptrtoint(addr_int, U32, span)
+        } else {
+            panic!("unexpected type used as pointer value: {addr_int_ty}");
+        };
+        if offset != 0 {
+            builder.ins().add_imm_checked(addr_u32, Immediate::U32(offset as u32), span)
+        } else {
+            addr_u32
         }
     };
-    builder.ins().inttoptr(full_addr_int, Type::Ptr(ptr_ty.clone().into()), span)
+    // TODO(pauls): For now, asserting alignment helps us catch mistakes/bugs, but we should
+    // probably make this something that can be disabled to avoid the overhead in release builds
+    if let Some(memarg) = memarg.filter(|memarg| memarg.align > 0) {
+        // Generate alignment assertion - aligned addresses should always produce 0 here
+        let align_offset = builder.ins().mod_imm_unchecked(
+            full_addr_int,
+            Immediate::U32(2u32.pow(memarg.align as u32)),
+            span,
+        );
+        builder
+            .ins()
+            .assertz_with_error(align_offset, midenc_hir::ASSERT_FAILED_ALIGNMENT, span);
+    }
+    builder.ins().inttoptr(full_addr_int, memory_ptr_type(ptr_ty, memory), span)
 }
 
 fn translate_call(
@@ -710,30 +1084,81 @@ fn translate_call(
     Ok(())
 }
 
-fn translate_return(
-    state: &mut FuncTranslationState,
+fn translate_return_call(
+    func_state: &mut FuncTranslationState,
+    module_state: &mut ModuleTranslationState,
     builder: &mut FunctionBuilderExt,
+    function_index: FuncIndex,
+    span: SourceSpan,
     diagnostics: &DiagnosticsHandler,
+) -> WasmResult<()> {
+    let func_id =
+        module_state.get_direct_func(builder.data_flow_graph_mut(), function_index, diagnostics)?;
+    let module_name = func_id.module.as_symbol();
+    if is_miden_intrinsics_module(module_name) || is_miden_abi_module(module_name) {
+        // Calls to the Miden SDK are lowered to inline code, or adapted to the Miden ABI, so the
+        // results are returned as usual
+        translate_call(func_state, module_state, builder, function_index, span, diagnostics)?;
+        translate_return(func_state, builder, span);
+        return Ok(());
+    }
+    let num_wasm_args = module_state.signature(function_index).params().len();
+    let args = func_state.peekn(num_wasm_args);
+    builder.ins().tail_exec(func_id, args, span);
+    func_state.popn(num_wasm_args);
+    func_state.reachable = false;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn translate_call_indirect(
+    func_state: &mut FuncTranslationState,
+    module_state: &mut ModuleTranslationState,
+    builder: &mut FunctionBuilderExt,
+    module: &Module,
+    mod_types: &ModuleTypes,
+    type_index: TypeIndex,
+    table_index: TableIndex,
     span: SourceSpan,
+    config: &WasmTranslationConfig,
+    diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<()> {
+    // The call is made through the function dispatching all of the indirect calls with this
+    // signature through this table, passing the index of the callee after the args
+    let sig_index = module.types[type_index].unwrap_function();
+    let dispatcher = module_state.get_dispatcher(
+        builder.data_flow_graph_mut(),
+        module,
+        mod_types,
+        table_index,
+        sig_index,
+        config,
+        diagnostics,
+    )?;
+    let callee = func_state.pop1_bitcasted(U32, builder, span);
+    let num_wasm_args = mod_types[sig_index].params().len();
+    let mut args = func_state.peekn(num_wasm_args).to_vec();
+    args.push(callee);
+    let call = builder.ins().exec(dispatcher, &args, span);
+    let results = builder.inst_results(call);
+    func_state.popn(num_wasm_args);
+    func_state.pushn(results);
+    Ok(())
+}
+
+fn translate_return(
+    state: &mut FuncTranslationState,
+    builder: &mut FunctionBuilderExt,
+    span: SourceSpan,
+) {
     let return_count = {
         let frame = &mut state.control_stack[0];
         frame.num_return_values()
     };
-    {
-        let return_args = match return_count {
-            0 => None,
-            1 => Some(*state.peekn_mut(return_count).first().unwrap()),
-            _ => {
-                unsupported_diag!(diagnostics, "Multiple values are not supported");
-            }
-        };
-
-        builder.ins().ret(return_args, span);
-    }
+    let return_args = state.peekn(return_count);
+    builder.ins().ret_values(return_args, span);
     state.popn(return_count);
     state.reachable = false;
-    Ok(())
 }
 
 fn translate_br(
@@ -863,15 +1288,37 @@ fn translate_br_table(
     Ok(())
 }
 
+fn translate_throw(
+    tag_index: u32,
+    builder: &mut FunctionBuilderExt,
+    state: &mut FuncTranslationState,
+    module: &Module,
+    mod_types: &ModuleTypes,
+    span: SourceSpan,
+) {
+    // The values carried by the exception are discarded, the failed assertion only identifies the
+    // tag of the exception which was thrown
+    let tag = module.tags[TagIndex::from_u32(tag_index)];
+    let num_params = mod_types[module.types[tag.ty].unwrap_function()].params().len();
+    state.popn(num_params);
+    let thrown = builder.ins().i1(false, span);
+    builder
+        .ins()
+        .assert_with_error(thrown, midenc_hir::ASSERT_FAILED_EXCEPTION + tag_index, span);
+    builder.ins().unreachable(span);
+    state.reachable = false;
+}
+
 fn translate_block(
     blockty: &wasmparser::BlockType,
     builder: &mut FunctionBuilderExt,
     state: &mut FuncTranslationState,
     mod_types: &ModuleTypes,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
     span: SourceSpan,
 ) -> WasmResult<()> {
-    let blockty = BlockType::from_wasm(blockty, mod_types, diagnostics)?;
+    let blockty = BlockType::from_wasm(blockty, mod_types, config, diagnostics)?;
     let next = builder.create_block_with_params(blockty.results.clone(), span);
     state.push_block(next, blockty.params.len(), blockty.results.len());
     Ok(())
@@ -988,10 +1435,11 @@ fn translate_if(
     state: &mut FuncTranslationState,
     builder: &mut FunctionBuilderExt,
     mod_types: &ModuleTypes,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
     span: SourceSpan,
 ) -> WasmResult<()> {
-    let blockty = BlockType::from_wasm(blockty, mod_types, diagnostics)?;
+    let blockty = BlockType::from_wasm(blockty, mod_types, config, diagnostics)?;
     let cond = state.pop1();
     // cond is expected to be a i32 value
     let cond_i1 = builder.ins().neq_imm(cond, Immediate::I32(0), span);
@@ -1046,10 +1494,11 @@ fn translate_loop(
     builder: &mut FunctionBuilderExt,
     state: &mut FuncTranslationState,
     mod_types: &ModuleTypes,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
     span: SourceSpan,
 ) -> WasmResult<()> {
-    let blockty = BlockType::from_wasm(blockty, mod_types, diagnostics)?;
+    let blockty = BlockType::from_wasm(blockty, mod_types, config, diagnostics)?;
     let loop_body = builder.create_block_with_params(blockty.params.clone(), span);
     let next = builder.create_block_with_params(blockty.results.clone(), span);
     builder.ins().br(loop_body, state.peekn(blockty.params.len()), span);
@@ -1068,6 +1517,7 @@ fn translate_unreachable_operator(
     builder: &mut FunctionBuilderExt,
     state: &mut FuncTranslationState,
     mod_types: &ModuleTypes,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
     span: SourceSpan,
 ) -> WasmResult<()> {
@@ -1076,7 +1526,7 @@ fn translate_unreachable_operator(
         Operator::If { blockty } => {
             // Push a placeholder control stack entry. The if isn't reachable,
             // so we don't have any branches anywhere.
-            let blockty = BlockType::from_wasm(&blockty, mod_types, diagnostics)?;
+            let blockty = BlockType::from_wasm(&blockty, mod_types, config, diagnostics)?;
             state.push_if(
                 Block::reserved_value(),
                 ElseData::NoElse {
@@ -1088,7 +1538,10 @@ fn translate_unreachable_operator(
                 blockty,
             );
         }
-        Operator::Loop { blockty: _ } | Operator::Block { blockty: _ } => {
+        Operator::Loop { blockty: _ }
+        | Operator::Block { blockty: _ }
+        | Operator::TryTable { .. }
+        | Operator::Try { .. } => {
             state.push_block(Block::reserved_value(), 0, 0);
         }
         Operator::Else => {
@@ -1145,7 +1598,7 @@ fn translate_unreachable_operator(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
+        Operator::End | Operator::Delegate { .. } => {
             let stack = &mut state.stack;
             let control_stack = &mut state.control_stack;
             let frame = control_stack.pop().unwrap();
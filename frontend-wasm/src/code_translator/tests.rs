@@ -8,8 +8,15 @@ use crate::{test_utils::test_context, translate, WasmTranslationConfig};
 /// Check IR generated for a Wasm op(s).
 /// Wrap Wasm ops in a function and check the IR generated for the entry block of that function.
 fn check_op(wat_op: &str, expected_ir: expect_test::Expect) {
-    let context = test_context();
+    check_op_with_config(wat_op, &WasmTranslationConfig::default(), expected_ir)
+}
 
+/// Like [check_op], but translates the Wasm using the given configuration
+fn check_op_with_config(
+    wat_op: &str,
+    config: &WasmTranslationConfig,
+    expected_ir: expect_test::Expect,
+) {
     let wat = format!(
         r#"
         (module
@@ -19,10 +26,20 @@ fn check_op(wat_op: &str, expected_ir: expect_test::Expect) {
             )
         )"#,
     );
+    check_module_with_config(&wat, config, expected_ir)
+}
+
+/// Like [check_op_with_config], but translates the given Wasm module, which is expected to define
+/// the `test_wrapper` function
+fn check_module_with_config(
+    wat: &str,
+    config: &WasmTranslationConfig,
+    expected_ir: expect_test::Expect,
+) {
+    let context = test_context();
+
     let wasm = wat::parse_str(wat).unwrap();
-    let module = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
-        .unwrap()
-        .unwrap_one_module();
+    let module = translate(&wasm, config, &context.session).unwrap().unwrap_one_module();
     let func = module.function(Ident::from("test_wrapper")).unwrap();
     // let fref = module.get_funcref_by_name("test_wrapper").unwrap();
     // let func = module.get_function(fref).unwrap();
@@ -308,6 +325,56 @@ fn i32_load() {
     )
 }
 
+#[test]
+fn i32_load_multi_memory() {
+    check_module_with_config(
+        r#"
+        (module
+            (memory $heap 16384)
+            (memory $sdk 1)
+            (func $test_wrapper
+                i32.const 1024
+                i32.load $sdk
+                drop
+            )
+        )"#,
+        &WasmTranslationConfig::default(),
+        expect![[r#"
+            (let (v0 i32) (const.i32 1024))
+            (let (v1 u32) (bitcast v0))
+            (let (v2 u32) (mod.unchecked v1 4))
+            (assertz 250 v2)
+            (let (v3 (ptr (addrspace 1) i32)) (inttoptr v1))
+            (let (v4 i32) (load v3))
+        "#]],
+    )
+}
+
+#[test]
+fn i32_load_memory64() {
+    check_module_with_config(
+        r#"
+        (module
+            (memory i64 16384)
+            (func $test_wrapper
+                i64.const 1024
+                i32.load
+                drop
+            )
+        )"#,
+        &WasmTranslationConfig::default(),
+        expect![[r#"
+            (let (v0 i64) (const.i64 1024))
+            (let (v1 u64) (bitcast v0))
+            (let (v2 u32) (cast v1))
+            (let (v3 u32) (mod.unchecked v2 4))
+            (assertz 250 v3)
+            (let (v4 (ptr i32)) (inttoptr v2))
+            (let (v5 i32) (load v4))
+        "#]],
+    )
+}
+
 #[test]
 fn i64_load() {
     check_op(
@@ -1547,3 +1614,430 @@ fn select_i32() {
         "#]],
     )
 }
+
+#[test]
+fn f64_add_soft_float() {
+    check_op_with_config(
+        r#"
+            f64.const 1.5
+            f64.const 2.5
+            f64.add
+            drop
+        "#,
+        &WasmTranslationConfig {
+            soft_float: true,
+            ..Default::default()
+        },
+        expect![[r#"
+            (let (v0 f64) (const.f64 1.5))
+            (let (v1 f64) (const.f64 2.5))
+            (let (v2 f64) (exec (#intrinsics::f64 #add) v1 v0))
+        "#]],
+    )
+}
+
+#[test]
+fn f64_lt_soft_float() {
+    check_op_with_config(
+        r#"
+            f64.const 1.5
+            f64.const 2.5
+            f64.lt
+            drop
+        "#,
+        &WasmTranslationConfig {
+            soft_float: true,
+            ..Default::default()
+        },
+        expect![[r#"
+            (let (v0 f64) (const.f64 1.5))
+            (let (v1 f64) (const.f64 2.5))
+            (let (v2 i1) (exec (#intrinsics::f64 #lt) v1 v0))
+            (let (v3 i32) (zext v2))
+        "#]],
+    )
+}
+
+#[test]
+fn i32_trunc_sat_f64_u_soft_float() {
+    check_op_with_config(
+        r#"
+            f64.const 1.5
+            i32.trunc_sat_f64_u
+            drop
+        "#,
+        &WasmTranslationConfig {
+            soft_float: true,
+            ..Default::default()
+        },
+        expect![[r#"
+            (let (v0 f64) (const.f64 1.5))
+            (let (v1 u32) (exec (#intrinsics::f64 #to_u32_sat) v0))
+            (let (v2 i32) (bitcast v1))
+        "#]],
+    )
+}
+
+#[test]
+fn f64_floor_soft_float() {
+    check_op_with_config(
+        r#"
+            f64.const -1.5
+            f64.floor
+            drop
+        "#,
+        &WasmTranslationConfig {
+            soft_float: true,
+            ..Default::default()
+        },
+        expect![[r#"
+            (let (v0 f64) (const.f64 -1.5))
+            (let (v1 f64) (exec (#intrinsics::f64 #floor) v0))
+        "#]],
+    )
+}
+
+#[test]
+fn unimplemented_soft_float_ops_trap() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (func $sqrt (param f64) (result f64)
+                local.get 0
+                f64.sqrt
+            )
+            (func $promote (param f32) (result f64)
+                local.get 0
+                f64.promote_f32
+            )
+            (func $demote (param f64) (result f32)
+                local.get 0
+                f32.demote_f64
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let config = WasmTranslationConfig {
+        soft_float: true,
+        ..Default::default()
+    };
+    let module = translate(&wasm, &config, &context.session).unwrap().unwrap_one_module();
+    // The module compiles, but each of these operations traps with a dedicated error code
+    for name in ["sqrt", "promote", "demote"] {
+        let func = module.function(Ident::from(name)).unwrap();
+        let insts = func.dfg.block_insts(func.dfg.entry_block()).collect::<Vec<_>>();
+        let assert = insts.iter().position(|inst| match func.dfg.inst(*inst) {
+            midenc_hir::Instruction::PrimOpImm(op) => {
+                op.op == midenc_hir::Opcode::Assert
+                    && op.imm
+                        == midenc_hir::Immediate::U32(midenc_hir::ASSERT_FAILED_UNSUPPORTED_F64)
+            }
+            _ => false,
+        });
+        assert!(assert.is_some(), "expected a failed assertion in {name}");
+        assert_eq!(
+            func.dfg.inst(insts[assert.unwrap() + 1]).opcode(),
+            midenc_hir::Opcode::Unreachable
+        );
+    }
+}
+
+#[test]
+fn f64_type_requires_soft_float() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (func $test_wrapper (param f64) (result f64)
+                local.get 0
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let Err(err) = translate(&wasm, &WasmTranslationConfig::default(), &context.session) else {
+        panic!("expected f64 to be rejected when soft-float is disabled");
+    };
+    assert_eq!(err.to_string(), "wasm error: unsupported type 'f64'");
+
+    let config = WasmTranslationConfig {
+        soft_float: true,
+        ..Default::default()
+    };
+    let module = translate(&wasm, &config, &context.session).unwrap().unwrap_one_module();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    assert_eq!(func.signature.params()[0].ty, midenc_hir::Type::F64);
+}
+
+#[test]
+fn call_indirect() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (type $i32_to_i32 (func (param i32) (result i32)))
+            (table 4 4 funcref)
+            (elem (i32.const 1) func $inc $id64)
+            (func $inc (param i32) (result i32)
+                local.get 0
+                i32.const 1
+                i32.add
+            )
+            (func $id64 (param i64) (result i64)
+                local.get 0
+            )
+            (func $test_wrapper (param i32) (result i32)
+                i32.const 7
+                local.get 0
+                call_indirect (type $i32_to_i32)
+                local.get 0
+                call_indirect (type $i32_to_i32)
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let module = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
+        .unwrap()
+        .unwrap_one_module();
+
+    // Both indirect calls go through the same dispatcher, passing the callee index last
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let calls = func
+        .dfg
+        .block_insts(func.dfg.entry_block())
+        .filter_map(|inst| match func.dfg.inst(inst) {
+            midenc_hir::Instruction::Call(call) => Some(call),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].callee, calls[1].callee);
+    assert_eq!(calls[0].args.len(&func.dfg.value_lists), 2);
+    let dispatcher = module.function(calls[0].callee.function).expect("expected a dispatcher");
+    assert_eq!(dispatcher.signature.params().len(), 2);
+    assert_eq!(dispatcher.signature.params()[1].ty, midenc_hir::Type::U32);
+    assert!(!dispatcher.is_public());
+
+    let call = dispatcher
+        .dfg
+        .block_insts(dispatcher.dfg.entry_block())
+        .find_map(|inst| match dispatcher.dfg.inst(inst) {
+            midenc_hir::Instruction::CallIndirect(call) => Some(call),
+            _ => None,
+        })
+        .expect("expected an indirect call");
+    // Only `$inc` matches the expected signature, all other entries trap when called
+    let table = call
+        .table
+        .iter()
+        .map(|entry| entry.map(|f| f.function.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(table, [None, Some("inc"), None, None]);
+    assert_eq!(call.args.len(&dispatcher.dfg.value_lists), 2);
+}
+
+#[test]
+fn return_call() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (func $inc (param i32) (result i32)
+                local.get 0
+                i32.const 1
+                i32.add
+            )
+            (func $test_wrapper (param i32) (result i32)
+                local.get 0
+                return_call $inc
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let module = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
+        .unwrap()
+        .unwrap_one_module();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    // The call terminates the entry block, and its results are those of the function
+    let terminator = func.dfg.last_inst(func.dfg.entry_block()).unwrap();
+    match func.dfg.inst(terminator) {
+        midenc_hir::Instruction::Call(call) => {
+            assert_eq!(call.op, midenc_hir::Opcode::TailExec);
+            assert_eq!(call.callee.function.as_str(), "inc");
+            assert_eq!(call.args.len(&func.dfg.value_lists), 1);
+        }
+        ix => panic!("expected a tail call, got {ix:?}"),
+    }
+    assert!(func.dfg.inst_results(terminator).is_empty());
+}
+
+#[test]
+fn multi_value() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (func $swap (param i32 i32) (result i32 i32)
+                local.get 1
+                local.get 0
+            )
+            (func $test_wrapper (param i32 i32) (result i32 i32)
+                (block (result i32 i32)
+                    local.get 0
+                    local.get 1
+                    call $swap
+                )
+                return
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let module = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
+        .unwrap()
+        .unwrap_one_module();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    // Both results of the call are returned, in the order given by the function signature
+    let ret = func
+        .dfg
+        .blocks()
+        .filter_map(|(block, _)| func.dfg.last_inst(block))
+        .find(|inst| func.dfg.inst(*inst).opcode() == midenc_hir::Opcode::Ret)
+        .expect("expected a return");
+    let call = func
+        .dfg
+        .blocks()
+        .flat_map(|(_, block)| block.insts())
+        .find(|inst| func.dfg.inst(*inst).opcode() == midenc_hir::Opcode::Exec)
+        .expect("expected a call");
+    assert_eq!(func.dfg.inst_results(call).len(), 2);
+    assert_eq!(func.dfg.inst_args(ret).len(), 2);
+}
+
+#[test]
+fn memory_init() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (memory (;0;) 1)
+            (data $hello "hello")
+            (func $test_wrapper (param i32)
+                local.get 0
+                i32.const 1
+                i32.const 3
+                memory.init $hello
+                data.drop $hello
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let module = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
+        .unwrap()
+        .unwrap_one_module();
+    // The passive segment is placed beyond the end of the heap, out of reach of linear memory
+    let segment = module.segments().iter().next().expect("expected a data segment");
+    assert_eq!(segment.offset(), midenc_hir::DataSegmentTable::HEAP_END);
+    assert_eq!(segment.init().as_slice(), b"hello");
+    assert!(segment.is_readonly());
+    assert_eq!(module.segments().next_available_offset(), 0);
+    // Whether the segment has been dropped is tracked by a global variable
+    assert!(module.globals().find(Ident::from("data0.dropped")).is_some());
+    // The copy is bounds checked against the segment, which is empty once dropped, before it is
+    // performed, and the segment is marked as dropped afterwards
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    let opcodes = func
+        .dfg
+        .block_insts(func.dfg.entry_block())
+        .map(|inst| func.dfg.inst(inst).opcode())
+        .collect::<Vec<_>>();
+    let position = |opcode| opcodes.iter().position(|op| *op == opcode);
+    let load_dropped = position(midenc_hir::Opcode::GlobalValue);
+    let bounds_check = position(midenc_hir::Opcode::Assert);
+    let memcpy = position(midenc_hir::Opcode::MemCpy);
+    let store_dropped = position(midenc_hir::Opcode::Store);
+    assert!(
+        load_dropped.is_some() && load_dropped < bounds_check,
+        "expected the bounds check to depend on whether the segment was dropped"
+    );
+    assert!(
+        bounds_check.is_some() && bounds_check < memcpy,
+        "expected a bounds check before the copy"
+    );
+    assert!(memcpy < store_dropped, "expected the segment to be marked as dropped");
+}
+
+#[test]
+fn ref_func() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (table 2 funcref)
+            (elem (i32.const 1) func $inc)
+            (func $inc (param i32) (result i32)
+                local.get 0
+            )
+            (func $test_wrapper (result i32 i32)
+                ref.func $inc
+                ref.null func
+                ref.is_null
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let module = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
+        .unwrap()
+        .unwrap_one_module();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    // A function reference is the index of the function in the table, and null is out of bounds
+    let constants = func
+        .dfg
+        .block_insts(func.dfg.entry_block())
+        .filter_map(|inst| match func.dfg.inst(inst) {
+            midenc_hir::Instruction::UnaryOpImm(op) => op.imm.as_i32(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(constants, [1, -1]);
+}
+
+#[test]
+fn throw() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (tag $e0)
+            (tag $e1 (param i32))
+            (func $test_wrapper (param i32) (result i32)
+                (block $handler (result i32)
+                    (try_table (catch $e1 $handler)
+                        local.get 0
+                        throw $e1
+                    )
+                    i32.const 0
+                )
+            )
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let module = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
+        .unwrap()
+        .unwrap_one_module();
+    let func = module.function(Ident::from("test_wrapper")).unwrap();
+    // Throwing an exception traps with an error code identifying its tag
+    let insts = func.dfg.block_insts(func.dfg.entry_block()).collect::<Vec<_>>();
+    let assert = insts.iter().position(|inst| match func.dfg.inst(*inst) {
+        midenc_hir::Instruction::PrimOpImm(op) => {
+            op.op == midenc_hir::Opcode::Assert
+                && op.imm == midenc_hir::Immediate::U32(midenc_hir::ASSERT_FAILED_EXCEPTION + 1)
+        }
+        _ => false,
+    });
+    assert!(assert.is_some(), "expected a failed assertion for tag 1");
+    assert_eq!(
+        func.dfg.inst(insts[assert.unwrap() + 1]).opcode(),
+        midenc_hir::Opcode::Unreachable
+    );
+}
+
+#[test]
+fn error_for_imported_table64() {
+    let context = test_context();
+    let wat = r#"
+        (module
+            (import "env" "table" (table i64 1 funcref))
+        )"#;
+    let wasm = wat::parse_str(wat).unwrap();
+    let err = translate(&wasm, &WasmTranslationConfig::default(), &context.session)
+        .err()
+        .expect("expected 64-bit tables to be rejected");
+    assert!(
+        err.to_string().contains("64-bit tables are not supported"),
+        "unexpected error: {err}"
+    );
+}
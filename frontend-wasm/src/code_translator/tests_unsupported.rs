@@ -3,6 +3,7 @@ use wasmparser::{MemArg, Operator, Operator::*};
 
 use super::translate_operator;
 use crate::{
+    config::WasmTranslationConfig,
     module::{
         func_translation_state::FuncTranslationState,
         function_builder_ext::{FunctionBuilderContext, FunctionBuilderExt},
@@ -12,7 +13,7 @@ use crate::{
     test_utils::test_context,
 };
 
-fn check_unsupported(op: &Operator) {
+fn check_unsupported(op: &Operator, config: &WasmTranslationConfig, expected: String) {
     let context = test_context();
     let mod_name = "noname";
     let module_info = Module::default();
@@ -28,8 +29,13 @@ fn check_unsupported(op: &Operator) {
     let mod_types = Default::default();
     let mut state = FuncTranslationState::new();
     let mut builder_ext = FunctionBuilderExt::new(&mut module_func_builder, &mut fb_ctx);
-    let mut module_state =
-        ModuleTranslationState::new(&module_info, &mod_types, vec![], &context.session.diagnostics);
+    let mut module_state = ModuleTranslationState::new(
+        &module_info,
+        &mod_types,
+        vec![],
+        config,
+        &context.session.diagnostics,
+    );
     let result = translate_operator(
         op,
         &mut builder_ext,
@@ -37,11 +43,12 @@ fn check_unsupported(op: &Operator) {
         &mut module_state,
         &module_info,
         &mod_types,
+        config,
         &context.session.diagnostics,
         SourceSpan::default(),
     );
     assert!(result.is_err(), "Expected unsupported op error for {:?}", op);
-    assert_eq!(result.unwrap_err().to_string(), format!("Wasm op {:?} is not supported", op));
+    assert_eq!(result.unwrap_err().to_string(), expected);
 }
 
 // Wasm Spec v1.0
@@ -150,7 +157,8 @@ const UNSUPPORTED_WASM_V1_OPS: &[Operator] = &[
 
 #[test]
 fn error_for_unsupported_wasm_v1_ops() {
+    let config = WasmTranslationConfig::default();
     for op in UNSUPPORTED_WASM_V1_OPS.iter() {
-        check_unsupported(op);
+        check_unsupported(op, &config, format!("Wasm op {:?} is not supported", op));
     }
 }
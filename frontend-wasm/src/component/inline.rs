@@ -905,6 +905,8 @@ impl<'a> Inliner<'a> {
                 EntityIndex::Table(i) => frame.tables[i].clone().into(),
                 EntityIndex::Global(i) => frame.globals[i].clone().into(),
                 EntityIndex::Memory(i) => frame.memories[i].clone().into(),
+                // Tags are rejected when parsing synthetic instances
+                EntityIndex::Tag(_) => unreachable!(),
             },
         }
     }
@@ -8,7 +8,7 @@ use std::{collections::HashMap, mem};
 use indexmap::IndexMap;
 use midenc_hir::{
     cranelift_entity::PrimaryMap,
-    diagnostics::{IntoDiagnostic, Severity},
+    diagnostics::{DiagnosticsHandler, IntoDiagnostic, Severity},
 };
 use midenc_session::Session;
 use rustc_hash::FxHashMap;
@@ -609,7 +609,7 @@ impl<'a, 'data> ComponentParser<'a, 'data> {
                     instantiate_module(index, &args)
                 }
                 wasmparser::Instance::FromExports(exports) => {
-                    instantiate_module_from_exports(&exports)
+                    instantiate_module_from_exports(&exports, &self.session.diagnostics)?
                 }
             };
             self.result.initializers.push(init);
@@ -692,7 +692,7 @@ impl<'a, 'data> ComponentParser<'a, 'data> {
                     name,
                 } => {
                     let instance = ModuleInstanceIndex::from_u32(instance_index);
-                    alias_module_instance_export(kind, instance, name)
+                    alias_module_instance_export(kind, instance, name, &self.session.diagnostics)?
                 }
             };
             self.result.initializers.push(init);
@@ -841,7 +841,8 @@ fn instantiate_module<'data>(
 /// module and their given names.
 fn instantiate_module_from_exports<'data>(
     exports: &[wasmparser::Export<'data>],
-) -> LocalInitializer<'data> {
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<LocalInitializer<'data>> {
     let mut map = HashMap::with_capacity_and_hasher(exports.len(), BuildFxHasher::default());
     for export in exports {
         let idx = match export.kind {
@@ -861,13 +862,15 @@ fn instantiate_module_from_exports<'data>(
                 let index = GlobalIndex::from_u32(export.index);
                 EntityIndex::Global(index)
             }
-
-            // doesn't get past validation
-            wasmparser::ExternalKind::Tag => unimplemented!("wasm exceptions"),
+            wasmparser::ExternalKind::Tag => unsupported_diag!(
+                diagnostics,
+                "exception tags cannot be exported from a core instance: '{}'",
+                export.name
+            ),
         };
         map.insert(export.name, idx);
     }
-    LocalInitializer::ModuleSynthetic(map)
+    Ok(LocalInitializer::ModuleSynthetic(map))
 }
 
 /// Converts wasmparser's `CanonicalOption` into our `LocalCanonicalOptions`.
@@ -907,20 +910,22 @@ fn canonical_options(opts: &[wasmparser::CanonicalOption]) -> LocalCanonicalOpti
 }
 
 /// Converts wasmparser module instance alias information into `LocalInitializer`.
-fn alias_module_instance_export(
+fn alias_module_instance_export<'data>(
     kind: wasmparser::ExternalKind,
     instance: ModuleInstanceIndex,
-    name: &str,
-) -> LocalInitializer<'_> {
-    match kind {
+    name: &'data str,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<LocalInitializer<'data>> {
+    Ok(match kind {
         wasmparser::ExternalKind::Func => LocalInitializer::AliasExportFunc(instance, name),
         wasmparser::ExternalKind::Memory => LocalInitializer::AliasExportMemory(instance, name),
         wasmparser::ExternalKind::Table => LocalInitializer::AliasExportTable(instance, name),
         wasmparser::ExternalKind::Global => LocalInitializer::AliasExportGlobal(instance, name),
-        wasmparser::ExternalKind::Tag => {
-            unimplemented!("wasm exceptions");
-        }
-    }
+        wasmparser::ExternalKind::Tag => unsupported_diag!(
+            diagnostics,
+            "exception tags cannot be aliased from a core instance: '{name}'"
+        ),
+    })
 }
 
 impl ParsedComponent<'_> {
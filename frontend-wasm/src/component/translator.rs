@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
+
 use midenc_hir::{
     cranelift_entity::PrimaryMap, diagnostics::Severity, CanonAbiImport, ComponentBuilder,
-    ComponentExport, FunctionIdent, FunctionType, Ident, InterfaceFunctionIdent, InterfaceIdent,
-    MidenAbiImport, Symbol,
+    ComponentExport, ComponentResource, FunctionIdent, FunctionType, Ident, InterfaceFunctionIdent,
+    InterfaceIdent, MidenAbiImport, ResourceHandle, Symbol,
 };
 use midenc_hir_type::Abi;
 use midenc_session::{DiagnosticsHandler, Session};
@@ -9,9 +11,10 @@ use rustc_hash::FxHashMap;
 
 use super::{
     interface_type_to_ir, CanonicalOptions, ComponentTypes, CoreDef, CoreExport, Export,
-    ExportItem, GlobalInitializer, InstantiateModule, LinearComponent, LinearComponentTranslation,
-    LoweredIndex, RuntimeImportIndex, RuntimeInstanceIndex, RuntimePostReturnIndex,
-    RuntimeReallocIndex, StaticModuleIndex, Trampoline, TypeFuncIndex,
+    ExportItem, GlobalInitializer, InstantiateModule, InterfaceType, LinearComponent,
+    LinearComponentTranslation, LoweredIndex, Resource, ResourceIndex, RuntimeImportIndex,
+    RuntimeInstanceIndex, RuntimePostReturnIndex, RuntimeReallocIndex, StaticModuleIndex,
+    Trampoline, TypeDef, TypeFuncIndex, TypeResourceTableIndex,
 };
 use crate::{
     component::StringEncoding,
@@ -47,6 +50,10 @@ pub struct ComponentTranslator<'a, 'data> {
     reallocs: FxHashMap<RuntimeReallocIndex, FunctionIdent>,
     /// The post return functions used in CanonicalOptions in this component
     post_returns: FxHashMap<RuntimePostReturnIndex, FunctionIdent>,
+    /// The names of the resource types imported by this component
+    imported_resources: FxHashMap<ResourceIndex, InterfaceFunctionIdent>,
+    /// The names of the resource types defined (and exported) by this component
+    defined_resources: FxHashMap<ResourceIndex, InterfaceFunctionIdent>,
     session: &'a Session,
 }
 
@@ -66,6 +73,8 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
             lower_imports: FxHashMap::default(),
             reallocs: FxHashMap::default(),
             post_returns: FxHashMap::default(),
+            imported_resources: FxHashMap::default(),
+            defined_resources: FxHashMap::default(),
         }
     }
 
@@ -77,6 +86,7 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
     ) -> WasmResult<midenc_hir::Component> {
         let mut component_builder: midenc_hir::ComponentBuilder<'a> =
             midenc_hir::ComponentBuilder::new(&self.session.diagnostics);
+        self.collect_resource_names(&wasm_translation.component);
         // dbg!(&wasm_translation.component.initializers);
         for initializer in &wasm_translation.component.initializers {
             match initializer {
@@ -110,11 +120,12 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
                     let func_id = self.func_id_from_core_def(&post_return.def)?;
                     self.post_returns.insert(post_return.index, func_id);
                 }
-                GlobalInitializer::Resource(_) => {
-                    unsupported_diag!(
-                        &self.session.diagnostics,
-                        "resource global initializers are not yet supported"
-                    );
+                GlobalInitializer::Resource(resource) => {
+                    self.translate_resource(
+                        resource,
+                        &wasm_translation.component,
+                        &mut component_builder,
+                    )?;
                 }
             }
         }
@@ -178,6 +189,7 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
                     module,
                     module_types,
                     module_args,
+                    self.config,
                     &self.session.diagnostics,
                 );
                 let ir_module = build_ir_module(
@@ -250,6 +262,61 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
                     None => Ok(None),
                 }
             }
+            Trampoline::ResourceNew(table)
+            | Trampoline::ResourceRep(table)
+            | Trampoline::ResourceDrop(table) => {
+                let op = match trampoline {
+                    Trampoline::ResourceNew(_) => "new",
+                    Trampoline::ResourceRep(_) => "rep",
+                    _ => "drop",
+                };
+                let resource = self.component_types[*table].ty;
+                if let Some(name) = self.defined_resources.get(&resource) {
+                    // The built-in functions accessing the handle table of the resource are
+                    // generated along with the lifting of the exports of the component
+                    return Ok(Some(ModuleArgument::Function(ComponentResource::builtin(
+                        *name, op,
+                    ))));
+                }
+                let Some(name) = self.imported_resources.get(&resource) else {
+                    unsupported_diag!(
+                        &self.session.diagnostics,
+                        "resources which are not exported from the component are not supported"
+                    );
+                };
+                if op != "drop" {
+                    unsupported_diag!(
+                        &self.session.diagnostics,
+                        "`resource.{op}` is not supported for the imported resource {name}"
+                    );
+                }
+                // The handles of an imported resource are those of the component defining it,
+                // which exports the function dropping them
+                let module_import = module.imports.get(idx).expect("module import not found");
+                let function_id = FunctionIdent {
+                    module: module.name(),
+                    function: module.func_name(module_import.index.unwrap_func()).into(),
+                };
+                let builtin = ComponentResource::builtin(*name, op);
+                let component_import =
+                    midenc_hir::ComponentImport::CanonAbiImport(CanonAbiImport::new(
+                        InterfaceFunctionIdent {
+                            interface: name.interface,
+                            function: builtin.function.as_symbol(),
+                        },
+                        FunctionType {
+                            params: vec![midenc_hir_type::Type::I32],
+                            results: vec![],
+                            abi: Abi::Wasm,
+                        },
+                        midenc_hir::CanonicalOptions {
+                            realloc: None,
+                            post_return: None,
+                        },
+                    ));
+                component_builder.add_import(function_id, component_import.clone());
+                Ok(Some(ModuleArgument::ComponentImport(component_import)))
+            }
             _ => unsupported_diag!(
                 &self.session.diagnostics,
                 "Not yet implemented trampoline type {:?}",
@@ -258,6 +325,72 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
         }
     }
 
+    /// Collect the names of the resource types imported and exported by the component, which
+    /// identify them across components
+    fn collect_resource_names(&mut self, wasm_component: &LinearComponent) {
+        for (resource, runtime_import_idx) in wasm_component.imported_resources.iter() {
+            let (import_idx, names) = &wasm_component.imports[*runtime_import_idx];
+            let (interface, _) = &wasm_component.import_types[*import_idx];
+            if let Some(name) = names.last() {
+                self.imported_resources
+                    .insert(resource, InterfaceFunctionIdent::from_full(interface, name));
+            }
+        }
+        for (interface, export) in &wasm_component.exports {
+            let Export::Instance(exports) = export else {
+                continue;
+            };
+            for (name, export) in exports {
+                if let Export::Type(TypeDef::Resource(table)) = export {
+                    let resource = self.component_types[*table].ty;
+                    if wasm_component.defined_resource_index(resource).is_some() {
+                        self.defined_resources.insert(
+                            resource,
+                            InterfaceFunctionIdent::from_full(interface.as_str(), name.as_str()),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Translate the definition of a resource type to the Miden IR component resource
+    fn translate_resource(
+        &self,
+        resource: &Resource,
+        wasm_component: &LinearComponent,
+        component_builder: &mut ComponentBuilder<'_>,
+    ) -> WasmResult<()> {
+        let index = wasm_component.resource_index(resource.index);
+        let Some(name) = self.defined_resources.get(&index) else {
+            unsupported_diag!(
+                &self.session.diagnostics,
+                "resources which are not exported from the component are not supported"
+            );
+        };
+        let dtor = match &resource.dtor {
+            Some(dtor) => Some(self.func_id_from_core_def(dtor)?),
+            None => None,
+        };
+        component_builder.add_resource(ComponentResource { name: *name, dtor });
+        Ok(())
+    }
+
+    /// Returns the handle to a resource defined by this component, if `ty` is one
+    fn resource_handle(&self, ty: &InterfaceType) -> Option<ResourceHandle> {
+        let (table, own): (&TypeResourceTableIndex, bool) = match ty {
+            InterfaceType::Own(table) => (table, true),
+            InterfaceType::Borrow(table) => (table, false),
+            _ => return None,
+        };
+        let name = *self.defined_resources.get(&self.component_types[*table].ty)?;
+        Some(if own {
+            ResourceHandle::Own(name)
+        } else {
+            ResourceHandle::Borrow(name)
+        })
+    }
+
     /// Build a module argument from the given module export
     fn module_arg_from_export(
         &self,
@@ -285,6 +418,10 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
                     &self.session.diagnostics,
                     "Exporting of core module globals are not yet supported"
                 ),
+                EntityIndex::Tag(_) => unsupported_diag!(
+                    &self.session.diagnostics,
+                    "Exporting of core module exception tags is not supported"
+                ),
             },
             ExportItem::Name(_) => unsupported_diag!(
                 &self.session.diagnostics,
@@ -406,10 +543,19 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
     ) -> WasmResult<ComponentExport> {
         let core_func_ident = self.func_id_from_core_def(func)?;
         let lifted_func_ty = convert_lifted_func_ty(ty, &self.component_types);
+        let type_func = &self.component_types[*ty];
+        let handles = |tys: &[InterfaceType]| -> BTreeMap<usize, ResourceHandle> {
+            tys.iter()
+                .enumerate()
+                .filter_map(|(index, ty)| Some((index, self.resource_handle(ty)?)))
+                .collect()
+        };
         let export = midenc_hir::ComponentExport {
             function: core_func_ident,
             function_ty: lifted_func_ty,
             options: self.translate_canonical_options(options)?,
+            param_handles: handles(&self.component_types[type_func.params].types[..]),
+            result_handles: handles(&self.component_types[type_func.results].types[..]),
         };
         Ok(export)
     }
@@ -424,7 +570,10 @@ impl<'a, 'data> ComponentTranslator<'a, 'data> {
                 let func_name = match core_export.item {
                     ExportItem::Index(idx) => match idx {
                         EntityIndex::Function(func_idx) => module.func_name(func_idx),
-                        EntityIndex::Table(_) | EntityIndex::Memory(_) | EntityIndex::Global(_) => {
+                        EntityIndex::Table(_)
+                        | EntityIndex::Memory(_)
+                        | EntityIndex::Global(_)
+                        | EntityIndex::Tag(_) => {
                             unsupported_diag!(
                                 &self.session.diagnostics,
                                 "Exporting of non-function entity {:?} is not supported",
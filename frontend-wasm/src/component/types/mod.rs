@@ -594,7 +594,9 @@ impl ComponentTypesBuilder {
                 let ty = convert_func_type(ty);
                 EntityType::Function(self.module_types_builder_mut().wasm_func_type(*idx, ty))
             }
-            types::EntityType::Table(ty) => EntityType::Table(convert_table_type(ty)),
+            types::EntityType::Table(ty) => {
+                EntityType::Table(convert_table_type(ty).map_err(|err| anyhow::anyhow!("{err}"))?)
+            }
             types::EntityType::Memory(ty) => EntityType::Memory((*ty).into()),
             types::EntityType::Global(ty) => EntityType::Global(convert_global_type(ty)),
             types::EntityType::Tag(_) => bail!("exceptions proposal not implemented"),
@@ -1737,9 +1739,11 @@ pub fn interface_type_to_ir(
         InterfaceType::S64 => midenc_hir_type::Type::I64,
         InterfaceType::U64 => midenc_hir_type::Type::U64,
         InterfaceType::Float32 => midenc_hir_type::Type::Felt,
-        InterfaceType::Float64 => todo!(),
-        InterfaceType::Char => todo!(),
-        InterfaceType::String => todo!(),
+        InterfaceType::Float64 => midenc_hir_type::Type::F64,
+        // A `char` is a Unicode scalar value, represented as its 32-bit code point
+        InterfaceType::Char => midenc_hir_type::Type::U32,
+        // Strings are UTF-8 encoded byte lists
+        InterfaceType::String => midenc_hir_type::Type::List(Box::new(midenc_hir_type::Type::U8)),
         InterfaceType::Record(idx) => {
             let tys = component_types.records[*idx]
                 .fields
@@ -1747,7 +1751,14 @@ pub fn interface_type_to_ir(
                 .map(|f| interface_type_to_ir(&f.ty, component_types));
             midenc_hir_type::Type::Struct(midenc_hir_type::StructType::new(tys))
         }
-        InterfaceType::Variant(_) => todo!(),
+        InterfaceType::Variant(idx) => {
+            let variant = &component_types.variants[*idx];
+            variant_to_ir(
+                variant.info.size,
+                variant.cases.iter().map(|case| case.ty.as_ref()),
+                component_types,
+            )
+        }
         InterfaceType::List(idx) => {
             let element_ty =
                 interface_type_to_ir(&component_types.lists[*idx].element, component_types);
@@ -1760,11 +1771,55 @@ pub fn interface_type_to_ir(
                 .map(|t| interface_type_to_ir(t, component_types));
             midenc_hir_type::Type::Struct(midenc_hir_type::StructType::new(tys))
         }
-        InterfaceType::Flags(_) => todo!(),
-        InterfaceType::Enum(_) => todo!(),
-        InterfaceType::Option(_) => todo!(),
-        InterfaceType::Result(_) => todo!(),
-        InterfaceType::Own(_) => todo!(),
-        InterfaceType::Borrow(_) => todo!(),
+        InterfaceType::Flags(idx) => {
+            match FlagsSize::from_count(component_types.flags[*idx].names.len()) {
+                FlagsSize::Size0 => midenc_hir_type::Type::Unit,
+                FlagsSize::Size1 => midenc_hir_type::Type::U8,
+                FlagsSize::Size2 => midenc_hir_type::Type::U16,
+                FlagsSize::Size4Plus(n) => {
+                    midenc_hir_type::Type::Struct(midenc_hir_type::StructType::new(
+                        core::iter::repeat(midenc_hir_type::Type::U32).take(n as usize),
+                    ))
+                }
+            }
+        }
+        InterfaceType::Enum(idx) => discriminant_to_ir(component_types.enums[*idx].info.size),
+        InterfaceType::Option(idx) => {
+            let option = &component_types.options[*idx];
+            variant_to_ir(option.info.size, [None, Some(&option.ty)], component_types)
+        }
+        InterfaceType::Result(idx) => {
+            let result = &component_types.results[*idx];
+            variant_to_ir(
+                result.info.size,
+                [result.ok.as_ref(), result.err.as_ref()],
+                component_types,
+            )
+        }
+        // Resource handles are indices into the resource table of the owning component
+        InterfaceType::Own(_) | InterfaceType::Borrow(_) => midenc_hir_type::Type::U32,
     }
 }
+
+/// Returns the IR type used to represent the discriminant of a variant-like type
+fn discriminant_to_ir(size: DiscriminantSize) -> midenc_hir_type::Type {
+    match size {
+        DiscriminantSize::Size1 => midenc_hir_type::Type::U8,
+        DiscriminantSize::Size2 => midenc_hir_type::Type::U16,
+        DiscriminantSize::Size4 => midenc_hir_type::Type::U32,
+    }
+}
+
+/// Converts a variant-like type (i.e. `variant`, `option` and `result`) with the given cases to
+/// a tagged union, identified by a discriminant of the given size
+fn variant_to_ir<'a>(
+    size: DiscriminantSize,
+    cases: impl IntoIterator<Item = Option<&'a InterfaceType>>,
+    component_types: &ComponentTypes,
+) -> midenc_hir_type::Type {
+    let cases = cases.into_iter().map(|ty| match ty {
+        Some(ty) => interface_type_to_ir(ty, component_types),
+        None => midenc_hir_type::Type::Unit,
+    });
+    midenc_hir_type::Type::Union(midenc_hir_type::UnionType::new(discriminant_to_ir(size), cases))
+}
@@ -16,6 +16,10 @@ pub struct WasmTranslationConfig {
 
     /// Whether or not to retain DWARF sections in compiled modules.
     pub parse_wasm_debuginfo: bool,
+
+    /// Whether or not to lower 64-bit floating point operations to calls into the soft-float
+    /// intrinsics, rather than rejecting them as unsupported.
+    pub soft_float: bool,
 }
 
 impl Default for WasmTranslationConfig {
@@ -25,6 +29,7 @@ impl Default for WasmTranslationConfig {
             override_name: None,
             generate_native_debuginfo: false,
             parse_wasm_debuginfo: true,
+            soft_float: false,
         }
     }
 }
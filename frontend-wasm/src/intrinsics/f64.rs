@@ -0,0 +1,124 @@
+use midenc_hir::{
+    AbiParam, FunctionIdent, FunctionType, Ident, InstBuilder, Signature, SourceSpan, Type, Value,
+};
+
+use crate::module::function_builder_ext::FunctionBuilderExt;
+
+/// The module containing the software implementation of 64-bit floating point arithmetic
+pub const MODULE_ID: &str = "intrinsics::f64";
+
+pub const ADD: &str = "add";
+pub const SUB: &str = "sub";
+pub const MUL: &str = "mul";
+pub const DIV: &str = "div";
+pub const MIN: &str = "min";
+pub const MAX: &str = "max";
+pub const COPYSIGN: &str = "copysign";
+pub const NEG: &str = "neg";
+pub const ABS: &str = "abs";
+pub const CEIL: &str = "ceil";
+pub const FLOOR: &str = "floor";
+pub const TRUNC: &str = "trunc";
+pub const NEAREST: &str = "nearest";
+pub const EQ: &str = "eq";
+pub const NE: &str = "ne";
+pub const LT: &str = "lt";
+pub const GT: &str = "gt";
+pub const LE: &str = "le";
+pub const GE: &str = "ge";
+pub const FROM_I32: &str = "from_i32";
+pub const FROM_U32: &str = "from_u32";
+pub const FROM_I64: &str = "from_i64";
+pub const FROM_U64: &str = "from_u64";
+pub const TO_I32: &str = "to_i32";
+pub const TO_U32: &str = "to_u32";
+pub const TO_I64: &str = "to_i64";
+pub const TO_U64: &str = "to_u64";
+pub const TO_I32_SAT: &str = "to_i32_sat";
+pub const TO_U32_SAT: &str = "to_u32_sat";
+pub const TO_I64_SAT: &str = "to_i64_sat";
+pub const TO_U64_SAT: &str = "to_u64_sat";
+
+/// Get the parameter and result types of the soft-float intrinsic `function`
+fn types(function: &str) -> Option<(Vec<Type>, Vec<Type>)> {
+    use Type::*;
+
+    let types = match function {
+        ADD | SUB | MUL | DIV | MIN | MAX | COPYSIGN => (vec![F64, F64], vec![F64]),
+        NEG | ABS | CEIL | FLOOR | TRUNC | NEAREST => (vec![F64], vec![F64]),
+        EQ | NE | LT | GT | LE | GE => (vec![F64, F64], vec![I1]),
+        FROM_I32 => (vec![I32], vec![F64]),
+        FROM_U32 => (vec![U32], vec![F64]),
+        FROM_I64 => (vec![I64], vec![F64]),
+        FROM_U64 => (vec![U64], vec![F64]),
+        TO_I32 | TO_I32_SAT => (vec![F64], vec![I32]),
+        TO_U32 | TO_U32_SAT => (vec![F64], vec![U32]),
+        TO_I64 | TO_I64_SAT => (vec![F64], vec![I64]),
+        TO_U64 | TO_U64_SAT => (vec![F64], vec![U64]),
+        _ => return None,
+    };
+    Some(types)
+}
+
+pub fn function_type(func_id: &FunctionIdent) -> FunctionType {
+    match types(func_id.function.as_symbol().as_str()) {
+        Some((params, results)) => FunctionType::new(params, results),
+        None => panic!("No soft-float intrinsics FunctionType found for {}", func_id),
+    }
+}
+
+fn signature(func_id: &FunctionIdent) -> Signature {
+    match types(func_id.function.as_symbol().as_str()) {
+        Some((params, results)) => Signature::new(
+            params.into_iter().map(AbiParam::new),
+            results.into_iter().map(AbiParam::new),
+        ),
+        None => panic!("No soft-float intrinsics Signature found for {}", func_id),
+    }
+}
+
+/// Convert a call to a soft-float intrinsic function
+pub(crate) fn convert_f64_intrinsics(
+    func_id: FunctionIdent,
+    args: &[Value],
+    builder: &mut FunctionBuilderExt<'_, '_, '_>,
+    span: SourceSpan,
+) -> Vec<Value> {
+    let signature = signature(&func_id);
+    assert_eq!(
+        args.len(),
+        signature.arity(),
+        "{} takes exactly {} argument(s)",
+        func_id,
+        signature.arity()
+    );
+    if builder
+        .data_flow_graph()
+        .get_import_by_name(func_id.module, func_id.function)
+        .is_none()
+    {
+        let _ = builder.data_flow_graph_mut().import_function(
+            func_id.module,
+            func_id.function,
+            signature,
+        );
+    }
+    let call = builder.ins().exec(func_id, args, span);
+    builder.inst_results(call).to_vec()
+}
+
+/// Emit a call to the soft-float intrinsic `function`, returning its result.
+///
+/// The arguments are given in stack order, i.e. for binary operations, `args` is `[rhs, lhs]`.
+pub(crate) fn call(
+    function: &str,
+    args: &[Value],
+    builder: &mut FunctionBuilderExt<'_, '_, '_>,
+    span: SourceSpan,
+) -> Value {
+    let func_id = FunctionIdent {
+        module: Ident::from(MODULE_ID),
+        function: Ident::from(function),
+    };
+    convert_f64_intrinsics(func_id, args, builder, span)[0]
+}
@@ -1,3 +1,4 @@
+pub mod f64;
 pub mod felt;
 pub mod mem;
 
@@ -18,6 +19,7 @@ fn modules() -> &'static HashSet<&'static str> {
         let mut s = HashSet::default();
         s.insert(mem::MODULE_ID);
         s.insert(felt::MODULE_ID);
+        s.insert(f64::MODULE_ID);
         s
     })
 }
@@ -32,6 +34,7 @@ pub fn convert_intrinsics_call(
     match func_id.module.as_symbol().as_str() {
         mem::MODULE_ID => mem::convert_mem_intrinsics(func_id, args, builder, span),
         felt::MODULE_ID => felt::convert_felt_intrinsics(func_id, args, builder, span),
+        f64::MODULE_ID => f64::convert_f64_intrinsics(func_id, args, builder, span),
         _ => panic!("No intrinsics found for {}", func_id),
     }
 }
@@ -39,6 +42,7 @@ pub fn convert_intrinsics_call(
 fn intrinsic_function_type(func_id: &FunctionIdent) -> FunctionType {
     match func_id.module.as_symbol().as_str() {
         mem::MODULE_ID => mem::function_type(func_id),
+        f64::MODULE_ID => f64::function_type(func_id),
         _ => panic!("No intrinsics FunctionType found for {}", func_id),
     }
 }
@@ -50,7 +54,7 @@ pub enum IntrinsicsConversionResult {
 
 pub fn intrinsics_conversion_result(func_id: &FunctionIdent) -> IntrinsicsConversionResult {
     match func_id.module.as_symbol().as_str() {
-        mem::MODULE_ID => {
+        mem::MODULE_ID | f64::MODULE_ID => {
             IntrinsicsConversionResult::FunctionType(intrinsic_function_type(func_id))
         }
         felt::MODULE_ID => IntrinsicsConversionResult::MidenVmOp,
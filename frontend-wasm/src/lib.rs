@@ -47,10 +47,15 @@ pub fn translate(
 /// The set of core WebAssembly features which we need to or wish to support
 pub(crate) fn supported_features() -> WasmFeatures {
     WasmFeatures::BULK_MEMORY
+        | WasmFeatures::EXCEPTIONS
         | WasmFeatures::FLOATS
         | WasmFeatures::FUNCTION_REFERENCES
+        | WasmFeatures::LEGACY_EXCEPTIONS
+        | WasmFeatures::MEMORY64
+        | WasmFeatures::MULTI_MEMORY
         | WasmFeatures::MULTI_VALUE
         | WasmFeatures::MUTABLE_GLOBAL
+        | WasmFeatures::REFERENCE_TYPES
         | WasmFeatures::SATURATING_FLOAT_TO_INT
         | WasmFeatures::SIGN_EXTENSION
         | WasmFeatures::TAIL_CALL
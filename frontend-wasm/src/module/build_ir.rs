@@ -2,22 +2,26 @@ use core::mem;
 
 use midenc_hir::{
     diagnostics::{DiagnosticsHandler, IntoDiagnostic, Severity, SourceSpan},
-    CallConv, ConstantData, Linkage, MidenAbiImport, ModuleBuilder, Symbol,
+    CallConv, ConstantData, InstBuilder, Linkage, MidenAbiImport, ModuleBuilder, Symbol, Type,
 };
 use midenc_session::Session;
 use wasmparser::Validator;
 
-use super::{module_translation_state::ModuleTranslationState, MemoryIndex, Module};
+use super::{
+    module_translation_state::{Dispatcher, ModuleTranslationState},
+    MemoryIndex, Module,
+};
 use crate::{
     error::WasmResult,
     intrinsics::is_miden_intrinsics_module,
-    miden_abi::miden_abi_function_type,
+    miden_abi::{is_miden_abi_module, miden_abi_function_type},
     module::{
+        debug_variables::ModuleVariables,
         func_translator::FuncTranslator,
         module_env::{FunctionBodyData, ModuleEnvironment, ParsedModule},
-        types::{ir_func_sig, ir_func_type, ir_type, ModuleTypes},
+        types::{ir_func_sig, ir_func_type, ir_type, DataIndex, ModuleTypes},
     },
-    WasmTranslationConfig,
+    unsupported_diag, WasmTranslationConfig,
 };
 
 /// Translate a valid Wasm core module binary into Miden IR component building
@@ -50,6 +54,7 @@ pub fn translate_module_as_component(
         &parsed_module.module,
         &module_types,
         vec![],
+        config,
         &session.diagnostics,
     );
     let module =
@@ -81,10 +86,11 @@ pub fn build_ir_module(
     parsed_module: &mut ParsedModule,
     module_types: &ModuleTypes,
     module_state: &mut ModuleTranslationState,
-    _config: &WasmTranslationConfig,
+    config: &WasmTranslationConfig,
     session: &Session,
 ) -> WasmResult<midenc_hir::Module> {
     let name = parsed_module.module.name();
+    check_memories(&parsed_module.module, &session.diagnostics)?;
     let memory_size = parsed_module
         .module
         .memories
@@ -94,7 +100,7 @@ pub fn build_ir_module(
     if let Some(memory_size) = memory_size {
         module_builder.with_reserved_memory_pages(memory_size);
     }
-    build_globals(&parsed_module.module, &mut module_builder, &session.diagnostics)?;
+    build_globals(&parsed_module.module, &mut module_builder, config, &session.diagnostics)?;
     build_data_segments(parsed_module, &mut module_builder, &session.diagnostics)?;
     let addr2line = addr2line::Context::from_dwarf(gimli::Dwarf {
         debug_abbrev: parsed_module.debuginfo.dwarf.debug_abbrev,
@@ -113,6 +119,12 @@ pub fn build_ir_module(
         ..Default::default()
     })
     .into_diagnostic()?;
+    // Variables are only reported to the debugger when debug decorators are emitted
+    let module_variables = if session.options.emit_debug_decorators() {
+        ModuleVariables::parse(&parsed_module.debuginfo.dwarf)
+    } else {
+        ModuleVariables::default()
+    };
     let mut func_translator = FuncTranslator::new();
     // Although this renders this parsed module invalid(without functiong
     // bodies), we don't support multiple module instances. Thus, this
@@ -123,7 +135,7 @@ pub fn build_ir_module(
         let func_type = &parsed_module.module.functions[*func_index];
         let func_name = &parsed_module.module.func_name(*func_index);
         let wasm_func_type = module_types[func_type.signature].clone();
-        let ir_func_type = ir_func_type(&wasm_func_type, &session.diagnostics)?;
+        let ir_func_type = ir_func_type(&wasm_func_type, config, &session.diagnostics)?;
         let linkage = if parsed_module.module.is_exported_function(func_index) {
             Linkage::External
         } else {
@@ -140,18 +152,103 @@ pub fn build_ir_module(
             parsed_module,
             module_types,
             &addr2line,
+            &module_variables,
+            config,
             session,
             &mut func_validator,
         )?;
         module_func_builder.build(&session.diagnostics)?;
     }
+    for dispatcher in module_state.take_dispatchers() {
+        build_dispatcher(dispatcher, &mut module_builder, module_state, &session.diagnostics)?;
+    }
     let module = module_builder.build();
     Ok(*module)
 }
 
+/// Defines the function dispatching indirect calls through a table to the functions with a given
+/// signature, see [ModuleTranslationState::get_dispatcher]
+fn build_dispatcher(
+    dispatcher: Dispatcher,
+    module_builder: &mut ModuleBuilder,
+    module_state: &mut ModuleTranslationState,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<()> {
+    let span = SourceSpan::UNKNOWN;
+    let mut builder =
+        module_builder.function(dispatcher.id.function, dispatcher.dispatch_signature())?;
+    let entry = builder.current_block();
+    let params = builder.block_params(entry).to_vec();
+    let (callee, args) = params.split_last().expect("expected the callee index param");
+    let mut table = Vec::with_capacity(dispatcher.table.len());
+    for entry in dispatcher.table {
+        let Some(func_index) = entry else {
+            table.push(None);
+            continue;
+        };
+        let func_id =
+            module_state.get_direct_func(builder.data_flow_graph_mut(), func_index, diagnostics)?;
+        let module_name = func_id.module.as_symbol();
+        if is_miden_intrinsics_module(module_name) || is_miden_abi_module(module_name) {
+            unsupported_diag!(
+                diagnostics,
+                "Indirect calls to Miden SDK functions are not supported: {func_id}"
+            );
+        }
+        table.push(Some(func_id));
+    }
+    let call = builder.ins().call_indirect(*callee, dispatcher.signature, table, args, span);
+    let results = builder.inst_results(call).to_vec();
+    builder.ins().ret_values(&results, span);
+    builder.build(diagnostics)?;
+    Ok(())
+}
+
+/// The maximum number of linear memories in a module.
+///
+/// Memory 0 is placed in the default address space, and each additional memory is placed in a
+/// separate, statically allocated, address space, of which only three are available.
+const MAX_MEMORIES: usize = 4;
+
+/// The number of Wasm pages in 2^32 bytes, i.e. the largest memory addressable with 32 bits
+const MAX_MEMORY_PAGES: u64 = 1 << 16;
+
+fn check_memories(wasm_module: &Module, diagnostics: &DiagnosticsHandler) -> WasmResult<()> {
+    if wasm_module.memories.len() > MAX_MEMORIES {
+        unsupported_diag!(
+            diagnostics,
+            "wasm error: modules with more than {MAX_MEMORIES} memories are not supported"
+        );
+    }
+    for (memory_idx, memory) in &wasm_module.memories {
+        if !memory.memory64 {
+            continue;
+        }
+        if memory.minimum > MAX_MEMORY_PAGES {
+            unsupported_diag!(
+                diagnostics,
+                "wasm error: 64-bit memory {} has a minimum size of {} pages, but at most \
+                 {MAX_MEMORY_PAGES} pages are addressable",
+                memory_idx.as_u32(),
+                memory.minimum
+            );
+        }
+        diagnostics
+            .diagnostic(Severity::Warning)
+            .with_message(format!(
+                "64-bit memory {} is addressed using 32-bit addresses: accessing an address which \
+                 does not fit in 32 bits will trap",
+                memory_idx.as_u32()
+            ))
+            .emit();
+    }
+    Ok(())
+}
+
 fn build_globals(
     wasm_module: &Module,
     module_builder: &mut ModuleBuilder,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<()> {
     for (global_idx, global) in &wasm_module.globals {
@@ -165,7 +262,7 @@ fn build_globals(
         let init = ConstantData::from(global_init.to_le_bytes(wasm_module, diagnostics)?);
         if let Err(e) = module_builder.declare_global_variable(
             global_name.as_str(),
-            ir_type(global.ty, diagnostics)?,
+            ir_type(global.ty, config, diagnostics)?,
             Linkage::External,
             Some(init.clone()),
             SourceSpan::default(),
@@ -185,21 +282,68 @@ fn build_globals(
 }
 
 fn build_data_segments(
-    translation: &ParsedModule,
+    translation: &mut ParsedModule,
     module_builder: &mut ModuleBuilder,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<()> {
     for (data_segment_idx, data_segment) in &translation.data_segments {
+        let Some(offset) = data_segment.offset else {
+            continue;
+        };
         let data_segment_name =
             translation.module.name_section.data_segment_names[&data_segment_idx];
         let readonly = data_segment_name.as_str().contains(".rodata");
         let init = ConstantData::from(data_segment.data);
-        let offset = data_segment.offset.as_i32(&translation.module, diagnostics)? as u32;
+        let offset = offset.as_i32(&translation.module, diagnostics)? as u32;
+        declare_data_segment(
+            module_builder,
+            data_segment_name,
+            offset,
+            init,
+            readonly,
+            diagnostics,
+        )?;
+    }
+    // Passive data segments are not part of the initial contents of linear memory, they are only
+    // copied into it by `memory.init`. So that they can be copied from, they are placed beyond the
+    // end of the heap, where linear memory can never reach. Whether each segment has been dropped
+    // via `data.drop` is tracked by a global variable, after which it may no longer be copied.
+    for (data_segment_idx, data_segment) in &translation.data_segments {
+        if data_segment.offset.is_some() {
+            continue;
+        }
+        let data_segment_name = translation
+            .module
+            .name_section
+            .data_segment_names
+            .get(&data_segment_idx)
+            .copied()
+            .unwrap_or_else(|| Symbol::intern(format!("data{}", data_segment_idx.as_u32())));
+        let init = ConstantData::from(data_segment.data);
+        let offset = module_builder.segments().next_available_offset_beyond_heap();
         let size = init.len() as u32;
-        if let Err(e) = module_builder.declare_data_segment(offset, size, init, readonly) {
+        if size > 0 {
+            declare_data_segment(
+                module_builder,
+                data_segment_name,
+                offset,
+                init,
+                true,
+                diagnostics,
+            )?;
+        }
+        let data_index = DataIndex::from_u32(data_segment_idx.as_u32());
+        let dropped_name = translation.module.data_dropped_name(data_index);
+        if let Err(e) = module_builder.declare_global_variable(
+            dropped_name.as_str(),
+            Type::I32,
+            Linkage::Internal,
+            None,
+            SourceSpan::default(),
+        ) {
             let message = format!(
-                "Failed to declare data segment '{data_segment_name}' with size '{size}' at \
-                 '{offset}' with error: {:?}",
+                "Failed to declare global variable '{dropped_name}' for data segment \
+                 '{data_segment_name}' with error: {:?}",
                 e
             );
             return Err(diagnostics
@@ -207,6 +351,30 @@ fn build_data_segments(
                 .with_message(message.clone())
                 .into_report());
         }
+        translation.module.passive_data_map.insert(data_index, offset..(offset + size));
+    }
+    Ok(())
+}
+
+fn declare_data_segment(
+    module_builder: &mut ModuleBuilder,
+    data_segment_name: Symbol,
+    offset: u32,
+    init: ConstantData,
+    readonly: bool,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<()> {
+    let size = init.len() as u32;
+    if let Err(e) = module_builder.declare_data_segment(offset, size, init, readonly) {
+        let message = format!(
+            "Failed to declare data segment '{data_segment_name}' with size '{size}' at \
+             '{offset}' with error: {:?}",
+            e
+        );
+        return Err(diagnostics
+            .diagnostic(Severity::Error)
+            .with_message(message.clone())
+            .into_report());
     }
     Ok(())
 }
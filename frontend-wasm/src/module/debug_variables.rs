@@ -0,0 +1,618 @@
+//! Extraction of the source-level variables of functions from DWARF debug information.
+//!
+//! Only variables which live in a Wasm local or global, or in the stack frame of the function, are
+//! supported, which covers what rustc/LLVM emit for unoptimized code. Each local or global which
+//! holds a variable, or the frame base of the function, is assigned a _debug slot_, and its value
+//! is reported to the debugger every time it is assigned, see [FunctionVariables::emit_assignment].
+
+use std::{collections::BTreeSet, ops::Range};
+
+use gimli::{AttributeValue, DebuggingInformationEntry, EntriesTreeNode, Operation, Unit};
+use midenc_hir::{
+    diagnostics::SourceSpan, DebugLocation, DebugType, DebugTypeEncoding, DebugVariable,
+    InstBuilder, Value, TRACE_DEBUG_VALUE,
+};
+
+use super::{
+    function_builder_ext::FunctionBuilderExt,
+    module_env::{Dwarf, DwarfReader},
+};
+
+/// The maximum depth of type indirections followed when resolving the type of a variable
+const MAX_TYPE_DEPTH: usize = 16;
+
+/// The storage of a Wasm function which holds a debug slot
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Storage {
+    Local(u32),
+    Global(u32),
+}
+
+/// A variable described by the DWARF debug information
+#[derive(Debug, Clone)]
+struct VariableDie {
+    name: String,
+    ty: DebugType,
+    storage: Storage,
+    location: DebugLocation,
+}
+
+/// The variables of a single subprogram, i.e. function, described by the DWARF debug information
+#[derive(Debug, Default)]
+struct SubprogramVariables {
+    /// The ranges of code section offsets covered by this subprogram
+    ranges: Vec<Range<u64>>,
+    variables: Vec<VariableDie>,
+}
+
+/// The variables of all functions of a module
+#[derive(Debug, Default)]
+pub struct ModuleVariables {
+    subprograms: Vec<SubprogramVariables>,
+}
+
+impl ModuleVariables {
+    /// Collect the variables of all subprograms described in `dwarf`
+    ///
+    /// Debug information we fail to parse is ignored, as it only affects the debugging experience.
+    pub fn parse(dwarf: &Dwarf<'_>) -> Self {
+        let mut variables = Self::default();
+        if let Err(err) = variables.parse_units(dwarf) {
+            log::debug!("failed to parse variables from DWARF debug information: {err}");
+        }
+        variables
+    }
+
+    fn parse_units(&mut self, dwarf: &Dwarf<'_>) -> gimli::Result<()> {
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let mut tree = unit.entries_tree(None)?;
+            self.visit(dwarf, &unit, tree.root()?)?;
+        }
+        Ok(())
+    }
+
+    fn visit<'a>(
+        &mut self,
+        dwarf: &Dwarf<'a>,
+        unit: &Unit<DwarfReader<'a>>,
+        node: EntriesTreeNode<'_, '_, '_, DwarfReader<'a>>,
+    ) -> gimli::Result<()> {
+        let entry = node.entry();
+        if entry.tag() == gimli::DW_TAG_subprogram {
+            let mut ranges = vec![];
+            let mut iter = dwarf.die_ranges(unit, entry)?;
+            while let Some(range) = iter.next()? {
+                ranges.push(range.begin..range.end);
+            }
+            // Declarations, e.g. of trait methods, have no code of their own
+            if !ranges.is_empty() {
+                let frame_base = frame_base(unit, entry)?;
+                let mut subprogram = SubprogramVariables {
+                    ranges,
+                    variables: vec![],
+                };
+                collect_variables(dwarf, unit, node, frame_base, &mut subprogram.variables)?;
+                self.subprograms.push(subprogram);
+                return Ok(());
+            }
+        }
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            self.visit(dwarf, unit, child)?;
+        }
+        Ok(())
+    }
+
+    /// Get the variables of the function whose code contains the given code section offset
+    pub fn function_at(&self, offset: u64) -> FunctionVariables {
+        let mut function = FunctionVariables::default();
+        let Some(subprogram) = self
+            .subprograms
+            .iter()
+            .find(|sp| sp.ranges.iter().any(|range| range.contains(&offset)))
+        else {
+            return function;
+        };
+        for var in subprogram.variables.iter() {
+            let Some(slot) = function.slot_or_insert(var.storage) else {
+                break;
+            };
+            function.variables.push(DebugVariable {
+                name: var.name.clone(),
+                ty: var.ty.clone(),
+                slot,
+                location: var.location,
+            });
+        }
+        function
+    }
+}
+
+/// The variables of the function being translated, and the debug slots tracking them
+#[derive(Debug, Default)]
+pub struct FunctionVariables {
+    /// The storage associated with each debug slot, indexed by slot
+    slots: Vec<Storage>,
+    /// The debug slots whose values do not fit in a single field element, and so are not reported
+    untracked: BTreeSet<u16>,
+    variables: Vec<DebugVariable>,
+}
+
+impl FunctionVariables {
+    /// Get the debug slot associated with `storage`, if any
+    pub fn slot(&self, storage: Storage) -> Option<u16> {
+        self.slots.iter().position(|s| s == &storage).map(|slot| slot as u16)
+    }
+
+    fn slot_or_insert(&mut self, storage: Storage) -> Option<u16> {
+        if let Some(slot) = self.slot(storage) {
+            return Some(slot);
+        }
+        let slot = u16::try_from(self.slots.len()).ok()?;
+        self.slots.push(storage);
+        Some(slot)
+    }
+
+    /// The storage holding a debug slot, in order of the slots
+    pub fn storages(&self) -> impl Iterator<Item = Storage> + '_ {
+        self.slots.iter().copied()
+    }
+
+    /// Take the variables of this function, leaving it empty
+    ///
+    /// Variables whose debug slot is not reported are marked [DebugLocation::Untracked], so the
+    /// debugger can tell why they have no value.
+    pub fn take_variables(&mut self) -> Vec<DebugVariable> {
+        self.slots.clear();
+        let untracked = core::mem::take(&mut self.untracked);
+        let mut variables = core::mem::take(&mut self.variables);
+        for var in variables.iter_mut().filter(|var| untracked.contains(&var.slot)) {
+            var.location = DebugLocation::Untracked;
+        }
+        variables
+    }
+
+    /// Report `value` as the new value of the debug slot associated with `storage`, if any.
+    ///
+    /// The value is passed to an inline assembly block which emits the corresponding
+    /// [midenc_hir::TraceEvent::DebugValue] event and drops the value. Values which do not fit in
+    /// a single field element are not tracked, and the slot is marked as such.
+    pub fn emit_assignment(
+        &mut self,
+        storage: Storage,
+        value: Value,
+        builder: &mut FunctionBuilderExt,
+        span: SourceSpan,
+    ) {
+        let Some(slot) = self.slot(storage) else {
+            return;
+        };
+        if builder.data_flow_graph().value_type(value).size_in_felts() != 1 {
+            self.untracked.insert(slot);
+            return;
+        }
+        let mut asm = builder.ins().inline_asm(&[value], [], span);
+        asm.ins().trace(TRACE_DEBUG_VALUE + slot as u32, span);
+        asm.ins().drop(span);
+        asm.build();
+    }
+}
+
+/// Collect the variables declared in the subprogram or lexical block represented by `node`
+fn collect_variables<'a>(
+    dwarf: &Dwarf<'a>,
+    unit: &Unit<DwarfReader<'a>>,
+    node: EntriesTreeNode<'_, '_, '_, DwarfReader<'a>>,
+    frame_base: Option<Storage>,
+    variables: &mut Vec<VariableDie>,
+) -> gimli::Result<()> {
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        match entry.tag() {
+            gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable => {
+                let Some(name) = die_name(dwarf, unit, entry)? else {
+                    continue;
+                };
+                let Some((storage, location)) = variable_location(unit, entry, frame_base)? else {
+                    continue;
+                };
+                let ty = match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(AttributeValue::UnitRef(offset)) => {
+                        debug_type(dwarf, unit, &unit.entry(offset)?, 0)?
+                    }
+                    _ => opaque_type("?".to_string(), 0),
+                };
+                variables.push(VariableDie {
+                    name,
+                    ty,
+                    storage,
+                    location,
+                });
+            }
+            gimli::DW_TAG_lexical_block => {
+                collect_variables(dwarf, unit, child, frame_base, variables)?;
+            }
+            // The variables of inlined functions, and nested functions, belong to other frames
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn die_name<'a>(
+    dwarf: &Dwarf<'a>,
+    unit: &Unit<DwarfReader<'a>>,
+    entry: &DebuggingInformationEntry<'_, '_, DwarfReader<'a>>,
+) -> gimli::Result<Option<String>> {
+    match entry.attr_value(gimli::DW_AT_name)? {
+        Some(value) => Ok(Some(dwarf.attr_string(unit, value)?.to_string_lossy().into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Get the local or global holding the frame base of a subprogram, if any
+fn frame_base(
+    unit: &Unit<DwarfReader<'_>>,
+    entry: &DebuggingInformationEntry<'_, '_, DwarfReader<'_>>,
+) -> gimli::Result<Option<Storage>> {
+    let Some(AttributeValue::Exprloc(expr)) = entry.attr_value(gimli::DW_AT_frame_base)? else {
+        return Ok(None);
+    };
+    let mut ops = expr.operations(unit.encoding());
+    Ok(match ops.next()? {
+        Some(Operation::WasmLocal { index }) => Some(Storage::Local(index)),
+        Some(Operation::WasmGlobal { index }) => Some(Storage::Global(index)),
+        _ => None,
+    })
+}
+
+/// Get the location of a variable, if it is of a form we support, i.e. a single Wasm local or
+/// global, or an offset from the frame base.
+fn variable_location(
+    unit: &Unit<DwarfReader<'_>>,
+    entry: &DebuggingInformationEntry<'_, '_, DwarfReader<'_>>,
+    frame_base: Option<Storage>,
+) -> gimli::Result<Option<(Storage, DebugLocation)>> {
+    // Location lists, i.e. variables whose location changes over the function, are not supported
+    let Some(AttributeValue::Exprloc(expr)) = entry.attr_value(gimli::DW_AT_location)? else {
+        return Ok(None);
+    };
+    let mut ops = expr.operations(unit.encoding());
+    let location = match ops.next()? {
+        Some(Operation::WasmLocal { index }) => (Storage::Local(index), DebugLocation::Value),
+        Some(Operation::WasmGlobal { index }) => (Storage::Global(index), DebugLocation::Value),
+        Some(Operation::FrameOffset { offset }) => match frame_base {
+            Some(storage) => (storage, DebugLocation::Memory { offset }),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    // Anything but a trailing DW_OP_stack_value describes a composite location
+    while let Some(op) = ops.next()? {
+        if !matches!(op, Operation::StackValue) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(location))
+}
+
+fn debug_type<'a>(
+    dwarf: &Dwarf<'a>,
+    unit: &Unit<DwarfReader<'a>>,
+    entry: &DebuggingInformationEntry<'_, '_, DwarfReader<'a>>,
+    depth: usize,
+) -> gimli::Result<DebugType> {
+    let name = die_name(dwarf, unit, entry)?;
+    let size = entry
+        .attr_value(gimli::DW_AT_byte_size)?
+        .and_then(|value| value.udata_value())
+        .unwrap_or(0) as u32;
+    let target = match entry.attr_value(gimli::DW_AT_type)? {
+        Some(AttributeValue::UnitRef(offset)) if depth < MAX_TYPE_DEPTH => {
+            Some(debug_type(dwarf, unit, &unit.entry(offset)?, depth + 1)?)
+        }
+        _ => None,
+    };
+    Ok(match entry.tag() {
+        gimli::DW_TAG_base_type => {
+            let encoding = match entry.attr_value(gimli::DW_AT_encoding)? {
+                Some(AttributeValue::Encoding(
+                    gimli::DW_ATE_signed | gimli::DW_ATE_signed_char,
+                )) => DebugTypeEncoding::Signed,
+                Some(AttributeValue::Encoding(
+                    gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char | gimli::DW_ATE_UTF,
+                )) => DebugTypeEncoding::Unsigned,
+                Some(AttributeValue::Encoding(gimli::DW_ATE_boolean)) => DebugTypeEncoding::Boolean,
+                Some(AttributeValue::Encoding(gimli::DW_ATE_float)) => DebugTypeEncoding::Float,
+                _ => DebugTypeEncoding::Opaque,
+            };
+            DebugType {
+                name: name.unwrap_or_else(|| "?".to_string()),
+                size,
+                encoding,
+            }
+        }
+        gimli::DW_TAG_pointer_type
+        | gimli::DW_TAG_reference_type
+        | gimli::DW_TAG_rvalue_reference_type => DebugType {
+            name: name.unwrap_or_else(|| {
+                format!("*{}", target.as_ref().map(|ty| ty.name.as_str()).unwrap_or("void"))
+            }),
+            size: 4,
+            encoding: DebugTypeEncoding::Address,
+        },
+        gimli::DW_TAG_typedef
+        | gimli::DW_TAG_const_type
+        | gimli::DW_TAG_volatile_type
+        | gimli::DW_TAG_atomic_type => match target {
+            Some(target) => DebugType {
+                name: name.unwrap_or(target.name),
+                ..target
+            },
+            None => opaque_type(name.unwrap_or_else(|| "?".to_string()), size),
+        },
+        _ => opaque_type(name.unwrap_or_else(|| "?".to_string()), size),
+    })
+}
+
+fn opaque_type(name: String, size: u32) -> DebugType {
+    DebugType {
+        name,
+        size,
+        encoding: DebugTypeEncoding::Opaque,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use midenc_hir::{diagnostics::NullEmitter, testing::TestContext, Ident, Instruction, MasmOp};
+    use midenc_session::{ColorChoice, DebugInfo, Options};
+
+    use super::*;
+    use crate::{translate, WasmTranslationConfig};
+
+    fn uleb(out: &mut Vec<u8>, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                break;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn string(out: &mut Vec<u8>, s: &str) {
+        out.extend(s.as_bytes());
+        out.push(0);
+    }
+
+    /// Encodes the abbreviations used by [debug_info]
+    fn debug_abbrev() -> Vec<u8> {
+        use gimli::*;
+
+        let abbrevs: [(u16, bool, &[(DwAt, DwForm)]); 7] = [
+            (DW_TAG_compile_unit.0, true, &[(DW_AT_name, DW_FORM_string)]),
+            (
+                DW_TAG_subprogram.0,
+                true,
+                &[
+                    (DW_AT_name, DW_FORM_string),
+                    (DW_AT_low_pc, DW_FORM_addr),
+                    (DW_AT_high_pc, DW_FORM_data4),
+                    (DW_AT_frame_base, DW_FORM_exprloc),
+                ],
+            ),
+            (
+                DW_TAG_formal_parameter.0,
+                false,
+                &[
+                    (DW_AT_name, DW_FORM_string),
+                    (DW_AT_location, DW_FORM_exprloc),
+                    (DW_AT_type, DW_FORM_ref4),
+                ],
+            ),
+            (
+                DW_TAG_variable.0,
+                false,
+                &[
+                    (DW_AT_name, DW_FORM_string),
+                    (DW_AT_location, DW_FORM_exprloc),
+                    (DW_AT_type, DW_FORM_ref4),
+                ],
+            ),
+            (
+                DW_TAG_base_type.0,
+                false,
+                &[
+                    (DW_AT_name, DW_FORM_string),
+                    (DW_AT_encoding, DW_FORM_data1),
+                    (DW_AT_byte_size, DW_FORM_data1),
+                ],
+            ),
+            (DW_TAG_pointer_type.0, false, &[(DW_AT_type, DW_FORM_ref4)]),
+            (DW_TAG_lexical_block.0, true, &[]),
+        ];
+        let mut out = vec![];
+        for (code, (tag, has_children, attrs)) in abbrevs.into_iter().enumerate() {
+            uleb(&mut out, code as u64 + 1);
+            uleb(&mut out, tag as u64);
+            out.push(has_children as u8);
+            for (name, form) in attrs {
+                uleb(&mut out, name.0 as u64);
+                uleb(&mut out, form.0 as u64);
+            }
+            out.extend([0, 0]);
+        }
+        out.push(0);
+        out
+    }
+
+    /// Encodes a compilation unit describing the variables of `test_wrapper`, in the Wasm module
+    /// of [variable_table_and_events], using the abbreviations of [debug_abbrev]
+    fn debug_info() -> Vec<u8> {
+        const DW_OP_WASM_LOCATION: u8 = 0xed;
+
+        // The unit length is patched once the unit is complete
+        let mut info = vec![0; 4];
+        info.extend(4u16.to_le_bytes());
+        info.extend(0u32.to_le_bytes());
+        info.push(4);
+
+        info.push(1);
+        string(&mut info, "lib.rs");
+
+        let mut base_type = |name: &str, encoding: gimli::DwAte, size: u8| {
+            let offset = info.len() as u32;
+            info.push(5);
+            string(&mut info, name);
+            info.extend([encoding.0, size]);
+            offset
+        };
+        let u32_ty = base_type("u32", gimli::DW_ATE_unsigned, 4);
+        let i64_ty = base_type("i64", gimli::DW_ATE_signed, 8);
+        let bool_ty = base_type("bool", gimli::DW_ATE_boolean, 1);
+        let ptr_ty = info.len() as u32;
+        info.push(6);
+        info.extend(u32_ty.to_le_bytes());
+
+        let exprloc = |info: &mut Vec<u8>, expr: &[u8]| {
+            uleb(info, expr.len() as u64);
+            info.extend(expr);
+        };
+        let variable = |info: &mut Vec<u8>, abbrev: u8, name: &str, expr: &[u8], ty: u32| {
+            info.push(abbrev);
+            string(info, name);
+            exprloc(info, expr);
+            info.extend(ty.to_le_bytes());
+        };
+
+        // The subprogram covers the whole code section, and its frame base is global 0
+        info.push(2);
+        string(&mut info, "test_wrapper");
+        info.extend(0u32.to_le_bytes());
+        info.extend(0x10000u32.to_le_bytes());
+        exprloc(&mut info, &[DW_OP_WASM_LOCATION, 1, 0]);
+        variable(&mut info, 3, "a", &[DW_OP_WASM_LOCATION, 0, 0], u32_ty);
+        variable(&mut info, 3, "b", &[DW_OP_WASM_LOCATION, 0, 1], i64_ty);
+        info.push(7);
+        variable(&mut info, 4, "c", &[DW_OP_WASM_LOCATION, 0, 2], ptr_ty);
+        info.push(0);
+        variable(&mut info, 4, "d", &[gimli::DW_OP_fbreg.0, 8], bool_ty);
+        // Composite locations are not supported
+        variable(&mut info, 4, "e", &[DW_OP_WASM_LOCATION, 0, 0, gimli::DW_OP_piece.0, 4], u32_ty);
+        variable(
+            &mut info,
+            4,
+            "f",
+            &[DW_OP_WASM_LOCATION, 0, 2, gimli::DW_OP_stack_value.0],
+            u32_ty,
+        );
+        // The end of the children of the subprogram, and of the unit
+        info.extend([0, 0]);
+
+        let len = (info.len() - 4) as u32;
+        info[..4].copy_from_slice(&len.to_le_bytes());
+        info
+    }
+
+    /// Appends a custom section named `name` to the Wasm module `wasm`
+    fn custom_section(wasm: &mut Vec<u8>, name: &str, data: &[u8]) {
+        let mut section = vec![];
+        uleb(&mut section, name.len() as u64);
+        section.extend(name.as_bytes());
+        section.extend(data);
+        wasm.push(0);
+        uleb(wasm, section.len() as u64);
+        wasm.extend(section);
+    }
+
+    #[test]
+    fn variable_table_and_events() {
+        let wat = r#"
+        (module
+            (global $__stack_pointer (mut i32) (i32.const 1048576))
+            (func $test_wrapper (param i32 i64) (local i32)
+                local.get 0
+                local.set 2
+                global.get $__stack_pointer
+                i32.const 16
+                i32.sub
+                global.set $__stack_pointer
+            )
+        )"#;
+        let mut wasm = wat::parse_str(wat).unwrap();
+        custom_section(&mut wasm, ".debug_abbrev", &debug_abbrev());
+        custom_section(&mut wasm, ".debug_info", &debug_info());
+
+        let options = Options::default().with_debug_info(DebugInfo::Full);
+        let emitter = Arc::new(NullEmitter::new(ColorChoice::Auto));
+        let context = TestContext::default_with_opts_and_emitter(options, Some(emitter));
+        let config = WasmTranslationConfig::default();
+        let module = translate(&wasm, &config, &context.session).unwrap().unwrap_one_module();
+        let func = module.function(Ident::from("test_wrapper")).unwrap();
+
+        let variable = |name: &str, ty: DebugType, slot, location| DebugVariable {
+            name: name.to_string(),
+            ty,
+            slot,
+            location,
+        };
+        let base_type = |name: &str, size, encoding| DebugType {
+            name: name.to_string(),
+            size,
+            encoding,
+        };
+        let u32_ty = base_type("u32", 4, DebugTypeEncoding::Unsigned);
+        assert_eq!(
+            func.dfg.debug_variables,
+            [
+                variable("a", u32_ty.clone(), 0, DebugLocation::Value),
+                // Values of i64 locals do not fit in a field element
+                variable(
+                    "b",
+                    base_type("i64", 8, DebugTypeEncoding::Signed),
+                    1,
+                    DebugLocation::Untracked
+                ),
+                variable(
+                    "c",
+                    base_type("*u32", 4, DebugTypeEncoding::Address),
+                    2,
+                    DebugLocation::Value
+                ),
+                variable(
+                    "d",
+                    base_type("bool", 1, DebugTypeEncoding::Boolean),
+                    3,
+                    DebugLocation::Memory { offset: 8 }
+                ),
+                // Variables in the same local share a debug slot
+                variable("f", u32_ty, 2, DebugLocation::Value),
+            ]
+        );
+
+        // The initial values of the tracked slots are reported on entry, followed by each
+        // assignment of local 2 and global 0
+        let entry_block = func.dfg.block(func.dfg.entry_block());
+        let slots = entry_block
+            .insts()
+            .filter_map(|inst| match &func.dfg[inst] {
+                Instruction::InlineAsm(asm) => Some(asm),
+                _ => None,
+            })
+            .flat_map(|asm| asm.blocks[asm.body].ops.iter().copied())
+            .filter_map(|op| match op.into_inner() {
+                MasmOp::Trace(event) => Some(event - TRACE_DEBUG_VALUE),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(slots, [0, 2, 3, 2, 3]);
+    }
+}
@@ -8,7 +8,7 @@
 use midenc_hir::{diagnostics::SourceSpan, Block, Inst, InstBuilder, Signature, Value};
 use midenc_hir_type::Type;
 
-use super::function_builder_ext::FunctionBuilderExt;
+use super::{debug_variables::FunctionVariables, function_builder_ext::FunctionBuilderExt};
 use crate::module::types::BlockType;
 
 /// Information about the presence of an associated `else` for an `if`, or the
@@ -229,6 +229,8 @@ pub struct FuncTranslationState {
     /// Is the current translation state still reachable? This is false when translating operators
     /// like End, Return, or Unreachable.
     pub(crate) reachable: bool,
+    /// The source-level variables of the function, and the debug slots tracking them
+    pub(crate) debug_variables: FunctionVariables,
 }
 
 impl FuncTranslationState {
@@ -238,6 +240,7 @@ impl FuncTranslationState {
             stack: Vec::new(),
             control_stack: Vec::new(),
             reachable: true,
+            debug_variables: FunctionVariables::default(),
         }
     }
 
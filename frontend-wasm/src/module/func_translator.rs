@@ -17,12 +17,14 @@ use wasmparser::{FuncValidator, FunctionBody, WasmModuleResources};
 use super::{module_env::ParsedModule, module_translation_state::ModuleTranslationState};
 use crate::{
     code_translator::translate_operator,
+    config::WasmTranslationConfig,
     error::WasmResult,
     module::{
+        debug_variables::{ModuleVariables, Storage},
         func_translation_state::FuncTranslationState,
         function_builder_ext::{FunctionBuilderContext, FunctionBuilderExt},
         module_env::DwarfReader,
-        types::{convert_valtype, ir_type, ModuleTypes},
+        types::{convert_valtype, ir_type, GlobalIndex, ModuleTypes, NULL_FUNCREF},
     },
     ssa::Variable,
     translation_utils::emit_zero,
@@ -57,6 +59,8 @@ impl FuncTranslator {
         module: &ParsedModule<'_>,
         mod_types: &ModuleTypes,
         addr2line: &addr2line::Context<DwarfReader<'_>>,
+        module_variables: &ModuleVariables,
+        config: &WasmTranslationConfig,
         session: &Session,
         func_validator: &mut FuncValidator<impl WasmModuleResources>,
     ) -> WasmResult<()> {
@@ -79,10 +83,17 @@ impl FuncTranslator {
             &mut builder,
             num_params,
             func_validator,
+            config,
             &session.diagnostics,
         )?;
 
         let mut reader = body.get_operators_reader().into_diagnostic()?;
+        let offset = (reader.original_position() as u64)
+            .checked_sub(module.wasm_file.code_section_offset)
+            .expect("function body occurs before start of code section");
+        self.state.debug_variables = module_variables.function_at(offset);
+        declare_debug_values(&mut builder, &mut self.state, module, config, &session.diagnostics)?;
+
         parse_function_body(
             &mut reader,
             &mut builder,
@@ -91,10 +102,12 @@ impl FuncTranslator {
             module,
             mod_types,
             addr2line,
+            config,
             session,
             func_validator,
         )?;
 
+        builder.data_flow_graph_mut().debug_variables = self.state.debug_variables.take_variables();
         builder.finalize();
         Ok(())
     }
@@ -126,6 +139,7 @@ fn parse_local_decls(
     builder: &mut FunctionBuilderExt,
     num_params: usize,
     validator: &mut FuncValidator<impl WasmModuleResources>,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<()> {
     let mut next_local = num_params;
@@ -135,7 +149,7 @@ fn parse_local_decls(
         let pos = reader.original_position();
         let (count, ty) = reader.read().into_diagnostic()?;
         validator.define_locals(pos, count, ty).into_diagnostic()?;
-        declare_locals(builder, count, ty, &mut next_local, diagnostics)?;
+        declare_locals(builder, count, ty, &mut next_local, config, diagnostics)?;
     }
 
     Ok(())
@@ -149,11 +163,15 @@ fn declare_locals(
     count: u32,
     wasm_type: wasmparser::ValType,
     next_local: &mut usize,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<()> {
-    let ty = ir_type(convert_valtype(wasm_type), diagnostics)?;
-    // All locals are initialized to 0.
-    let init = emit_zero(&ty, builder, diagnostics)?;
+    let ty = ir_type(convert_valtype(wasm_type), config, diagnostics)?;
+    // All locals are initialized to 0, or null in the case of references.
+    let init = match wasm_type {
+        wasmparser::ValType::Ref(_) => builder.ins().i32(NULL_FUNCREF, SourceSpan::default()),
+        _ => emit_zero(&ty, builder, diagnostics)?,
+    };
     for _ in 0..count {
         let local = Variable::new(*next_local);
         builder.declare_var(local, ty.clone());
@@ -163,6 +181,32 @@ fn declare_locals(
     Ok(())
 }
 
+/// Report the initial value of every debug slot of the function, i.e. the values of the locals
+/// and globals holding source-level variables upon entry.
+fn declare_debug_values(
+    builder: &mut FunctionBuilderExt,
+    state: &mut FuncTranslationState,
+    module: &ParsedModule<'_>,
+    config: &WasmTranslationConfig,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<()> {
+    let span = SourceSpan::default();
+    let storages = state.debug_variables.storages().collect::<Vec<_>>();
+    for storage in storages {
+        let value = match storage {
+            Storage::Local(index) => builder.use_var(Variable::from_u32(index)),
+            Storage::Global(index) => {
+                let global_index = GlobalIndex::from_u32(index);
+                let name = module.module.global_name(global_index);
+                let ty = ir_type(module.module.globals[global_index].ty, config, diagnostics)?;
+                builder.ins().load_symbol(name.as_str(), ty, span)
+            }
+        };
+        state.debug_variables.emit_assignment(storage, value, builder, span);
+    }
+    Ok(())
+}
+
 /// Parse the function body in `reader`.
 ///
 /// This assumes that the local variable declarations have already been parsed and function
@@ -176,6 +220,7 @@ fn parse_function_body(
     module: &ParsedModule<'_>,
     mod_types: &ModuleTypes,
     addr2line: &addr2line::Context<DwarfReader<'_>>,
+    config: &WasmTranslationConfig,
     session: &Session,
     func_validator: &mut FuncValidator<impl WasmModuleResources>,
 ) -> WasmResult<()> {
@@ -228,6 +273,7 @@ fn parse_function_body(
             module_state,
             &module.module,
             mod_types,
+            config,
             &session.diagnostics,
             span,
         )?;
@@ -241,7 +287,7 @@ fn parse_function_body(
     // If the exit block is unreachable, it may not have the correct arguments, so we would
     // generate a return instruction that doesn't match the signature.
     if state.reachable && !builder.is_unreachable() {
-        builder.ins().ret(state.stack.first().cloned(), end_span);
+        builder.ins().ret_values(&state.stack, end_span);
     }
 
     // Discard any remaining values on the stack. Either we just returned them,
@@ -17,6 +17,7 @@ use self::types::*;
 use crate::{component::SignatureIndex, error::WasmResult, unsupported_diag};
 
 pub mod build_ir;
+pub mod debug_variables;
 pub mod func_translation_state;
 pub mod func_translator;
 pub mod function_builder_ext;
@@ -116,7 +117,8 @@ pub struct Module {
     /// `passive_elements`.
     pub passive_elements_map: BTreeMap<ElemIndex, usize>,
 
-    /// The map from passive data index (data segment index space) to index in `passive_data`.
+    /// The map from passive data index (data segment index space) to the range of linear memory
+    /// in which the contents of that segment are placed.
     pub passive_data_map: BTreeMap<DataIndex, Range<u32>>,
 
     /// Types declared in the wasm module.
@@ -152,6 +154,9 @@ pub struct Module {
     /// WebAssembly module memories.
     pub memories: PrimaryMap<MemoryIndex, Memory>,
 
+    /// WebAssembly exception tags, imported and local.
+    pub tags: PrimaryMap<TagIndex, Tag>,
+
     /// Parsed names section.
     name_section: NameSection,
 
@@ -266,6 +271,12 @@ impl Module {
             .unwrap_or(Symbol::intern(format!("global{}", index.as_u32()).as_str()))
     }
 
+    /// Returns the name of the global variable which records whether the passive data segment
+    /// `index` has been dropped via `data.drop`
+    pub fn data_dropped_name(&self, index: DataIndex) -> Symbol {
+        Symbol::intern(format!("data{}.dropped", index.as_u32()))
+    }
+
     /// Returns the type of an item based on its index
     pub fn type_of(&self, index: EntityIndex) -> EntityType {
         match index {
@@ -273,6 +284,7 @@ impl Module {
             EntityIndex::Table(i) => EntityType::Table(self.tables[i]),
             EntityIndex::Memory(i) => EntityType::Memory(self.memories[i]),
             EntityIndex::Function(i) => EntityType::Function(self.functions[i].signature),
+            EntityIndex::Tag(i) => EntityType::Tag(self.tags[i]),
         }
     }
 
@@ -22,8 +22,8 @@ use crate::{
         types::{
             convert_func_type, convert_global_type, convert_table_type, convert_valtype,
             DataSegmentOffset, DefinedFuncIndex, ElemIndex, EntityIndex, EntityType, FuncIndex,
-            GlobalIndex, GlobalInit, MemoryIndex, ModuleTypesBuilder, TableIndex, TypeIndex,
-            WasmType,
+            GlobalIndex, GlobalInit, MemoryIndex, ModuleTypesBuilder, TableIndex, TagIndex,
+            TypeIndex, WasmType,
         },
         FuncRefIndex, Module, ModuleType, TableSegment,
     },
@@ -176,12 +176,7 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
             Payload::FunctionSection(functions) => self.function_section(functions)?,
             Payload::TableSection(tables) => self.table_section(tables, diagnostics)?,
             Payload::MemorySection(memories) => self.memory_section(memories)?,
-            Payload::TagSection(tags) => {
-                self.validator.tag_section(&tags).into_diagnostic()?;
-                // This feature isn't enabled at this time, so we should
-                // never get here.
-                unreachable!();
-            }
+            Payload::TagSection(tags) => self.tag_section(tags)?,
             Payload::GlobalSection(globals) => self.global_section(globals, diagnostics)?,
             Payload::ExportSection(exports) => self.export_section(exports)?,
             Payload::StartSection { func, range } => self.start_section(func, range)?,
@@ -277,6 +272,7 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                         minimum: ty.initial,
                         maximum: ty.maximum,
                         imported: true,
+                        memory64: ty.memory64,
                     });
                     EntityType::Memory(ty.into())
                 }
@@ -286,11 +282,9 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                 }
                 TypeRef::Table(ty) => {
                     self.result.module.num_imported_tables += 1;
-                    EntityType::Table(convert_table_type(&ty))
+                    EntityType::Table(convert_table_type(&ty)?)
                 }
-
-                // doesn't get past validation
-                TypeRef::Tag(_) => unreachable!(),
+                TypeRef::Tag(ty) => EntityType::Tag(ty.into()),
             };
             self.declare_import(import.module, import.name, ty);
         }
@@ -323,7 +317,7 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
         self.result.module.tables.reserve_exact(cnt);
         for entry in tables {
             let wasmparser::Table { ty, init } = entry.into_diagnostic()?;
-            let table = convert_table_type(&ty);
+            let table = convert_table_type(&ty)?;
             self.result.module.tables.push(table);
             let init = match init {
                 wasmparser::TableInit::RefNull => TableInitialValue::Null {
@@ -360,8 +354,6 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
         memories: wasmparser::MemorySectionReader<'data>,
     ) -> Result<(), Report> {
         self.validator.memory_section(&memories).into_diagnostic()?;
-        let cnt = usize::try_from(memories.count()).unwrap();
-        assert_eq!(cnt, 1, "only one memory per module is supported");
         for memory in memories {
             let memory = memory.into_diagnostic()?;
             self.result.module.memories.push(memory.into());
@@ -369,6 +361,15 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
         Ok(())
     }
 
+    fn tag_section(&mut self, tags: wasmparser::TagSectionReader<'data>) -> Result<(), Report> {
+        self.validator.tag_section(&tags).into_diagnostic()?;
+        for tag in tags {
+            let tag = tag.into_diagnostic()?;
+            self.result.module.tags.push(tag.into());
+        }
+        Ok(())
+    }
+
     fn global_section(
         &mut self,
         globals: wasmparser::GlobalSectionReader<'data>,
@@ -424,9 +425,7 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                 ExternalKind::Table => EntityIndex::Table(TableIndex::from_u32(index)),
                 ExternalKind::Memory => EntityIndex::Memory(MemoryIndex::from_u32(index)),
                 ExternalKind::Global => EntityIndex::Global(GlobalIndex::from_u32(index)),
-
-                // this never gets past validation
-                ExternalKind::Tag => unreachable!(),
+                ExternalKind::Tag => EntityIndex::Tag(TagIndex::from_u32(index)),
             };
             self.result.module.exports.insert(String::from(name), entity);
         }
@@ -591,14 +590,24 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                     memory_index,
                     offset_expr,
                 } => {
-                    assert_eq!(
-                        memory_index, 0,
-                        "data section memory index must be 0 (only one memory per module is \
-                         supported)"
-                    );
+                    if memory_index != 0 {
+                        unsupported_diag!(
+                            diagnostics,
+                            "wasm error: active data segments are only supported for memory 0, \
+                             got memory {memory_index}"
+                        );
+                    }
                     let mut offset_expr_reader = offset_expr.get_binary_reader();
                     let offset = match offset_expr_reader.read_operator().into_diagnostic()? {
                         Operator::I32Const { value } => DataSegmentOffset::I32Const(value),
+                        // Offsets into 64-bit memories are truncated to 32 bits, like addresses
+                        Operator::I64Const { value } => match u32::try_from(value) {
+                            Ok(value) => DataSegmentOffset::I32Const(value as i32),
+                            Err(_) => unsupported_diag!(
+                                diagnostics,
+                                "wasm error: data segment offset {value} does not fit in 32 bits"
+                            ),
+                        },
                         Operator::GlobalGet { global_index } => {
                             DataSegmentOffset::GetGlobal(GlobalIndex::from_u32(global_index))
                         }
@@ -610,14 +619,15 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
                             );
                         }
                     };
-                    let segment = DataSegment { offset, data };
+                    let segment = DataSegment {
+                        offset: Some(offset),
+                        data,
+                    };
                     self.result.data_segments.push(segment);
                 }
                 DataKind::Passive => {
-                    unsupported_diag!(
-                        diagnostics,
-                        "wasm error: unsupported passive data segment in data section"
-                    );
+                    let segment = DataSegment { offset: None, data };
+                    self.result.data_segments.push(segment);
                 }
             }
         }
@@ -785,6 +795,7 @@ impl<'a, 'data> ModuleEnvironment<'a, 'data> {
             EntityType::Table(ty) => EntityIndex::Table(self.result.module.tables.push(ty)),
             EntityType::Memory(ty) => EntityIndex::Memory(self.result.module.memories.push(ty)),
             EntityType::Global(ty) => EntityIndex::Global(self.result.module.globals.push(ty)),
+            EntityType::Tag(ty) => EntityIndex::Tag(self.result.module.tags.push(ty)),
         }
     }
 
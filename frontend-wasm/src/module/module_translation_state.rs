@@ -1,15 +1,24 @@
+use std::collections::BTreeMap;
+
 use midenc_hir::{
+    cranelift_entity::packed_option::ReservedValue,
     diagnostics::{DiagnosticsHandler, Severity},
-    AbiParam, CallConv, DataFlowGraph, FunctionIdent, Ident, Linkage, Signature,
+    AbiParam, CallConv, DataFlowGraph, FunctionIdent, Ident, Linkage, Signature, Type,
 };
 use rustc_hash::FxHashMap;
 
-use super::{instance::ModuleArgument, ir_func_type, EntityIndex, FuncIndex, Module, ModuleTypes};
+use super::{
+    instance::ModuleArgument, ir_func_type, EntityIndex, FuncIndex, GlobalInit, Module,
+    ModuleTypes, TableIndex, TableInitialValue,
+};
 use crate::{
+    component::SignatureIndex,
+    config::WasmTranslationConfig,
     error::WasmResult,
     intrinsics::is_miden_intrinsics_module,
     miden_abi::{is_miden_abi_module, miden_abi_function_type, recover_imported_masm_function_id},
     translation_utils::sig_from_func_type,
+    unsupported_diag,
 };
 
 pub struct ModuleTranslationState {
@@ -18,6 +27,10 @@ pub struct ModuleTranslationState {
     functions: FxHashMap<FuncIndex, (FunctionIdent, Signature)>,
     /// Number of imported or aliased functions in the module.
     pub num_imported_funcs: usize,
+    /// The contents of the function tables used by the module, computed on first use
+    tables: FxHashMap<TableIndex, Vec<Option<FuncIndex>>>,
+    /// The functions dispatching the indirect calls of the module, by table and signature
+    dispatchers: BTreeMap<(TableIndex, SignatureIndex), Dispatcher>,
     // stable_imported_miden_abi_functions: FxHashMap<FunctionIdent, String>,
 }
 
@@ -26,6 +39,7 @@ impl ModuleTranslationState {
         module: &Module,
         mod_types: &ModuleTypes,
         module_args: Vec<ModuleArgument>,
+        config: &WasmTranslationConfig,
         diagnostics: &DiagnosticsHandler,
     ) -> Self {
         let mut function_import_subst = FxHashMap::default();
@@ -53,7 +67,7 @@ impl ModuleTranslationState {
         let mut functions = FxHashMap::default();
         for (index, func_type) in &module.functions {
             let wasm_func_type = mod_types[func_type.signature].clone();
-            let ir_func_type = ir_func_type(&wasm_func_type, diagnostics).unwrap();
+            let ir_func_type = ir_func_type(&wasm_func_type, config, diagnostics).unwrap();
             let sig = sig_from_func_type(&ir_func_type, CallConv::SystemV, Linkage::External);
             if let Some(subst) = function_import_subst.get(&index) {
                 functions.insert(index, (*subst, sig));
@@ -75,6 +89,8 @@ impl ModuleTranslationState {
         Self {
             functions,
             num_imported_funcs: module.num_imported_funcs,
+            tables: FxHashMap::default(),
+            dispatchers: BTreeMap::default(),
         }
     }
 
@@ -84,6 +100,98 @@ impl ModuleTranslationState {
         &self.functions[&index].1
     }
 
+    /// Returns the contents of the table `index` after instantiation, where `None` represents a
+    /// null entry.
+    ///
+    /// Tables are never modified after instantiation, so the contents are fully determined by the
+    /// table initializer and the active element segments of the module.
+    pub(crate) fn table_elements(
+        &mut self,
+        module: &Module,
+        index: TableIndex,
+        diagnostics: &DiagnosticsHandler,
+    ) -> WasmResult<&[Option<FuncIndex>]> {
+        if !self.tables.contains_key(&index) {
+            let elements = initial_table_elements(module, index, diagnostics)?;
+            self.tables.insert(index, elements);
+        }
+        Ok(self.tables[&index].as_slice())
+    }
+
+    /// Get the function dispatching the indirect calls through table `table_index` to functions
+    /// with signature `sig_index`, declaring it on first use.
+    ///
+    /// The dispatcher is shared by all such calls, so that the dispatch over the entries of the
+    /// table is emitted once per signature, rather than at every call site. It is defined after
+    /// all of the functions of the module are translated, see [Self::take_dispatchers].
+    ///
+    /// Import the dispatcher into `func`'s DFG if it is not already present.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn get_dispatcher(
+        &mut self,
+        dfg: &mut DataFlowGraph,
+        module: &Module,
+        mod_types: &ModuleTypes,
+        table_index: TableIndex,
+        sig_index: SignatureIndex,
+        config: &WasmTranslationConfig,
+        diagnostics: &DiagnosticsHandler,
+    ) -> WasmResult<FunctionIdent> {
+        let key = (table_index, sig_index);
+        if !self.dispatchers.contains_key(&key) {
+            let wasm_func_type = &mod_types[sig_index];
+            let ir_func_type = ir_func_type(wasm_func_type, config, diagnostics)?;
+            let signature = sig_from_func_type(&ir_func_type, CallConv::SystemV, Linkage::Internal);
+            // Calling an entry with any other signature traps, so such entries are treated the
+            // same as null entries
+            let table = self
+                .table_elements(module, table_index, diagnostics)?
+                .iter()
+                .map(|entry| {
+                    entry.filter(|func_index| {
+                        mod_types[module.functions[*func_index].signature] == *wasm_func_type
+                    })
+                })
+                .collect();
+            let name =
+                format!("call_indirect_table{}_sig{}", table_index.as_u32(), sig_index.as_u32());
+            let id = FunctionIdent {
+                module: module.name(),
+                function: Ident::from(name.as_str()),
+            };
+            self.dispatchers.insert(
+                key,
+                Dispatcher {
+                    id,
+                    signature,
+                    table,
+                },
+            );
+        }
+
+        let dispatcher = &self.dispatchers[&key];
+        if dfg.get_import(&dispatcher.id).is_none() {
+            let sig = dispatcher.dispatch_signature();
+            dfg.import_function(dispatcher.id.module, dispatcher.id.function, sig).map_err(
+                |_e| {
+                    let message = format!(
+                        "Function with name {} in module {} is already imported with a different \
+                         signature than the one of an indirect call dispatcher",
+                        dispatcher.id.function, dispatcher.id.module
+                    );
+                    diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
+                },
+            )?;
+        }
+        Ok(dispatcher.id)
+    }
+
+    /// Takes the dispatchers declared by the indirect calls of the module, in a deterministic
+    /// order, see [Self::get_dispatcher]
+    pub(crate) fn take_dispatchers(&mut self) -> Vec<Dispatcher> {
+        core::mem::take(&mut self.dispatchers).into_values().collect()
+    }
+
     /// Get the `FunctionIdent` that should be used to make a direct call to function
     /// `index`.
     ///
@@ -132,3 +240,75 @@ impl ModuleTranslationState {
         Ok(func_id)
     }
 }
+
+/// A function shared by the indirect calls through a table to functions with a given signature,
+/// which calls the entry of the table selected by its last parameter, and traps if the entry is
+/// null, out of bounds, or has a different signature.
+pub(crate) struct Dispatcher {
+    pub id: FunctionIdent,
+    /// The signature of the functions called through the dispatcher
+    pub signature: Signature,
+    /// The entries of the table having `signature`, the others are `None`
+    pub table: Vec<Option<FuncIndex>>,
+}
+
+impl Dispatcher {
+    /// The signature of the dispatcher: the params of the callee, followed by its `u32` index in
+    /// the table, and the results of the callee
+    pub fn dispatch_signature(&self) -> Signature {
+        let mut sig = self.signature.clone();
+        sig.params.push(AbiParam::new(Type::U32));
+        sig
+    }
+}
+
+/// Computes the contents of the table `index` after instantiation of `module`
+fn initial_table_elements(
+    module: &Module,
+    index: TableIndex,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<Vec<Option<FuncIndex>>> {
+    let Some(defined_index) = module.defined_table_index(index) else {
+        unsupported_diag!(diagnostics, "Imported tables are not supported");
+    };
+    let as_entry = |func: FuncIndex| (!func.is_reserved_value()).then_some(func);
+    let size = module.tables[index].minimum as usize;
+    let mut elements = match module.table_initialization.initial_values[defined_index] {
+        TableInitialValue::Null { ref precomputed } => {
+            let mut elements = vec![None; size];
+            for (entry, func) in elements.iter_mut().zip(precomputed.iter().copied()) {
+                *entry = as_entry(func);
+            }
+            elements
+        }
+        TableInitialValue::FuncRef(func) => vec![as_entry(func); size],
+    };
+    for segment in module.table_initialization.segments.iter().filter(|s| s.table_index == index) {
+        let base = match segment.base {
+            None => 0,
+            Some(global) => match module
+                .defined_global_index(global)
+                .map(|global| module.global_initializers[global])
+            {
+                Some(GlobalInit::I32Const(base)) => base as u32,
+                _ => unsupported_diag!(
+                    diagnostics,
+                    "Element segment offsets must be a constant or a constant global"
+                ),
+            },
+        };
+        let start = base.wrapping_add(segment.offset) as usize;
+        let Some(entries) = elements.get_mut(start..start + segment.elements.len()) else {
+            return Err(diagnostics
+                .diagnostic(Severity::Error)
+                .with_message(format!(
+                    "Element segment at offset {start} is out of bounds for table of size {size}"
+                ))
+                .into_report());
+        };
+        for (entry, func) in entries.iter_mut().zip(segment.elements.iter().copied()) {
+            *entry = as_entry(func);
+        }
+    }
+    Ok(elements)
+}
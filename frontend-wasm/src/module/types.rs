@@ -11,7 +11,13 @@ use midenc_hir::{
 use midenc_hir_type::{self as hir, Abi};
 use wasmparser::types::CoreTypeId;
 
-use crate::{component::SignatureIndex, error::WasmResult, module::Module, unsupported_diag};
+use crate::{
+    component::SignatureIndex,
+    config::WasmTranslationConfig,
+    error::{WasmError, WasmResult},
+    module::Module,
+    unsupported_diag,
+};
 
 /// Generates a new index type for each entity.
 #[macro_export]
@@ -70,6 +76,9 @@ pub struct TypeIndex(u32);
 /// Index type of a data segment inside the WebAssembly module.
 pub struct DataSegmentIndex(u32);
 
+/// Index type of an exception tag (imported or defined) inside the WebAssembly module.
+pub struct TagIndex(u32);
+
 }
 
 /// WebAssembly value type -- equivalent of `wasmparser`'s Type.
@@ -147,6 +156,58 @@ pub enum WasmHeapType {
     ///
     /// Introduced in the references-types proposal.
     Extern,
+    /// The bottom type of the function reference hierarchy, i.e. a null function reference.
+    ///
+    /// Introduced in the GC proposal.
+    NoFunc,
+    /// The bottom type of the external reference hierarchy, i.e. a null external reference.
+    ///
+    /// Introduced in the GC proposal.
+    NoExtern,
+    /// The top type of the internal (GC) reference hierarchy.
+    ///
+    /// Introduced in the GC proposal.
+    Any,
+    /// The bottom type of the internal (GC) reference hierarchy.
+    ///
+    /// Introduced in the GC proposal.
+    None,
+    /// References which can be compared for equality.
+    ///
+    /// Introduced in the GC proposal.
+    Eq,
+    /// References to structs.
+    ///
+    /// Introduced in the GC proposal.
+    Struct,
+    /// References to arrays.
+    ///
+    /// Introduced in the GC proposal.
+    Array,
+    /// Unboxed 31-bit scalars.
+    ///
+    /// Introduced in the GC proposal.
+    I31,
+    /// References to exceptions.
+    ///
+    /// Introduced in the exception-handling proposal.
+    Exn,
+    /// The bottom type of the exception reference hierarchy.
+    ///
+    /// Introduced in the exception-handling proposal.
+    NoExn,
+}
+
+impl WasmHeapType {
+    /// Returns true if references of this type are function references
+    pub fn is_func(&self) -> bool {
+        matches!(self, Self::Func | Self::NoFunc)
+    }
+
+    /// Returns true if references of this type are exception references
+    pub fn is_exn(&self) -> bool {
+        matches!(self, Self::Exn | Self::NoExn)
+    }
 }
 
 impl fmt::Display for WasmHeapType {
@@ -154,6 +215,16 @@ impl fmt::Display for WasmHeapType {
         match self {
             Self::Func => write!(f, "func"),
             Self::Extern => write!(f, "extern"),
+            Self::NoFunc => write!(f, "nofunc"),
+            Self::NoExtern => write!(f, "noextern"),
+            Self::Any => write!(f, "any"),
+            Self::None => write!(f, "none"),
+            Self::Eq => write!(f, "eq"),
+            Self::Struct => write!(f, "struct"),
+            Self::Array => write!(f, "array"),
+            Self::I31 => write!(f, "i31"),
+            Self::Exn => write!(f, "exn"),
+            Self::NoExn => write!(f, "noexn"),
         }
     }
 }
@@ -228,6 +299,8 @@ pub enum EntityIndex {
     Memory(MemoryIndex),
     /// Global index.
     Global(GlobalIndex),
+    /// Tag index.
+    Tag(TagIndex),
 }
 
 impl EntityIndex {
@@ -252,6 +325,8 @@ pub enum EntityType {
     /// A function type where the index points to the type section and records a
     /// function signature.
     Function(SignatureIndex),
+    /// An exception tag, whose type describes the values carried by the exception
+    Tag(Tag),
 }
 
 impl EntityType {
@@ -368,6 +443,8 @@ pub struct Memory {
     pub maximum: Option<u64>,
     /// Is this memory imported in the current [Module]
     pub imported: bool,
+    /// Is this a 64-bit memory, i.e. are addresses in this memory `i64` rather than `i32`
+    pub memory64: bool,
 }
 
 impl From<wasmparser::MemoryType> for Memory {
@@ -376,6 +453,7 @@ impl From<wasmparser::MemoryType> for Memory {
             minimum: ty.initial,
             maximum: ty.maximum,
             imported: false,
+            memory64: ty.memory64,
         }
     }
 }
@@ -434,8 +512,9 @@ impl DataSegmentOffset {
 /// https://www.w3.org/TR/wasm-core-1/#data-segments%E2%91%A0
 #[derive(Debug)]
 pub struct DataSegment<'a> {
-    /// The offset of the data segment inside the linear memory.
-    pub offset: DataSegmentOffset,
+    /// The offset of the data segment inside the linear memory, or `None` if this is a passive
+    /// segment, which is only copied into linear memory by `memory.init`.
+    pub offset: Option<DataSegmentOffset>,
     /// The initialization data.
     pub data: &'a [u8],
 }
@@ -450,25 +529,26 @@ impl BlockType {
     pub fn from_wasm(
         block_ty: &wasmparser::BlockType,
         mod_types: &ModuleTypes,
+        config: &WasmTranslationConfig,
         diagnostics: &DiagnosticsHandler,
     ) -> WasmResult<Self> {
         Ok(match block_ty {
             wasmparser::BlockType::Empty => Self::default(),
             wasmparser::BlockType::Type(ty) => Self {
                 params: vec![],
-                results: vec![ir_type(convert_valtype(*ty), diagnostics)?],
+                results: vec![ir_type(convert_valtype(*ty), config, diagnostics)?],
             },
             wasmparser::BlockType::FuncType(ty_index) => {
                 let func_type = &mod_types[SignatureIndex::from_u32(*ty_index)];
                 let params = func_type
                     .params()
                     .iter()
-                    .map(|t| ir_type(*t, diagnostics))
+                    .map(|t| ir_type(*t, config, diagnostics))
                     .collect::<WasmResult<Vec<hir::Type>>>()?;
                 let results = func_type
                     .returns()
                     .iter()
-                    .map(|t| ir_type(*t, diagnostics))
+                    .map(|t| ir_type(*t, config, diagnostics))
                     .collect::<WasmResult<Vec<hir::Type>>>()?;
                 Self { params, results }
             }
@@ -559,17 +639,18 @@ where
 /// Converts a Wasm function type into a Miden IR function type
 pub fn ir_func_type(
     ty: &WasmFuncType,
+    config: &WasmTranslationConfig,
     diagnostics: &DiagnosticsHandler,
 ) -> WasmResult<hir::FunctionType> {
     let params = ty
         .params()
         .iter()
-        .map(|t| ir_type(*t, diagnostics))
+        .map(|t| ir_type(*t, config, diagnostics))
         .collect::<WasmResult<Vec<hir::Type>>>()?;
     let results = ty
         .returns()
         .iter()
-        .map(|t| ir_type(*t, diagnostics))
+        .map(|t| ir_type(*t, config, diagnostics))
         .collect::<WasmResult<Vec<hir::Type>>>()?;
     Ok(hir::FunctionType {
         abi: Abi::Canonical,
@@ -579,17 +660,36 @@ pub fn ir_func_type(
 }
 
 /// Converts a Wasm type into a Miden IR type
-pub fn ir_type(ty: WasmType, diagnostics: &DiagnosticsHandler) -> WasmResult<hir::Type> {
+///
+/// 64-bit floats are only supported when soft-float lowering is enabled in `config`.
+pub fn ir_type(
+    ty: WasmType,
+    config: &WasmTranslationConfig,
+    diagnostics: &DiagnosticsHandler,
+) -> WasmResult<hir::Type> {
     Ok(match ty {
         WasmType::I32 => hir::Type::I32,
         WasmType::I64 => hir::Type::I64,
         WasmType::F32 => hir::Type::Felt,
+        WasmType::F64 if config.soft_float => hir::Type::F64,
+        WasmType::Ref(rt) if rt.heap_type.is_func() || rt.heap_type.is_exn() => hir::Type::I32,
         ty @ (WasmType::F64 | WasmType::V128 | WasmType::Ref(_)) => {
             unsupported_diag!(diagnostics, "wasm error: unsupported type '{}'", ty)
         }
     })
 }
 
+/// The value of a null function reference.
+///
+/// A function reference is represented by an index into a table, which is how it is called via
+/// `call_indirect`: `table.get` produces the index it was given, and `ref.func` produces the index
+/// of the function in table 0. The null reference is out of bounds of any table, so calling it
+/// traps.
+///
+/// Exception references share this representation, but are always null, as exceptions are never
+/// caught.
+pub const NULL_FUNCREF: i32 = -1;
+
 /// Makes an IR function signature from a Wasm function type
 pub fn ir_func_sig(
     func_type: &hir::FunctionType,
@@ -613,14 +713,18 @@ pub fn convert_global_type(ty: &wasmparser::GlobalType) -> Global {
 }
 
 /// Converts a wasmparser table type
-pub fn convert_table_type(ty: &wasmparser::TableType) -> Table {
-    assert!(!ty.table64, "64-bit tables are not supported");
+///
+/// Returns an error if `ty` is a 64-bit table, as those are not supported.
+pub fn convert_table_type(ty: &wasmparser::TableType) -> WasmResult<Table> {
+    if ty.table64 {
+        return Err(WasmError::Unsupported("64-bit tables are not supported".to_string()).into());
+    }
 
-    Table {
+    Ok(Table {
         wasm_ty: convert_ref_type(ty.element_type),
         minimum: ty.initial as u32,
         maximum: ty.maximum.map(|n| n as u32),
-    }
+    })
 }
 
 /// Converts a wasmparser function type
@@ -657,10 +761,19 @@ pub fn convert_heap_type(ty: wasmparser::HeapType) -> WasmHeapType {
         wasmparser::HeapType::Abstract { ty, shared: _ } => match ty {
             AbstractHeapType::Func => WasmHeapType::Func,
             AbstractHeapType::Extern => WasmHeapType::Extern,
-            ty => unimplemented!("unsupported abstract heap type {ty:?}"),
+            AbstractHeapType::NoFunc => WasmHeapType::NoFunc,
+            AbstractHeapType::NoExtern => WasmHeapType::NoExtern,
+            AbstractHeapType::Any => WasmHeapType::Any,
+            AbstractHeapType::None => WasmHeapType::None,
+            AbstractHeapType::Eq => WasmHeapType::Eq,
+            AbstractHeapType::Struct => WasmHeapType::Struct,
+            AbstractHeapType::Array => WasmHeapType::Array,
+            AbstractHeapType::I31 => WasmHeapType::I31,
+            AbstractHeapType::Exn => WasmHeapType::Exn,
+            AbstractHeapType::NoExn => WasmHeapType::NoExn,
         },
-        wasmparser::HeapType::Concrete(_) => {
-            unimplemented!("user-defined types are not supported yet")
-        }
+        // Without the GC proposal, the only user-defined types are function types, and a typed
+        // function reference is represented the same way as any other function reference
+        wasmparser::HeapType::Concrete(_) => WasmHeapType::Func,
     }
 }
@@ -130,6 +130,7 @@ pub fn emit_zero(
         | Type::Struct(_)
         | Type::Array(..)
         | Type::List(_)
+        | Type::Union(_)
         | Type::Unknown
         | Type::Unit
         | Type::Never => {
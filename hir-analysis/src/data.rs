@@ -1,6 +1,7 @@
 use midenc_hir::{
     pass::{Analysis, AnalysisManager, AnalysisResult},
-    Function, FunctionIdent, GlobalValue, GlobalValueData, GlobalVariableTable, Module, Program,
+    Function, FunctionIdent, GlobalValue, GlobalValueData, GlobalVariableTable, Ident, Module,
+    Program,
 };
 use midenc_session::Session;
 use rustc_hash::FxHashMap;
@@ -33,26 +34,11 @@ impl Analysis for GlobalVariableAnalysis<Program> {
         _analyses: &mut AnalysisManager,
         _session: &Session,
     ) -> AnalysisResult<Self> {
-        let mut layout = GlobalVariableLayout {
-            global_table_offset: core::cmp::max(
-                program.reserved_memory_bytes().next_multiple_of(32),
-                program.segments().next_available_offset(),
-            ),
-            ..GlobalVariableLayout::default()
-        };
-
+        let mut layout = GlobalVariableLayout::for_program(program);
         let globals = program.globals();
         for module in program.modules().iter() {
             for function in module.functions() {
-                let mut function_offsets = FxHashMap::default();
-                for gv in function.dfg.globals.keys() {
-                    if let Some(addr) =
-                        compute_global_value_addr(gv, layout.global_table_offset, function, globals)
-                    {
-                        function_offsets.insert(gv, addr);
-                    }
-                }
-                layout.offsets.insert(function.id, function_offsets);
+                layout.compute_function_addrs(function, globals);
             }
         }
 
@@ -80,16 +66,9 @@ impl Analysis for GlobalVariableAnalysis<Module> {
         };
 
         let globals = module.globals();
+        layout.compute_symbol_addrs(globals);
         for function in module.functions() {
-            let mut function_offsets = FxHashMap::default();
-            for gv in function.dfg.globals.keys() {
-                if let Some(addr) =
-                    compute_global_value_addr(gv, layout.global_table_offset, function, globals)
-                {
-                    function_offsets.insert(gv, addr);
-                }
-            }
-            layout.offsets.insert(function.id, function_offsets);
+            layout.compute_function_addrs(function, globals);
         }
 
         Ok(Self {
@@ -104,6 +83,7 @@ impl Analysis for GlobalVariableAnalysis<Module> {
 pub struct GlobalVariableLayout {
     global_table_offset: u32,
     offsets: FxHashMap<FunctionIdent, FxHashMap<GlobalValue, u32>>,
+    symbols: FxHashMap<Ident, u32>,
 }
 impl GlobalVariableLayout {
     /// Get the address/offset at which global variables will start being allocated
@@ -119,6 +99,54 @@ impl GlobalVariableLayout {
     pub fn get_computed_addr(&self, function: &FunctionIdent, gv: GlobalValue) -> Option<u32> {
         self.offsets.get(function).and_then(|offsets| offsets.get(&gv).copied())
     }
+
+    /// Get the statically-allocated address of the global variable named `name`.
+    ///
+    /// This function returns `None` if no such global variable is defined.
+    pub fn get_symbol_addr(&self, name: Ident) -> Option<u32> {
+        self.symbols.get(&name).copied()
+    }
+
+    /// Compute the layout of the global variables of `program` as seen from `function`.
+    ///
+    /// Unlike [GlobalVariableAnalysis], `function` need not be part of `program`, which is useful
+    /// when generating code for a function which is in the midst of being rewritten.
+    pub fn for_function(program: &Program, function: &Function) -> Self {
+        let mut layout = Self::for_program(program);
+        layout.compute_function_addrs(function, program.globals());
+        layout
+    }
+
+    fn for_program(program: &Program) -> Self {
+        let mut layout = GlobalVariableLayout {
+            global_table_offset: core::cmp::max(
+                program.reserved_memory_bytes().next_multiple_of(32),
+                program.segments().next_available_offset(),
+            ),
+            ..GlobalVariableLayout::default()
+        };
+        layout.compute_symbol_addrs(program.globals());
+        layout
+    }
+
+    fn compute_function_addrs(&mut self, function: &Function, globals: &GlobalVariableTable) {
+        let mut function_offsets = FxHashMap::default();
+        for gv in function.dfg.globals.keys() {
+            if let Some(addr) =
+                compute_global_value_addr(gv, self.global_table_offset, function, globals)
+            {
+                function_offsets.insert(gv, addr);
+            }
+        }
+        self.offsets.insert(function.id, function_offsets);
+    }
+
+    fn compute_symbol_addrs(&mut self, globals: &GlobalVariableTable) {
+        for global in globals.iter() {
+            let offset = unsafe { globals.offset_of(global.id()) };
+            self.symbols.insert(global.name, self.global_table_offset + offset);
+        }
+    }
 }
 
 /// Computes the absolute offset (address) represented by the given global value
@@ -7,6 +7,7 @@ pub mod dependency_graph;
 mod dominance;
 mod liveness;
 mod loops;
+mod recursion;
 pub mod spill;
 mod treegraph;
 mod validation;
@@ -19,6 +20,7 @@ pub use self::{
     dominance::{DominanceFrontier, DominatorTree, DominatorTreePreorder},
     liveness::LivenessAnalysis,
     loops::{Loop, LoopAnalysis, LoopLevel},
+    recursion::RecursionAnalysis,
     spill::{Reload, ReloadInfo, Spill, SpillAnalysis, SpillInfo},
     treegraph::{OrderedTreeGraph, TreeGraph},
     validation::{ModuleValidationAnalysis, Rule},
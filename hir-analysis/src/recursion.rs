@@ -0,0 +1,33 @@
+use midenc_hir::{
+    pass::{Analysis, AnalysisManager, AnalysisResult},
+    Program, RecursiveCalls,
+};
+use midenc_session::Session;
+
+/// This analysis provides the set of recursive calls in a [Program], as determined by the linker
+/// when the program was linked.
+///
+/// See [RecursiveCalls] for details on how such calls are handled.
+#[derive(Default)]
+pub struct RecursionAnalysis {
+    calls: RecursiveCalls,
+}
+impl RecursionAnalysis {
+    pub fn calls(&self) -> &RecursiveCalls {
+        &self.calls
+    }
+}
+
+impl Analysis for RecursionAnalysis {
+    type Entity = Program;
+
+    fn analyze(
+        program: &Self::Entity,
+        _analyses: &mut AnalysisManager,
+        _session: &Session,
+    ) -> AnalysisResult<Self> {
+        Ok(Self {
+            calls: program.recursive_calls().clone(),
+        })
+    }
+}
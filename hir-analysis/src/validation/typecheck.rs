@@ -230,6 +230,34 @@ impl<'a> Rule<BlockData> for TypeCheck<'a> {
                         }
                     }
                 }
+                Instruction::Call(Call {
+                    op: Opcode::TailExec,
+                    ref callee,
+                    ref args,
+                }) => {
+                    // The results of the callee are returned to the caller of this function
+                    let matches = self.dfg.get_import(callee).is_some_and(|import| {
+                        let types = |params: &[AbiParam]| {
+                            params.iter().map(|p| p.ty.clone()).collect::<Vec<_>>()
+                        };
+                        types(&import.signature.results) == types(&self.signature.results)
+                    });
+                    if !matches {
+                        return Err(diagnostics
+                            .diagnostic(Severity::Error)
+                            .with_message("invalid instruction")
+                            .with_primary_label(
+                                span,
+                                format!(
+                                    "{callee} cannot be called in tail position, as its results \
+                                     differ from those of the function signature"
+                                ),
+                            )
+                            .into_report());
+                    }
+                    let args = args.as_slice(&self.dfg.value_lists);
+                    typechecker.check(args, results)?;
+                }
                 Instruction::GlobalValue(_)
                 | Instruction::BinaryOp(_)
                 | Instruction::PrimOp(_)
@@ -239,6 +267,38 @@ impl<'a> Rule<BlockData> for TypeCheck<'a> {
                     let args = node.arguments(&self.dfg.value_lists);
                     typechecker.check(args, results)?;
                 }
+                Instruction::CallIndirect(CallIndirect {
+                    ref signature,
+                    ref table,
+                    ref args,
+                    ..
+                }) => {
+                    // Every function in the table must be callable with the expected signature
+                    for callee in table.iter().flatten() {
+                        let matches = self.dfg.get_import(callee).is_some_and(|import| {
+                            let types = |params: &[AbiParam]| {
+                                params.iter().map(|p| p.ty.clone()).collect::<Vec<_>>()
+                            };
+                            types(&import.signature.params) == types(&signature.params)
+                                && types(&import.signature.results) == types(&signature.results)
+                        });
+                        if !matches {
+                            return Err(diagnostics
+                                .diagnostic(Severity::Error)
+                                .with_message("invalid instruction")
+                                .with_primary_label(
+                                    span,
+                                    format!(
+                                        "{callee} is not imported with the signature expected by \
+                                         this indirect call"
+                                    ),
+                                )
+                                .into_report());
+                        }
+                    }
+                    let args = args.as_slice(&self.dfg.value_lists);
+                    typechecker.check(args, results)?;
+                }
                 Instruction::Ret(Ret { ref args, .. }) => {
                     let args = args.as_slice(&self.dfg.value_lists);
                     if args.len() != self.signature.results.len() {
@@ -495,6 +555,8 @@ pub enum TypePattern {
     /// Matches any signed integer type
     #[allow(dead_code)]
     Sint,
+    /// Matches any integer or floating-point type
+    IntOrFloat,
     /// Matches any pointer type
     Pointer,
     /// Matches any primitive numeric or pointer type
@@ -510,6 +572,7 @@ impl TypePattern {
             Self::Int => ty.is_integer(),
             Self::Uint => ty.is_unsigned_integer(),
             Self::Sint => ty.is_signed_integer(),
+            Self::IntOrFloat => ty.is_integer() || ty.is_float(),
             Self::Pointer => ty.is_pointer(),
             Self::Primitive => ty.is_numeric() || ty.is_pointer(),
             Self::Exact(expected) => expected.eq(ty),
@@ -529,6 +592,7 @@ impl fmt::Display for TypePattern {
             Self::Int => f.write_str("integer"),
             Self::Uint => f.write_str("unsigned integer"),
             Self::Sint => f.write_str("signed integer"),
+            Self::IntOrFloat => f.write_str("integer or float"),
             Self::Pointer => f.write_str("pointer"),
             Self::Primitive => f.write_str("primitive"),
             Self::Exact(ty) => write!(f, "{ty}"),
@@ -1195,7 +1259,9 @@ impl<'a> InstTypeChecker<'a> {
             ),
             Opcode::PtrToInt => InstPattern::UnaryMap(TypePattern::Pointer, TypePattern::Int),
             Opcode::IntToPtr => InstPattern::UnaryMap(TypePattern::Uint, TypePattern::Pointer),
-            Opcode::Bitcast => InstPattern::UnaryMap(TypePattern::Int, TypePattern::Int),
+            Opcode::Bitcast => {
+                InstPattern::UnaryMap(TypePattern::IntOrFloat, TypePattern::IntOrFloat)
+            }
             Opcode::Cast => InstPattern::UnaryMap(TypePattern::Int, TypePattern::Int),
             Opcode::Trunc => InstPattern::UnaryNarrowingCast(TypePattern::Int, TypePattern::Int),
             Opcode::Zext => InstPattern::UnaryWideningCast(TypePattern::Int, TypePattern::Uint),
@@ -1233,7 +1299,8 @@ impl<'a> InstTypeChecker<'a> {
             }
             Opcode::IsOdd => InstPattern::Exact(vec![TypePattern::Int], vec![Type::I1.into()]),
             Opcode::Min | Opcode::Max => InstPattern::BinaryMatching(TypePattern::Int),
-            Opcode::Exec | Opcode::Call | Opcode::Syscall => match node.as_ref() {
+            Opcode::Exec | Opcode::Call | Opcode::Syscall | Opcode::TailExec => match node.as_ref()
+            {
                 Instruction::Call(Call { ref callee, .. }) => {
                     if let Some(import) = dfg.get_import(callee) {
                         let args = import
@@ -1242,12 +1309,16 @@ impl<'a> InstTypeChecker<'a> {
                             .iter()
                             .map(|p| TypePattern::Exact(p.ty.clone()))
                             .collect();
-                        let results = import
-                            .signature
-                            .results
-                            .iter()
-                            .map(|p| TypePattern::Exact(p.ty.clone()))
-                            .collect();
+                        // Tail calls have no results of their own
+                        let results = match opcode {
+                            Opcode::TailExec => vec![],
+                            _ => import
+                                .signature
+                                .results
+                                .iter()
+                                .map(|p| TypePattern::Exact(p.ty.clone()))
+                                .collect(),
+                        };
                         InstPattern::Exact(args, results)
                     } else {
                         return Err(diagnostics
@@ -1265,6 +1336,21 @@ impl<'a> InstTypeChecker<'a> {
                 }
                 inst => panic!("invalid opcode '{opcode}' for {inst:#?}"),
             },
+            Opcode::CallIndirect => match node.as_ref() {
+                Instruction::CallIndirect(CallIndirect { ref signature, .. }) => {
+                    let args = core::iter::once(Type::U32)
+                        .chain(signature.params.iter().map(|p| p.ty.clone()))
+                        .map(TypePattern::Exact)
+                        .collect();
+                    let results = signature
+                        .results
+                        .iter()
+                        .map(|p| TypePattern::Exact(p.ty.clone()))
+                        .collect();
+                    InstPattern::Exact(args, results)
+                }
+                inst => panic!("invalid opcode '{opcode}' for {inst:#?}"),
+            },
             Opcode::Br => InstPattern::Any,
             Opcode::CondBr => InstPattern::Exact(vec![Type::I1.into()], vec![]),
             Opcode::Switch => InstPattern::Exact(vec![Type::U32.into()], vec![]),
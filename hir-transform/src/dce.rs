@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+
+use midenc_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use midenc_hir_analysis::{ControlFlowGraph, DominatorTree};
+use midenc_session::{diagnostics::IntoDiagnostic, Session};
+use rustc_hash::FxHashSet;
+use smallvec::SmallVec;
+
+/// This pass removes code which has no observable effect on the behavior of a function.
+///
+/// Specifically, it removes:
+///
+/// * Blocks which are unreachable from the entry block
+/// * Instructions without side effects, none of whose results are used
+/// * Block parameters which are never used, along with the corresponding arguments passed by
+///   each predecessor of that block
+///
+/// Liveness is computed optimistically: a value is only considered live if it is used by an
+/// instruction with side effects (including terminators), or by the definition of some other live
+/// value. As a result, values which are only used to compute each other, e.g. a loop counter
+/// threaded through a block argument which is never read outside of the loop, are also removed.
+///
+/// The parameters of the entry block are never removed, as they are dictated by the function
+/// signature.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct EliminateDeadCode;
+impl RewritePass for EliminateDeadCode {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let cfg = analyses.get_or_compute::<ControlFlowGraph>(function, session)?;
+        let domtree = analyses.get_or_compute::<DominatorTree>(function, session)?;
+
+        // Remove all blocks which are unreachable from the entry
+        let unreachable = function
+            .dfg
+            .blocks()
+            .map(|(b, _)| b)
+            .filter(|b| !domtree.is_reachable(*b))
+            .collect::<SmallVec<[Block; 4]>>();
+        for block in unreachable.iter().copied() {
+            function.dfg.detach_block(block);
+        }
+
+        let live = compute_live_values(function, &cfg);
+
+        // Remove dead block parameters, starting from the last parameter of each block, so that
+        // the indices of the remaining parameters remain valid while we remove them.
+        let mut changed = !unreachable.is_empty();
+        let entry = function.dfg.entry_block();
+        let blocks = domtree.cfg_postorder().to_vec();
+        for block in blocks.iter().copied() {
+            if block == entry {
+                continue;
+            }
+            let params = SmallVec::<[Value; 4]>::from_slice(function.dfg.block_params(block));
+            let mut predecessors = cfg
+                .pred_iter(block)
+                .filter(|pred| domtree.is_reachable(pred.block))
+                .map(|pred| pred.inst)
+                .collect::<SmallVec<[Inst; 4]>>();
+            predecessors.sort();
+            predecessors.dedup();
+            for (index, param) in params.iter().copied().enumerate().rev() {
+                if live.contains(&param) {
+                    continue;
+                }
+                for pred in predecessors.iter().copied() {
+                    function.dfg.remove_branch_destination_argument(pred, block, index);
+                }
+                function.dfg.remove_block_param(param);
+                changed = true;
+            }
+        }
+
+        // Remove dead instructions
+        for block in blocks {
+            let insts = function.dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                if function.dfg.inst(inst).has_side_effects() {
+                    continue;
+                }
+                if function.dfg.inst_results(inst).iter().any(|v| live.contains(v)) {
+                    continue;
+                }
+                function.dfg.block_mut(block).cursor_mut_at_inst(inst).remove();
+                changed = true;
+            }
+        }
+
+        if !changed {
+            analyses.mark_all_preserved::<Function>(&function.id);
+        } else if unreachable.is_empty() {
+            // We only modified the contents of blocks, not the shape of the CFG
+            analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+            analyses.mark_preserved::<DominatorTree>(&function.id);
+        }
+
+        session.print(&*function, Self::FLAG).into_diagnostic()?;
+        if session.should_print_cfg(Self::FLAG) {
+            use std::io::Write;
+            let cfg = function.cfg_printer();
+            let mut stdout = std::io::stdout().lock();
+            write!(&mut stdout, "{cfg}").into_diagnostic()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the set of live values in `function`, which must contain only reachable blocks.
+fn compute_live_values(function: &hir::Function, cfg: &ControlFlowGraph) -> FxHashSet<Value> {
+    let dfg = &function.dfg;
+    let mut live = FxHashSet::<Value>::default();
+    let mut worklist = VecDeque::<Value>::default();
+
+    // The roots of the analysis are the operands of all instructions with side effects. Note
+    // that for branches, this does not include successor arguments, which are only live if the
+    // corresponding block parameter is live.
+    for (_, block) in dfg.blocks() {
+        for inst in block.insts() {
+            if !dfg.inst(inst).has_side_effects() {
+                continue;
+            }
+            for arg in dfg.inst_args(inst) {
+                if live.insert(*arg) {
+                    worklist.push_back(*arg);
+                }
+            }
+        }
+    }
+
+    while let Some(value) = worklist.pop_front() {
+        match dfg.value_data(value) {
+            // The operands of a live instruction are live
+            ValueData::Inst { inst, .. } => {
+                for arg in dfg.inst_args(*inst) {
+                    if live.insert(*arg) {
+                        worklist.push_back(*arg);
+                    }
+                }
+            }
+            // The arguments passed to a live block parameter are live
+            ValueData::Param { block, num, .. } => {
+                for pred in cfg.pred_iter(*block) {
+                    if !dfg.is_block_linked(pred.block) {
+                        continue;
+                    }
+                    let args = match dfg.analyze_branch(pred.inst) {
+                        BranchInfo::NotABranch => continue,
+                        BranchInfo::SingleDest(succ) => {
+                            SmallVec::<[Value; 2]>::from_iter(succ.args.get(*num as usize).copied())
+                        }
+                        BranchInfo::MultiDest(succs) => succs
+                            .iter()
+                            .filter(|succ| succ.destination == *block)
+                            .filter_map(|succ| succ.args.get(*num as usize).copied())
+                            .collect(),
+                    };
+                    for arg in args {
+                        if live.insert(arg) {
+                            worklist.push_back(arg);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    live
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, BranchInfo, Function, FunctionBuilder, Immediate, InstBuilder, Signature,
+        SourceSpan, Type,
+    };
+    use pretty_assertions::assert_eq;
+
+    use crate::EliminateDeadCode;
+
+    /// Run dead code elimination on the following IR:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32) -> u32 {
+    /// entry(n0: u32):
+    ///    unused = mul.wrapping n0, 3;
+    ///    br blk0(n0, 0);
+    ///
+    /// blk0(n1: u32, count0: u32):
+    ///    count1 = add.wrapping count0, 1;
+    ///    is_zero = eq n1, 0;
+    ///    n2 = sub.wrapping n1, 1;
+    ///    condbr is_zero, blk1, blk0(n2, count1);
+    ///
+    /// blk1:
+    ///    ret n1;
+    ///
+    /// blk2:
+    ///    ret 0;
+    /// }
+    /// ```
+    ///
+    /// We expect `unused` to be removed, along with the `count0` block parameter, and the
+    /// instructions used to compute its value, as it is never observed outside of the loop.
+    /// Additionally, `blk2` is unreachable, and should be removed.
+    #[test]
+    fn dce_removes_dead_values_and_blocks() {
+        let context = TestContext::default();
+        let id = "test::dce".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        let (entry, blk0, blk1, blk2, n0, n1) = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let n0 = builder.block_params(entry)[0];
+
+            let blk0 = builder.create_block();
+            let n1 = builder.append_block_param(blk0, Type::U32, SourceSpan::UNKNOWN);
+            let count0 = builder.append_block_param(blk0, Type::U32, SourceSpan::UNKNOWN);
+            let blk1 = builder.create_block();
+            let blk2 = builder.create_block();
+
+            builder.ins().mul_imm_wrapping(n0, Immediate::U32(3), SourceSpan::UNKNOWN);
+            let zero = builder.ins().u32(0, SourceSpan::UNKNOWN);
+            builder.ins().br(blk0, &[n0, zero], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk0);
+            let count1 =
+                builder.ins().add_imm_wrapping(count0, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let is_zero = builder.ins().eq_imm(n1, Immediate::U32(0), SourceSpan::UNKNOWN);
+            let n2 = builder.ins().sub_imm_wrapping(n1, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(is_zero, blk1, &[], blk0, &[n2, count1], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk1);
+            builder.ins().ret(Some(n1), SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk2);
+            let zero = builder.ins().u32(0, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(zero), SourceSpan::UNKNOWN);
+
+            (entry, blk0, blk1, blk2, n0, n1)
+        };
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = EliminateDeadCode;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("dead code elimination failed");
+
+        // The unreachable block was removed
+        assert!(function.dfg.is_block_linked(blk1));
+        assert!(!function.dfg.is_block_linked(blk2));
+
+        // Only the branch remains in the entry block, and it only passes `n0`
+        assert_eq!(function.dfg.block_insts(entry).count(), 1);
+        let br = function.dfg.last_inst(entry).unwrap();
+        match function.dfg.analyze_branch(br) {
+            BranchInfo::SingleDest(succ) => assert_eq!(succ.args, &[n0]),
+            _ => panic!("expected unconditional branch"),
+        }
+
+        // The loop counter was removed entirely
+        assert_eq!(function.dfg.block_params(blk0), &[n1]);
+        assert_eq!(function.dfg.block_insts(blk0).count(), 3);
+    }
+}
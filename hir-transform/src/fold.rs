@@ -0,0 +1,252 @@
+//! This module provides the constant folding rules shared by the optimization passes in this
+//! crate.
+//!
+//! Folding is only performed when the result is fully determined by the semantics of the
+//! instruction, i.e. we never fold an operation whose behavior at runtime would be to trap, and
+//! we never fold operations whose result is unspecified (such as unchecked arithmetic which
+//! overflows). In those cases the folding functions return `None`, and the instruction is left
+//! to be evaluated at runtime.
+use midenc_hir::{Immediate, Opcode, Overflow, Type};
+
+/// Attempt to fold a unary operation `op` applied to the constant `arg`, producing a value of
+/// type `ty`.
+pub fn fold_unary(
+    op: Opcode,
+    overflow: Option<Overflow>,
+    ty: &Type,
+    arg: Immediate,
+) -> Option<Immediate> {
+    let arg_ty = arg.ty();
+    let value = to_value(arg)?;
+    match op {
+        Opcode::Not => {
+            let b = arg.as_bool()?;
+            Some(Immediate::I1(!b))
+        }
+        Opcode::Bnot => from_bits(!to_bits(value, &arg_ty)?, &arg_ty),
+        Opcode::Neg => checked(-value, &arg_ty, overflow),
+        Opcode::Incr => checked(value + 1, &arg_ty, overflow),
+        Opcode::IsOdd => Some(Immediate::I1(value & 1 == 1)),
+        Opcode::Popcnt => Some(Immediate::U32(to_bits(value, &arg_ty)?.count_ones())),
+        Opcode::Clz => {
+            let width = int_width(&arg_ty)?;
+            let bits = to_bits(value, &arg_ty)?;
+            Some(Immediate::U32(bits.leading_zeros() - (64 - width)))
+        }
+        Opcode::Clo => {
+            let width = int_width(&arg_ty)?;
+            let bits = to_bits(value, &arg_ty)? << (64 - width);
+            Some(Immediate::U32(bits.leading_ones().min(width)))
+        }
+        Opcode::Ctz => {
+            let width = int_width(&arg_ty)?;
+            let bits = to_bits(value, &arg_ty)?;
+            Some(Immediate::U32(bits.trailing_zeros().min(width)))
+        }
+        Opcode::Cto => {
+            let width = int_width(&arg_ty)?;
+            let bits = to_bits(value, &arg_ty)?;
+            Some(Immediate::U32(bits.trailing_ones().min(width)))
+        }
+        Opcode::Ilog2 if value > 0 => Some(Immediate::U32(value.ilog2())),
+        Opcode::Pow2 if (0..64).contains(&value) => checked(1i128 << value, ty, overflow),
+        Opcode::Zext => {
+            // The source is reinterpreted as unsigned before being extended
+            let bits = to_bits(value, &arg_ty)?;
+            from_value(bits as i128, ty)
+        }
+        Opcode::Sext => {
+            // The source is reinterpreted as signed before being extended
+            let bits = to_bits(value, &arg_ty)?;
+            let width = int_width(&arg_ty)?;
+            let signed = sign_extend(bits, width);
+            from_bits(to_bits(signed, ty)?, ty)
+        }
+        Opcode::Trunc => from_bits(to_bits(value, &arg_ty)?, ty),
+        Opcode::Bitcast if int_width(&arg_ty) == int_width(ty) => {
+            from_bits(to_bits(value, &arg_ty)?, ty)
+        }
+        _ => None,
+    }
+}
+
+/// Attempt to fold a binary operation `op` applied to the constants `lhs` and `rhs`.
+///
+/// The type of the result is derived from the opcode and the type of `lhs`.
+pub fn fold_binary(
+    op: Opcode,
+    overflow: Option<Overflow>,
+    lhs: Immediate,
+    rhs: Immediate,
+) -> Option<Immediate> {
+    let ty = lhs.ty();
+    let l = to_value(lhs)?;
+    let r = to_value(rhs)?;
+    match op {
+        Opcode::Eq => Some(Immediate::I1(l == r)),
+        Opcode::Neq => Some(Immediate::I1(l != r)),
+        Opcode::Gt => Some(Immediate::I1(l > r)),
+        Opcode::Gte => Some(Immediate::I1(l >= r)),
+        Opcode::Lt => Some(Immediate::I1(l < r)),
+        Opcode::Lte => Some(Immediate::I1(l <= r)),
+        Opcode::And => Some(Immediate::I1(lhs.as_bool()? && rhs.as_bool()?)),
+        Opcode::Or => Some(Immediate::I1(lhs.as_bool()? || rhs.as_bool()?)),
+        Opcode::Xor => Some(Immediate::I1(lhs.as_bool()? ^ rhs.as_bool()?)),
+        Opcode::Min => from_value(l.min(r), &ty),
+        Opcode::Max => from_value(l.max(r), &ty),
+        Opcode::Add => checked(l + r, &ty, overflow),
+        Opcode::Sub => checked(l - r, &ty, overflow),
+        Opcode::Mul => checked(l * r, &ty, overflow),
+        // Division by zero traps, so must be left for runtime
+        Opcode::Div if r != 0 => checked(l / r, &ty, overflow),
+        Opcode::Mod if r != 0 => checked(l % r, &ty, overflow),
+        Opcode::Band => from_bits(to_bits(l, &ty)? & to_bits(r, &ty)?, &ty),
+        Opcode::Bor => from_bits(to_bits(l, &ty)? | to_bits(r, &ty)?, &ty),
+        Opcode::Bxor => from_bits(to_bits(l, &ty)? ^ to_bits(r, &ty)?, &ty),
+        Opcode::Shl | Opcode::Shr | Opcode::Rotl | Opcode::Rotr => {
+            let width = int_width(&ty)?;
+            let shift = u32::try_from(r).ok().filter(|shift| *shift < width)?;
+            let bits = to_bits(l, &ty)?;
+            match op {
+                Opcode::Shl => from_bits(bits << shift, &ty),
+                Opcode::Shr if ty.is_signed_integer() => from_value(l >> shift, &ty),
+                Opcode::Shr => from_bits(bits >> shift, &ty),
+                Opcode::Rotl if shift == 0 => from_bits(bits, &ty),
+                Opcode::Rotl => from_bits((bits << shift) | (bits >> (width - shift)), &ty),
+                Opcode::Rotr if shift == 0 => from_bits(bits, &ty),
+                Opcode::Rotr => from_bits((bits >> shift) | (bits << (width - shift)), &ty),
+                _ => unreachable!(),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if the constant `value` is representable as an integer of type `ty`
+pub fn fits_in(value: Immediate, ty: &Type) -> Option<bool> {
+    let value = to_value(value)?;
+    let (min, max) = range(ty)?;
+    Some(value >= min && value <= max)
+}
+
+/// Get the [Opcode] used to materialize a constant of the same type as `imm`
+pub fn constant_opcode(imm: &Immediate) -> Opcode {
+    match imm {
+        Immediate::I1(_) => Opcode::ImmI1,
+        Immediate::U8(_) => Opcode::ImmU8,
+        Immediate::I8(_) => Opcode::ImmI8,
+        Immediate::U16(_) => Opcode::ImmU16,
+        Immediate::I16(_) => Opcode::ImmI16,
+        Immediate::U32(_) => Opcode::ImmU32,
+        Immediate::I32(_) => Opcode::ImmI32,
+        Immediate::U64(_) => Opcode::ImmU64,
+        Immediate::I64(_) => Opcode::ImmI64,
+        Immediate::U128(_) => Opcode::ImmU128,
+        Immediate::I128(_) => Opcode::ImmI128,
+        Immediate::F64(_) => Opcode::ImmF64,
+        Immediate::Felt(_) => Opcode::ImmFelt,
+    }
+}
+
+/// Produce the result of an arithmetic operation whose mathematical result is `value`,
+/// according to the overflow semantics of the operation.
+fn checked(value: i128, ty: &Type, overflow: Option<Overflow>) -> Option<Immediate> {
+    match from_value(value, ty) {
+        Some(imm) => Some(imm),
+        None => match overflow {
+            Some(Overflow::Wrapping) => from_bits(to_bits(value, ty)?, ty),
+            // Checked operations trap, unchecked operations produce an unspecified value, and
+            // overflowing operations produce two results - none of which we fold here.
+            _ => None,
+        },
+    }
+}
+
+/// The width in bits of the integral types we can fold
+fn int_width(ty: &Type) -> Option<u32> {
+    match ty {
+        Type::I1 => Some(1),
+        Type::I8 | Type::U8 => Some(8),
+        Type::I16 | Type::U16 => Some(16),
+        Type::I32 | Type::U32 => Some(32),
+        Type::I64 | Type::U64 => Some(64),
+        _ => None,
+    }
+}
+
+/// The range of values representable by `ty`, as a pair of `(min, max)`
+fn range(ty: &Type) -> Option<(i128, i128)> {
+    let width = int_width(ty)?;
+    if ty.is_signed_integer() {
+        Some((-(1i128 << (width - 1)), (1i128 << (width - 1)) - 1))
+    } else {
+        Some((0, (1i128 << width) - 1))
+    }
+}
+
+/// Get the mathematical value of an integral immediate
+fn to_value(imm: Immediate) -> Option<i128> {
+    match imm {
+        Immediate::I1(b) => Some(b as i128),
+        Immediate::U8(i) => Some(i as i128),
+        Immediate::I8(i) => Some(i as i128),
+        Immediate::U16(i) => Some(i as i128),
+        Immediate::I16(i) => Some(i as i128),
+        Immediate::U32(i) => Some(i as i128),
+        Immediate::I32(i) => Some(i as i128),
+        Immediate::U64(i) => Some(i as i128),
+        Immediate::I64(i) => Some(i as i128),
+        Immediate::U128(_) | Immediate::I128(_) | Immediate::F64(_) | Immediate::Felt(_) => None,
+    }
+}
+
+/// Construct an immediate of type `ty` from `value`, if it is in range for that type
+fn from_value(value: i128, ty: &Type) -> Option<Immediate> {
+    let (min, max) = range(ty)?;
+    if value < min || value > max {
+        return None;
+    }
+    Some(match ty {
+        Type::I1 => Immediate::I1(value == 1),
+        Type::U8 => Immediate::U8(value as u8),
+        Type::I8 => Immediate::I8(value as i8),
+        Type::U16 => Immediate::U16(value as u16),
+        Type::I16 => Immediate::I16(value as i16),
+        Type::U32 => Immediate::U32(value as u32),
+        Type::I32 => Immediate::I32(value as i32),
+        Type::U64 => Immediate::U64(value as u64),
+        Type::I64 => Immediate::I64(value as i64),
+        _ => unreachable!(),
+    })
+}
+
+/// Get the two's complement bit pattern of `value` when represented as a `ty`
+fn to_bits(value: i128, ty: &Type) -> Option<u64> {
+    let width = int_width(ty)?;
+    Some((value as u64) & mask(width))
+}
+
+/// Construct an immediate of type `ty` by reinterpreting the low bits of `bits`
+fn from_bits(bits: u64, ty: &Type) -> Option<Immediate> {
+    let width = int_width(ty)?;
+    let bits = bits & mask(width);
+    if ty.is_signed_integer() {
+        from_value(sign_extend(bits, width), ty)
+    } else {
+        from_value(bits as i128, ty)
+    }
+}
+
+fn sign_extend(bits: u64, width: u32) -> i128 {
+    let shift = 64 - width;
+    (((bits << shift) as i64) >> shift) as i128
+}
+
+#[inline]
+fn mask(width: u32) -> u64 {
+    if width == 64 {
+        u64::MAX
+    } else {
+        (1u64 << width) - 1
+    }
+}
@@ -0,0 +1,299 @@
+use std::rc::Rc;
+
+use midenc_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use midenc_hir_analysis::{ControlFlowGraph, DominatorTree, DominatorTreePreorder};
+use midenc_session::{diagnostics::IntoDiagnostic, Session};
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+
+use crate::adt::ScopedMap;
+
+/// This pass performs global value numbering (GVN), eliminating redundant computations of the
+/// same value within a function.
+///
+/// The dominator tree is traversed in preorder, and each pure instruction is assigned a key
+/// derived from its opcode, types, immediates and (already numbered) operands. If an instruction
+/// with the same key was seen in a dominating position, the results of the redundant instruction
+/// are replaced with those of the dominating one, and the redundant instruction is removed.
+///
+/// Only instructions which have no side effects, and do not read memory, are candidates, e.g.
+/// arithmetic, comparisons, casts, and address computations, including those derived from global
+/// symbols. Global values which load from memory are never considered equivalent. Constants are
+/// also left alone, as materializing a constant is cheaper than keeping it live on the operand
+/// stack until its next use.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct GlobalValueNumbering;
+impl RewritePass for GlobalValueNumbering {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let domtree = analyses.get_or_compute::<DominatorTree>(function, session)?;
+        let preorder = DominatorTreePreorder::with_function(&domtree, function);
+
+        let mut changed = false;
+        let mut rewrites = FxHashMap::<Value, Value>::default();
+        let mut worklist = vec![(function.dfg.entry_block(), Rc::new(ScopedMap::default()))];
+        while let Some((block, parent)) = worklist.pop() {
+            let mut available = ScopedMap::<ValueKey, Inst>::new(Some(parent));
+
+            let insts = function.dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                // Rewrite uses of values which were found to be redundant
+                let uses = used_values(&function.dfg, inst);
+                for value in uses {
+                    if let Some(replacement) = rewrites.get(&value).copied() {
+                        function.dfg.replace_uses(inst, value, replacement);
+                    }
+                }
+
+                let Some(key) = value_key(&function.dfg, inst) else {
+                    continue;
+                };
+                match available.get(&key).copied() {
+                    Some(available) => {
+                        let redundant = function.dfg.inst_results(inst);
+                        let replacements = function.dfg.inst_results(available);
+                        debug_assert_eq!(redundant.len(), replacements.len());
+                        rewrites
+                            .extend(redundant.iter().copied().zip(replacements.iter().copied()));
+                        function.dfg.block_mut(block).cursor_mut_at_inst(inst).remove();
+                        changed = true;
+                    }
+                    None => {
+                        available.insert(key, inst);
+                    }
+                }
+            }
+
+            let available = Rc::new(available);
+            for child in preorder.children(block) {
+                worklist.push((child, Rc::clone(&available)));
+            }
+        }
+
+        if changed {
+            // We've only removed instructions which were not terminators, so the shape of the CFG
+            // has not changed
+            analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+            analyses.mark_preserved::<DominatorTree>(&function.id);
+        } else {
+            analyses.mark_all_preserved::<Function>(&function.id);
+        }
+
+        session.print(&*function, Self::FLAG).into_diagnostic()?;
+        if session.should_print_cfg(Self::FLAG) {
+            use std::io::Write;
+            let cfg = function.cfg_printer();
+            let mut stdout = std::io::stdout().lock();
+            write!(&mut stdout, "{cfg}").into_diagnostic()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The key used to identify instructions which compute the same value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ValueKey {
+    /// A pure instruction, with its operands in canonical order
+    Inst {
+        opcode: Opcode,
+        overflow: Option<Overflow>,
+        /// For most instructions, this is the type of the result, but for instructions whose
+        /// result type is fixed, e.g. `test`, it is the type the instruction is parameterized on
+        ty: Type,
+        imm: Option<Immediate>,
+        args: SmallVec<[Value; 2]>,
+    },
+    /// The address of a global symbol, or an offset from such an address
+    Global { ty: Type, global: GlobalKey },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum GlobalKey {
+    Symbol {
+        name: Ident,
+        offset: i32,
+    },
+    IAddImm {
+        base: Box<GlobalKey>,
+        offset: i32,
+        ty: Type,
+    },
+}
+
+/// Get the set of values used by `inst`, including successor arguments
+fn used_values(dfg: &DataFlowGraph, inst: Inst) -> SmallVec<[Value; 4]> {
+    let mut uses = SmallVec::<[Value; 4]>::from_slice(dfg.inst_args(inst));
+    match dfg.analyze_branch(inst) {
+        BranchInfo::NotABranch => (),
+        BranchInfo::SingleDest(succ) => uses.extend_from_slice(succ.args),
+        BranchInfo::MultiDest(succs) => {
+            for succ in succs.iter() {
+                uses.extend_from_slice(succ.args);
+            }
+        }
+    }
+    uses
+}
+
+/// Compute the [ValueKey] for `inst`, if it is a candidate for value numbering
+fn value_key(dfg: &DataFlowGraph, inst: Inst) -> Option<ValueKey> {
+    let ix = dfg.inst(inst);
+    let opcode = ix.opcode();
+    if opcode.has_side_effects() || opcode.reads_memory() {
+        return None;
+    }
+
+    let results = dfg.inst_results(inst);
+    let ty = dfg.value_type(*results.first()?).clone();
+    let overflow = ix.overflow();
+    let (ty, imm, mut args) = match ix {
+        Instruction::GlobalValue(GlobalValueOp { global, .. }) => {
+            let global = global_key(dfg, *global)?;
+            return Some(ValueKey::Global { ty, global });
+        }
+        Instruction::BinaryOp(BinaryOp { args, .. }) => (ty, None, SmallVec::from_slice(args)),
+        Instruction::BinaryOpImm(BinaryOpImm { arg, imm, .. }) => {
+            (ty, Some(*imm), SmallVec::from_slice(&[*arg]))
+        }
+        Instruction::UnaryOp(UnaryOp { arg, .. }) => (ty, None, SmallVec::from_slice(&[*arg])),
+        Instruction::PrimOp(PrimOp { args, .. }) => {
+            (ty, None, SmallVec::from_slice(args.as_slice(&dfg.value_lists)))
+        }
+        Instruction::PrimOpImm(PrimOpImm { imm, args, .. }) => {
+            (ty, Some(*imm), SmallVec::from_slice(args.as_slice(&dfg.value_lists)))
+        }
+        Instruction::Test(Test { arg, ty, .. }) => {
+            (ty.clone(), None, SmallVec::from_slice(&[*arg]))
+        }
+        // Constants are handled by the catch-all here, see the pass documentation for why
+        _ => return None,
+    };
+
+    if opcode.is_commutative() {
+        args.sort();
+    }
+
+    Some(ValueKey::Inst {
+        opcode,
+        overflow,
+        ty,
+        imm,
+        args,
+    })
+}
+
+/// Compute a [GlobalKey] for `gv`, if it is an address which does not depend on memory
+fn global_key(dfg: &DataFlowGraph, gv: GlobalValue) -> Option<GlobalKey> {
+    match dfg.global_value(gv) {
+        GlobalValueData::Symbol { name, offset } => Some(GlobalKey::Symbol {
+            name: *name,
+            offset: *offset,
+        }),
+        GlobalValueData::IAddImm { base, offset, ty } => Some(GlobalKey::IAddImm {
+            base: Box::new(global_key(dfg, *base)?),
+            offset: *offset,
+            ty: ty.clone(),
+        }),
+        GlobalValueData::Load { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use pretty_assertions::assert_eq;
+
+    use crate::GlobalValueNumbering;
+
+    /// Run GVN on the following IR:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32, u32) -> u32 {
+    /// entry(a: u32, b: u32):
+    ///    x0 = add.checked a, b;
+    ///    is_zero = eq x0, 0;
+    ///    condbr is_zero, blk0, blk1;
+    ///
+    /// blk0:
+    ///    x1 = add.checked b, a;
+    ///    ret x1;
+    ///
+    /// blk1:
+    ///    x2 = add.checked a, b;
+    ///    y = mul.checked x2, x2;
+    ///    ret y;
+    /// }
+    /// ```
+    ///
+    /// We expect `x1` and `x2` to be replaced with `x0`, as they are dominated by it, and
+    /// compute the same value (addition is commutative).
+    #[test]
+    fn gvn_removes_redundant_dominated_instructions() {
+        let context = TestContext::default();
+        let id = "test::gvn".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        let (blk0, blk1, x0) = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (a, b) = {
+                let params = builder.block_params(entry);
+                (params[0], params[1])
+            };
+            let blk0 = builder.create_block();
+            let blk1 = builder.create_block();
+
+            let x0 = builder.ins().add_checked(a, b, SourceSpan::UNKNOWN);
+            let is_zero = builder.ins().eq_imm(x0, Immediate::U32(0), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(is_zero, blk0, &[], blk1, &[], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk0);
+            let x1 = builder.ins().add_checked(b, a, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(x1), SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk1);
+            let x2 = builder.ins().add_checked(a, b, SourceSpan::UNKNOWN);
+            let y = builder.ins().mul_checked(x2, x2, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(y), SourceSpan::UNKNOWN);
+
+            (blk0, blk1, x0)
+        };
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = GlobalValueNumbering;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("global value numbering failed");
+
+        // blk0 now returns x0 directly
+        assert_eq!(function.dfg.block_insts(blk0).count(), 1);
+        let ret = function.dfg.last_inst(blk0).unwrap();
+        assert_eq!(function.dfg.inst_args(ret), &[x0]);
+
+        // blk1 squares x0
+        assert_eq!(function.dfg.block_insts(blk1).count(), 2);
+        let mul = function.dfg.block_insts(blk1).next().unwrap();
+        assert_eq!(function.dfg.inst_args(mul), &[x0, x0]);
+    }
+}
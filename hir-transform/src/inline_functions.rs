@@ -0,0 +1,1023 @@
+use midenc_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult, RewriteSet},
+    *,
+};
+use midenc_session::{diagnostics::IntoDiagnostic, OptLevel, Session};
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+
+use crate::fold;
+
+/// An [InlineCostModel] measures the size of the code generated for functions and calls, which
+/// [InlineFunctions] uses to decide whether inlining a call is profitable.
+pub trait InlineCostModel {
+    /// Returns the size of the code generated for the body of `function`.
+    ///
+    /// `function` may be in the midst of being rewritten, in which case it is not part of
+    /// `program`, which is provided for context, e.g. the layout of global variables.
+    fn function_cost(
+        &mut self,
+        function: &Function,
+        program: &Program,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> usize;
+
+    /// Returns the size of the code generated for a call to a function with `signature`, not
+    /// counting the body of the callee.
+    fn call_cost(&mut self, signature: &Signature) -> usize;
+}
+
+/// An [InlineCostModel] which estimates the size of the generated code from the HIR alone.
+///
+/// This is a rough approximation of the output of the code generator, intended to reflect the
+/// relative costs of different instructions, for use when the code generator is not available.
+#[derive(Default)]
+pub struct EstimatedCostModel;
+impl InlineCostModel for EstimatedCostModel {
+    fn function_cost(
+        &mut self,
+        function: &Function,
+        _program: &Program,
+        _analyses: &mut AnalysisManager,
+        _session: &Session,
+    ) -> usize {
+        function
+            .dfg
+            .blocks()
+            .flat_map(|(_, block)| block.insts())
+            .map(|inst| inst_cost(&function.dfg, inst))
+            .sum()
+    }
+
+    fn call_cost(&mut self, signature: &Signature) -> usize {
+        1 + signature.params().len() + signature.results().len()
+    }
+}
+
+/// This pass inlines calls to functions defined in the program being compiled, using an
+/// [InlineCostModel] to decide which call sites are profitable to inline.
+///
+/// Code produced from Rust is full of tiny wrapper functions, e.g. SDK bindings and `core` shims,
+/// and each call to such a function costs an `exec`, as well as the operand stack manipulation
+/// needed to set up arguments and receive results. When the cost of a function body is no more
+/// than the cost of calling it, inlining it always reduces code size, so we always do so. Beyond
+/// that, the optimization level determines how much code growth we're willing to accept in
+/// exchange for avoiding the call:
+///
+/// * When optimizing for size, we only inline when doing so does not grow the code
+/// * When optimizing for speed, larger functions are inlined
+///
+/// Functions with internal linkage which are called from a single call site, and whose address
+/// is never taken, are always inlined, and removed from the program afterwards, regardless of
+/// their size, as doing so is a net win.
+///
+/// Calls are inlined across module boundaries, unless the callee references functions with
+/// internal linkage in its own module, as those cannot be referenced from any other module.
+///
+/// Only `exec` call sites are considered, as `call` and `syscall` cross context boundaries, and
+/// so cannot be inlined without changing the semantics of the program. Recursive functions are
+/// never inlined, see below.
+///
+/// This pass runs on a linked [Program], and so after the rewrites required for code generation
+/// have been applied to each function. Inlining a function does not preserve the invariants those
+/// rewrites establish, so the `cleanup` rewrites given to [InlineFunctions::new] are applied to
+/// each function into which a call was inlined. Additionally, the linker has already decided how
+/// cycles in the call graph are broken, which inlining a recursive function would invalidate.
+///
+/// Functions are visited bottom-up in the call graph, so that by the time we consider inlining a
+/// function, it has already had its own call sites inlined, and its cost reflects that.
+#[derive(PassInfo)]
+pub struct InlineFunctions {
+    cost_model: Box<dyn InlineCostModel>,
+    cleanup: RewriteSet<Function>,
+}
+impl Default for InlineFunctions {
+    fn default() -> Self {
+        Self::new(EstimatedCostModel, RewriteSet::default())
+    }
+}
+impl InlineFunctions {
+    /// Create an [InlineFunctions] pass which uses `cost_model` to decide which calls to inline,
+    /// and applies `cleanup` to every function into which a call was inlined.
+    pub fn new(cost_model: impl InlineCostModel + 'static, cleanup: RewriteSet<Function>) -> Self {
+        Self {
+            cost_model: Box::new(cost_model),
+            cleanup,
+        }
+    }
+}
+impl RewritePass for InlineFunctions {
+    type Entity = hir::Program;
+
+    fn apply(
+        &mut self,
+        program: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let threshold = inline_threshold(session.options.optimize);
+
+        // Take ownership of the modules of the program, so that we can rewrite a function in one
+        // module while reading the functions of the others
+        let mut modules = FxHashMap::<Ident, Box<Module>>::default();
+        for module in program.modules_mut().take().into_iter() {
+            modules.insert(module.name, module);
+        }
+        let defined = modules
+            .values()
+            .flat_map(|module| module.functions())
+            .map(|function| function.id)
+            .collect::<FxHashSet<_>>();
+
+        // Gather the call graph of the program, the initial cost of each function, and the set of
+        // symbols whose address is taken
+        let mut costs = FxHashMap::<FunctionIdent, usize>::default();
+        let mut callees = FxHashMap::<FunctionIdent, SmallVec<[FunctionIdent; 4]>>::default();
+        let mut call_sites = FxHashMap::<FunctionIdent, usize>::default();
+        let mut address_taken = FxHashSet::<Ident>::default();
+        for function in modules.values().flat_map(|module| module.functions()) {
+            let cost = self.cost_model.function_cost(function, program, analyses, session);
+            costs.insert(function.id, cost);
+            let calls = callees.entry(function.id).or_default();
+            for (_, _, callee) in program_call_sites(function, &defined) {
+                calls.push(callee);
+                *call_sites.entry(callee).or_default() += 1;
+            }
+            for data in function.dfg.globals.values() {
+                if let GlobalValueData::Symbol { name, .. } = data {
+                    address_taken.insert(*name);
+                }
+            }
+        }
+
+        let (postorder, recursive) = call_graph_postorder(&defined, &callees);
+
+        let mut changed = false;
+        let mut inlined = FxHashSet::<FunctionIdent>::default();
+        for caller_id in postorder {
+            // Detach the caller from its module, so that we can modify it while reading the
+            // bodies of the functions it calls, remembering where to put it back afterwards
+            let (mut caller, next) = {
+                let module = modules.get_mut(&caller_id.module).expect("undefined module");
+                let mut cursor = module.cursor_mut_at(caller_id.function);
+                let next = cursor.peek_next().get().map(|f| f.id.function);
+                (cursor.remove().expect("expected function to be in module"), next)
+            };
+
+            let mut caller_changed = false;
+            for (call, op, callee_id) in program_call_sites(&caller, &defined) {
+                // Recursive functions are never inlined, as the calls in their bodies which close
+                // a cycle in the call graph have been chosen by the linker to be emitted as
+                // dynamic calls, which is only valid from within the cycle itself.
+                if op != Opcode::Exec || callee_id == caller_id || recursive.contains(&callee_id) {
+                    continue;
+                }
+                let Some(callee) = modules
+                    .get(&callee_id.module)
+                    .and_then(|module| module.function(callee_id.function))
+                else {
+                    continue;
+                };
+                if callee.is_kernel() {
+                    continue;
+                }
+                if callee_id.module != caller_id.module && !is_portable(callee, &modules, &defined)
+                {
+                    continue;
+                }
+
+                let is_removable = is_removable(callee, &address_taken);
+                let is_single_call_site = call_sites.get(&callee_id).copied() == Some(1);
+                let callee_cost = costs[&callee_id];
+                let call_cost = self.cost_model.call_cost(&callee.signature);
+                if !(is_removable && is_single_call_site)
+                    && callee_cost > call_cost.saturating_add(threshold)
+                {
+                    continue;
+                }
+
+                log::trace!(
+                    "inlining {} into {} (cost = {callee_cost}, call cost = {call_cost})",
+                    callee.id,
+                    caller.id
+                );
+                for (_, _, transitive) in program_call_sites(callee, &defined) {
+                    *call_sites.entry(transitive).or_default() += 1;
+                }
+                if let Some(count) = call_sites.get_mut(&callee_id) {
+                    *count -= 1;
+                }
+                inline_call(&mut caller, call, callee);
+                inlined.insert(callee_id);
+                caller_changed = true;
+            }
+
+            if caller_changed {
+                analyses.invalidate::<Function>(&caller.id);
+                self.cleanup.apply(&mut caller, analyses, session)?;
+                let cost = self.cost_model.function_cost(&caller, program, analyses, session);
+                costs.insert(caller_id, cost);
+                changed = true;
+            }
+
+            let module = modules.get_mut(&caller_id.module).expect("undefined module");
+            match next {
+                Some(next) => module.insert_before(caller, next),
+                None => module.push(caller),
+            }
+            .expect("unexpected symbol conflict when reinserting function");
+        }
+
+        // Remove functions which were inlined, and are no longer referenced
+        for callee_id in inlined {
+            let module = modules.get_mut(&callee_id.module).expect("undefined module");
+            let Some(callee) = module.function(callee_id.function) else {
+                continue;
+            };
+            if is_removable(callee, &address_taken)
+                && call_sites.get(&callee_id).copied().unwrap_or(0) == 0
+            {
+                let callee = module.unlink(callee_id.function);
+                analyses.invalidate::<Function>(&callee.id);
+            }
+        }
+
+        for module in modules.into_values() {
+            program.modules_mut().insert(module);
+        }
+
+        if !changed {
+            analyses.mark_all_preserved::<Program>(&ProgramAnalysisKey);
+        }
+
+        for module in program.modules().iter() {
+            session.print(module, Self::FLAG).into_diagnostic()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The additional cost, beyond the cost of the call itself, that we're willing to accept when
+/// inlining a function at the given optimization level
+fn inline_threshold(level: OptLevel) -> usize {
+    if level.prefers_size() {
+        return 0;
+    }
+    match level {
+        OptLevel::Balanced => 16,
+        OptLevel::Max => 64,
+        _ => 0,
+    }
+}
+
+/// Returns true if `function` can be removed from the program once all calls to it are inlined,
+/// i.e. it is not visible outside of its module, and its address is never taken.
+fn is_removable(function: &Function, address_taken: &FxHashSet<Ident>) -> bool {
+    function.linkage() == Linkage::Internal
+        && !function.has_attribute(&symbols::Entrypoint)
+        && !address_taken.contains(&function.id.function)
+}
+
+/// Returns true if the body of `function` can be inlined into a function in another module, i.e.
+/// it does not call any function with internal linkage in its own module.
+fn is_portable(
+    function: &Function,
+    modules: &FxHashMap<Ident, Box<Module>>,
+    defined: &FxHashSet<FunctionIdent>,
+) -> bool {
+    program_call_sites(function, defined).into_iter().all(|(_, _, callee)| {
+        callee.module != function.id.module
+            || modules
+                .get(&callee.module)
+                .and_then(|module| module.function(callee.function))
+                .map(|f| f.is_public())
+                .unwrap_or(true)
+    })
+}
+
+/// Get the set of call sites in `function` whose callee is one of the `defined` functions
+///
+/// Indirect calls produce a call site for each function in their table which is defined, so
+/// that those functions are considered referenced, even though they are never inlined.
+fn program_call_sites(
+    function: &Function,
+    defined: &FxHashSet<FunctionIdent>,
+) -> SmallVec<[(Inst, Opcode, FunctionIdent); 4]> {
+    let mut calls = SmallVec::default();
+    for (_, block) in function.dfg.blocks() {
+        for inst in block.insts() {
+            match function.dfg.inst(inst) {
+                Instruction::Call(Call { op, callee, .. }) if defined.contains(callee) => {
+                    calls.push((inst, *op, *callee));
+                }
+                Instruction::CallIndirect(CallIndirect { op, table, .. }) => {
+                    calls.extend(
+                        table
+                            .iter()
+                            .flatten()
+                            .filter(|callee| defined.contains(callee))
+                            .map(|callee| (inst, *op, *callee)),
+                    );
+                }
+                _ => (),
+            }
+        }
+    }
+    calls
+}
+
+/// Compute a postorder of the call graph of the `defined` functions, i.e. callees are visited
+/// before callers, along with the set of functions which are part of a cycle in the call graph,
+/// i.e. are recursive.
+///
+/// The latter are the members of the non-trivial strongly-connected components of the call graph,
+/// which are found using Tarjan's algorithm during the same traversal.
+fn call_graph_postorder(
+    defined: &FxHashSet<FunctionIdent>,
+    callees: &FxHashMap<FunctionIdent, SmallVec<[FunctionIdent; 4]>>,
+) -> (Vec<FunctionIdent>, FxHashSet<FunctionIdent>) {
+    let mut postorder = Vec::with_capacity(callees.len());
+    let mut recursive = FxHashSet::default();
+    // The order in which each function was visited, and the lowest such index reachable from it
+    let mut index = FxHashMap::<FunctionIdent, (usize, usize)>::default();
+    // The functions visited which have not yet been assigned to a component
+    let mut component = Vec::<FunctionIdent>::new();
+    let mut on_stack = FxHashSet::<FunctionIdent>::default();
+
+    // Visit the roots in a deterministic order
+    let mut roots = defined.iter().copied().collect::<Vec<_>>();
+    roots.sort();
+    for root in roots {
+        if index.contains_key(&root) {
+            continue;
+        }
+        // Each entry is a function, and the index of the next callee of that function to visit
+        let mut stack = vec![(root, 0usize)];
+        let order = index.len();
+        index.insert(root, (order, order));
+        component.push(root);
+        on_stack.insert(root);
+        while let Some((function, next)) = stack.last_mut() {
+            let function = *function;
+            let calls = callees.get(&function).map(|c| c.as_slice()).unwrap_or(&[]);
+            if let Some(callee) = calls.get(*next).copied() {
+                *next += 1;
+                if !callees.contains_key(&callee) {
+                    continue;
+                }
+                match index.get(&callee).copied() {
+                    None => {
+                        let order = index.len();
+                        index.insert(callee, (order, order));
+                        component.push(callee);
+                        on_stack.insert(callee);
+                        stack.push((callee, 0));
+                    }
+                    Some((order, _)) if on_stack.contains(&callee) => {
+                        let lowlink = &mut index.get_mut(&function).unwrap().1;
+                        *lowlink = core::cmp::min(*lowlink, order);
+                    }
+                    Some(_) => (),
+                }
+            } else {
+                stack.pop();
+                postorder.push(function);
+                let (order, lowlink) = index[&function];
+                if let Some((parent, _)) = stack.last() {
+                    let parent_lowlink = &mut index.get_mut(parent).unwrap().1;
+                    *parent_lowlink = core::cmp::min(*parent_lowlink, lowlink);
+                }
+                // If `function` is the root of a component, pop the component off the stack
+                if order == lowlink {
+                    let start = component
+                        .iter()
+                        .rposition(|f| *f == function)
+                        .expect("expected function to be on the stack");
+                    let members = component.split_off(start);
+                    for member in members.iter() {
+                        on_stack.remove(member);
+                    }
+                    if members.len() > 1 || calls.contains(&function) {
+                        recursive.extend(members);
+                    }
+                }
+            }
+        }
+    }
+
+    (postorder, recursive)
+}
+
+/// Estimate the number of Miden Assembly instructions emitted for `inst`.
+///
+/// This is a rough approximation of the output of the emitter, but is intended to reflect the
+/// relative costs of different instructions, e.g. operations on 64-bit integers are implemented
+/// via intrinsics, memory accesses require address translation, and calls require arguments and
+/// results to be moved into place on the operand stack.
+fn inst_cost(dfg: &DataFlowGraph, inst: Inst) -> usize {
+    let args = dfg.inst_args(inst).len();
+    let results = dfg.inst_results(inst);
+    match dfg.inst(inst).opcode() {
+        Opcode::Exec | Opcode::Call | Opcode::Syscall | Opcode::TailExec => {
+            1 + args + results.len()
+        }
+        // An indirect call is lowered to a dispatch over the entries of its table
+        Opcode::CallIndirect => match dfg.inst(inst) {
+            Instruction::CallIndirect(CallIndirect { table, .. }) => {
+                1 + args + results.len() + 4 * table.iter().flatten().count()
+            }
+            _ => unreachable!(),
+        },
+        Opcode::Load | Opcode::Store => 8,
+        Opcode::MemCpy | Opcode::MemSet => 16,
+        Opcode::InlineAsm => 8,
+        Opcode::Br | Opcode::CondBr | Opcode::Switch | Opcode::Ret | Opcode::RetImm => 1 + args,
+        _ => match results.last().map(|result| dfg.value_type(*result)) {
+            Some(ty) if !ty.is_felt() && ty.size_in_bits() > 32 => 4,
+            _ => 1,
+        },
+    }
+}
+
+/// Inline the body of `callee` in place of `call`, an `exec` instruction in `caller`.
+///
+/// The block containing `call` is split in two, and the body of `callee` is cloned between
+/// them, with returns from `callee` rewritten as branches to the continuation, whose parameters
+/// replace the results of the call.
+fn inline_call(caller: &mut Function, call: Inst, callee: &Function) {
+    let dfg = &mut caller.dfg;
+    let block = dfg.inst_block(call).unwrap();
+    let span = dfg.inst_span(call);
+    let args = SmallVec::<[Value; 4]>::from_slice(dfg.inst_args(call));
+    let results = SmallVec::<[Value; 2]>::from_slice(dfg.inst_results(call));
+
+    // Split the block after the call, and replace the results of the call with parameters of the
+    // continuation block
+    let continuation = dfg.split_block(block, call);
+    dfg.block_mut(continuation).cursor_mut_at_inst(call).remove();
+    let mut params = SmallVec::<[Value; 2]>::default();
+    for result in results.iter().copied() {
+        let ty = dfg.value_type(result).clone();
+        params.push(dfg.append_block_param(continuation, ty, span));
+    }
+    let blocks = dfg.blocks().map(|(b, _)| b).collect::<SmallVec<[Block; 16]>>();
+    for b in blocks {
+        let insts = dfg.block_insts(b).collect::<SmallVec<[Inst; 16]>>();
+        for inst in insts {
+            for (result, param) in results.iter().copied().zip(params.iter().copied()) {
+                dfg.replace_uses(inst, result, param);
+            }
+        }
+    }
+
+    // Make sure everything the callee references is available in the caller
+    for import in callee.dfg.imports() {
+        dfg.imports.entry(import.id).or_insert_with(|| import.clone());
+    }
+    let mut locals = FxHashMap::<LocalId, LocalId>::default();
+    for local in callee.dfg.locals.values() {
+        locals.insert(local.id, dfg.alloc_local(local.ty.clone()));
+    }
+    let mut globals = FxHashMap::<GlobalValue, GlobalValue>::default();
+    for gv in callee.dfg.globals.keys() {
+        map_global_value(gv, callee, dfg, &mut globals);
+    }
+
+    // Compute a reverse postorder of the reachable blocks of the callee, which ensures that when
+    // we clone the body of the callee in this order, definitions are always cloned before uses
+    let mut visited = FxHashSet::<Block>::default();
+    let mut order = Vec::default();
+    let mut stack = vec![(callee.dfg.entry_block(), false)];
+    while let Some((b, done)) = stack.pop() {
+        if done {
+            order.push(b);
+            continue;
+        }
+        if !visited.insert(b) {
+            continue;
+        }
+        stack.push((b, true));
+        let terminator = callee.dfg.last_inst(b).unwrap();
+        match callee.dfg.analyze_branch(terminator) {
+            BranchInfo::NotABranch => (),
+            BranchInfo::SingleDest(succ) => stack.push((succ.destination, false)),
+            BranchInfo::MultiDest(succs) => {
+                stack.extend(succs.iter().rev().map(|succ| (succ.destination, false)));
+            }
+        }
+    }
+    order.reverse();
+
+    // Create a block in the caller for every reachable block in the callee, with the same
+    // parameters, between the calling block and the continuation
+    let mut block_map = FxHashMap::<Block, Block>::default();
+    let mut value_map = FxHashMap::<Value, Value>::default();
+    let mut insert_after = block;
+    for callee_block in order.iter().copied() {
+        let new_block = dfg.create_block_after(insert_after);
+        insert_after = new_block;
+        block_map.insert(callee_block, new_block);
+        for param in callee.dfg.block_params(callee_block) {
+            let ty = callee.dfg.value_type(*param).clone();
+            let param_span = callee.dfg.value_span(*param);
+            let new_param = dfg.append_block_param(new_block, ty, param_span);
+            value_map.insert(*param, new_param);
+        }
+    }
+
+    // Transfer control to the inlined entry block in place of the call
+    let entry = block_map[&callee.dfg.entry_block()];
+    DefaultInstBuilder::at(dfg, InsertionPoint::after(ProgramPoint::Block(block)))
+        .br(entry, &args, span);
+
+    for callee_block in order {
+        let new_block = block_map[&callee_block];
+        for inst in callee.dfg.block_insts(callee_block) {
+            let inst_span = callee.dfg.inst_span(inst);
+            let ip = InsertionPoint::after(ProgramPoint::Block(new_block));
+            match callee.dfg.inst(inst) {
+                // Returns are rewritten as branches to the continuation
+                Instruction::Ret(Ret { args, .. }) => {
+                    let args = args
+                        .as_slice(&callee.dfg.value_lists)
+                        .iter()
+                        .map(|v| value_map[v])
+                        .collect::<SmallVec<[Value; 2]>>();
+                    DefaultInstBuilder::at(dfg, ip).br(continuation, &args, inst_span);
+                }
+                Instruction::RetImm(RetImm { arg, .. }) => {
+                    let (imm, dfg) = DefaultInstBuilder::at(dfg, ip).UnaryImm(
+                        fold::constant_opcode(arg),
+                        arg.ty(),
+                        *arg,
+                        inst_span,
+                    );
+                    let imm = dfg.first_result(imm);
+                    DefaultInstBuilder::at(dfg, ip).br(continuation, &[imm], inst_span);
+                }
+                // Tail calls are no longer in tail position, so they are rewritten as calls whose
+                // results are passed to the continuation
+                Instruction::Call(Call {
+                    op: Opcode::TailExec,
+                    callee: target,
+                    args,
+                }) => {
+                    let args = args
+                        .as_slice(&callee.dfg.value_lists)
+                        .iter()
+                        .map(|v| value_map[v])
+                        .collect::<SmallVec<[Value; 2]>>();
+                    let call = DefaultInstBuilder::at(dfg, ip).exec(*target, &args, inst_span);
+                    let results = SmallVec::<[Value; 2]>::from_slice(dfg.inst_results(call));
+                    DefaultInstBuilder::at(dfg, ip).br(continuation, &results, inst_span);
+                }
+                ix => {
+                    let data = copy_instruction(
+                        ix,
+                        &callee.dfg.value_lists,
+                        &mut dfg.value_lists,
+                        &value_map,
+                        &block_map,
+                        &locals,
+                        &globals,
+                    );
+                    let callee_results = callee.dfg.inst_results(inst);
+                    let ctrl_ty = callee_results
+                        .last()
+                        .map(|v| callee.dfg.value_type(*v).clone())
+                        .unwrap_or(Type::Unit);
+                    let new_inst = dfg.insert_inst(ip, data, ctrl_ty, inst_span);
+                    let new_results = dfg.inst_results(new_inst);
+                    debug_assert_eq!(callee_results.len(), new_results.len());
+                    for (old, new) in callee_results.iter().zip(new_results.iter()) {
+                        value_map.insert(*old, *new);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Copy the global value `gv` from `callee` into `dfg`, along with any global values it is
+/// derived from, recording the mapping in `globals`.
+fn map_global_value(
+    gv: GlobalValue,
+    callee: &Function,
+    dfg: &mut DataFlowGraph,
+    globals: &mut FxHashMap<GlobalValue, GlobalValue>,
+) -> GlobalValue {
+    if let Some(mapped) = globals.get(&gv) {
+        return *mapped;
+    }
+    let data = match callee.dfg.global_value(gv).clone() {
+        data @ GlobalValueData::Symbol { .. } => data,
+        GlobalValueData::Load { base, offset, ty } => GlobalValueData::Load {
+            base: map_global_value(base, callee, dfg, globals),
+            offset,
+            ty,
+        },
+        GlobalValueData::IAddImm { base, offset, ty } => GlobalValueData::IAddImm {
+            base: map_global_value(base, callee, dfg, globals),
+            offset,
+            ty,
+        },
+    };
+    let mapped = dfg.create_global_value(data);
+    globals.insert(gv, mapped);
+    mapped
+}
+
+/// Copy `ix` from the value list pool `from`, to `to`, rewriting all references to values,
+/// blocks, locals and global values using the provided mappings.
+fn copy_instruction(
+    ix: &Instruction,
+    from: &ValueListPool,
+    to: &mut ValueListPool,
+    values: &FxHashMap<Value, Value>,
+    blocks: &FxHashMap<Block, Block>,
+    locals: &FxHashMap<LocalId, LocalId>,
+    globals: &FxHashMap<GlobalValue, GlobalValue>,
+) -> Instruction {
+    let copy_list = |list: &ValueList, to: &mut ValueListPool| {
+        ValueList::from_iter(list.as_slice(from).iter().map(|v| values[v]), to)
+    };
+    let copy_successor = |succ: &Successor, to: &mut ValueListPool| Successor {
+        destination: blocks[&succ.destination],
+        args: copy_list(&succ.args, to),
+    };
+
+    let mut data = match ix {
+        Instruction::GlobalValue(op) => Instruction::GlobalValue(GlobalValueOp {
+            global: globals[&op.global],
+            ..op.clone()
+        }),
+        Instruction::LocalVar(op) => Instruction::LocalVar(LocalVarOp {
+            local: locals[&op.local],
+            args: copy_list(&op.args, to),
+            ..op.clone()
+        }),
+        Instruction::BinaryOp(op) => Instruction::BinaryOp(op.clone()),
+        Instruction::BinaryOpImm(op) => Instruction::BinaryOpImm(op.clone()),
+        Instruction::UnaryOp(op) => Instruction::UnaryOp(op.clone()),
+        Instruction::UnaryOpImm(op) => Instruction::UnaryOpImm(op.clone()),
+        Instruction::Call(op) => Instruction::Call(Call {
+            args: copy_list(&op.args, to),
+            ..op.clone()
+        }),
+        Instruction::CallIndirect(op) => Instruction::CallIndirect(CallIndirect {
+            args: copy_list(&op.args, to),
+            ..op.clone()
+        }),
+        Instruction::Br(op) => Instruction::Br(Br {
+            successor: copy_successor(&op.successor, to),
+            ..op.clone()
+        }),
+        Instruction::CondBr(op) => Instruction::CondBr(CondBr {
+            then_dest: copy_successor(&op.then_dest, to),
+            else_dest: copy_successor(&op.else_dest, to),
+            ..op.clone()
+        }),
+        Instruction::Switch(op) => Instruction::Switch(Switch {
+            arms: op
+                .arms
+                .iter()
+                .map(|arm| SwitchArm {
+                    value: arm.value,
+                    successor: copy_successor(&arm.successor, to),
+                })
+                .collect(),
+            default: copy_successor(&op.default, to),
+            ..op.clone()
+        }),
+        Instruction::Ret(op) => Instruction::Ret(Ret {
+            args: copy_list(&op.args, to),
+            ..op.clone()
+        }),
+        Instruction::RetImm(op) => Instruction::RetImm(op.clone()),
+        Instruction::Load(op) => Instruction::Load(op.clone()),
+        Instruction::PrimOp(op) => Instruction::PrimOp(PrimOp {
+            args: copy_list(&op.args, to),
+            ..op.clone()
+        }),
+        Instruction::PrimOpImm(op) => Instruction::PrimOpImm(PrimOpImm {
+            args: copy_list(&op.args, to),
+            ..op.clone()
+        }),
+        Instruction::Test(op) => Instruction::Test(op.clone()),
+        Instruction::InlineAsm(op) => Instruction::InlineAsm(InlineAsm {
+            args: copy_list(&op.args, to),
+            ..op.clone()
+        }),
+    };
+
+    // The value lists have already been remapped above, so we only need to rewrite the operands
+    // which are stored inline in the instruction
+    match &mut data {
+        Instruction::BinaryOp(BinaryOp { args, .. }) => {
+            for arg in args.iter_mut() {
+                *arg = values[arg];
+            }
+        }
+        Instruction::BinaryOpImm(BinaryOpImm { arg, .. })
+        | Instruction::UnaryOp(UnaryOp { arg, .. })
+        | Instruction::Test(Test { arg, .. })
+        | Instruction::Switch(Switch { arg, .. }) => *arg = values[arg],
+        Instruction::CondBr(CondBr { cond, .. }) => *cond = values[cond],
+        Instruction::Load(LoadOp { addr, .. }) => *addr = values[addr],
+        _ => (),
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::{self, TestContext},
+        AbiParam, Function, FunctionBuilder, FunctionIdent, Immediate, InstBuilder, Instruction,
+        Linkage, Module, ModuleBuilder, Opcode, Program, Signature, SourceSpan, Type,
+    };
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use crate::InlineFunctions;
+
+    /// Build `fn add1(u32) -> u32` with the given linkage, which is cheaper than a call to it
+    fn add1(id: FunctionIdent, linkage: Linkage) -> Box<Function> {
+        let mut add1 = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+        add1.set_linkage(linkage);
+        {
+            let mut builder = FunctionBuilder::new(&mut add1);
+            let entry = builder.current_block();
+            let n = builder.block_params(entry)[0];
+            let m = builder.ins().add_imm_wrapping(n, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(m), SourceSpan::UNKNOWN);
+        }
+        Box::new(add1)
+    }
+
+    /// Build `pub fn <id>(u32) -> u32`, which calls `callee` `n` times, feeding the result of each
+    /// call to the next
+    fn call_n(id: FunctionIdent, callee: FunctionIdent, n: usize) -> Box<Function> {
+        let signature = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+        let mut function = Function::new(id, signature.clone());
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let callee = builder
+                .import_function(callee.module, callee.function, signature, SourceSpan::UNKNOWN)
+                .unwrap();
+            let entry = builder.current_block();
+            let mut value = builder.block_params(entry)[0];
+            for _ in 0..n {
+                let call = builder.ins().exec(callee, &[value], SourceSpan::UNKNOWN);
+                value = builder.first_result(call);
+            }
+            builder.ins().ret(Some(value), SourceSpan::UNKNOWN);
+        }
+        Box::new(function)
+    }
+
+    fn inline(program: &mut Program, context: &TestContext) {
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = InlineFunctions::default();
+        rewrite
+            .apply(program, &mut analyses, &context.session)
+            .expect("inlining failed");
+    }
+
+    fn count_ops(function: &Function, opcode: Opcode) -> usize {
+        function
+            .dfg
+            .blocks()
+            .flat_map(|(_, block)| block.insts())
+            .filter(|inst| function.dfg.inst(*inst).opcode() == opcode)
+            .count()
+    }
+
+    fn function<'a>(program: &'a Program, id: FunctionIdent) -> Option<&'a Function> {
+        program.modules().find(&id.module).get()?.function(id.function)
+    }
+
+    /// Construct a module with the following functions:
+    ///
+    /// ```text,ignore
+    /// (internal) fn add1(u32) -> u32 {
+    /// entry(n: u32):
+    ///    m = add.wrapping n, 1;
+    ///    ret m;
+    /// }
+    ///
+    /// pub fn test(u32) -> u32 {
+    /// entry(a: u32):
+    ///    b = exec add1(a);
+    ///    c = exec add1(b);
+    ///    ret c;
+    /// }
+    /// ```
+    ///
+    /// `add1` is cheaper than calling it, so we expect both calls to be inlined, and for `add1`
+    /// to be removed from the module, as it has internal linkage.
+    #[test]
+    fn inline_functions_cheaper_than_call() {
+        let context = TestContext::default();
+        let mut module = Module::new("test");
+
+        let add1_id = "test::add1".parse().unwrap();
+        let test_id = "test::test".parse().unwrap();
+        module.push(add1(add1_id, Linkage::Internal)).unwrap();
+        module.push(call_n(test_id, add1_id, 2)).unwrap();
+
+        let mut program = Program::default();
+        program.modules_mut().insert(Box::new(module));
+        inline(&mut program, &context);
+
+        assert!(function(&program, add1_id).is_none());
+        let test = function(&program, test_id).expect("expected test to remain");
+        assert_eq!(count_ops(test, Opcode::Exec), 0, "expected all calls to be inlined");
+        assert_eq!(count_ops(test, Opcode::Add), 2);
+
+        // The result of the function is now the result of the second inlined add
+        let returns = test
+            .dfg
+            .blocks()
+            .filter_map(|(b, _)| test.dfg.last_inst(b))
+            .filter(|inst| matches!(test.dfg.inst(*inst), Instruction::Ret(_)))
+            .count();
+        assert_eq!(returns, 1);
+    }
+
+    /// Calls to a public function in another module are inlined, but the callee is kept, as it
+    /// may be called from outside of the program
+    #[test]
+    fn inline_functions_across_modules() {
+        let context = TestContext::default();
+
+        let add1_id = "a::add1".parse().unwrap();
+        let test_id = "b::test".parse().unwrap();
+        let mut a = Module::new("a");
+        a.push(add1(add1_id, Linkage::External)).unwrap();
+        let mut b = Module::new("b");
+        b.push(call_n(test_id, add1_id, 1)).unwrap();
+
+        let mut program = Program::default();
+        program.modules_mut().insert(Box::new(a));
+        program.modules_mut().insert(Box::new(b));
+        inline(&mut program, &context);
+
+        assert!(function(&program, add1_id).is_some());
+        let test = function(&program, test_id).expect("expected test to remain");
+        assert_eq!(count_ops(test, Opcode::Exec), 0, "expected the call to be inlined");
+        assert_eq!(count_ops(test, Opcode::Add), 1);
+    }
+
+    /// A public function which calls an internal function of its own module cannot be inlined
+    /// into another module, as the internal function is not visible there
+    #[test]
+    fn inline_functions_across_modules_respects_linkage() {
+        let context = TestContext::default();
+
+        let add1_id = "a::add1".parse().unwrap();
+        let add2_id = "a::add2".parse().unwrap();
+        let other_id = "a::other".parse().unwrap();
+        let test_id = "b::test".parse().unwrap();
+        let mut a = Module::new("a");
+        a.push(add1(add1_id, Linkage::Internal)).unwrap();
+        let mut add2 = call_n(add2_id, add1_id, 2);
+        add2.set_linkage(Linkage::External);
+        a.push(add2).unwrap();
+        a.push(call_n(other_id, add2_id, 1)).unwrap();
+        let mut b = Module::new("b");
+        b.push(call_n(test_id, add2_id, 1)).unwrap();
+
+        let mut program = Program::default();
+        program.modules_mut().insert(Box::new(a));
+        program.modules_mut().insert(Box::new(b));
+        // Use a cost model in which everything but `add1` is cheap enough to inline
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = InlineFunctions::new(Costly(add1_id), Default::default());
+        rewrite
+            .apply(&mut program, &mut analyses, &context.session)
+            .expect("inlining failed");
+
+        // `add2` is inlined into `other`, which is in the same module
+        let other = function(&program, other_id).expect("expected other to remain");
+        assert_eq!(count_ops(other, Opcode::Exec), 2, "expected add2 to be inlined into other");
+        // But not into `test`, as `add1` cannot be called from `b`
+        let test = function(&program, test_id).expect("expected test to remain");
+        assert_eq!(count_ops(test, Opcode::Exec), 1, "expected add2 to be called from test");
+        assert!(function(&program, add1_id).is_some());
+    }
+
+    /// A cost model in which every function but one is free to inline
+    struct Costly(FunctionIdent);
+    impl super::InlineCostModel for Costly {
+        fn function_cost(
+            &mut self,
+            function: &Function,
+            _program: &Program,
+            _analyses: &mut AnalysisManager,
+            _session: &midenc_session::Session,
+        ) -> usize {
+            if function.id == self.0 {
+                1000
+            } else {
+                0
+            }
+        }
+
+        fn call_cost(&mut self, _signature: &Signature) -> usize {
+            1
+        }
+    }
+
+    /// An internal function whose address is taken must not be removed once inlined
+    #[test]
+    fn inline_functions_keeps_address_taken_functions() {
+        let context = TestContext::default();
+        let mut module = Module::new("test");
+
+        let add1_id = "test::add1".parse().unwrap();
+        let test_id = "test::test".parse::<FunctionIdent>().unwrap();
+        module.push(add1(add1_id, Linkage::Internal)).unwrap();
+
+        let signature = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+        let mut test = Function::new(test_id, signature.clone());
+        {
+            let mut builder = FunctionBuilder::new(&mut test);
+            let add1 =
+                builder.import_function("test", "add1", signature, SourceSpan::UNKNOWN).unwrap();
+            let entry = builder.current_block();
+            let a = builder.block_params(entry)[0];
+            let ptr_ty = Type::Ptr(Box::new(Type::U8));
+            builder.ins().symbol_addr("add1", ptr_ty, SourceSpan::UNKNOWN);
+            let call = builder.ins().exec(add1, &[a], SourceSpan::UNKNOWN);
+            let b = builder.first_result(call);
+            builder.ins().ret(Some(b), SourceSpan::UNKNOWN);
+        }
+        module.push(Box::new(test)).unwrap();
+
+        let mut program = Program::default();
+        program.modules_mut().insert(Box::new(module));
+        inline(&mut program, &context);
+
+        let test = function(&program, test_id).expect("expected test to remain");
+        assert_eq!(count_ops(test, Opcode::Exec), 0, "expected the call to be inlined");
+        assert!(function(&program, add1_id).is_some(), "expected add1 to remain");
+    }
+
+    /// Recursive functions are never inlined, nor are functions too large to inline at the
+    /// current optimization level which have more than one caller
+    #[test]
+    fn inline_functions_skips_recursive_and_large_functions() {
+        let context = TestContext::default();
+
+        let mut builder = ModuleBuilder::new("test");
+        let fact = testing::fact(&mut builder, &context);
+        let sum_matrix = testing::sum_matrix(&mut builder, &context);
+        let mut module = builder.build();
+        let sig = |id: FunctionIdent| module.function(id.function).unwrap().signature.clone();
+        let (fact_sig, sum_matrix_sig) = (sig(fact), sig(sum_matrix));
+
+        let test_id = "test::test".parse::<FunctionIdent>().unwrap();
+        let mut test = Function::new(test_id, fact_sig.clone());
+        {
+            let mut builder = FunctionBuilder::new(&mut test);
+            let fact = builder
+                .import_function(fact.module, fact.function, fact_sig, SourceSpan::UNKNOWN)
+                .unwrap();
+            let sum_matrix = builder
+                .import_function(
+                    sum_matrix.module,
+                    sum_matrix.function,
+                    sum_matrix_sig,
+                    SourceSpan::UNKNOWN,
+                )
+                .unwrap();
+            let entry = builder.current_block();
+            let n = builder.block_params(entry)[0];
+            let call = builder.ins().exec(fact, &[n], SourceSpan::UNKNOWN);
+            let result = builder.first_result(call);
+            let ptr_ty = Type::Ptr(Box::new(Type::U32));
+            let ptr = builder.ins().inttoptr(n, ptr_ty, SourceSpan::UNKNOWN);
+            builder.ins().exec(sum_matrix, &[ptr, n, n], SourceSpan::UNKNOWN);
+            builder.ins().exec(sum_matrix, &[ptr, n, n], SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        }
+        module.push(Box::new(test)).unwrap();
+
+        let mut program = Program::default();
+        program.modules_mut().insert(module);
+        inline(&mut program, &context);
+
+        let test = function(&program, test_id).expect("expected test to remain");
+        assert_eq!(count_ops(test, Opcode::Exec), 3, "expected no calls to be inlined");
+        let fact = function(&program, fact).expect("expected fact to remain");
+        assert_ne!(count_ops(fact, Opcode::Exec), 0, "expected fact to remain recursive");
+    }
+}
@@ -1,12 +1,25 @@
 pub(crate) mod adt;
+mod dce;
+mod fold;
+mod gvn;
 mod inline_blocks;
+mod inline_functions;
+mod licm;
+mod sccp;
 mod spill;
 mod split_critical_edges;
+mod tail_recursion;
 mod treeify;
 
 pub use self::{
+    dce::EliminateDeadCode,
+    gvn::GlobalValueNumbering,
     inline_blocks::InlineBlocks,
+    inline_functions::{EstimatedCostModel, InlineCostModel, InlineFunctions},
+    licm::LoopInvariantCodeMotion,
+    sccp::Sccp,
     spill::{ApplySpills, InsertSpills, RewriteSpills},
     split_critical_edges::SplitCriticalEdges,
+    tail_recursion::EliminateTailRecursion,
     treeify::Treeify,
 };
@@ -0,0 +1,400 @@
+use std::rc::Rc;
+
+use midenc_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use midenc_hir_analysis::{ControlFlowGraph, DominatorTree, Loop, LoopAnalysis};
+use midenc_session::{diagnostics::IntoDiagnostic, Session};
+use smallvec::SmallVec;
+
+/// This pass performs loop-invariant code motion (LICM), hoisting computations whose value does
+/// not change between iterations of a loop, out of that loop.
+///
+/// An instruction is hoisted when it is pure (i.e. it has no side effects and does not read
+/// memory), and all of its operands are defined outside of the loop, or by other hoisted
+/// instructions. Hoisted instructions are placed in the preheader of the loop, i.e. the unique
+/// block outside the loop from which control enters the loop header. If a loop does not have
+/// such a block, one is created, by splitting the edges from outside the loop into the header,
+/// in the same manner as [crate::SplitCriticalEdges].
+///
+/// Loops are visited innermost first, so an instruction can be hoisted out of an entire loop
+/// nest, if it is invariant with respect to each loop in the nest.
+///
+/// Instructions which may trap, e.g. checked arithmetic or division, are only hoisted from the
+/// loop header, as the header is always executed when the loop is entered, whereas other blocks
+/// of the loop may not be. Instructions without operands, e.g. constants, are left where they
+/// are, as they are cheaper to materialize where they are used than to keep live on the operand
+/// stack for the duration of the loop.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct LoopInvariantCodeMotion;
+impl RewritePass for LoopInvariantCodeMotion {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let loops = analyses.get_or_compute::<LoopAnalysis>(function, session)?;
+        if loops.loops().next().is_none() {
+            analyses.mark_all_preserved::<Function>(&function.id);
+            return Ok(());
+        }
+
+        // Make sure every loop has a preheader before we start hoisting, recomputing the loop
+        // analysis if we had to modify the CFG to do so.
+        let mut cfg = analyses
+            .take::<ControlFlowGraph>(&function.id)
+            .unwrap_or_else(|| ControlFlowGraph::with_function(function));
+        let mut cfg_changed = false;
+        for lp in loops.loops() {
+            cfg_changed |= insert_preheader(function, &mut cfg, &loops, lp);
+        }
+        let (domtree, loops) = if cfg_changed {
+            let domtree = DominatorTree::with_function(function, &cfg);
+            let loops = LoopAnalysis::with_function(function, &cfg, &domtree);
+            (Rc::new(domtree), Rc::new(loops))
+        } else {
+            (analyses.get_or_compute::<DominatorTree>(function, session)?, loops)
+        };
+
+        // Visit the loops innermost first
+        let mut nest = loops.loops().collect::<SmallVec<[Loop; 4]>>();
+        nest.sort_by_key(|lp| core::cmp::Reverse(loops.level(*lp)));
+
+        let mut changed = cfg_changed;
+        for lp in nest {
+            let header = loops.loop_header(lp);
+            let Some(preheader) = find_preheader(function, &cfg, &loops, lp) else {
+                continue;
+            };
+            let terminator = function.dfg.last_inst(preheader).unwrap();
+
+            // By visiting the blocks of the loop in reverse postorder, we are guaranteed to visit
+            // the definitions of all values defined in the loop before their uses, excluding
+            // block parameters, which are never invariant.
+            for block in domtree.cfg_postorder().iter().rev().copied() {
+                if !loops.is_in_loop(block, lp) {
+                    continue;
+                }
+                let insts = function.dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+                for inst in insts {
+                    if !is_hoistable(&function.dfg, inst, block == header) {
+                        continue;
+                    }
+                    let invariant = function
+                        .dfg
+                        .inst_args(inst)
+                        .iter()
+                        .all(|arg| !is_defined_in_loop(&function.dfg, &loops, lp, *arg));
+                    if !invariant {
+                        continue;
+                    }
+                    function.dfg.move_inst_before(inst, terminator);
+                    changed = true;
+                }
+            }
+        }
+
+        analyses.insert(function.id, cfg);
+        if !changed {
+            analyses.mark_all_preserved::<Function>(&function.id);
+        } else if !cfg_changed {
+            // We've only moved instructions which were not terminators, so the shape of the CFG
+            // has not changed
+            analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+            analyses.mark_preserved::<DominatorTree>(&function.id);
+            analyses.mark_preserved::<LoopAnalysis>(&function.id);
+        } else {
+            // The control flow graph was updated as preheaders were inserted, but the dominator
+            // tree and loop analysis will need to be recomputed
+            analyses.mark_preserved::<ControlFlowGraph>(&function.id);
+        }
+
+        session.print(&*function, Self::FLAG).into_diagnostic()?;
+        if session.should_print_cfg(Self::FLAG) {
+            use std::io::Write;
+            let cfg = function.cfg_printer();
+            let mut stdout = std::io::stdout().lock();
+            write!(&mut stdout, "{cfg}").into_diagnostic()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the predecessors of the header of `lp` which are outside of the loop
+fn entering_predecessors(
+    cfg: &ControlFlowGraph,
+    loops: &LoopAnalysis,
+    lp: Loop,
+) -> SmallVec<[(Block, Inst); 2]> {
+    let header = loops.loop_header(lp);
+    let mut preds = cfg
+        .pred_iter(header)
+        .filter(|pred| !loops.is_in_loop(pred.block, lp))
+        .map(|pred| (pred.block, pred.inst))
+        .collect::<SmallVec<[(Block, Inst); 2]>>();
+    preds.sort();
+    preds.dedup();
+    preds
+}
+
+/// Get the preheader of `lp`, if it has one.
+///
+/// A preheader is the only predecessor of the loop header outside of the loop, and it must have
+/// the loop header as its only successor.
+fn find_preheader(
+    function: &hir::Function,
+    cfg: &ControlFlowGraph,
+    loops: &LoopAnalysis,
+    lp: Loop,
+) -> Option<Block> {
+    match entering_predecessors(cfg, loops, lp).as_slice() {
+        [(block, _)] if cfg.num_successors(*block) == 1 => {
+            debug_assert!(function.dfg.is_block_linked(*block));
+            Some(*block)
+        }
+        _ => None,
+    }
+}
+
+/// Create a preheader for `lp`, if it does not already have one.
+///
+/// Returns `true` if the CFG was modified.
+fn insert_preheader(
+    function: &mut hir::Function,
+    cfg: &mut ControlFlowGraph,
+    loops: &LoopAnalysis,
+    lp: Loop,
+) -> bool {
+    if find_preheader(function, cfg, loops, lp).is_some() {
+        return false;
+    }
+
+    // If the loop header has no predecessors outside of the loop, it must be the entry block,
+    // and we can't hoist anything out of the loop without changing the function signature.
+    let preds = entering_predecessors(cfg, loops, lp);
+    if preds.is_empty() {
+        return false;
+    }
+
+    // The preheader has the same parameters as the header, and simply forwards them, so that
+    // each predecessor can continue to pass the same arguments as it did before.
+    let header = loops.loop_header(lp);
+    let span = function.dfg.inst_span(preds[0].1);
+    let preheader = function.dfg.create_block_before(header);
+    let params = function
+        .dfg
+        .block_params(header)
+        .iter()
+        .map(|param| (function.dfg.value_type(*param).clone(), function.dfg.value_span(*param)))
+        .collect::<SmallVec<[_; 4]>>();
+    let mut args = ValueList::default();
+    for (ty, span) in params {
+        let arg = function.dfg.append_block_param(preheader, ty, span);
+        args.push(arg, &mut function.dfg.value_lists);
+    }
+    function.dfg.insert_inst(
+        InsertionPoint {
+            at: ProgramPoint::Block(preheader),
+            action: Insert::After,
+        },
+        Instruction::Br(hir::Br {
+            op: hir::Opcode::Br,
+            successor: hir::Successor {
+                destination: header,
+                args,
+            },
+        }),
+        Type::Unknown,
+        span,
+    );
+
+    // Rewrite the edges entering the loop to go via the preheader
+    for (block, inst) in preds {
+        redirect_branch(function.dfg.inst_mut(inst), header, preheader);
+        cfg.recompute_block(&function.dfg, block);
+    }
+    cfg.recompute_block(&function.dfg, preheader);
+
+    true
+}
+
+/// Rewrite all of the successors of `branch` which transfer control to `from`, to go to `to`
+fn redirect_branch(branch: &mut Instruction, from: Block, to: Block) {
+    match branch {
+        Instruction::Br(hir::Br {
+            ref mut successor, ..
+        }) => {
+            debug_assert_eq!(successor.destination, from);
+            successor.destination = to;
+        }
+        Instruction::CondBr(hir::CondBr {
+            ref mut then_dest,
+            ref mut else_dest,
+            ..
+        }) => {
+            for succ in [then_dest, else_dest] {
+                if succ.destination == from {
+                    succ.destination = to;
+                }
+            }
+        }
+        Instruction::Switch(hir::Switch {
+            ref mut arms,
+            ref mut default,
+            ..
+        }) => {
+            for succ in arms.iter_mut().map(|arm| &mut arm.successor) {
+                if succ.destination == from {
+                    succ.destination = to;
+                }
+            }
+            if default.destination == from {
+                default.destination = to;
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Returns true if `inst` can be moved out of a loop, assuming its operands are loop-invariant.
+///
+/// If `in_header` is false, only instructions which can be executed speculatively are hoistable.
+fn is_hoistable(dfg: &DataFlowGraph, inst: Inst, in_header: bool) -> bool {
+    let ix = dfg.inst(inst);
+    let opcode = ix.opcode();
+    if opcode.has_side_effects() || opcode.reads_memory() {
+        return false;
+    }
+    if dfg.inst_args(inst).is_empty() {
+        return false;
+    }
+    in_header || !may_trap(ix)
+}
+
+/// Returns true if executing `ix` may raise an error, depending on the value of its operands
+fn may_trap(ix: &Instruction) -> bool {
+    if ix.overflow().is_some_and(|overflow| overflow.is_checked()) {
+        return true;
+    }
+    matches!(
+        ix.opcode(),
+        Opcode::Div
+            | Opcode::Mod
+            | Opcode::DivMod
+            | Opcode::Inv
+            | Opcode::Ilog2
+            | Opcode::Pow2
+            | Opcode::Exp
+            | Opcode::Cast
+            | Opcode::IntToPtr
+    )
+}
+
+/// Returns true if `value` is defined by a block parameter or instruction inside `lp`
+fn is_defined_in_loop(dfg: &DataFlowGraph, loops: &LoopAnalysis, lp: Loop, value: Value) -> bool {
+    let block = match dfg.value_data(value) {
+        ValueData::Inst { inst, .. } => dfg.inst_block(*inst).unwrap(),
+        ValueData::Param { block, .. } => *block,
+    };
+    loops.is_in_loop(block, lp)
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use crate::LoopInvariantCodeMotion;
+
+    /// Run LICM on the following IR:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32, u32) -> u32 {
+    /// entry(a: u32, b: u32):
+    ///    is_zero = eq a, 0;
+    ///    condbr is_zero, blk0(b), blk1;
+    ///
+    /// blk0(n0: u32):
+    ///    scale = mul.wrapping a, b;
+    ///    offset = add.wrapping scale, 1;
+    ///    n1 = sub.wrapping n0, offset;
+    ///    done = eq n1, 0;
+    ///    condbr done, blk1, blk0(n1);
+    ///
+    /// blk1:
+    ///    ret a;
+    /// }
+    /// ```
+    ///
+    /// We expect `scale` and `offset` to be hoisted out of the loop headed by `blk0`. As the
+    /// entry block has multiple successors, a preheader must be created to hold them.
+    #[test]
+    fn licm_hoists_invariant_instructions_into_preheader() {
+        let context = TestContext::default();
+        let id = "test::licm".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        let (entry, blk0, scale, offset) = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (a, b) = {
+                let params = builder.block_params(entry);
+                (params[0], params[1])
+            };
+            let blk0 = builder.create_block();
+            let n0 = builder.append_block_param(blk0, Type::U32, SourceSpan::UNKNOWN);
+            let blk1 = builder.create_block();
+
+            let is_zero = builder.ins().eq_imm(a, Immediate::U32(0), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(is_zero, blk0, &[b], blk1, &[], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk0);
+            let scale = builder.ins().mul_wrapping(a, b, SourceSpan::UNKNOWN);
+            let offset =
+                builder.ins().add_imm_wrapping(scale, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let n1 = builder.ins().sub_wrapping(n0, offset, SourceSpan::UNKNOWN);
+            let done = builder.ins().eq_imm(n1, Immediate::U32(0), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(done, blk1, &[], blk0, &[n1], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk1);
+            builder.ins().ret(Some(a), SourceSpan::UNKNOWN);
+
+            (entry, blk0, scale, offset)
+        };
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = LoopInvariantCodeMotion;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("loop-invariant code motion failed");
+
+        // The invariant instructions were moved to a new preheader block
+        let scale_inst = function.dfg.value_data(scale).unwrap_inst();
+        let offset_inst = function.dfg.value_data(offset).unwrap_inst();
+        let preheader = function.dfg.inst_block(scale_inst).unwrap();
+        assert_ne!(preheader, entry);
+        assert_ne!(preheader, blk0);
+        assert_eq!(function.dfg.inst_block(offset_inst), Some(preheader));
+        assert_eq!(function.dfg.block_insts(preheader).count(), 3);
+        assert_eq!(function.dfg.block_params(preheader).len(), 1);
+
+        // The loop body only contains the variant instructions
+        assert_eq!(function.dfg.block_insts(blk0).count(), 3);
+    }
+}
@@ -0,0 +1,570 @@
+use std::collections::VecDeque;
+
+use midenc_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use midenc_hir_analysis::{ControlFlowGraph, DominatorTree};
+use midenc_session::{diagnostics::IntoDiagnostic, Session};
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+
+use crate::fold;
+
+/// This pass performs sparse conditional constant propagation (SCCP) on a function.
+///
+/// SCCP simultaneously propagates constants through the data flow graph, and determines which
+/// edges of the control flow graph can be taken at runtime. Values are only considered along
+/// edges that are executable, so a block argument which receives the same constant along all
+/// executable edges is itself a constant, even if other (unreachable) predecessors pass something
+/// else. This is strictly more powerful than performing constant folding and unreachable code
+/// elimination separately, even if iterated to a fixpoint.
+///
+/// Once the analysis converges, the function is rewritten as follows:
+///
+/// * Instructions without side effects whose result is a constant are replaced with the constant
+/// * Block arguments which are constant are replaced with a constant materialized in that block
+/// * Conditional branches and switches with a constant selector are replaced with an
+///   unconditional branch to the selected successor
+/// * Blocks which are not executable are removed from the function
+///
+/// The now-unused definitions are left in place, and are expected to be cleaned up by dead code
+/// elimination.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct Sccp;
+impl RewritePass for Sccp {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let mut solver = Solver::new(function);
+        solver.solve(function);
+        let changed = solver.rewrite(function);
+
+        if changed {
+            // Recompute the CFG and dominator tree for subsequent passes
+            let cfg = ControlFlowGraph::with_function(function);
+            let domtree = DominatorTree::with_function(function, &cfg);
+            analyses.insert(function.id, cfg);
+            analyses.insert(function.id, domtree);
+        } else {
+            analyses.mark_all_preserved::<Function>(&function.id);
+        }
+
+        session.print(&*function, Self::FLAG).into_diagnostic()?;
+        if session.should_print_cfg(Self::FLAG) {
+            use std::io::Write;
+            let cfg = function.cfg_printer();
+            let mut stdout = std::io::stdout().lock();
+            write!(&mut stdout, "{cfg}").into_diagnostic()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The lattice of abstract values tracked for each SSA value during propagation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LatticeValue {
+    /// No definition has been observed yet, i.e. the value may still be anything
+    Unknown,
+    /// The value is known to always be the given constant
+    Constant(Immediate),
+    /// The value may take on more than one value at runtime
+    Overdefined,
+}
+impl LatticeValue {
+    /// Compute the greatest lower bound of `self` and `other`
+    fn meet(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Unknown, x) | (x, Self::Unknown) => x,
+            (Self::Constant(a), Self::Constant(b)) if is_same_constant(a, b) => self,
+            _ => Self::Overdefined,
+        }
+    }
+}
+
+/// Returns true if `a` and `b` are the same constant, of the same type
+fn is_same_constant(a: Immediate, b: Immediate) -> bool {
+    match (a, b) {
+        (Immediate::I1(x), Immediate::I1(y)) => x == y,
+        (Immediate::U8(x), Immediate::U8(y)) => x == y,
+        (a, b) => a == b,
+    }
+}
+
+struct Solver {
+    /// The current lattice value of each value in the function, missing entries are `Unknown`
+    values: FxHashMap<Value, LatticeValue>,
+    /// The instructions which use each value, either as an operand or a successor argument
+    users: FxHashMap<Value, SmallVec<[Inst; 2]>>,
+    /// The set of blocks known to be executable
+    executable: FxHashSet<Block>,
+    /// The set of control flow edges known to be executable
+    edges: FxHashSet<(Block, Block)>,
+    block_worklist: VecDeque<Block>,
+    value_worklist: VecDeque<Value>,
+}
+impl Solver {
+    fn new(function: &hir::Function) -> Self {
+        let mut users = FxHashMap::<Value, SmallVec<[Inst; 2]>>::default();
+        for (_, block) in function.dfg.blocks() {
+            for inst in block.insts() {
+                for arg in function.dfg.inst_args(inst) {
+                    users.entry(*arg).or_default().push(inst);
+                }
+                match function.dfg.analyze_branch(inst) {
+                    BranchInfo::NotABranch => (),
+                    BranchInfo::SingleDest(succ) => {
+                        for arg in succ.args {
+                            users.entry(*arg).or_default().push(inst);
+                        }
+                    }
+                    BranchInfo::MultiDest(succs) => {
+                        for arg in succs.iter().flat_map(|succ| succ.args.iter()) {
+                            users.entry(*arg).or_default().push(inst);
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            values: Default::default(),
+            users,
+            executable: Default::default(),
+            edges: Default::default(),
+            block_worklist: Default::default(),
+            value_worklist: Default::default(),
+        }
+    }
+
+    fn get(&self, value: Value) -> LatticeValue {
+        self.values.get(&value).copied().unwrap_or(LatticeValue::Unknown)
+    }
+
+    /// Lower the lattice value of `value` by meeting it with `lattice`
+    fn update(&mut self, value: Value, lattice: LatticeValue) {
+        let prev = self.get(value);
+        let next = prev.meet(lattice);
+        if prev != next {
+            self.values.insert(value, next);
+            self.value_worklist.push_back(value);
+        }
+    }
+
+    fn solve(&mut self, function: &hir::Function) {
+        // The function parameters may be anything
+        let entry = function.dfg.entry_block();
+        for param in function.dfg.block_params(entry) {
+            self.update(*param, LatticeValue::Overdefined);
+        }
+        self.executable.insert(entry);
+        self.block_worklist.push_back(entry);
+
+        while !self.block_worklist.is_empty() || !self.value_worklist.is_empty() {
+            while let Some(value) = self.value_worklist.pop_front() {
+                let users = self.users.get(&value).cloned().unwrap_or_default();
+                for user in users {
+                    let block = function.dfg.inst_block(user).unwrap();
+                    if self.executable.contains(&block) {
+                        self.visit_inst(user, function);
+                    }
+                }
+            }
+
+            if let Some(block) = self.block_worklist.pop_front() {
+                for inst in function.dfg.block_insts(block) {
+                    self.visit_inst(inst, function);
+                }
+            }
+        }
+    }
+
+    fn visit_inst(&mut self, inst: Inst, function: &hir::Function) {
+        let dfg = &function.dfg;
+        let block = dfg.inst_block(inst).unwrap();
+        match dfg.analyze_branch(inst) {
+            BranchInfo::SingleDest(succ) => {
+                self.mark_edge(block, succ.destination, succ.args, dfg);
+                return;
+            }
+            BranchInfo::MultiDest(_) => {
+                self.visit_multi_branch(block, inst, dfg);
+                return;
+            }
+            BranchInfo::NotABranch => (),
+        }
+
+        let results = dfg.inst_results(inst);
+        if results.is_empty() {
+            return;
+        }
+
+        let lattice = if results.len() == 1 {
+            self.evaluate(inst, dfg)
+        } else {
+            LatticeValue::Overdefined
+        };
+        for result in results {
+            self.update(*result, lattice);
+        }
+    }
+
+    fn visit_multi_branch(&mut self, block: Block, inst: Inst, dfg: &DataFlowGraph) {
+        match dfg.inst(inst) {
+            Instruction::CondBr(CondBr {
+                cond,
+                then_dest,
+                else_dest,
+                ..
+            }) => {
+                let (take_then, take_else) = match self.get(*cond) {
+                    LatticeValue::Unknown => return,
+                    LatticeValue::Constant(imm) => match imm.as_bool() {
+                        Some(b) => (b, !b),
+                        None => (true, true),
+                    },
+                    LatticeValue::Overdefined => (true, true),
+                };
+                if take_then {
+                    let args = then_dest.args.as_slice(&dfg.value_lists);
+                    self.mark_edge(block, then_dest.destination, args, dfg);
+                }
+                if take_else {
+                    let args = else_dest.args.as_slice(&dfg.value_lists);
+                    self.mark_edge(block, else_dest.destination, args, dfg);
+                }
+            }
+            Instruction::Switch(Switch {
+                arg, arms, default, ..
+            }) => match self.get(*arg) {
+                LatticeValue::Unknown => (),
+                LatticeValue::Constant(imm) => {
+                    let selected = imm
+                        .as_u32()
+                        .and_then(|selector| arms.iter().find(|arm| arm.value == selector))
+                        .map(|arm| &arm.successor)
+                        .unwrap_or(default);
+                    let args = selected.args.as_slice(&dfg.value_lists);
+                    self.mark_edge(block, selected.destination, args, dfg);
+                }
+                LatticeValue::Overdefined => {
+                    for successor in arms.iter().map(|arm| &arm.successor).chain([default]) {
+                        let args = successor.args.as_slice(&dfg.value_lists);
+                        self.mark_edge(block, successor.destination, args, dfg);
+                    }
+                }
+            },
+            ix => unreachable!("unexpected multi-way branch: {ix:?}"),
+        }
+    }
+
+    /// Mark the edge from `from` to `to` as executable, and propagate the successor arguments
+    /// to the parameters of `to`.
+    fn mark_edge(&mut self, from: Block, to: Block, args: &[Value], dfg: &DataFlowGraph) {
+        self.edges.insert((from, to));
+        if self.executable.insert(to) {
+            self.block_worklist.push_back(to);
+        }
+        for (param, arg) in dfg.block_params(to).iter().zip(args.iter()) {
+            let lattice = self.get(*arg);
+            self.update(*param, lattice);
+        }
+    }
+
+    /// Evaluate the lattice value of the single result of `inst`
+    fn evaluate(&self, inst: Inst, dfg: &DataFlowGraph) -> LatticeValue {
+        use LatticeValue::*;
+
+        let ix = dfg.inst(inst);
+        if ix.has_side_effects() {
+            return Overdefined;
+        }
+
+        let result_ty = dfg.value_type(dfg.first_result(inst));
+        let folded = match ix {
+            Instruction::UnaryOpImm(UnaryOpImm { imm, .. }) => Some(*imm),
+            Instruction::UnaryOp(UnaryOp {
+                op, overflow, arg, ..
+            }) => match self.get(*arg) {
+                Constant(arg) => fold::fold_unary(*op, *overflow, result_ty, arg),
+                lattice => return lattice,
+            },
+            Instruction::BinaryOp(BinaryOp {
+                op, overflow, args, ..
+            }) => {
+                // NOTE: Arguments are in stack order, i.e. `[rhs, lhs]`
+                match (self.get(args[1]), self.get(args[0])) {
+                    (Constant(lhs), Constant(rhs)) => fold::fold_binary(*op, *overflow, lhs, rhs),
+                    (Overdefined, _) | (_, Overdefined) => None,
+                    _ => return Unknown,
+                }
+            }
+            Instruction::BinaryOpImm(BinaryOpImm {
+                op,
+                overflow,
+                arg,
+                imm,
+                ..
+            }) => match self.get(*arg) {
+                Constant(lhs) => fold::fold_binary(*op, *overflow, lhs, *imm),
+                lattice => return lattice,
+            },
+            Instruction::Test(Test { arg, ty, .. }) => match self.get(*arg) {
+                Constant(value) => fold::fits_in(value, ty).map(Immediate::I1),
+                lattice => return lattice,
+            },
+            Instruction::PrimOp(PrimOp {
+                op: Opcode::Select,
+                args,
+            }) => {
+                let args = args.as_slice(&dfg.value_lists);
+                return match self.get(args[0]) {
+                    Unknown => Unknown,
+                    Constant(cond) => match cond.as_bool() {
+                        Some(true) => self.get(args[1]),
+                        Some(false) => self.get(args[2]),
+                        None => Overdefined,
+                    },
+                    Overdefined => self.get(args[1]).meet(self.get(args[2])),
+                };
+            }
+            _ => None,
+        };
+
+        match folded {
+            // Only treat the result as a constant if it can be materialized with the same type
+            Some(imm) if &imm.ty() == result_ty => Constant(imm),
+            _ => Overdefined,
+        }
+    }
+
+    /// Rewrite `function` using the results of the analysis, returning true if it was modified
+    fn rewrite(&self, function: &mut hir::Function) -> bool {
+        let mut changed = false;
+
+        let blocks = function.dfg.blocks().map(|(b, _)| b).collect::<SmallVec<[Block; 8]>>();
+        for block in blocks.iter().copied() {
+            if !self.executable.contains(&block) {
+                continue;
+            }
+
+            // Replace constant block parameters with a materialized constant
+            let params = SmallVec::<[Value; 4]>::from_slice(function.dfg.block_params(block));
+            for param in params {
+                let LatticeValue::Constant(imm) = self.get(param) else {
+                    continue;
+                };
+                let users = match self.users.get(&param) {
+                    Some(users) if !users.is_empty() => users,
+                    _ => continue,
+                };
+                let span = function.dfg.value_span(param);
+                let ip = InsertionPoint::before(ProgramPoint::Block(block));
+                let constant = {
+                    let builder = DefaultInstBuilder::at(&mut function.dfg, ip);
+                    let (inst, dfg) =
+                        builder.UnaryImm(fold::constant_opcode(&imm), imm.ty(), imm, span);
+                    dfg.first_result(inst)
+                };
+                for user in users.iter().copied() {
+                    function.dfg.replace_uses(user, param, constant);
+                }
+                changed = true;
+            }
+
+            let insts = function.dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                changed |= self.rewrite_inst(inst, function);
+            }
+        }
+
+        // Remove all blocks which can never be executed
+        for block in blocks {
+            if !self.executable.contains(&block) {
+                function.dfg.detach_block(block);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn rewrite_inst(&self, inst: Inst, function: &mut hir::Function) -> bool {
+        let dfg = &mut function.dfg;
+        match dfg.inst(inst) {
+            Instruction::CondBr(CondBr {
+                cond,
+                then_dest,
+                else_dest,
+                ..
+            }) => {
+                let LatticeValue::Constant(imm) = self.get(*cond) else {
+                    return false;
+                };
+                let successor = match imm.as_bool() {
+                    Some(true) => then_dest.clone(),
+                    Some(false) => else_dest.clone(),
+                    None => return false,
+                };
+                *dfg.inst_mut(inst) = Instruction::Br(Br {
+                    op: Opcode::Br,
+                    successor,
+                });
+                true
+            }
+            Instruction::Switch(Switch {
+                arg, arms, default, ..
+            }) => {
+                let LatticeValue::Constant(imm) = self.get(*arg) else {
+                    return false;
+                };
+                let Some(selector) = imm.as_u32() else {
+                    return false;
+                };
+                let successor = arms
+                    .iter()
+                    .find(|arm| arm.value == selector)
+                    .map(|arm| arm.successor.clone())
+                    .unwrap_or_else(|| default.clone());
+                *dfg.inst_mut(inst) = Instruction::Br(Br {
+                    op: Opcode::Br,
+                    successor,
+                });
+                true
+            }
+            Instruction::UnaryOpImm(_) => false,
+            ix if ix.has_side_effects() => false,
+            _ => {
+                let results = dfg.inst_results(inst);
+                if results.len() != 1 {
+                    return false;
+                }
+                let LatticeValue::Constant(imm) = self.get(results[0]) else {
+                    return false;
+                };
+                let span = dfg.inst_span(inst);
+                ReplaceBuilder::new(dfg, inst).UnaryImm(
+                    fold::constant_opcode(&imm),
+                    imm.ty(),
+                    imm,
+                    span,
+                );
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Instruction, Opcode,
+        Signature, SourceSpan, Type,
+    };
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use crate::Sccp;
+
+    /// Run SCCP on the following IR:
+    ///
+    /// ```text,ignore
+    /// pub fn test(u32) -> u32 {
+    /// entry(n: u32):
+    ///    a = const.u32 8;
+    ///    b = add.wrapping a, 4;
+    ///    is_big = gt b, 10;
+    ///    condbr is_big, blk0(b), blk1(n);
+    ///
+    /// blk0(x: u32):
+    ///    br blk2(x);
+    ///
+    /// blk1(y: u32):
+    ///    br blk2(y);
+    ///
+    /// blk2(result: u32):
+    ///    ret result;
+    /// }
+    /// ```
+    ///
+    /// We expect the condition to be folded to `true`, the conditional branch to become an
+    /// unconditional branch to `blk0`, `blk1` to be removed, and the function to return the
+    /// constant `12`.
+    #[test]
+    fn sccp_folds_constant_branches() {
+        let context = TestContext::default();
+        let id = "test::sccp".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        let (entry, blk0, blk1, blk2, result) = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let n = builder.block_params(entry)[0];
+
+            let blk0 = builder.create_block();
+            let x = builder.append_block_param(blk0, Type::U32, SourceSpan::UNKNOWN);
+            let blk1 = builder.create_block();
+            let y = builder.append_block_param(blk1, Type::U32, SourceSpan::UNKNOWN);
+            let blk2 = builder.create_block();
+            let result = builder.append_block_param(blk2, Type::U32, SourceSpan::UNKNOWN);
+
+            let a = builder.ins().u32(8, SourceSpan::UNKNOWN);
+            let b = builder.ins().add_imm_wrapping(a, Immediate::U32(4), SourceSpan::UNKNOWN);
+            let is_big = builder.ins().gt_imm(b, Immediate::U32(10), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(is_big, blk0, &[b], blk1, &[n], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk0);
+            builder.ins().br(blk2, &[x], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk1);
+            builder.ins().br(blk2, &[y], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk2);
+            builder.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+            (entry, blk0, blk1, blk2, result)
+        };
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = Sccp;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("constant propagation failed");
+
+        // The conditional branch is now unconditional
+        let terminator = function.dfg.last_inst(entry).unwrap();
+        match function.dfg.inst(terminator) {
+            Instruction::Br(br) => assert_eq!(br.successor.destination, blk0),
+            ix => panic!("expected unconditional branch, got {ix:?}"),
+        }
+
+        // The untaken block has been removed
+        assert!(function.dfg.is_block_linked(blk0));
+        assert!(!function.dfg.is_block_linked(blk1));
+        assert!(function.dfg.is_block_linked(blk2));
+
+        // The returned value is a constant materialized in the return block
+        let ret = function.dfg.last_inst(blk2).unwrap();
+        let returned = function.dfg.inst_args(ret)[0];
+        assert_ne!(returned, result);
+        let def = function.dfg.value_data(returned).unwrap_inst();
+        match function.dfg.inst(def) {
+            Instruction::UnaryOpImm(op) => {
+                assert_eq!(op.op, Opcode::ImmU32);
+                assert_eq!(op.imm, Immediate::U32(12));
+            }
+            ix => panic!("expected constant, got {ix:?}"),
+        }
+    }
+}
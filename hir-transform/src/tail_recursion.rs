@@ -0,0 +1,188 @@
+use midenc_hir::{
+    self as hir,
+    pass::{AnalysisManager, RewritePass, RewriteResult},
+    *,
+};
+use midenc_session::{diagnostics::IntoDiagnostic, Session};
+use smallvec::SmallVec;
+
+/// This pass rewrites self-recursive tail calls as loops.
+///
+/// A function which calls itself in tail position, i.e. using `exec.tail`, has no further use for
+/// its own frame once the call is made, so the call can be replaced with a branch back to the start
+/// of the function, passing the call arguments in place of the function parameters. This is done
+/// by turning the entry block into a loop header: a new entry block, with the same parameters, is
+/// introduced which branches unconditionally to the old one, and each self-recursive tail call is
+/// replaced with a branch to the old entry block.
+///
+/// Besides avoiding the overhead of a call, a function whose only recursive calls are in tail
+/// position is no longer recursive after this pass, so it does not require the dynamic calls used
+/// to implement recursion in general.
+#[derive(Default, PassInfo, ModuleRewritePassAdapter)]
+pub struct EliminateTailRecursion;
+impl RewritePass for EliminateTailRecursion {
+    type Entity = hir::Function;
+
+    fn apply(
+        &mut self,
+        function: &mut Self::Entity,
+        analyses: &mut AnalysisManager,
+        session: &Session,
+    ) -> RewriteResult {
+        let tail_calls = function
+            .dfg
+            .blocks()
+            .filter_map(|(block, _)| function.dfg.last_inst(block))
+            .filter(|inst| match function.dfg.inst(*inst) {
+                Instruction::Call(Call {
+                    op: Opcode::TailExec,
+                    callee,
+                    ..
+                }) => *callee == function.id,
+                _ => false,
+            })
+            .collect::<SmallVec<[Inst; 2]>>();
+
+        if tail_calls.is_empty() {
+            analyses.mark_all_preserved::<Function>(&function.id);
+            return Ok(());
+        }
+
+        // Introduce a new entry block which forwards the function parameters to the old one
+        let header = function.dfg.entry_block();
+        let entry = function.dfg.create_block_before(header);
+        let params = function
+            .dfg
+            .block_params(header)
+            .iter()
+            .map(|param| (function.dfg.value_type(*param).clone(), function.dfg.value_span(*param)))
+            .collect::<SmallVec<[_; 4]>>();
+        let args = params
+            .into_iter()
+            .map(|(ty, span)| function.dfg.append_block_param(entry, ty, span))
+            .collect::<SmallVec<[Value; 4]>>();
+        function.dfg.entry = entry;
+        DefaultInstBuilder::at(
+            &mut function.dfg,
+            InsertionPoint::after(ProgramPoint::Block(entry)),
+        )
+        .br(header, &args, function.id.span());
+
+        // Replace each tail call with a branch to the loop header
+        for inst in tail_calls {
+            let span = function.dfg.inst_span(inst);
+            let args = SmallVec::<[Value; 4]>::from_slice(function.dfg.inst_args(inst));
+            function.dfg.replace(inst).br(header, &args, span);
+        }
+
+        session.print(&*function, Self::FLAG).into_diagnostic()?;
+        if session.should_print_cfg(Self::FLAG) {
+            use std::io::Write;
+            let cfg = function.cfg_printer();
+            let mut stdout = std::io::stdout().lock();
+            write!(&mut stdout, "{cfg}").into_diagnostic()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::{
+        pass::{AnalysisManager, RewritePass},
+        testing::TestContext,
+        AbiParam, BranchInfo, Function, FunctionBuilder, Immediate, InstBuilder, Opcode, Signature,
+        SourceSpan, Type,
+    };
+    use pretty_assertions::assert_eq;
+
+    use crate::EliminateTailRecursion;
+
+    /// Run tail recursion elimination on the following IR:
+    ///
+    /// ```text,ignore
+    /// pub fn count(u32, u32) -> u32 {
+    /// entry(n0: u32, acc0: u32):
+    ///    is_zero = eq n0, 0;
+    ///    condbr is_zero, blk0, blk1;
+    ///
+    /// blk0:
+    ///    ret acc0;
+    ///
+    /// blk1:
+    ///    n1 = sub.wrapping n0, 1;
+    ///    acc1 = add.wrapping acc0, 1;
+    ///    exec.tail count(n1, acc1);
+    /// }
+    /// ```
+    ///
+    /// We expect a new entry block to be introduced which branches to the old entry block, and
+    /// the tail call to be replaced with a branch to the old entry block.
+    #[test]
+    fn tail_recursion_is_rewritten_as_loop() {
+        let context = TestContext::default();
+        let id = "test::count".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        let (header, blk1, n1, acc1) = {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (n0, acc0) = {
+                let params = builder.block_params(entry);
+                (params[0], params[1])
+            };
+
+            let blk0 = builder.create_block();
+            let blk1 = builder.create_block();
+
+            let is_zero = builder.ins().eq_imm(n0, Immediate::U32(0), SourceSpan::UNKNOWN);
+            builder.ins().cond_br(is_zero, blk0, &[], blk1, &[], SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk0);
+            builder.ins().ret(Some(acc0), SourceSpan::UNKNOWN);
+
+            builder.switch_to_block(blk1);
+            let n1 = builder.ins().sub_imm_wrapping(n0, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let acc1 = builder.ins().add_imm_wrapping(acc0, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder.ins().tail_exec(id, &[n1, acc1], SourceSpan::UNKNOWN);
+
+            (entry, blk1, n1, acc1)
+        };
+
+        let mut analyses = AnalysisManager::default();
+        let mut rewrite = EliminateTailRecursion;
+        rewrite
+            .apply(&mut function, &mut analyses, &context.session)
+            .expect("tail recursion elimination failed");
+
+        // The new entry block forwards its parameters to the old one
+        let entry = function.dfg.entry_block();
+        assert_ne!(entry, header);
+        let br = function.dfg.last_inst(entry).unwrap();
+        match function.dfg.analyze_branch(br) {
+            BranchInfo::SingleDest(succ) => {
+                assert_eq!(succ.destination, header);
+                assert_eq!(succ.args, function.dfg.block_params(entry));
+            }
+            _ => panic!("expected unconditional branch"),
+        }
+
+        // The tail call now continues the loop
+        let br = function.dfg.last_inst(blk1).unwrap();
+        assert_eq!(function.dfg.inst(br).opcode(), Opcode::Br);
+        match function.dfg.analyze_branch(br) {
+            BranchInfo::SingleDest(succ) => {
+                assert_eq!(succ.destination, header);
+                assert_eq!(succ.args, &[n1, acc1]);
+            }
+            _ => panic!("expected unconditional branch"),
+        }
+    }
+}
@@ -395,9 +395,13 @@ impl Type {
                     _ => (split, Some(remaining.into())),
                 }
             }
-            Type::List(_) => {
-                todo!("invalid type: list has no defined representation yet, so cannot be split")
+            // A list is split like a struct of its pointer and length
+            Self::List(elem_ty) => {
+                Type::Struct(StructType::new([Type::Ptr(elem_ty), Type::U32])).split(n)
             }
+            // The layout of the payload depends on the discriminant, so a union is split as an
+            // array of bytes
+            Type::Union(_) => Type::Array(Box::new(Type::U8), size_in_bytes).split(n),
             // These types either have no size, or are 1 byte in size, so must have
             // been handled above when checking if the size of the type is <= the
             // requested split size
@@ -428,8 +432,10 @@ impl Type {
             Self::Struct(ref struct_ty) => struct_ty.min_alignment(),
             // Arrays use the minimum alignment of their element type
             Self::Array(ref element_ty, _) => element_ty.min_alignment(),
-            // Lists use the minimum alignment of their element type
-            Self::List(ref element_ty) => element_ty.min_alignment(),
+            // Lists are represented as a pointer and a length, which must be element-aligned
+            Self::List(_) => 4,
+            // Unions use the maximum alignment of their discriminant and cases
+            Self::Union(ref union_ty) => union_ty.min_alignment(),
         }
     }
 
@@ -469,10 +475,9 @@ impl Type {
                 let padded_element_size = element_size.align_up(min_align);
                 element_size + (padded_element_size * (n - 1))
             }
-            Type::List(_) => todo!(
-                "invalid type: list has no defined representation yet, so its size cannot be \
-                 determined"
-            ),
+            // Lists are a pointer to their first element, followed by their length as a u32
+            Self::List(_) => 64,
+            Self::Union(ref union_ty) => union_ty.size() * 8,
         }
     }
 
@@ -713,6 +718,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn list_type_test() {
+        // A list is a pointer and a length, regardless of its element type
+        let string_ty = Type::List(Box::new(Type::U8));
+        assert_eq!(string_ty.size_in_bytes(), 8);
+        assert_eq!(string_ty.min_alignment(), 4);
+        assert_eq!(Type::List(Box::new(Type::U128)).layout(), string_ty.layout());
+        assert_eq!(
+            string_ty.clone().to_raw_parts(),
+            Some(smallvec![Type::Ptr(Box::new(Type::U8)), Type::U32])
+        );
+
+        // Records holding lists, e.g. `record { name: string, id: u32 }`
+        let struct_ty = StructType::new([string_ty.clone(), Type::U32]);
+        assert_eq!(struct_ty.min_alignment(), 4);
+        assert_eq!(struct_ty.size(), 12);
+        assert_eq!(struct_ty.get(1).offset, 8);
+        let struct_ty = StructType::new([Type::U8, string_ty.clone()]);
+        assert_eq!(struct_ty.get(1).offset, 4);
+        assert_eq!(struct_ty.size(), 12);
+
+        // Lists of such records
+        let list_ty = Type::List(Box::new(struct_ty.into()));
+        assert_eq!(list_ty.size_in_bytes(), 8);
+        assert_eq!(Type::Array(Box::new(string_ty), 2).size_in_bytes(), 16);
+    }
+
+    #[test]
+    fn union_type_test() {
+        // The payload is aligned for every case, and sized for the largest one
+        let union_ty = UnionType::new(
+            Type::U8,
+            [Type::Unit, Type::U16, StructType::new([Type::U32, Type::U8]).into()],
+        );
+        assert_eq!(union_ty.min_alignment(), 4);
+        assert_eq!(union_ty.payload_offset(), 4);
+        assert_eq!(union_ty.size(), 12);
+
+        // Cases without a payload add nothing to the discriminant
+        let union_ty = UnionType::new(Type::U16, [Type::Unit, Type::Unit]);
+        assert_eq!(union_ty.min_alignment(), 2);
+        assert_eq!(union_ty.payload_offset(), 2);
+        assert_eq!(union_ty.size(), 2);
+        assert_eq!(Type::Union(union_ty).size_in_bytes(), 2);
+
+        // Lists are held as a pointer and a length, regardless of their element type
+        let string_ty = Type::List(Box::new(Type::U8));
+        let union_ty = UnionType::new(Type::U8, [Type::Unit, string_ty.clone()]);
+        assert_eq!(union_ty.min_alignment(), 4);
+        assert_eq!(union_ty.payload_offset(), 4);
+        assert_eq!(union_ty.size(), 12);
+        assert_eq!(Type::Union(union_ty).size_in_bytes(), 12);
+
+        // Cases wrapping lists, e.g. `result<record { s: string }, u8>`
+        let record_ty = StructType::new([string_ty.clone()]);
+        let union_ty = UnionType::new(Type::U8, [record_ty.into(), Type::U8]);
+        assert_eq!(union_ty.payload_offset(), 4);
+        assert_eq!(union_ty.size(), 12);
+
+        // `option<tuple<string, u32>>`
+        let tuple_ty = StructType::new([string_ty, Type::U32]);
+        let union_ty = UnionType::new(Type::U8, [Type::Unit, tuple_ty.into()]);
+        assert_eq!(union_ty.min_alignment(), 4);
+        assert_eq!(union_ty.size(), 16);
+        let option_ty = Type::Union(union_ty);
+        assert_eq!(option_ty.size_in_bytes(), 16);
+
+        // Lists and records of such unions
+        assert_eq!(Type::List(Box::new(option_ty.clone())).size_in_bytes(), 8);
+        assert_eq!(StructType::new([Type::U8, option_ty]).size(), 20);
+    }
+
     #[test]
     fn type_to_raw_parts_test() {
         let ty = Type::Array(Box::new(Type::U8), 5);
@@ -66,6 +66,10 @@ pub enum Type {
     /// two words will be loaded, depending on the type being loaded, unused elements will be
     /// dropped, and if the byte offset is non-zero, the data will be shifted bitwise into
     /// alignment on an element boundary.
+    ///
+    /// NOTE: Pointers into a statically allocated address space, i.e. [AddressSpace::Id], are an
+    /// exception to the above: like [Type::Ptr], they are represented as a byte address, which is
+    /// relative to the start of that address space.
     NativePtr(Box<Type>, AddressSpace),
     /// A compound type of fixed shape and size
     Struct(StructType),
@@ -73,10 +77,14 @@ pub enum Type {
     Array(Box<Type>, usize),
     /// A dynamically sized list of values of the given type
     ///
-    /// NOTE: Currently this only exists to support the Wasm Canonical ABI,
-    /// but it has no defined representation yet, so in practice cannot be
-    /// used in most places except during initial translation in the Wasm frontend.
+    /// A list is represented as in the Wasm Canonical ABI, i.e. as a 32-bit pointer to its first
+    /// element, followed by its length as a 32-bit integer, so its contents are not part of its
+    /// layout.
+    ///
+    /// NOTE: Currently this only exists to support the Wasm Canonical ABI.
     List(Box<Type>),
+    /// A tagged union, i.e. a value of one of a fixed set of cases, identified by a discriminant
+    Union(UnionType),
 }
 impl Type {
     /// Returns true if this type is a zero-sized type, which includes:
@@ -108,7 +116,8 @@ impl Type {
             | Self::Felt
             | Self::Ptr(_)
             | Self::NativePtr(..)
-            | Self::List(_) => false,
+            | Self::List(_)
+            | Self::Union(_) => false,
         }
     }
 
@@ -290,6 +299,12 @@ impl From<StructType> for Type {
         Type::Struct(ty)
     }
 }
+impl From<UnionType> for Type {
+    #[inline]
+    fn from(ty: UnionType) -> Type {
+        Type::Union(ty)
+    }
+}
 impl fmt::Display for Type {
     /// Print this type for display using the provided module context
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -325,6 +340,7 @@ impl fmt::Display for Type {
             Self::Struct(sty) => write!(f, "{sty}"),
             Self::Array(element_ty, arity) => write!(f, "(array {element_ty} {arity})"),
             Self::List(ty) => write!(f, "(list {ty})"),
+            Self::Union(uty) => write!(f, "{uty}"),
         }
     }
 }
@@ -589,6 +605,98 @@ impl fmt::Display for StructType {
     }
 }
 
+/// This represents a tagged union, e.g. a Wasm Component Model `variant`, `option` or `result`
+///
+/// In memory, the discriminant comes first, followed by the payload of the case it identifies,
+/// which is placed at the first offset satisfying the alignment of every case, so that each case
+/// is found at the same offset. A union is large enough to hold the payload of its largest case.
+///
+/// NOTE: Unlike [StructType], the layout of a union is computed on demand.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnionType {
+    /// The type of the discriminant, an unsigned integer
+    pub(crate) discriminant: Box<Type>,
+    /// The payload type of each case, indexed by discriminant, `Type::Unit` if it has none
+    pub(crate) cases: Vec<Type>,
+}
+impl UnionType {
+    /// Create a new union of the given cases, identified by a discriminant of type
+    /// `discriminant`.
+    ///
+    /// This function will panic if the discriminant is not an unsigned integer type large enough
+    /// to identify every case.
+    pub fn new<I: IntoIterator<Item = Type>>(discriminant: Type, cases: I) -> Self {
+        let cases = cases.into_iter().collect::<Vec<_>>();
+        assert!(
+            matches!(discriminant, Type::U8 | Type::U16 | Type::U32),
+            "invalid union: expected discriminant to be an unsigned integer, got {discriminant}"
+        );
+        assert!(
+            (cases.len() as u64) <= 1u64 << discriminant.size_in_bits(),
+            "invalid union: {discriminant} cannot identify {} cases",
+            cases.len()
+        );
+        Self {
+            discriminant: Box::new(discriminant),
+            cases,
+        }
+    }
+
+    /// Get the type of the discriminant
+    #[inline]
+    pub fn discriminant(&self) -> &Type {
+        &self.discriminant
+    }
+
+    /// Get the payload types of the cases, indexed by discriminant
+    #[inline]
+    pub fn cases(&self) -> &[Type] {
+        self.cases.as_slice()
+    }
+
+    /// Get the minimum alignment for the payload of every case
+    pub fn payload_alignment(&self) -> usize {
+        self.cases.iter().map(|ty| ty.min_alignment()).max().unwrap_or(1)
+    }
+
+    /// Get the offset of the payload relative to the base of the union
+    pub fn payload_offset(&self) -> u32 {
+        let size = self.discriminant.size_in_bytes() as u32;
+        size.align_up(self.payload_alignment() as u32)
+    }
+
+    /// Get the minimum alignment for this union
+    pub fn min_alignment(&self) -> usize {
+        core::cmp::max(self.discriminant.min_alignment(), self.payload_alignment())
+    }
+
+    /// Get the total size in bytes required to hold this union, including alignment padding
+    pub fn size(&self) -> usize {
+        let payload_size = self.cases.iter().map(|ty| ty.size_in_bytes()).max().unwrap_or(0);
+        (self.payload_offset() as usize + payload_size).align_up(self.min_alignment())
+    }
+}
+impl TryFrom<Type> for UnionType {
+    type Error = Type;
+
+    fn try_from(ty: Type) -> Result<Self, Self::Error> {
+        match ty {
+            Type::Union(ty) => Ok(ty),
+            other => Err(other),
+        }
+    }
+}
+impl fmt::Display for UnionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(union {}", &self.discriminant)?;
+        for case in self.cases.iter() {
+            write!(f, " {case}")?;
+        }
+        f.write_str(")")
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
 #[cfg_attr(
     feature = "serde",
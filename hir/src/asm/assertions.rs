@@ -2,3 +2,19 @@
 
 /// This assertion fails when a pointer address does not meet minimum alignment for the type
 pub const ASSERT_FAILED_ALIGNMENT: u32 = 0xfa;
+
+/// This assertion fails when a handle to a component resource is used while it is not valid for
+/// that use, e.g. it was already dropped, or it is dropped while it is borrowed
+pub const ASSERT_FAILED_INVALID_HANDLE: u32 = 0xfb;
+
+/// This assertion fails when a component creates more resources than its handle table can hold
+pub const ASSERT_FAILED_TOO_MANY_HANDLES: u32 = 0xfc;
+
+/// This assertion fails when a 64-bit floating point operation which has no soft-float
+/// implementation, e.g. `f64.sqrt`, is executed
+pub const ASSERT_FAILED_UNSUPPORTED_F64: u32 = 0xfd;
+
+/// This assertion fails when a Wasm exception is thrown, as exceptions are never caught.
+///
+/// The error code is this value plus the index of the tag of the exception in its module.
+pub const ASSERT_FAILED_EXCEPTION: u32 = 0xe000_0000;
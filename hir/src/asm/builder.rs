@@ -167,6 +167,11 @@ impl<'a> MasmOpBuilder<'a> {
         self.build(self.ip, MasmOp::PushU32(imm), span);
     }
 
+    /// Emits the trace event `event` to the host, without modifying the stack
+    pub fn trace(mut self, event: u32, span: SourceSpan) {
+        self.build(self.ip, MasmOp::Trace(event), span);
+    }
+
     /// Drops the element on the top of the stack
     pub fn drop(mut self, span: SourceSpan) {
         self.build(self.ip, MasmOp::Drop, span);
@@ -11,6 +11,15 @@ pub const TRACE_FRAME_START: u32 = 0xf0;
 /// The mnemonic here is F = frame, C = close
 pub const TRACE_FRAME_END: u32 = 0xfc;
 
+/// This event is emitted via `trace`, and indicates that the value on top of the operand stack is
+/// the new value of a debug slot of the current function, see [crate::DebugVariable].
+///
+/// The index of the slot is added to this value, so the events for all slots are in the range
+/// `TRACE_DEBUG_VALUE..=(TRACE_DEBUG_VALUE + u16::MAX)`.
+///
+/// The mnemonic here is DB = debug
+pub const TRACE_DEBUG_VALUE: u32 = 0xdb00_0000;
+
 /// A typed wrapper around the raw trace events known to the compiler
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(u32)]
@@ -18,6 +27,7 @@ pub enum TraceEvent {
     FrameStart,
     FrameEnd,
     AssertionFailed(Option<NonZeroU32>),
+    DebugValue(u16),
     Unknown(u32),
 }
 impl TraceEvent {
@@ -36,6 +46,9 @@ impl From<u32> for TraceEvent {
         match raw {
             TRACE_FRAME_START => Self::FrameStart,
             TRACE_FRAME_END => Self::FrameEnd,
+            raw if raw & !(u16::MAX as u32) == TRACE_DEBUG_VALUE => {
+                Self::DebugValue((raw - TRACE_DEBUG_VALUE) as u16)
+            }
             _ => Self::Unknown(raw),
         }
     }
@@ -47,6 +60,7 @@ impl From<TraceEvent> for u32 {
             TraceEvent::FrameEnd => TRACE_FRAME_END,
             TraceEvent::AssertionFailed(None) => 0,
             TraceEvent::AssertionFailed(Some(code)) => code.get(),
+            TraceEvent::DebugValue(slot) => TRACE_DEBUG_VALUE + slot as u32,
             TraceEvent::Unknown(code) => code,
         }
     }
@@ -1387,6 +1387,52 @@ pub trait InstBuilder<'f>: InstBuilderBase<'f> {
         self.Call(Opcode::Syscall, callee, vlist, span).0
     }
 
+    /// Call `callee` in tail position, returning its results to the caller of this function.
+    ///
+    /// This terminates the current block, and `callee` must have the same results as the function
+    /// being built.
+    fn tail_exec(mut self, callee: FunctionIdent, args: &[Value], span: SourceSpan) -> Inst {
+        let mut vlist = ValueList::default();
+        {
+            let dfg = self.data_flow_graph_mut();
+            assert!(
+                dfg.get_import(&callee).is_some(),
+                "must import callee ({}) before calling it",
+                &callee
+            );
+            vlist.extend(args.iter().copied(), &mut dfg.value_lists);
+        }
+        self.Call(Opcode::TailExec, callee, vlist, span).0
+    }
+
+    /// Call the function at index `callee` of `table`, which must be a `u32`.
+    ///
+    /// Every function in `table` must have been imported with `signature` before calling it,
+    /// entries for functions with any other signature must be `None`, as calling them will trap.
+    fn call_indirect(
+        mut self,
+        callee: Value,
+        signature: Signature,
+        table: Vec<Option<FunctionIdent>>,
+        args: &[Value],
+        span: SourceSpan,
+    ) -> Inst {
+        let mut vlist = ValueList::default();
+        {
+            let dfg = self.data_flow_graph_mut();
+            for entry in table.iter().flatten() {
+                assert!(
+                    dfg.get_import(entry).is_some(),
+                    "must import callee ({}) before calling it",
+                    entry
+                );
+            }
+            vlist.push(callee, &mut dfg.value_lists);
+            vlist.extend(args.iter().copied(), &mut dfg.value_lists);
+        }
+        self.CallIndirect(signature, table, vlist, span).0
+    }
+
     fn select(mut self, cond: Value, a: Value, b: Value, span: SourceSpan) -> Value {
         let mut vlist = ValueList::default();
         let ty = require_matching_operands!(self, a, b).clone();
@@ -1445,6 +1491,16 @@ pub trait InstBuilder<'f>: InstBuilderBase<'f> {
         self.Ret(vlist, span).0
     }
 
+    /// Return `returning` to the caller, for functions with any number of results
+    fn ret_values(mut self, returning: &[Value], span: SourceSpan) -> Inst {
+        let mut vlist = ValueList::default();
+        {
+            let pool = &mut self.data_flow_graph_mut().value_lists;
+            vlist.extend(returning.iter().copied(), pool);
+        }
+        self.Ret(vlist, span).0
+    }
+
     fn ret_imm(self, arg: Immediate, span: SourceSpan) -> Inst {
         let data = Instruction::RetImm(RetImm {
             op: Opcode::Ret,
@@ -1549,6 +1605,23 @@ pub trait InstBuilder<'f>: InstBuilderBase<'f> {
         self.build(data, Type::Unit, span)
     }
 
+    #[allow(non_snake_case)]
+    fn CallIndirect(
+        self,
+        signature: Signature,
+        table: Vec<Option<FunctionIdent>>,
+        args: ValueList,
+        span: SourceSpan,
+    ) -> (Inst, &'f mut DataFlowGraph) {
+        let data = Instruction::CallIndirect(CallIndirect {
+            op: Opcode::CallIndirect,
+            signature,
+            table,
+            args,
+        });
+        self.build(data, Type::Unit, span)
+    }
+
     #[allow(non_snake_case)]
     fn Binary(
         self,
@@ -136,6 +136,68 @@ impl formatter::PrettyPrint for ComponentImport {
     }
 }
 
+/// A handle to a resource defined by the component, passed as a param or result of one of its
+/// exported functions, whose lifetime is checked when crossing the component boundary
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResourceHandle {
+    /// An owned handle (`own<T>`), whose ownership is transferred by the call
+    Own(InterfaceFunctionIdent),
+    /// A borrowed handle (`borrow<T>`), which is lent for the duration of the call
+    Borrow(InterfaceFunctionIdent),
+}
+
+impl ResourceHandle {
+    /// Returns the resource this is a handle to
+    pub fn resource(&self) -> InterfaceFunctionIdent {
+        match self {
+            Self::Own(resource) | Self::Borrow(resource) => *resource,
+        }
+    }
+}
+
+/// A resource type defined by a component.
+///
+/// The handles to the resources of this type are indices into a handle table maintained in the
+/// memory of the component, which is accessed using the canonical built-in functions of the
+/// resource, see [ComponentResource::builtin].
+#[derive(Debug, Clone)]
+pub struct ComponentResource {
+    /// The interface exporting the resource, and the name of the resource
+    pub name: InterfaceFunctionIdent,
+    /// The function called with the representation of a resource when its owned handle is
+    /// dropped, if specified
+    pub dtor: Option<FunctionIdent>,
+}
+
+impl ComponentResource {
+    /// Returns the identifier of the built-in function `op` of the resource `name`, e.g.
+    /// `[resource-drop]vault`, named as in the Wasm Component Model
+    pub fn builtin(name: InterfaceFunctionIdent, op: &str) -> FunctionIdent {
+        FunctionIdent {
+            module: Ident::with_empty_span(name.interface.full_name),
+            function: Ident::with_empty_span(Symbol::intern(format!(
+                "[resource-{op}]{}",
+                name.function
+            ))),
+        }
+    }
+}
+
+impl formatter::PrettyPrint for ComponentResource {
+    fn render(&self) -> formatter::Document {
+        use crate::formatter::*;
+
+        let dtor = self
+            .dtor
+            .as_ref()
+            .map(|dtor| const_text(" (dtor ") + display(dtor) + const_text(")"))
+            .unwrap_or(Document::Empty);
+        flatten(
+            const_text("(") + const_text("resource ") + self.name.render() + dtor + const_text(")"),
+        )
+    }
+}
+
 /// A component export
 #[derive(Debug, Clone)]
 pub struct ComponentExport {
@@ -145,6 +207,12 @@ pub struct ComponentExport {
     pub function_ty: FunctionType,
     /// Any options associated with this export
     pub options: CanonicalOptions,
+    /// The handles to the resources defined by this component among the params of the function,
+    /// indexed by param
+    pub param_handles: BTreeMap<usize, ResourceHandle>,
+    /// The handles to the resources defined by this component among the results of the function,
+    /// indexed by result
+    pub result_handles: BTreeMap<usize, ResourceHandle>,
 }
 
 impl formatter::PrettyPrint for ComponentExport {
@@ -175,6 +243,9 @@ pub struct Component {
 
     /// A list of this component's exports, indexed by export name
     exports: BTreeMap<InterfaceFunctionIdent, ComponentExport>,
+
+    /// A list of the resource types defined by this component, indexed by name
+    resources: BTreeMap<InterfaceFunctionIdent, ComponentResource>,
 }
 
 impl Component {
@@ -231,6 +302,10 @@ impl Component {
         &self.exports
     }
 
+    pub fn resources(&self) -> &BTreeMap<InterfaceFunctionIdent, ComponentResource> {
+        &self.resources
+    }
+
     /// Get the first module in this component
     pub fn first_module(&self) -> &Module {
         self.modules
@@ -272,6 +347,14 @@ impl formatter::PrettyPrint for Component {
             .map(|doc| const_text(";; Component Imports") + nl() + doc)
             .unwrap_or(Document::Empty);
 
+        let resources = self
+            .resources
+            .values()
+            .map(PrettyPrint::render)
+            .reduce(|acc, doc| acc + nl() + doc)
+            .map(|doc| const_text(";; Component Resources") + nl() + doc)
+            .unwrap_or(Document::Empty);
+
         let modules = self
             .modules
             .values()
@@ -296,7 +379,7 @@ impl formatter::PrettyPrint for Component {
             .map(|doc| const_text(";; Component Exports") + nl() + doc)
             .unwrap_or(Document::Empty);
 
-        let body = vec![imports, modules, exports]
+        let body = vec![imports, resources, modules, exports]
             .into_iter()
             .filter(|section| !section.is_empty())
             .fold(nl(), |a, b| {
@@ -349,6 +432,7 @@ pub struct ComponentBuilder<'a> {
     modules: IndexMap<Ident, Box<Module>>,
     imports: BTreeMap<FunctionIdent, ComponentImport>,
     exports: BTreeMap<InterfaceFunctionIdent, ComponentExport>,
+    resources: BTreeMap<InterfaceFunctionIdent, ComponentResource>,
     entry: Option<FunctionIdent>,
     diagnostics: &'a DiagnosticsHandler,
 }
@@ -360,6 +444,7 @@ impl<'a> ComponentBuilder<'a> {
             diagnostics,
             exports: Default::default(),
             imports: Default::default(),
+            resources: Default::default(),
         }
     }
 
@@ -369,6 +454,7 @@ impl<'a> ComponentBuilder<'a> {
             modules: component.modules,
             imports: component.imports,
             exports: component.exports,
+            resources: component.resources,
             entry: None,
             diagnostics,
         }
@@ -448,6 +534,12 @@ impl<'a> ComponentBuilder<'a> {
         self.exports.insert(name, export);
     }
 
+    /// Add a resource type defined by the [Component] being built. Overwrites any existing
+    /// resource with the same name.
+    pub fn add_resource(&mut self, resource: ComponentResource) {
+        self.resources.insert(resource.name, resource);
+    }
+
     pub fn imports(&self) -> &BTreeMap<FunctionIdent, ComponentImport> {
         &self.imports
     }
@@ -456,6 +548,10 @@ impl<'a> ComponentBuilder<'a> {
         &self.exports
     }
 
+    pub fn resources(&self) -> &BTreeMap<InterfaceFunctionIdent, ComponentResource> {
+        &self.resources
+    }
+
     /// Look up the signature of a function in this program by `id`
     pub fn signature(&self, id: &FunctionIdent) -> Option<&Signature> {
         let module = self.modules.get(&id.module)?;
@@ -495,6 +591,7 @@ impl<'a> ComponentBuilder<'a> {
             modules: self.modules,
             imports: self.imports,
             exports: self.exports,
+            resources: self.resources,
         }
     }
 }
@@ -21,6 +21,8 @@ pub struct DataFlowGraph {
     pub globals: PrimaryMap<GlobalValue, GlobalValueData>,
     pub locals: PrimaryMap<LocalId, Local>,
     pub constants: ConstantPool,
+    /// The source-level variables of this function, if debug information is available
+    pub debug_variables: Vec<DebugVariable>,
 }
 impl Default for DataFlowGraph {
     fn default() -> Self {
@@ -46,6 +48,7 @@ impl DataFlowGraph {
             globals: PrimaryMap::new(),
             locals: PrimaryMap::new(),
             constants: ConstantPool::default(),
+            debug_variables: vec![],
         }
     }
 
@@ -364,7 +367,9 @@ impl DataFlowGraph {
         self.results[inst].clear(&mut self.value_lists);
 
         let opcode = self.insts[inst].opcode();
-        if let Some(fdata) = self.call_signature(inst) {
+        // The results of a tail call are those of the caller, not of the call itself
+        let signature = self.call_signature(inst).filter(|_| opcode != Opcode::TailExec);
+        if let Some(fdata) = signature {
             let results =
                 SmallVec::<[Type; 2]>::from_iter(fdata.results().iter().map(|abi| abi.ty.clone()));
             for ty in results.into_iter() {
@@ -393,7 +398,8 @@ impl DataFlowGraph {
         let old_results =
             SmallVec::<[Value; 1]>::from_slice(self.results[inst].as_slice(&self.value_lists));
         let mut new_results = SmallVec::<[Type; 1]>::default();
-        if let Some(fdata) = self.call_signature(inst) {
+        let signature = self.call_signature(inst).filter(|_| opcode != Opcode::TailExec);
+        if let Some(fdata) = signature {
             new_results.extend(fdata.results().iter().map(|p| p.ty.clone()));
         } else {
             match self.insts[inst].data.deref() {
@@ -583,6 +589,7 @@ impl DataFlowGraph {
         match self.insts[inst].analyze_call(&self.value_lists) {
             CallInfo::NotACall => None,
             CallInfo::Direct(ref f, _) => Some(&self.imports[f].signature),
+            CallInfo::Indirect(signature, _) => Some(signature),
         }
     }
 
@@ -678,6 +685,18 @@ impl DataFlowGraph {
         self.blocks.append(block, BlockData::new(block));
     }
 
+    /// Creates a new block, inserted into the function layout just before `block`
+    pub fn create_block_before(&mut self, block: Block) -> Block {
+        let id = self.blocks.create();
+        let data = BlockData::new(id);
+        assert!(
+            self.blocks.get(block).is_some(),
+            "cannot insert a new block before {block}, it is not linked"
+        );
+        self.blocks.insert_before(id, block, data);
+        id
+    }
+
     /// Creates a new block, inserted into the function layout just after `block`
     pub fn create_block_after(&mut self, block: Block) -> Block {
         let id = self.blocks.create();
@@ -690,6 +709,46 @@ impl DataFlowGraph {
         id
     }
 
+    /// Splits `block` in two at `inst`, moving `inst` and all of the instructions which follow it
+    /// to a new block, inserted immediately after `block` in the layout. The new block is returned.
+    ///
+    /// NOTE: `block` is left without a terminator, it is up to the caller to append one.
+    pub fn split_block(&mut self, block: Block, inst: Inst) -> Block {
+        debug_assert_eq!(self.inst_block(inst), Some(block));
+        let split = self.create_block_after(block);
+        let mut moved = SmallVec::<[UnsafeRef<InstNode>; 8]>::default();
+        {
+            let mut cursor = self.blocks[block].cursor_mut_at_inst(inst);
+            while let Some(node) = cursor.remove() {
+                moved.push(node);
+            }
+        }
+        for node in moved.into_iter() {
+            let key = node.key;
+            self.blocks[split].append(node);
+            self.insts[key].block = split;
+        }
+        split
+    }
+
+    /// Moves `inst` from its current position, to just before `before`, which may be in a
+    /// different block.
+    ///
+    /// NOTE: It is up to the caller to ensure that the arguments of `inst` dominate its new
+    /// position, and that its results dominate all of their uses.
+    pub fn move_inst_before(&mut self, inst: Inst, before: Inst) {
+        let from = self.inst_block(inst).expect("cannot move a detached instruction");
+        let to = self
+            .inst_block(before)
+            .expect("cannot move an instruction before a detached one");
+        let node = self.blocks[from]
+            .cursor_mut_at_inst(inst)
+            .remove()
+            .expect("instruction is not linked in its block");
+        self.blocks[to].cursor_mut_at_inst(before).insert_before(node);
+        self.insts[inst].block = to;
+    }
+
     /// Removes `block` from the body of this function, without destroying it's data
     pub fn detach_block(&mut self, block: Block) {
         self.blocks.remove(block);
@@ -825,6 +884,52 @@ impl DataFlowGraph {
         }
     }
 
+    /// Removes the argument at `index` from the argument list of every successor of `branch_inst`
+    /// whose destination block is `dest`.
+    /// Panics if `branch_inst` is not a branch instruction.
+    pub fn remove_branch_destination_argument(
+        &mut self,
+        branch_inst: Inst,
+        dest: Block,
+        index: usize,
+    ) {
+        match self.insts[branch_inst].data.deref_mut() {
+            Instruction::Br(Br {
+                ref mut successor, ..
+            }) => {
+                debug_assert_eq!(successor.destination, dest);
+                successor.args.remove(index, &mut self.value_lists);
+            }
+            Instruction::CondBr(CondBr {
+                ref mut then_dest,
+                ref mut else_dest,
+                ..
+            }) => {
+                if then_dest.destination == dest {
+                    then_dest.args.remove(index, &mut self.value_lists);
+                }
+                if else_dest.destination == dest {
+                    else_dest.args.remove(index, &mut self.value_lists);
+                }
+            }
+            Instruction::Switch(Switch {
+                ref mut arms,
+                default: ref mut default_succ,
+                ..
+            }) => {
+                if default_succ.destination == dest {
+                    default_succ.args.remove(index, &mut self.value_lists);
+                }
+                for arm in arms.iter_mut() {
+                    if arm.successor.destination == dest {
+                        arm.successor.args.remove(index, &mut self.value_lists);
+                    }
+                }
+            }
+            _ => panic!("{} must be a branch instruction", branch_inst),
+        }
+    }
+
     /// Try to locate a valid definition of `value` in the current block, looking up the block from
     /// `user`
     pub fn nearest_definition_in_block(&self, user: Inst, value: Value) -> Option<Value> {
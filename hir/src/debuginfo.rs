@@ -0,0 +1,122 @@
+//! Source-level debug information about the variables of a function.
+//!
+//! Variables are not tracked through the IR directly, as values move in and out of the operand
+//! stack, locals and memory throughout code generation. Instead, each variable is associated with a
+//! _debug slot_ of its function, and every time the slot is assigned, the new value is reported to
+//! the debugger using a [crate::TraceEvent::DebugValue] event, with the value on top of the operand
+//! stack, see [crate::MasmOpBuilder::trace].
+
+/// A source-level variable of a function, e.g. a Rust local or parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DebugVariable {
+    /// The name of the variable in the source program
+    pub name: String,
+    /// The type of the variable in the source program
+    pub ty: DebugType,
+    /// The debug slot whose value is used to locate the variable
+    pub slot: u16,
+    /// How the value of the variable is derived from the value of its debug slot
+    pub location: DebugLocation,
+}
+
+/// How to find the value of a [DebugVariable], given the value of its debug slot
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "kebab-case"))]
+pub enum DebugLocation {
+    /// The value of the slot is the value of the variable
+    Value,
+    /// The variable is in linear memory, at the given byte offset from the address held in the
+    /// slot, e.g. the frame base of the function
+    Memory { offset: i64 },
+    /// The value of the variable is not reported to the debugger, as it is held in a local or
+    /// global whose values do not fit in a single field element, e.g. an `i64`
+    Untracked,
+}
+
+/// The type of a [DebugVariable], as described by the source program
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DebugType {
+    /// The name of the type, e.g. `u32` or `&str`
+    pub name: String,
+    /// The size in bytes of a value of this type
+    pub size: u32,
+    /// How the bytes of a value of this type are interpreted
+    pub encoding: DebugTypeEncoding,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum DebugTypeEncoding {
+    Signed,
+    Unsigned,
+    Boolean,
+    Float,
+    /// A pointer or reference, i.e. a byte address in linear memory
+    Address,
+    /// Any other type, e.g. a struct, whose bytes are displayed as-is
+    Opaque,
+}
+
+/// The debug information of a function, as emitted alongside a compiled program
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionDebugInfo {
+    /// The fully-qualified name of the procedure the function was compiled to
+    pub procedure: String,
+    pub variables: Vec<DebugVariable>,
+}
+
+/// The debug information of a program, as emitted alongside it in a `.dbg` file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProgramDebugInfo {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub functions: Vec<FunctionDebugInfo>,
+}
+impl ProgramDebugInfo {
+    /// Get the debug information of the function compiled to `procedure`
+    ///
+    /// The procedure name may be qualified with a namespace which is not known to the compiler,
+    /// so a name is also matched if it ends with the path of the procedure.
+    pub fn function(&self, procedure: &str) -> Option<&FunctionDebugInfo> {
+        self.functions.iter().find(|f| {
+            procedure == f.procedure
+                || procedure
+                    .strip_suffix(f.procedure.as_str())
+                    .is_some_and(|prefix| prefix.ends_with("::"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(procedure: &str) -> FunctionDebugInfo {
+        FunctionDebugInfo {
+            procedure: procedure.to_string(),
+            variables: vec![],
+        }
+    }
+
+    #[test]
+    fn function_lookup() {
+        let debug_info = ProgramDebugInfo {
+            functions: vec![function("root_ns:root@1.0.0::foo::bar"), function("baz::bar")],
+        };
+        let lookup = |procedure| debug_info.function(procedure).map(|f| f.procedure.as_str());
+
+        assert_eq!(lookup("root_ns:root@1.0.0::foo::bar"), Some("root_ns:root@1.0.0::foo::bar"));
+        assert_eq!(lookup("baz::bar"), Some("baz::bar"));
+        // The procedure may be qualified with a namespace unknown to the compiler
+        assert_eq!(lookup("#exec::baz::bar"), Some("baz::bar"));
+        // Only whole path components are matched
+        assert_eq!(lookup("foobaz::bar"), None);
+        assert_eq!(lookup("bar"), None);
+        assert_eq!(lookup("foo::bar"), None);
+    }
+}
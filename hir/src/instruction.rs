@@ -103,6 +103,7 @@ pub enum Instruction {
     UnaryOp(UnaryOp),
     UnaryOpImm(UnaryOpImm),
     Call(Call),
+    CallIndirect(CallIndirect),
     Br(Br),
     CondBr(CondBr),
     Switch(Switch),
@@ -127,6 +128,10 @@ impl Instruction {
                 args: call.args.deep_clone(value_lists),
                 ..call.clone()
             }),
+            Self::CallIndirect(call) => Self::CallIndirect(CallIndirect {
+                args: call.args.deep_clone(value_lists),
+                ..call.clone()
+            }),
             Self::Br(br) => Self::Br(Br {
                 successor: br.successor.deep_clone(value_lists),
                 ..br.clone()
@@ -179,6 +184,7 @@ impl Instruction {
             | Self::UnaryOp(UnaryOp { ref op, .. })
             | Self::UnaryOpImm(UnaryOpImm { ref op, .. })
             | Self::Call(Call { ref op, .. })
+            | Self::CallIndirect(CallIndirect { ref op, .. })
             | Self::Br(Br { ref op, .. })
             | Self::CondBr(CondBr { ref op, .. })
             | Self::Switch(Switch { ref op, .. })
@@ -235,6 +241,7 @@ impl Instruction {
             Self::BinaryOpImm(BinaryOpImm { ref arg, .. }) => core::slice::from_ref(arg),
             Self::UnaryOp(UnaryOp { ref arg, .. }) => core::slice::from_ref(arg),
             Self::Call(Call { ref args, .. }) => args.as_slice(pool),
+            Self::CallIndirect(CallIndirect { ref args, .. }) => args.as_slice(pool),
             Self::CondBr(CondBr { ref cond, .. }) => core::slice::from_ref(cond),
             Self::Switch(Switch { ref arg, .. }) => core::slice::from_ref(arg),
             Self::Ret(Ret { ref args, .. }) => args.as_slice(pool),
@@ -254,6 +261,7 @@ impl Instruction {
             Self::BinaryOpImm(BinaryOpImm { ref mut arg, .. }) => core::slice::from_mut(arg),
             Self::UnaryOp(UnaryOp { ref mut arg, .. }) => core::slice::from_mut(arg),
             Self::Call(Call { ref mut args, .. }) => args.as_mut_slice(pool),
+            Self::CallIndirect(CallIndirect { ref mut args, .. }) => args.as_mut_slice(pool),
             Self::CondBr(CondBr { ref mut cond, .. }) => core::slice::from_mut(cond),
             Self::Switch(Switch { ref mut arg, .. }) => core::slice::from_mut(arg),
             Self::Ret(Ret { ref mut args, .. }) => args.as_mut_slice(pool),
@@ -299,6 +307,7 @@ impl Instruction {
     pub fn analyze_call<'a>(&'a self, pool: &'a ValueListPool) -> CallInfo<'a> {
         match self {
             Self::Call(ref c) => CallInfo::Direct(c.callee, c.args.as_slice(pool)),
+            Self::CallIndirect(ref c) => CallInfo::Indirect(&c.signature, c.args.as_slice(pool)),
             _ => CallInfo::NotACall,
         }
     }
@@ -314,6 +323,8 @@ pub enum BranchInfo<'a> {
 pub enum CallInfo<'a> {
     NotACall,
     Direct(FunctionIdent, &'a [Value]),
+    /// An indirect call with the given signature, the first argument is the callee index
+    Indirect(&'a Signature, &'a [Value]),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -460,6 +471,13 @@ pub enum Opcode {
     Exec,
     Call,
     Syscall,
+    /// Calls a function selected at runtime from a function table, in the current context
+    CallIndirect,
+    /// Calls a function in the current context, in tail position.
+    ///
+    /// This is a terminator, the results of the callee are returned directly to the caller of the
+    /// current function, so the callee must have the same results as the current function.
+    TailExec,
     Br,
     CondBr,
     Switch,
@@ -478,7 +496,10 @@ pub enum Opcode {
 }
 impl Opcode {
     pub fn is_terminator(&self) -> bool {
-        matches!(self, Self::Br | Self::CondBr | Self::Switch | Self::Ret | Self::Unreachable)
+        matches!(
+            self,
+            Self::Br | Self::CondBr | Self::Switch | Self::Ret | Self::TailExec | Self::Unreachable
+        )
     }
 
     pub fn is_branch(&self) -> bool {
@@ -486,7 +507,10 @@ impl Opcode {
     }
 
     pub fn is_call(&self) -> bool {
-        matches!(self, Self::Exec | Self::Call | Self::Syscall)
+        matches!(
+            self,
+            Self::Exec | Self::Call | Self::Syscall | Self::CallIndirect | Self::TailExec
+        )
     }
 
     pub fn is_commutative(&self) -> bool {
@@ -519,6 +543,8 @@ impl Opcode {
                 | Self::MemSize
                 | Self::Load
                 | Self::Exec
+                | Self::CallIndirect
+                | Self::TailExec
                 | Self::Syscall
                 | Self::InlineAsm
                 | Self::Reload
@@ -533,6 +559,8 @@ impl Opcode {
                 | Self::MemCpy
                 | Self::Store
                 | Self::Exec
+                | Self::CallIndirect
+                | Self::TailExec
                 | Self::Syscall
                 | Self::InlineAsm
                 | Self::Spill
@@ -553,6 +581,8 @@ impl Opcode {
             | Self::Exec
             | Self::Call
             | Self::Syscall
+            | Self::CallIndirect
+            | Self::TailExec
             | Self::Br
             | Self::CondBr
             | Self::Switch
@@ -703,7 +733,9 @@ impl Opcode {
             // memcpy requires source, destination, and arity
             Self::MemSet | Self::MemCpy => 3,
             // Calls are entirely variable
-            Self::Exec | Self::Call | Self::Syscall => 0,
+            Self::Exec | Self::Call | Self::Syscall | Self::TailExec => 0,
+            // Indirect calls require at least the callee index
+            Self::CallIndirect => 1,
             // Unconditional branches have no fixed arguments
             Self::Br => 0,
             // Ifs have a single argument, the conditional
@@ -740,6 +772,8 @@ impl Opcode {
             | Self::Ret
             | Self::Unreachable
             | Self::Spill => smallvec![],
+            // Tail calls return the results of the callee directly to the caller
+            Self::TailExec => smallvec![],
             // These ops have fixed result types
             Self::Test
             | Self::IsOdd
@@ -813,7 +847,9 @@ impl Opcode {
                 smallvec![ctrl_ty.pointee().expect("expected pointer type").clone()]
             }
             // Call results are handled separately
-            Self::Exec | Self::Call | Self::Syscall | Self::InlineAsm => unreachable!(),
+            Self::Exec | Self::Call | Self::Syscall | Self::CallIndirect | Self::InlineAsm => {
+                unreachable!()
+            }
         }
     }
 }
@@ -857,6 +893,8 @@ impl fmt::Display for Opcode {
             Self::Exec => f.write_str("exec"),
             Self::Call => f.write_str("call"),
             Self::Syscall => f.write_str("syscall"),
+            Self::CallIndirect => f.write_str("call.indirect"),
+            Self::TailExec => f.write_str("exec.tail"),
             Self::Ret => f.write_str("ret"),
             Self::Test => f.write_str("test"),
             Self::Select => f.write_str("select"),
@@ -913,7 +951,7 @@ impl fmt::Display for Opcode {
 /// Always check the documentation of the specific instruction involved to see if there
 /// are any specific differences in how this enum is interpreted compared to the default
 /// meaning of each variant.
-#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Hash)]
 pub enum Overflow {
     /// Typically, this means the operation is performed using the equivalent field element
     /// operation, rather than a dedicated operation for the given type. Because of this, the
@@ -1013,6 +1051,24 @@ pub struct Call {
     pub args: ValueList,
 }
 
+/// An indirect call through a function table.
+///
+/// The callee is selected at runtime by its index in `table`, execution traps if the index is
+/// out of bounds, or refers to an empty entry. Entries are expected to be resolved ahead of time,
+/// so that functions whose signature does not match `signature` are represented as empty.
+#[derive(Debug, Clone)]
+pub struct CallIndirect {
+    pub op: Opcode,
+    /// The signature expected of the callee
+    pub signature: Signature,
+    /// The functions which may be called, indexed by the callee operand
+    pub table: Vec<Option<FunctionIdent>>,
+    /// NOTE: The first argument is the callee index, followed by the call arguments in stack
+    /// order, i.e. the callee index is on top of the operand stack, followed by the first
+    /// function argument
+    pub args: ValueList,
+}
+
 /// Branch
 #[derive(Debug, Clone)]
 pub struct Br {
@@ -1198,6 +1254,10 @@ impl<'a> PartialEq for InstructionWithValueListPool<'a> {
                 l.callee == r.callee
                     && l.args.as_slice(self.value_lists) == r.args.as_slice(self.value_lists)
             }
+            (Instruction::CallIndirect(l), Instruction::CallIndirect(r)) => {
+                l.table == r.table
+                    && l.args.as_slice(self.value_lists) == r.args.as_slice(other.value_lists)
+            }
             (Instruction::Br(l), Instruction::Br(r)) => {
                 let l = SuccessorInfo::new(&l.successor, self.value_lists);
                 let r = SuccessorInfo::new(&r.successor, self.value_lists);
@@ -1372,6 +1432,36 @@ impl<'a> formatter::PrettyPrint for InstPrettyPrinter<'a> {
                 operands.extend(args.as_slice(&self.dfg.value_lists).iter().copied().map(display));
                 (vec![], operands)
             }
+            Instruction::CallIndirect(CallIndirect {
+                signature,
+                table,
+                args,
+                ..
+            }) => {
+                let entries = table.iter().map(|entry| match entry {
+                    None => const_text("_"),
+                    Some(callee) if callee.module == self.current_function.module => {
+                        display(callee.function)
+                    }
+                    Some(callee) => {
+                        const_text("(")
+                            + display(callee.module)
+                            + const_text(" ")
+                            + display(callee.function)
+                            + const_text(")")
+                    }
+                });
+                let table = entries.fold(const_text("(") + const_text("table"), |acc, entry| {
+                    acc + const_text(" ") + entry
+                }) + const_text(")");
+                let signature = signature.render();
+                let mut operands = match signature {
+                    Document::Empty => vec![table],
+                    signature => vec![signature, table],
+                };
+                operands.extend(args.as_slice(&self.dfg.value_lists).iter().copied().map(display));
+                (vec![], operands)
+            }
             Instruction::CondBr(CondBr {
                 cond,
                 ref then_dest,
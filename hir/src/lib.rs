@@ -131,6 +131,7 @@ mod builder;
 mod component;
 mod constants;
 mod dataflow;
+mod debuginfo;
 mod display;
 pub mod formatter;
 mod function;
@@ -163,6 +164,10 @@ pub use self::{
     component::*,
     constants::{Constant, ConstantData, ConstantPool, IntoBytes},
     dataflow::DataFlowGraph,
+    debuginfo::{
+        DebugLocation, DebugType, DebugTypeEncoding, DebugVariable, FunctionDebugInfo,
+        ProgramDebugInfo,
+    },
     display::{Decorator, DisplayValues},
     function::*,
     globals::*,
@@ -177,7 +182,7 @@ pub use self::{
         AnalysisKey, ConversionPassRegistration, ModuleRewritePassAdapter, PassInfo,
         RewritePassRegistration,
     },
-    program::{Linker, Program, ProgramAnalysisKey, ProgramBuilder},
+    program::{DynamicCallee, Linker, Program, ProgramAnalysisKey, ProgramBuilder, RecursiveCalls},
     segments::{DataSegment, DataSegmentAdapter, DataSegmentError, DataSegmentTable},
     value::{Value, ValueData, ValueList, ValueListPool},
 };
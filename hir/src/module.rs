@@ -740,6 +740,14 @@ impl ModuleBuilder {
         self.module.name
     }
 
+    pub fn reserved_memory_bytes(&self) -> u32 {
+        self.module.reserved_memory_bytes()
+    }
+
+    pub fn segments(&self) -> &DataSegmentTable {
+        self.module.segments()
+    }
+
     pub fn declare_global_variable<S: AsRef<str>>(
         &mut self,
         name: S,
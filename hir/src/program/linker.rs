@@ -136,9 +136,10 @@ pub struct Linker<'a> {
     /// functions in the program, so that we may emit Miden Assembly
     /// such that all procedure definitions occur before their uses.
     ///
-    /// It is allowed for there to be cyclical module dependencies, but
-    /// we do not permit cyclical function dependencies (i.e. recursive
-    /// function calls).
+    /// It is allowed for there to be cyclical module dependencies, as well
+    /// as cyclical function dependencies (i.e. recursive function calls),
+    /// however the latter require special treatment during code generation,
+    /// see [RecursiveCalls].
     ///
     /// The edge weight is unused.
     callgraph: DiGraphMap<FunctionIdent, ()>,
@@ -269,7 +270,6 @@ impl<'a> Linker<'a> {
     /// The following conditions can cause an error to be raised, if applicable to the object given:
     ///
     /// * The object is invalid
-    /// * The object introduces recursion into the call graph
     /// * Two or more objects export a module with the same name
     /// * Two or more objects contain conflicting data segment declarations
     /// * Two or more objects contain conflicting global variable declarations
@@ -313,7 +313,6 @@ impl<'a> Linker<'a> {
     /// * Multiple modules with the same name
     /// * Conflicting data segment declarations
     /// * Conflicting global variable declarations
-    /// * Recursion in the local call graph of the module (global analysis comes later)
     ///
     /// If any of the above errors occurs, a [Report] is returned.
     fn add_hir_object(&mut self, mut module: Box<Module>) -> Result<(), Report> {
@@ -377,14 +376,14 @@ impl<'a> Linker<'a> {
         }
 
         // Compute the topographical ordering of functions in this module
-        let topography = petgraph::algo::toposort(&self.local_callgraph, None).map_err(|_| {
-            // The error here only gives us the caller, but we'd like to know
-            // the callee for our error diagnostics. To get it, we call the
-            // call graph validation routine which does a traversal specifically
-            // designed to obtain that information.
-            validate_callgraph(&self.local_callgraph, self.diagnostics)
-                .expect_err("expected call graph to contain a cycle")
-        })?;
+        //
+        // Recursive calls are permitted, so we must first remove the edges which close a cycle in
+        // the call graph, the resulting order is otherwise unaffected by them.
+        for (caller, callee) in back_edges(&self.local_callgraph) {
+            self.local_callgraph.remove_edge(caller, callee);
+        }
+        let topography = petgraph::algo::toposort(&self.local_callgraph, None)
+            .expect("expected call graph to be acyclic once back edges are removed");
 
         // Preprocess all functions in this module by:
         //
@@ -447,16 +446,14 @@ impl<'a> Linker<'a> {
     /// * Verify that all referenced functions exist, or are known to be provided at runtime, and
     ///   that the signature known to the caller matches the actual definition.
     /// * Verifies that the entrypoint, if set, is valid
-    /// * Verify that there are no cycles in the call graph, i.e. that there is no recursion present
+    /// * Verify that at most one module uses statically allocated address spaces
+    /// * Break cycles in the call graph caused by recursion, see [RecursiveCalls]
     /// * Verify that all references to global symbols have corresponding definitions
     /// * Perform garbage collection of unreferenced globals
     /// * TODO: If linking an executable program, garbage collect unused modules/functions
     ///
     /// Once linked, a [Program] can be emitted to Miden Assembly using the code generation passes.
     pub fn link(mut self) -> Result<Box<Program>, Report> {
-        // Look for cycles in the call graph
-        validate_callgraph(&self.callgraph, self.diagnostics)?;
-
         // Verify the entrypoint, if declared
         if let Some(entry) = self.program.entrypoint() {
             // NOTE(pauls): Currently, we always raise an error here, but since we do allow
@@ -601,6 +598,8 @@ impl<'a> Linker<'a> {
             }
         }
 
+        self.verify_address_spaces()?;
+
         // Verify global symbol references, and garbage collect unused globals
         for node in self.globals.nodes() {
             // Skip nodes in the graph which aren't globals
@@ -630,6 +629,9 @@ impl<'a> Linker<'a> {
             }
         }
 
+        // Determine how to emit recursive calls, if there are any
+        self.break_cycles()?;
+
         // Run the garbage collector
         self.garbage_collect();
 
@@ -649,6 +651,169 @@ impl<'a> Linker<'a> {
         Ok(self.program)
     }
 
+    /// Verify that at most one of the modules being linked uses statically allocated address
+    /// spaces, i.e. pointers of type [Type::NativePtr] in an [AddressSpace::Id].
+    ///
+    /// Each of these address spaces is assigned a fixed region of Miden's address space, so the
+    /// regions would be shared by all modules using the same address space identifier, e.g. the
+    /// linear memory 1 of two Wasm modules.
+    fn verify_address_spaces(&self) -> Result<(), Report> {
+        let mut modules = self.pending.values().filter_map(|object| match object {
+            Object::Hir(module) if uses_static_address_spaces(module) => Some(module.name),
+            _ => None,
+        });
+        let (Some(first), Some(second)) = (modules.next(), modules.next()) else {
+            return Ok(());
+        };
+        Err(self
+            .diagnostics
+            .diagnostic(Severity::Error)
+            .with_message(format!(
+                "linker error: both '{first}' and '{second}' use statically allocated address \
+                 spaces"
+            ))
+            .with_help(
+                "Statically allocated address spaces, e.g. the linear memories of a Wasm module \
+                 other than memory 0, are not isolated between modules, so at most one module of \
+                 a program may use them",
+            )
+            .into_report())
+    }
+
+    /// Break up the cycles in the call graph caused by recursion.
+    ///
+    /// The back edges found by a depth-first traversal of the call graph are recorded in the
+    /// [RecursiveCalls] of the program, along with the strongly-connected component of each
+    /// function involved. A global variable is declared for each target of a back edge, to hold
+    /// the MAST root of the stub used to call it dynamically.
+    ///
+    /// An error is raised if a recursive component cannot be supported, i.e. if any of its members
+    /// are called via `call` or `syscall`, or if a library is being linked, and one of its members
+    /// is exported.
+    fn break_cycles(&mut self) -> Result<(), Report> {
+        let back_edges = back_edges(&self.callgraph);
+        if back_edges.is_empty() {
+            return Ok(());
+        }
+
+        let mut recursive_calls = RecursiveCalls::default();
+        let mut components = BTreeMap::<FunctionIdent, usize>::default();
+        let mut next_component = 0;
+        for scc in petgraph::algo::tarjan_scc(&self.callgraph).into_iter() {
+            // Only components containing a back edge are recursive, the rest are single functions
+            // which are not self-recursive
+            let is_recursive = scc
+                .iter()
+                .any(|function| back_edges.iter().any(|(caller, _)| caller == function));
+            if !is_recursive {
+                continue;
+            }
+            let component = next_component;
+            next_component += 1;
+            for function in scc.into_iter() {
+                components.insert(function, component);
+                recursive_calls.set_component(function, component);
+            }
+        }
+
+        for function in components.keys().copied() {
+            self.verify_recursive_function(function)?;
+        }
+
+        for (caller, callee) in back_edges.into_iter() {
+            let component = components[&caller];
+            let Some(target) = recursive_calls.insert(component, caller, callee) else {
+                continue;
+            };
+            self.program.globals.declare(
+                target.root,
+                RecursiveCalls::root_type(),
+                Linkage::Internal,
+                None,
+            )?;
+        }
+
+        self.program.recursive_calls = recursive_calls;
+
+        Ok(())
+    }
+
+    /// Verify that `function`, a member of a recursive component, can be called recursively.
+    ///
+    /// The MAST roots of the stubs used to call the members of a component dynamically are stored
+    /// in memory, which is not shared across contexts, so the component must be entered, and
+    /// recursed into, via `exec` in a single context. When linking a library, there is nothing to
+    /// write those roots before the component is entered from outside the library, so it must not
+    /// have any exported functions.
+    fn verify_recursive_function(&self, function: FunctionIdent) -> Result<(), Report> {
+        if let Some(Object::Hir(module)) = self.pending.get(&function.module) {
+            let is_exported = module
+                .function(function.function)
+                .is_some_and(|definition| definition.is_public());
+            if is_exported && self.program.entrypoint().is_none() {
+                return Err(self
+                    .diagnostics
+                    .diagnostic(Severity::Error)
+                    .with_message("linker error")
+                    .with_primary_label(
+                        function.function.span,
+                        "this function is recursive, and is exported from a library",
+                    )
+                    .with_help(
+                        "Recursive functions can only be called from outside a library once the \
+                         library is linked into a program with an entrypoint",
+                    )
+                    .into_report());
+            }
+        }
+
+        for caller in self.callgraph.neighbors_directed(function, Direction::Incoming) {
+            let Some(Object::Hir(module)) = self.pending.get(&caller.module) else {
+                continue;
+            };
+            let Some(definition) = module.function(caller.function) else {
+                continue;
+            };
+            for (_, block) in definition.dfg.blocks() {
+                for inst in block.insts() {
+                    let is_context_switch = match definition.dfg.inst(inst) {
+                        Instruction::Call(Call {
+                            op: Opcode::Call | Opcode::Syscall,
+                            callee,
+                            ..
+                        }) => *callee == function,
+                        Instruction::CallIndirect(CallIndirect {
+                            op: Opcode::Call | Opcode::Syscall,
+                            table,
+                            ..
+                        }) => table.contains(&Some(function)),
+                        _ => false,
+                    };
+                    if is_context_switch {
+                        return Err(self
+                            .diagnostics
+                            .diagnostic(Severity::Error)
+                            .with_message("linker error")
+                            .with_primary_label(
+                                definition.dfg.inst_span(inst),
+                                format!(
+                                    "'{function}' is recursive, and cannot be called using 'call' \
+                                     or 'syscall'"
+                                ),
+                            )
+                            .with_help(
+                                "Recursive functions are only supported within a single context, \
+                                 i.e. they must be called using 'exec'",
+                            )
+                            .into_report());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// If an executable is being linked, discover unused functions and garbage collect them.
     ///
     /// Once a function has been identified as dead and is collected, any transitive items it
@@ -756,26 +921,31 @@ fn is_matching_param(expected: &AbiParam, actual: &AbiParam) -> bool {
     }
 }
 
-/// Validate the given call graph by looking for cycles caused by recursion.
+/// Returns the set of `(caller, callee)` edges of the given call graph which close a cycle, as
+/// found by a depth-first traversal of it.
 ///
-/// Returns a [Report] if a cycle is found.
-fn validate_callgraph(
+/// Removing these edges from the call graph makes it acyclic.
+fn back_edges(
     callgraph: &DiGraphMap<FunctionIdent, ()>,
-    diagnostics: &DiagnosticsHandler,
-) -> Result<(), Report> {
+) -> BTreeSet<(FunctionIdent, FunctionIdent)> {
     use petgraph::visit::{depth_first_search, DfsEvent, IntoNodeIdentifiers};
 
-    depth_first_search(callgraph, callgraph.node_identifiers(), |event| match event {
-        DfsEvent::BackEdge(caller, callee) => Err(diagnostics
-            .diagnostic(Severity::Error)
-            .with_message("linker error")
-            .with_primary_label(caller.span(), "this function contains recursion")
-            .with_secondary_label(callee.span(), "due to one or more calls to this function")
-            .with_help(
-                "If you need to make the call recursive, you may need to use indirect calls to \
-                 acheive this",
-            )
-            .into_report()),
-        _ => Ok(()),
+    let mut back_edges = BTreeSet::default();
+    depth_first_search(callgraph, callgraph.node_identifiers(), |event| {
+        if let DfsEvent::BackEdge(caller, callee) = event {
+            back_edges.insert((caller, callee));
+        }
+    });
+    back_edges
+}
+
+/// Returns true if any value in `module` is a pointer into a statically allocated address space
+fn uses_static_address_spaces(module: &Module) -> bool {
+    module.functions().any(|function| {
+        function
+            .dfg
+            .values
+            .values()
+            .any(|value| matches!(value.ty(), Type::NativePtr(_, AddressSpace::Id(_))))
     })
 }
@@ -1,4 +1,5 @@
 mod linker;
+mod recursion;
 
 use alloc::collections::BTreeMap;
 use core::ops::{Deref, DerefMut};
@@ -7,7 +8,10 @@ use intrusive_collections::RBTree;
 use miden_assembly::Library as CompiledLibrary;
 use miden_core::crypto::hash::RpoDigest;
 
-pub use self::linker::Linker;
+pub use self::{
+    linker::Linker,
+    recursion::{DynamicCallee, RecursiveCalls},
+};
 use crate::{
     diagnostics::{DiagnosticsHandler, Report},
     *,
@@ -56,6 +60,9 @@ pub struct Program {
     /// modules in this program. The layout of this table corresponds to the layout of
     /// global variables in the linear memory heap at runtime.
     globals: GlobalVariableTable,
+    /// The recursive calls in this program, and how they are to be emitted, as determined by
+    /// the linker.
+    recursive_calls: RecursiveCalls,
 }
 
 impl Default for Program {
@@ -68,6 +75,7 @@ impl Default for Program {
             entrypoint: Default::default(),
             segments: Default::default(),
             globals: Default::default(),
+            recursive_calls: Default::default(),
         }
     }
 }
@@ -97,6 +105,12 @@ impl Program {
         self.reserved_memory_pages * self.page_size
     }
 
+    /// Get the set of recursive calls in this program, see [RecursiveCalls] for details.
+    #[inline]
+    pub fn recursive_calls(&self) -> &RecursiveCalls {
+        &self.recursive_calls
+    }
+
     /// Add to the set of libraries this [Program] will be assembled with
     pub fn add_library(&mut self, lib: CompiledLibrary) {
         self.libraries.insert(*lib.digest(), lib);
@@ -0,0 +1,119 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::num::NonZeroU16;
+
+use crate::*;
+
+/// [RecursiveCalls] describes how cycles in the call graph of a [Program] are broken up so that
+/// recursive functions can be emitted as Miden Assembly.
+///
+/// A procedure in Miden Assembly is identified by the MAST root of its body, so it can never refer
+/// to itself statically, whether directly or via some other procedure. The linker therefore picks
+/// a set of call graph edges, the back edges of a depth-first traversal, which once removed leave
+/// the call graph acyclic. Calls along those edges are emitted as a `dynexec` of a stub for the
+/// callee (see [DynamicCallee]), whose MAST root is read from a global variable.
+///
+/// The global variable for a callee can only be written by code outside of the strongly-connected
+/// component of the call graph containing it, so any call which enters such a component from
+/// outside of it first writes the roots of all of the stubs used within the component.
+#[derive(Default, Debug, Clone)]
+pub struct RecursiveCalls {
+    /// The set of `(caller, callee)` edges which must be emitted as dynamic calls
+    back_edges: BTreeSet<(FunctionIdent, FunctionIdent)>,
+    /// Maps each function which is part of a recursive component to the index of that component
+    components: BTreeMap<FunctionIdent, usize>,
+    /// The targets of the back edges of each recursive component
+    targets: Vec<Vec<DynamicCallee>>,
+}
+
+/// A function which is the target of one or more recursive calls
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DynamicCallee {
+    /// The function being called
+    pub callee: FunctionIdent,
+    /// The stub procedure used as the target of `dynexec`.
+    ///
+    /// The stub is defined in the same module as the callee, and is responsible for dropping the
+    /// MAST root left on the operand stack by `dynexec`, before executing the callee.
+    pub stub: FunctionIdent,
+    /// The name of the global variable which holds the MAST root of `stub`
+    pub root: Ident,
+}
+
+impl RecursiveCalls {
+    /// The type of the global variables holding the MAST root of a [DynamicCallee] stub.
+    ///
+    /// This is a single word, which must be word-aligned so that it can be accessed natively.
+    pub fn root_type() -> Type {
+        Type::Struct(StructType::new_with_repr(
+            TypeRepr::Align(NonZeroU16::new(16).unwrap()),
+            [Type::Felt, Type::Felt, Type::Felt, Type::Felt],
+        ))
+    }
+
+    /// Returns true if there are no recursive calls in the program
+    pub fn is_empty(&self) -> bool {
+        self.back_edges.is_empty()
+    }
+
+    /// Returns true if the call from `caller` to `callee` must be emitted as a dynamic call
+    pub fn is_back_edge(&self, caller: FunctionIdent, callee: FunctionIdent) -> bool {
+        self.back_edges.contains(&(caller, callee))
+    }
+
+    /// Get the [DynamicCallee] corresponding to `callee`, if it is the target of a back edge
+    pub fn get(&self, callee: FunctionIdent) -> Option<&DynamicCallee> {
+        let component = self.components.get(&callee).copied()?;
+        self.targets.get(component)?.iter().find(|target| target.callee == callee)
+    }
+
+    /// Get the [DynamicCallee]s whose stub roots must be written before `caller` calls `callee`.
+    ///
+    /// This is non-empty only when `callee` belongs to a recursive component of which `caller` is
+    /// not a member.
+    pub fn entering(&self, caller: FunctionIdent, callee: FunctionIdent) -> &[DynamicCallee] {
+        match self.components.get(&callee) {
+            Some(component) if self.components.get(&caller) != Some(component) => {
+                self.targets[*component].as_slice()
+            }
+            _ => &[],
+        }
+    }
+
+    /// Get an iterator over all of the [DynamicCallee]s in the program
+    pub fn iter(&self) -> impl Iterator<Item = &DynamicCallee> + '_ {
+        self.targets.iter().flatten()
+    }
+
+    /// Record `(caller, callee)` as a back edge of the recursive component with index `component`,
+    /// returning the [DynamicCallee] for `callee` if it was not already known.
+    pub(super) fn insert(
+        &mut self,
+        component: usize,
+        caller: FunctionIdent,
+        callee: FunctionIdent,
+    ) -> Option<DynamicCallee> {
+        self.back_edges.insert((caller, callee));
+        if self.get(callee).is_some() {
+            return None;
+        }
+        if self.targets.len() <= component {
+            self.targets.resize_with(component + 1, Vec::new);
+        }
+        let stub = FunctionIdent {
+            module: callee.module,
+            function: Ident::new(
+                Symbol::intern(format!("{}.dynexec", callee.function.as_str())),
+                callee.function.span,
+            ),
+        };
+        let root = Ident::with_empty_span(Symbol::intern(format!("{callee}.dynexec_root")));
+        let target = DynamicCallee { callee, stub, root };
+        self.targets[component].push(target);
+        Some(target)
+    }
+
+    /// Record `function` as a member of the recursive component with index `component`
+    pub(super) fn set_component(&mut self, function: FunctionIdent, component: usize) {
+        self.components.insert(function, component);
+    }
+}
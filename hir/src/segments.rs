@@ -86,14 +86,25 @@ impl Clone for DataSegmentTable {
     }
 }
 impl DataSegmentTable {
+    /// The address beyond which linear memory cannot grow, see `intrinsics::mem::memory_grow`.
+    ///
+    /// Segments placed at or beyond this address are never part of linear memory, so this is
+    /// where data which must not be reachable by the program itself is placed, e.g. the contents
+    /// of passive Wasm data segments, which are only copied into linear memory on request.
+    pub const HEAP_END: Offset = 0x1000_0000;
+
     /// Returns true if the table has no segments defined
     pub fn is_empty(&self) -> bool {
         self.segments.is_empty()
     }
 
     /// Returns the offset in linear memory where the last data segment ends
+    ///
+    /// Segments placed beyond [Self::HEAP_END] are not part of linear memory, and are ignored.
     pub fn next_available_offset(&self) -> u32 {
-        if let Some(last_segment) = self.last() {
+        let last_segment =
+            self.segments.iter().rev().find(|segment| segment.offset() < Self::HEAP_END);
+        if let Some(last_segment) = last_segment {
             let next_offset = last_segment.offset() + last_segment.size();
             // Ensure the start of the globals segment is word-aligned
             next_offset.align_up(32)
@@ -102,6 +113,16 @@ impl DataSegmentTable {
         }
     }
 
+    /// Returns the offset beyond [Self::HEAP_END] where the last data segment ends
+    pub fn next_available_offset_beyond_heap(&self) -> u32 {
+        match self.last() {
+            Some(last_segment) if last_segment.offset() >= Self::HEAP_END => {
+                (last_segment.offset() + last_segment.size()).align_up(32)
+            }
+            _ => Self::HEAP_END,
+        }
+    }
+
     /// Declare a new [DataSegment], with the given offset, size, and data.
     ///
     /// Returns `Err` if the declared segment overlaps/conflicts with an existing segment.
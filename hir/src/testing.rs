@@ -283,6 +283,56 @@ pub fn fib1(builder: &mut ModuleBuilder, context: &TestContext) -> FunctionIdent
         .expect("unexpected validation error, see diagnostics output")
 }
 
+/// Construct a recursive implementation of a function which computes the factorial of `n`.
+///
+/// ```text,ignore
+/// pub fn fact(n: u32) -> u32 {
+///     if n <= 1 {
+///         1
+///     } else {
+///         n * fact(n - 1)
+///     }
+/// }
+/// ```
+pub fn fact(builder: &mut ModuleBuilder, context: &TestContext) -> FunctionIdent {
+    let sig = Signature {
+        params: vec![AbiParam::new(Type::U32)],
+        results: vec![AbiParam::new(Type::U32)],
+        cc: CallConv::SystemV,
+        linkage: Linkage::External,
+    };
+    let module = builder.name();
+    let mut fb = builder.function("fact", sig.clone()).expect("unexpected symbol conflict");
+    let fact = fb.import_function(module, "fact", sig).expect("unexpected symbol conflict");
+
+    let entry = fb.current_block();
+    let n = {
+        let args = fb.block_params(entry);
+        args[0]
+    };
+
+    let base_case = fb.create_block();
+    let recursive_case = fb.create_block();
+
+    fb.switch_to_block(entry);
+    let is_base_case = fb.ins().lte_imm(n, Immediate::U32(1), context.current_span());
+    fb.ins()
+        .cond_br(is_base_case, base_case, &[], recursive_case, &[], context.current_span());
+
+    fb.switch_to_block(base_case);
+    fb.ins().ret_imm(Immediate::U32(1), context.current_span());
+
+    fb.switch_to_block(recursive_case);
+    let n1 = fb.ins().sub_imm_checked(n, Immediate::U32(1), context.current_span());
+    let call = fb.ins().exec(fact, &[n1], context.current_span());
+    let result = fb.first_result(call);
+    let result = fb.ins().mul_checked(n, result, context.current_span());
+    fb.ins().ret(Some(result), context.current_span());
+
+    fb.build(&context.session.diagnostics)
+        .expect("unexpected validation error, see diagnostics output")
+}
+
 /// Construct an implementation of a function which computes the sum
 /// of a matrix of u32 values, with dimensions `rows` by `cols`.
 ///
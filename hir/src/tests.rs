@@ -142,3 +142,154 @@ fn linker_test() {
         .link()
         .expect("failed to link program");
 }
+
+/// Test that recursive functions are linked, with the recursive call emitted dynamically
+#[test]
+fn linker_recursion_test() {
+    let context = TestContext::default();
+
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    let fact = {
+        let mut mb = builder.module("test");
+        let fact = testing::fact(mb.as_mut(), &context);
+        mb.build().expect("unexpected error building test module");
+        fact
+    };
+
+    let program = builder.with_entrypoint(fact).link().expect("failed to link program");
+    let recursive_calls = program.recursive_calls();
+    assert!(recursive_calls.is_back_edge(fact, fact));
+    let target = recursive_calls.get(fact).expect("expected 'fact' to be called dynamically");
+    assert!(program.globals().find(target.root).is_some());
+}
+
+/// Test that a library exporting a recursive function is rejected, as there is nothing to write
+/// the roots of the stubs it depends on when it is called from outside the library
+#[test]
+fn linker_recursion_exported_from_library_test() {
+    let context = TestContext::default();
+
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    {
+        let mut mb = builder.module("test");
+        testing::fact(mb.as_mut(), &context);
+        mb.build().expect("unexpected error building test module");
+    }
+
+    let err = builder.link().expect_err("expected linking a recursive library export to fail");
+    assert!(
+        format!("{err:?}").contains("exported from a library"),
+        "unexpected error: {err:?}"
+    );
+}
+
+/// Test that recursion via `call` is rejected, as the stub roots are not shared across contexts
+#[test]
+fn linker_recursion_across_contexts_test() {
+    let context = TestContext::default();
+
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    let countdown = {
+        let mut mb = builder.module("test");
+        let sig = Signature::new([AbiParam::new(Type::U32)], []);
+        let mut fb = mb.function("countdown", sig.clone()).expect("unexpected symbol conflict");
+        let countdown = fb
+            .import_function("test", "countdown", sig)
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let n = fb.block_params(entry)[0];
+        let done = fb.create_block();
+        let recurse = fb.create_block();
+        let is_zero = fb.ins().eq_imm(n, Immediate::U32(0), context.current_span());
+        fb.ins().cond_br(is_zero, done, &[], recurse, &[], context.current_span());
+
+        fb.switch_to_block(done);
+        fb.ins().ret(None, context.current_span());
+
+        fb.switch_to_block(recurse);
+        let n1 = fb.ins().sub_imm_checked(n, Immediate::U32(1), context.current_span());
+        fb.ins().call(countdown, &[n1], context.current_span());
+        fb.ins().ret(None, context.current_span());
+
+        let countdown = fb.build().expect("unexpected validation error");
+        mb.build().expect("unexpected error building test module");
+        countdown
+    };
+
+    let err = builder
+        .with_entrypoint(countdown)
+        .link()
+        .expect_err("expected linking recursion via 'call' to fail");
+    assert!(
+        format!("{err:?}").contains("cannot be called using 'call'"),
+        "unexpected error: {err:?}"
+    );
+}
+
+/// Test that linking fails if more than one module uses statically allocated address spaces,
+/// which would otherwise share the same memory
+#[test]
+fn linker_static_address_spaces_test() {
+    let context = TestContext::default();
+
+    // Builds a module `name` with a function which stores to address space `id`
+    let build_module = |builder: &mut ProgramBuilder<'_>, name: &str, id: u16| {
+        let mut mb = builder.module(name);
+        let sig = Signature::new([AbiParam::new(Type::U32)], []);
+        let mut fb = mb.function("store", sig).expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let addr = fb.block_params(entry)[0];
+        let addrspace = AddressSpace::Id(core::num::NonZeroU16::new(id).unwrap());
+        let ptr_ty = Type::NativePtr(Box::new(Type::U32), addrspace);
+        let ptr = fb.ins().inttoptr(addr, ptr_ty, context.current_span());
+        fb.ins().store(ptr, addr, context.current_span());
+        fb.ins().ret(None, context.current_span());
+        fb.build().expect("unexpected validation error");
+        mb.build().expect("unexpected error building test module");
+    };
+
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    build_module(&mut builder, "a", 1);
+    {
+        let mut mb = builder.module("test");
+        testing::fib1(mb.as_mut(), &context);
+        mb.build().expect("unexpected error building test module");
+    }
+    builder.link().expect("expected a single module using address spaces to link");
+
+    let mut builder = ProgramBuilder::new(&context.session.diagnostics);
+    build_module(&mut builder, "a", 1);
+    build_module(&mut builder, "b", 2);
+    let err = builder
+        .link()
+        .expect_err("expected linking two modules using address spaces to fail");
+    assert!(
+        format!("{err:?}").contains("use statically allocated address spaces"),
+        "unexpected error: {err:?}"
+    );
+}
+
+/// Test that global variables are laid out at addresses which satisfy their alignment, and that
+/// the size of the table accounts for the padding this requires
+#[test]
+fn global_variable_layout_test() {
+    let mut globals = GlobalVariableTable::default();
+    let mut declare = |name: &str, ty: Type| {
+        globals
+            .declare(Ident::with_empty_span(Symbol::intern(name)), ty, Linkage::Internal, None)
+            .expect("unexpected global variable conflict")
+    };
+    // Naturally packed globals, e.g. those of a Wasm module, are laid out contiguously
+    let a = declare("a", Type::I32);
+    let b = declare("b", Type::I32);
+    // A byte leaves the next free address unaligned for anything larger
+    let c = declare("c", Type::U8);
+    let d = declare("d", Type::U32);
+    // The roots of the stubs for recursive calls must be word-aligned
+    let e = declare("e", Type::U8);
+    let f = declare("f", RecursiveCalls::root_type());
+
+    let offsets = [a, b, c, d, e, f].map(|gv| unsafe { globals.offset_of(gv) });
+    assert_eq!(offsets, [0, 4, 8, 12, 16, 32]);
+    assert_eq!(globals.size_in_bytes(), 48);
+}
@@ -261,6 +261,10 @@ pub struct CodegenOptions {
     /// Tell the compiler to generate Miden Assembly from the inputs without linking them
     #[arg(long, default_value_t = false)]
     pub no_link: bool,
+    /// Lower 64-bit floating point operations to calls into a software implementation of IEEE
+    /// 754 arithmetic, rather than rejecting them
+    #[arg(long, default_value_t = false)]
+    pub soft_float: bool,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -437,6 +441,7 @@ impl Compiler {
         options.analyze_only = codegen.analyze_only;
         options.link_only = codegen.link_only;
         options.no_link = codegen.no_link;
+        options.soft_float = codegen.soft_float;
         options.print_cfg_after_all = unstable.print_cfg_after_all;
         options.print_cfg_after_pass = unstable.print_cfg_after_pass;
         options.print_ir_after_all = unstable.print_ir_after_all;
@@ -1,5 +1,7 @@
 use miden_package::{Dependency, MastArtifact, Package};
 use midenc_codegen_masm::MasmArtifact;
+use midenc_hir::{FunctionDebugInfo, ProgramDebugInfo};
+use midenc_session::{OutputFile, OutputType};
 
 use super::*;
 
@@ -49,6 +51,7 @@ impl Stage for AssembleStage {
                 );
                 session.emit(OutputMode::Text, &mast).into_diagnostic()?;
                 session.emit(OutputMode::Binary, &mast).into_diagnostic()?;
+                emit_debug_info(&masm_artifact, session)?;
                 Ok(Artifact::Assembled(build_package(mast, &masm_artifact, session)))
             }
             Left(masm_artifact) => {
@@ -118,3 +121,43 @@ fn build_package(mast: MastArtifact, masm: &MasmArtifact, session: &Session) ->
         manifest,
     }
 }
+
+/// Write the source-level variables of the compiled functions to a `.dbg` file next to the
+/// package, for use by the debugger
+fn emit_debug_info(masm: &MasmArtifact, session: &Session) -> CompilerResult<()> {
+    if !session.options.emit_debug_decorators() || !session.should_emit(OutputType::Masp) {
+        return Ok(());
+    }
+    let OutputFile::Real(path) =
+        session.output_files.output_file(OutputType::Masp, Some(session.name.as_str()))
+    else {
+        return Ok(());
+    };
+
+    let modules: Vec<&masm::Module> = match masm {
+        MasmArtifact::Executable(program) => program.modules().collect(),
+        MasmArtifact::Library(lib) => lib.modules().collect(),
+    };
+    let mut debug_info = ProgramDebugInfo::default();
+    for module in modules {
+        for function in module.functions() {
+            if function.debug_variables.is_empty() {
+                continue;
+            }
+            debug_info.functions.push(FunctionDebugInfo {
+                procedure: format!("{}::{}", module.name, function.name.function),
+                variables: function.debug_variables.clone(),
+            });
+        }
+    }
+    if debug_info.functions.is_empty() {
+        return Ok(());
+    }
+
+    let path = path.with_extension("dbg");
+    log::debug!("emitting debug information to {}", path.display());
+    let content = toml::to_string_pretty(&debug_info).into_diagnostic()?;
+    std::fs::write(&path, content)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to write debug information to {}", path.display()))
+}
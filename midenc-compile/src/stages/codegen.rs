@@ -23,7 +23,11 @@ impl Stage for CodegenStage {
             masm: mut masm_modules,
         } = linker_output;
         match linked {
-            Left(program) => {
+            Left(mut program) => {
+                log::debug!("applying program-wide rewrites");
+                let mut rewrites = masm::default_program_rewrites(session);
+                rewrites.apply(&mut program, analyses, session)?;
+
                 log::debug!("lowering hir program to masm");
                 let mut convert_to_masm = masm::ConvertHirToMasm::<hir::Program>::default();
                 let mut artifact = convert_to_masm.convert(program, analyses, session)?;
@@ -71,12 +75,19 @@ impl Stage for CodegenStage {
 }
 
 fn required_intrinsics_modules(session: &Session) -> Vec<masm::Module> {
-    vec![
+    let mut modules = vec![
         masm::intrinsics::load("intrinsics::mem", &session.source_manager)
             .expect("undefined intrinsics module"),
         masm::intrinsics::load("intrinsics::i32", &session.source_manager)
             .expect("undefined intrinsics module"),
         masm::intrinsics::load("intrinsics::i64", &session.source_manager)
             .expect("undefined intrinsics module"),
-    ]
+    ];
+    if session.options.soft_float {
+        modules.push(
+            masm::intrinsics::load("intrinsics::f64", &session.source_manager)
+                .expect("undefined intrinsics module"),
+        );
+    }
+    modules
 }
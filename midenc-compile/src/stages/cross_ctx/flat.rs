@@ -6,7 +6,7 @@
 
 use midenc_hir::{
     types::Abi, AbiParam, ArgumentExtension, ArgumentPurpose, CallConv, FunctionType, Linkage,
-    Signature, StructType, Type,
+    Signature, Type, UnionType,
 };
 
 /// Flattens the given CanonABI type into a list of ABI parameters.
@@ -43,13 +43,10 @@ pub fn flatten_type(ty: &Type) -> Result<Vec<AbiParam>, String> {
         Type::U32 => vec![AbiParam::new(Type::I32)],
         Type::I64 => vec![AbiParam::new(Type::I64)],
         Type::U64 => vec![AbiParam::new(Type::I64)],
-        Type::I128 | Type::U128 | Type::U256 => {
-            panic!("CanonABI type flattening: not yet implemented {}", ty)
-        }
-        Type::F64 => {
-            let message = "CanonABI type flattening: unexpected f64 type".to_string();
-            return Err(message);
-        }
+        // There are no 128- and 256-bit integers in core Wasm, so these are passed as 64-bit limbs
+        Type::I128 | Type::U128 => vec![AbiParam::new(Type::I64); 2],
+        Type::U256 => vec![AbiParam::new(Type::I64); 4],
+        Type::F64 => vec![AbiParam::new(Type::F64)],
         Type::Felt => vec![AbiParam::new(Type::Felt)],
         Type::Struct(struct_ty) => struct_ty
             .fields()
@@ -59,19 +56,76 @@ pub fn flatten_type(ty: &Type) -> Result<Vec<AbiParam>, String> {
             .into_iter()
             .flatten()
             .collect(),
-        Type::Array(elem_ty, len) => vec![AbiParam::new(*elem_ty.clone()); *len],
-        Type::List(elem_ty) => vec![
-            // pointer to the list element type
-            AbiParam::sret(*elem_ty.clone()),
+        Type::Array(elem_ty, len) => {
+            let elem = flatten_type(elem_ty)?;
+            (0..*len).flat_map(|_| elem.iter().cloned()).collect()
+        }
+        Type::List(_) => vec![
+            // pointer to the first element of the list
+            AbiParam::new(Type::I32),
             // length of the list
             AbiParam::new(Type::I32),
         ],
+        Type::Union(union_ty) => {
+            // The discriminant, followed by the payload slots shared by all of the cases
+            let mut flat = vec![AbiParam::new(Type::I32)];
+            flat.extend(flatten_union_payload(union_ty)?.into_iter().map(AbiParam::new));
+            flat
+        }
         Type::Unknown | Type::Never | Type::Ptr(_) | Type::NativePtr(..) => {
-            panic!("CanonABI type flattening: unexpected {} type", ty)
+            return Err(format!("CanonABI type flattening: unexpected {ty} type"));
         }
     })
 }
 
+/// Flattens the payloads of the cases of the given union into the types of the slots they share.
+///
+/// The flattened payload of each case occupies a prefix of the slots, and each slot has the type
+/// which can hold the values of all of the cases in that position, see [join_flat_types].
+pub fn flatten_union_payload(union_ty: &UnionType) -> Result<Vec<Type>, String> {
+    let mut slots: Vec<Type> = vec![];
+    for case in union_ty.cases() {
+        for (i, param) in flatten_type(case)?.into_iter().enumerate() {
+            match slots.get_mut(i) {
+                Some(slot) => *slot = join_flat_types(slot, &param.ty),
+                None => slots.push(param.ty),
+            }
+        }
+    }
+    Ok(slots)
+}
+
+/// Joins two flattened types occupying the same slot in different cases of a union
+fn join_flat_types(a: &Type, b: &Type) -> Type {
+    match (a, b) {
+        (a, b) if a == b => a.clone(),
+        (Type::I32, Type::Felt) | (Type::Felt, Type::I32) => Type::I32,
+        _ => Type::I64,
+    }
+}
+
+/// Returns true if the given type is, or contains, a list
+pub fn contains_list(ty: &Type) -> bool {
+    match ty {
+        Type::List(_) => true,
+        Type::Struct(struct_ty) => struct_ty.fields().iter().any(|field| contains_list(&field.ty)),
+        Type::Array(elem_ty, _) => contains_list(elem_ty),
+        Type::Union(union_ty) => union_ty.cases().iter().any(contains_list),
+        _ => false,
+    }
+}
+
+/// Returns the number of commitments passed along with a list of `elem_ty` across contexts: the
+/// commitment to its contents, and if the elements contain lists, the commitment to the
+/// commitments of those lists, see [super::transfer]
+pub fn list_commitments(elem_ty: &Type) -> usize {
+    if contains_list(elem_ty) {
+        2
+    } else {
+        1
+    }
+}
+
 /// Flattens the given list of CanonABI types into a list of ABI parameters.
 pub fn flatten_types(tys: &[Type]) -> Result<Vec<AbiParam>, String> {
     Ok(tys
@@ -83,6 +137,14 @@ pub fn flatten_types(tys: &[Type]) -> Result<Vec<AbiParam>, String> {
         .collect())
 }
 
+/// The maximum number of flattened params passed to a core Wasm function, before they are spilled
+/// to memory
+const MAX_FLAT_PARAMS: usize = 16;
+
+/// The maximum number of flattened results returned from a core Wasm function, before they are
+/// spilled to memory
+const MAX_FLAT_RESULTS: usize = 1;
+
 /// Flattens the given CanonABI function type
 pub fn flatten_function_type(func_ty: &FunctionType, cc: CallConv) -> Result<Signature, String> {
     // from https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md#flattening
@@ -93,8 +155,6 @@ pub fn flatten_function_type(func_ty: &FunctionType, cc: CallConv) -> Result<Sig
     // the C ABI) not yet being able to express multi-value returns. Hopefully this limitation is
     // temporary and can be lifted before the Component Model is fully standardized.
     assert_eq!(func_ty.abi, Abi::Wasm, "expected Wasm CM type");
-    const MAX_FLAT_PARAMS: usize = 16;
-    const MAX_FLAT_RESULTS: usize = 1;
     let mut flat_params = flatten_types(&func_ty.params)?;
     let mut flat_results = flatten_types(&func_ty.results)?;
     if flat_params.len() > MAX_FLAT_PARAMS {
@@ -103,8 +163,7 @@ pub fn flatten_function_type(func_ty: &FunctionType, cc: CallConv) -> Result<Sig
         // When there are too many flat values, in general, a single `i32` pointer can be passed instead
         // (pointing to a tuple in linear memory). When lowering into linear memory, this requires the
         // Canonical ABI to call `realloc` to allocate space to put the tuple.
-        let tuple = Type::Struct(StructType::new(func_ty.params.clone()));
-        flat_params = vec![AbiParam::sret(Type::Ptr(tuple.into()))];
+        flat_params = vec![AbiParam::new(Type::I32)];
     }
     if flat_results.len() > MAX_FLAT_RESULTS {
         // from https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md#flattening
@@ -114,13 +173,12 @@ pub fn flatten_function_type(func_ty: &FunctionType, cc: CallConv) -> Result<Sig
         // efficiently on the stack), passing in an `i32` pointer as an parameter instead of
         // returning an `i32` as a return value.
         assert_eq!(func_ty.results.len(), 1, "expected a single result");
-        let result = func_ty.results.first().expect("unexpected empty results").clone();
         match cc {
             CallConv::CanonLift => {
-                flat_results = vec![AbiParam::sret(Type::Ptr(result.into()))];
+                flat_results = vec![AbiParam::new(Type::I32)];
             }
             CallConv::CanonLower => {
-                flat_params.push(AbiParam::sret(Type::Ptr(result.into())));
+                flat_params.push(AbiParam::new(Type::I32));
                 flat_results = vec![];
             }
             _ => panic!("unexpected call convention, only CanonLift and CanonLower are supported"),
@@ -134,15 +192,96 @@ pub fn flatten_function_type(func_ty: &FunctionType, cc: CallConv) -> Result<Sig
     })
 }
 
-/// Checks if the given function signature needs to be transformed, i.e., if it contains a pointer
-pub fn needs_transformation(sig: &Signature) -> bool {
-    sig.params().iter().any(|param| param.purpose == ArgumentPurpose::StructReturn)
-        || sig
-            .results()
+/// Returns true if the flattened params of the given CanonABI function type exceed
+/// [MAX_FLAT_PARAMS], i.e. the core Wasm function expects a single pointer to a tuple of its params
+pub fn params_spilled(func_ty: &FunctionType) -> Result<bool, String> {
+    Ok(flatten_types(&func_ty.params)?.len() > MAX_FLAT_PARAMS)
+}
+
+/// Returns true if the flattened results of the given CanonABI function type exceed
+/// [MAX_FLAT_RESULTS], i.e. the core Wasm function either returns a pointer to its results
+/// (`CanonLift`), or expects a pointer to the return area as its last param (`CanonLower`)
+pub fn results_spilled(func_ty: &FunctionType) -> Result<bool, String> {
+    Ok(flatten_types(&func_ty.results)?.len() > MAX_FLAT_RESULTS)
+}
+
+/// Flattens the given CanonABI type into the types of its cross-context Miden ABI
+/// representation.
+///
+/// This is the same as [flatten_type], except that lists are passed as the commitment to their
+/// contents (4 felts), followed by the commitment to the lists contained in their elements, if
+/// any (4 felts), and then by their pointer and length, see [super::transfer]. Since the
+/// lists of a union can only be transferred for the case which is active, the payload of each case
+/// of a union containing lists is passed separately, rather than in shared slots.
+pub fn flatten_cross_ctx_type(ty: &Type) -> Result<Vec<Type>, String> {
+    Ok(match ty {
+        Type::List(elem_ty) => {
+            let mut flat = vec![Type::Felt; list_commitments(elem_ty) * 4];
+            flat.extend([Type::I32, Type::I32]);
+            flat
+        }
+        Type::Union(union_ty) if contains_list(ty) => {
+            let mut flat = vec![Type::I32];
+            for case in union_ty.cases() {
+                flat.extend(flatten_cross_ctx_type(case)?);
+            }
+            flat
+        }
+        Type::Struct(struct_ty) => struct_ty
+            .fields()
             .iter()
-            .any(|result| result.purpose == ArgumentPurpose::StructReturn)
+            .map(|field| flatten_cross_ctx_type(&field.ty))
+            .collect::<Result<Vec<Vec<Type>>, String>>()?
+            .into_iter()
+            .flatten()
+            .collect(),
+        Type::Array(elem_ty, len) => {
+            let elem = flatten_cross_ctx_type(elem_ty)?;
+            (0..*len).flat_map(|_| elem.iter().cloned()).collect()
+        }
+        ty => flatten_type(ty)?.into_iter().map(|param| param.ty).collect(),
+    })
 }
 
+/// Flattens the given CanonABI function type into its cross-context Miden ABI signature.
+///
+/// Unlike [flatten_function_type], the params and results are never spilled to memory, since
+/// the caller and callee do not share memory, instead they are passed on the operand stack.
+pub fn flatten_cross_ctx_function_type(
+    func_ty: &FunctionType,
+    cc: CallConv,
+) -> Result<Signature, String> {
+    assert_eq!(func_ty.abi, Abi::Wasm, "expected Wasm CM type");
+    let mut params = vec![];
+    for ty in func_ty.params.iter() {
+        params.extend(flatten_cross_ctx_type(ty)?.into_iter().map(AbiParam::new));
+    }
+    let mut results = vec![];
+    for ty in func_ty.results.iter() {
+        results.extend(flatten_cross_ctx_type(ty)?.into_iter().map(AbiParam::new));
+    }
+    let size_in_felts = |params: &[AbiParam]| -> usize {
+        params.iter().map(|param| param.ty.size_in_felts()).sum()
+    };
+    if size_in_felts(&params) > MAX_CROSS_CTX_STACK_ELEMENTS
+        || size_in_felts(&results) > MAX_CROSS_CTX_STACK_ELEMENTS
+    {
+        return Err(format!(
+            "the params or results of {func_ty} do not fit in the {MAX_CROSS_CTX_STACK_ELEMENTS} \
+             operand stack elements available to a cross-context call"
+        ));
+    }
+    Ok(Signature {
+        params,
+        results,
+        cc,
+        linkage: Linkage::External,
+    })
+}
+
+/// The number of operand stack elements which are passed between contexts on `call`
+const MAX_CROSS_CTX_STACK_ELEMENTS: usize = 16;
+
 /// Asserts that the given core Wasm signature is equivalent to the given flattened signature
 /// This checks that we flattened the Wasm CM function type correctly.
 pub fn assert_core_wasm_signature_equivalence(
@@ -165,3 +304,180 @@ pub fn assert_core_wasm_signature_equivalence(
         assert_eq!(wasm_core_param.ty, flattened_param.ty, "expected the same param type");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::StructType;
+
+    use super::*;
+
+    fn string() -> Type {
+        Type::List(Box::new(Type::U8))
+    }
+
+    fn option(ty: Type) -> Type {
+        Type::Union(UnionType::new(Type::U8, [Type::Unit, ty]))
+    }
+
+    fn result(ok: Type, err: Type) -> Type {
+        Type::Union(UnionType::new(Type::U8, [ok, err]))
+    }
+
+    fn flat_tys(ty: &Type) -> Vec<Type> {
+        flatten_type(ty).unwrap().into_iter().map(|param| param.ty).collect()
+    }
+
+    #[test]
+    fn flatten_scalars() {
+        let flat = flatten_type(&Type::I8).unwrap();
+        assert_eq!(flat.len(), 1);
+        assert_eq!(flat[0].ty, Type::I32);
+        assert_eq!(flat[0].extension, ArgumentExtension::Sext);
+        assert_eq!(flat_tys(&Type::U128), vec![Type::I64; 2]);
+        assert_eq!(flat_tys(&Type::U256), vec![Type::I64; 4]);
+        assert_eq!(flat_tys(&Type::Unit), vec![]);
+    }
+
+    #[test]
+    fn flatten_string() {
+        assert_eq!(flat_tys(&string()), vec![Type::I32, Type::I32]);
+        let record = Type::Struct(StructType::new([Type::Felt, string()]));
+        assert_eq!(flat_tys(&record), vec![Type::Felt, Type::I32, Type::I32]);
+    }
+
+    #[test]
+    fn flatten_strings_in_aggregates() {
+        // `record { name: string, id: u32 }`
+        let record = Type::Struct(StructType::new([string(), Type::U32]));
+        assert_eq!(flat_tys(&record), vec![Type::I32; 3]);
+        // `tuple<u8, string, u64>`
+        let tuple = Type::Struct(StructType::new([Type::U8, string(), Type::U64]));
+        assert_eq!(flat_tys(&tuple), vec![Type::I32, Type::I32, Type::I32, Type::I64]);
+        // `list<record { name: string, id: u32 }>`
+        assert_eq!(flat_tys(&Type::List(Box::new(record.clone()))), vec![Type::I32; 2]);
+        // `option<record { name: string, id: u32 }>`
+        assert_eq!(flat_tys(&option(record.clone())), vec![Type::I32; 4]);
+        // `result<record { name: string, id: u32 }, u64>`
+        assert_eq!(
+            flat_tys(&result(record, Type::U64)),
+            vec![Type::I32, Type::I64, Type::I32, Type::I32]
+        );
+    }
+
+    #[test]
+    fn flatten_function_type_spills_strings_in_aggregates() {
+        let record = Type::Struct(StructType::new([string(), Type::U32]));
+
+        // Returned via the return area
+        let func_ty = FunctionType::new_wasm([record.clone()], [record.clone()]);
+        assert!(!params_spilled(&func_ty).unwrap());
+        assert!(results_spilled(&func_ty).unwrap());
+        let lift = flatten_function_type(&func_ty, CallConv::CanonLift).unwrap();
+        assert_eq!(lift.params().len(), 3);
+        assert_eq!(lift.results().len(), 1);
+        let lower = flatten_function_type(&func_ty, CallConv::CanonLower).unwrap();
+        assert_eq!(lower.params().len(), 4);
+        assert!(lower.results().is_empty());
+
+        // Passed as a pointer to the tuple of params, i.e. 18 flattened params
+        let func_ty = FunctionType::new_wasm(vec![record; 6], [Type::U32]);
+        assert!(params_spilled(&func_ty).unwrap());
+        let lift = flatten_function_type(&func_ty, CallConv::CanonLift).unwrap();
+        assert_eq!(lift.params().len(), 1);
+        assert_eq!(lift.params()[0].ty, Type::I32);
+    }
+
+    #[test]
+    fn flatten_option() {
+        assert_eq!(flat_tys(&option(Type::U32)), vec![Type::I32, Type::I32]);
+        assert_eq!(flat_tys(&option(Type::Unit)), vec![Type::I32]);
+        assert_eq!(flat_tys(&option(string())), vec![Type::I32, Type::I32, Type::I32]);
+    }
+
+    #[test]
+    fn flatten_result_joins_payload_slots() {
+        // The error case shares the first slot of the string
+        assert_eq!(flat_tys(&result(string(), Type::U32)), vec![Type::I32; 3]);
+        assert_eq!(flat_tys(&result(Type::Felt, Type::U32)), vec![Type::I32, Type::I32]);
+        assert_eq!(flat_tys(&result(Type::U64, Type::Felt)), vec![Type::I32, Type::I64]);
+        assert_eq!(flat_tys(&result(Type::U32, Type::U64)), vec![Type::I32, Type::I64]);
+        assert_eq!(flat_tys(&result(Type::Unit, Type::Unit)), vec![Type::I32]);
+    }
+
+    #[test]
+    fn flatten_unexpected_type_is_an_error() {
+        assert!(flatten_type(&Type::Unknown).is_err());
+        assert!(flatten_type(&Type::Ptr(Box::new(Type::U8))).is_err());
+        assert!(flatten_type(&option(Type::Never)).is_err());
+        let func_ty = FunctionType::new_wasm([Type::Unknown], []);
+        assert!(flatten_function_type(&func_ty, CallConv::CanonLift).is_err());
+        assert!(flatten_cross_ctx_function_type(&func_ty, CallConv::CanonLift).is_err());
+    }
+
+    #[test]
+    fn flatten_function_type_spills_results() {
+        let func_ty = FunctionType::new_wasm([string()], [string()]);
+        let lift = flatten_function_type(&func_ty, CallConv::CanonLift).unwrap();
+        assert_eq!(lift.params().len(), 2);
+        assert_eq!(lift.results().len(), 1);
+        let lower = flatten_function_type(&func_ty, CallConv::CanonLower).unwrap();
+        assert_eq!(lower.params().len(), 3);
+        assert!(lower.results().is_empty());
+        assert!(results_spilled(&func_ty).unwrap());
+        assert!(!params_spilled(&func_ty).unwrap());
+    }
+
+    #[test]
+    fn flatten_function_type_spills_params() {
+        let func_ty = FunctionType::new_wasm(vec![string(); 8], [Type::U32]);
+        assert!(!params_spilled(&func_ty).unwrap());
+        assert!(!results_spilled(&func_ty).unwrap());
+        assert_eq!(
+            flatten_function_type(&func_ty, CallConv::CanonLift).unwrap().params().len(),
+            16
+        );
+
+        let func_ty = FunctionType::new_wasm(vec![string(); 9], [Type::U32]);
+        assert!(params_spilled(&func_ty).unwrap());
+        let lift = flatten_function_type(&func_ty, CallConv::CanonLift).unwrap();
+        assert_eq!(lift.params().len(), 1);
+        assert_eq!(lift.params()[0].ty, Type::I32);
+    }
+
+    #[test]
+    fn flatten_cross_ctx_lists() {
+        let mut expected = vec![Type::Felt; 4];
+        expected.extend([Type::I32, Type::I32]);
+        assert_eq!(flatten_cross_ctx_type(&string()).unwrap(), expected);
+
+        // The commitment to the strings is followed by the commitment to their commitments
+        let mut expected = vec![Type::Felt; 8];
+        expected.extend([Type::I32, Type::I32]);
+        let list_of_strings = Type::List(Box::new(string()));
+        assert_eq!(flatten_cross_ctx_type(&list_of_strings).unwrap(), expected);
+    }
+
+    #[test]
+    fn flatten_cross_ctx_unions() {
+        // Without lists, a union is flattened as in the Canonical ABI
+        assert_eq!(flatten_cross_ctx_type(&option(Type::U32)).unwrap(), vec![Type::I32; 2]);
+
+        // With lists, each case is passed separately
+        let mut expected = vec![Type::I32];
+        expected.extend([Type::Felt; 4]);
+        expected.extend([Type::I32, Type::I32]);
+        expected.push(Type::I32);
+        assert_eq!(flatten_cross_ctx_type(&result(string(), Type::U32)).unwrap(), expected);
+    }
+
+    #[test]
+    fn flatten_cross_ctx_function_type_limits_stack_elements() {
+        let func_ty = FunctionType::new_wasm([string(), string()], [result(string(), Type::U32)]);
+        let sig = flatten_cross_ctx_function_type(&func_ty, CallConv::CanonLift).unwrap();
+        assert_eq!(sig.params().len(), 12);
+        assert_eq!(sig.results().len(), 8);
+
+        let func_ty = FunctionType::new_wasm([string(), string(), string()], []);
+        assert!(flatten_cross_ctx_function_type(&func_ty, CallConv::CanonLift).is_err());
+    }
+}
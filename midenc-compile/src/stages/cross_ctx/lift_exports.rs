@@ -7,12 +7,18 @@ use midenc_hir::{
     diagnostics::Severity,
     pass::AnalysisManager,
     types::Abi::{self, Canonical},
-    CallConv, ComponentBuilder, ComponentExport, FunctionType, InstBuilder, InterfaceFunctionIdent,
+    CallConv, ComponentBuilder, ComponentExport, ComponentResource, FunctionType, InstBuilder,
+    InterfaceFunctionIdent, ModuleFunctionBuilder, ResourceHandle, SourceSpan, Value,
 };
 use midenc_session::{DiagnosticsHandler, Session};
 
-use super::flat::{
-    assert_core_wasm_signature_equivalence, flatten_function_type, needs_transformation,
+use super::{
+    flat::{
+        assert_core_wasm_signature_equivalence, flatten_cross_ctx_function_type,
+        flatten_function_type, params_spilled, results_spilled,
+    },
+    resources::{builtin_signature, generate_handle_tables},
+    transfer::{ret_values, Transfer},
 };
 use crate::{stage::Stage, CompilerResult, LinkerInput};
 
@@ -23,6 +29,12 @@ use crate::{stage::Stage, CompilerResult, LinkerInput};
 /// ABI, converts them to the Wasm CABI, calls the core Wasm module exported function,
 /// converts the results to the cross-context Miden ABI
 ///
+/// The handles to the resources defined by the component which are passed to, or returned from,
+/// the exported functions are checked by the lifting functions: an owned handle must be valid and
+/// not borrowed, while a borrowed handle must be valid, and is lent for the duration of the call,
+/// the core Wasm function receiving the representation of the resource. The handle tables of the
+/// resources are generated by this stage as well, see [super::resources].
+///
 /// After this stage all exported functons are expected to be called using the Miden ABI for
 /// cross-context calls, i.e. using the stack and the advice provider for arguments and results.
 pub struct LiftExportsCrossCtxStage;
@@ -48,7 +60,8 @@ impl Stage for LiftExportsCrossCtxStage {
 
         let mut component_builder = ComponentBuilder::load(*component, &session.diagnostics);
 
-        let mut lifted_exports: BTreeMap<InterfaceFunctionIdent, ComponentExport> = BTreeMap::new();
+        let mut lifted_exports: BTreeMap<InterfaceFunctionIdent, ComponentExport> =
+            generate_handle_tables(&mut component_builder, &session.diagnostics)?;
         let exports = component_builder.exports().clone();
         for (id, export) in exports.into_iter() {
             if let Canonical = export.function_ty.abi() {
@@ -106,11 +119,8 @@ fn generate_lifting_function(
         })?
         .clone();
 
-    // get or create the module for the interface
-    let module_id = export_id.interface.full_name;
-    let mut module_builder = component_builder.module(module_id);
-    let cross_ctx_export_sig = flatten_function_type(&export.function_ty, CallConv::CanonLift)
-        .map_err(|e| {
+    let core_export_sig =
+        flatten_function_type(&export.function_ty, CallConv::CanonLift).map_err(|e| {
             let message = format!(
                 "Miden CCABI export lifting generation. Signature for exported function {} \
                  requires flattening. Error: {}",
@@ -118,16 +128,30 @@ fn generate_lifting_function(
             );
             diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
         })?;
-    if needs_transformation(&cross_ctx_export_sig) {
+    assert_core_wasm_signature_equivalence(&export_func_sig, &core_export_sig);
+    let lifting_error = |e: String| {
         let message = format!(
             "Miden CCABI export lifting generation. Signature for exported function {} requires \
-             lifting. This is not yet supported",
-            export.function
+             lifting. Error: {}",
+            export.function, e
         );
-        return Err(diagnostics.diagnostic(Severity::Error).with_message(message).into_report());
-    }
-    assert_core_wasm_signature_equivalence(&export_func_sig, &cross_ctx_export_sig);
+        diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
+    };
+    let cross_ctx_export_sig =
+        flatten_cross_ctx_function_type(&export.function_ty, CallConv::CanonLift)
+            .map_err(lifting_error)?;
+    let params_spilled = params_spilled(&export.function_ty).map_err(lifting_error)?;
+    let results_spilled = results_spilled(&export.function_ty).map_err(lifting_error)?;
+    let realloc = export.options.realloc.and_then(|realloc| {
+        component_builder.signature(&realloc).map(|sig| (realloc, sig.clone()))
+    });
+    let post_return = export.options.post_return.and_then(|post_return| {
+        component_builder.signature(&post_return).map(|sig| (post_return, sig.clone()))
+    });
 
+    // get or create the module for the interface
+    let module_id = export_id.interface.full_name;
+    let mut module_builder = component_builder.module(module_id);
     let mut builder = module_builder.function(export_id.function, cross_ctx_export_sig.clone())?;
     let entry = builder.current_block();
     let params = builder.block_params(entry).to_vec();
@@ -151,12 +175,77 @@ fn generate_lifting_function(
         })?;
     }
     let span = export.function.function.span();
-    // NOTE: handle lifting/lowering for non-scalar types
-    // see https://github.com/0xPolygonMiden/compiler/pull/357
-    let call = builder.ins().exec(export.function, &params, span);
-    // dbg!(&sig);
-    let result = builder.first_result(call);
-    builder.ins().ret(Some(result), span);
+    let param_tys = &export.function_ty.params;
+    let result_tys = &export.function_ty.results;
+
+    // convert the params to the Wasm CABI, spilling them to memory if there are too many
+    let mut transfer = Transfer::new(&mut builder, realloc.clone(), span);
+    let mut args = vec![];
+    let mut values = params.into_iter();
+    let mut lent = vec![];
+    for (index, ty) in param_tys.iter().enumerate() {
+        let position = args.len();
+        transfer.lift(ty, &mut values, &mut args).map_err(lifting_error)?;
+        match export.param_handles.get(&index) {
+            Some(ResourceHandle::Own(resource)) => {
+                exec_builtin(transfer.builder(), *resource, "own", args[position], span)
+                    .map_err(lifting_error)?;
+            }
+            Some(ResourceHandle::Borrow(resource)) => {
+                // The core Wasm function receives the representation of a borrowed resource
+                let handle = args[position];
+                args[position] = exec_builtin(transfer.builder(), *resource, "lend", handle, span)
+                    .map_err(lifting_error)?
+                    .unwrap();
+                lent.push((*resource, handle));
+            }
+            None => (),
+        }
+    }
+    if params_spilled {
+        let ptr = transfer.alloc_tuple(param_tys).map_err(lifting_error)?;
+        transfer.store_tuple(param_tys, ptr, args);
+        args = vec![ptr];
+    }
+
+    let call = builder.ins().exec(export.function, &args, span);
+    let core_results = builder.inst_results(call).to_vec();
+    for (resource, handle) in lent {
+        exec_builtin(&mut builder, resource, "unlend", handle, span).map_err(lifting_error)?;
+    }
+
+    // convert the results to the Miden CCABI, loading them from the return area if they were
+    // spilled to memory
+    let mut transfer = Transfer::new(&mut builder, realloc, span);
+    let results = if results_spilled {
+        transfer.load_tuple(result_tys, core_results[0])
+    } else {
+        core_results.clone()
+    };
+    let mut lowered = vec![];
+    let mut values = results.into_iter();
+    for (index, ty) in result_tys.iter().enumerate() {
+        let position = lowered.len();
+        transfer.lower(ty, &mut values, &mut lowered).map_err(lifting_error)?;
+        if let Some(handle) = export.result_handles.get(&index) {
+            exec_builtin(transfer.builder(), handle.resource(), "own", lowered[position], span)
+                .map_err(lifting_error)?;
+        }
+    }
+    // the results have been copied out of the core Wasm module memory, so it can clean up
+    if let Some((post_return, sig)) = post_return {
+        if builder.data_flow_graph().get_import(&post_return).is_none() {
+            builder.import_function(post_return.module, post_return.function, sig).map_err(
+                |_| {
+                    lifting_error(format!(
+                        "{post_return} is already imported with a different signature"
+                    ))
+                },
+            )?;
+        }
+        builder.ins().exec(post_return, &core_results, span);
+    }
+    ret_values(&mut builder, lowered, span);
     let function_id = builder.build()?;
     module_builder.build()?;
     let component_export = ComponentExport {
@@ -169,3 +258,22 @@ fn generate_lifting_function(
     };
     Ok(component_export)
 }
+
+/// Calls the built-in function `op` of the resource `resource` with the given `handle`, returning
+/// its result, if any, see [ComponentResource::builtin]
+fn exec_builtin(
+    builder: &mut ModuleFunctionBuilder<'_>,
+    resource: InterfaceFunctionIdent,
+    op: &str,
+    handle: Value,
+    span: SourceSpan,
+) -> Result<Option<Value>, String> {
+    let builtin = ComponentResource::builtin(resource, op);
+    if builder.data_flow_graph().get_import(&builtin).is_none() {
+        builder
+            .import_function(builtin.module, builtin.function, builtin_signature(op))
+            .map_err(|_| format!("{builtin} is already imported with a different signature"))?;
+    }
+    let call = builder.ins().exec(builtin, &[handle], span);
+    Ok(builder.inst_results(call).first().copied())
+}
@@ -5,13 +5,17 @@ use std::collections::{BTreeMap, VecDeque};
 use miden_assembly::Spanned;
 use midenc_hir::{
     diagnostics::Severity, pass::AnalysisManager, types::Abi, Block, Call, CallConv,
-    ComponentBuilder, ComponentImport, Function, FunctionIdent, FunctionType, InstBuilder,
-    Instruction, MidenAbiImport, Signature, SourceSpan, Symbol, UnsafeRef,
+    CanonicalOptions, ComponentBuilder, ComponentImport, Function, FunctionIdent, FunctionType,
+    InstBuilder, Instruction, MidenAbiImport, Signature, SourceSpan, Symbol, UnsafeRef,
 };
 use midenc_session::{DiagnosticsHandler, Session};
 
-use super::flat::{
-    assert_core_wasm_signature_equivalence, flatten_function_type, needs_transformation,
+use super::{
+    flat::{
+        assert_core_wasm_signature_equivalence, flatten_cross_ctx_function_type,
+        flatten_function_type, params_spilled, results_spilled,
+    },
+    transfer::{ret_values, Transfer},
 };
 use crate::{stage::Stage, CompilerResult, LinkerInput};
 
@@ -78,6 +82,7 @@ impl Stage for LowerImportsCrossCtxStage {
             let (new_import, lowering_func_id) = generate_lowering_function(
                 &mut component_builder,
                 &cabi_import.interface_function_ty,
+                &cabi_import.options,
                 import_func_id,
                 import_func_sig.clone(),
                 core_import_func_id.function.span(),
@@ -110,6 +115,7 @@ impl Stage for LowerImportsCrossCtxStage {
 fn generate_lowering_function(
     component_builder: &mut ComponentBuilder<'_>,
     high_func_ty: &FunctionType,
+    options: &CanonicalOptions,
     import_func_id: FunctionIdent,
     import_func_sig: Signature,
     span: SourceSpan,
@@ -121,7 +127,7 @@ fn generate_lowering_function(
     // dbg!(&lowering_module_id);
     let mut module_builder = component_builder.module(lowering_module_id);
 
-    let import_lowered_sig =
+    let core_import_sig =
         flatten_function_type(high_func_ty, CallConv::CanonLower).map_err(|e| {
             let message = format!(
                 "Miden CCABI import lowering generation. Signature for imported function {} \
@@ -130,20 +136,32 @@ fn generate_lowering_function(
             );
             diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
         })?;
-
-    if needs_transformation(&import_lowered_sig) {
+    assert_core_wasm_signature_equivalence(&import_func_sig, &core_import_sig);
+    let lowering_error = |e: String| {
         let message = format!(
-            "Miden CCABI export lowering generation. Signature for exported function {} requires \
-             lowering. This is not supported yet.",
-            import_func_id
+            "Miden CCABI import lowering generation. Signature for imported function {} requires \
+             lowering. Error: {}",
+            import_func_id, e
         );
-        return Err(diagnostics.diagnostic(Severity::Error).with_message(message).into_report());
-    }
-    assert_core_wasm_signature_equivalence(&import_func_sig, &import_lowered_sig);
+        diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
+    };
+    let import_lowered_sig = flatten_cross_ctx_function_type(high_func_ty, CallConv::CanonLower)
+        .map_err(lowering_error)?;
+    let params_spilled = params_spilled(high_func_ty).map_err(lowering_error)?;
+    let results_spilled = results_spilled(high_func_ty).map_err(lowering_error)?;
+    let realloc = options.realloc.and_then(|realloc| {
+        component_builder.signature(&realloc).map(|sig| (realloc, sig.clone()))
+    });
+
+    // get or create the module for the interface
+    let lowering_module_id =
+        Symbol::intern(format!("lower-imports-{}", import_func_id.module.as_str()));
+    // dbg!(&lowering_module_id);
+    let mut module_builder = component_builder.module(lowering_module_id);
 
     let mut builder = module_builder.function(import_func_id.function, import_func_sig)?;
     let entry = builder.current_block();
-    let params = builder.block_params(entry).to_vec();
+    let mut params = builder.block_params(entry).to_vec();
 
     let dfg = builder.data_flow_graph_mut();
     if dfg.get_import(&import_func_id).is_none() {
@@ -162,12 +180,37 @@ fn generate_lowering_function(
             diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
         })?;
     }
-    // NOTE: handle lifting/lowering for non-scalar types
-    // see https://github.com/0xPolygonMiden/compiler/pull/357
-    let call = builder.ins().call(import_func_id, &params, span);
-    // dbg!(&sig);
-    let result = builder.first_result(call);
-    builder.ins().ret(Some(result), span);
+    let param_tys = &high_func_ty.params;
+    let result_tys = &high_func_ty.results;
+
+    // convert the params to the Miden CCABI, loading them from memory if they were spilled
+    let ret_ptr = if results_spilled { params.pop() } else { None };
+    let mut transfer = Transfer::new(&mut builder, realloc.clone(), span);
+    if params_spilled {
+        params = transfer.load_tuple(param_tys, params[0]);
+    }
+    let mut args = vec![];
+    let mut values = params.into_iter();
+    for ty in param_tys.iter() {
+        transfer.lower(ty, &mut values, &mut args).map_err(lowering_error)?;
+    }
+
+    let call = builder.ins().call(import_func_id, &args, span);
+    let results = builder.inst_results(call).to_vec();
+
+    // convert the results to the Wasm CABI, storing them in the return area provided by the
+    // caller if there are too many
+    let mut transfer = Transfer::new(&mut builder, realloc, span);
+    let mut lifted = vec![];
+    let mut values = results.into_iter();
+    for ty in result_tys.iter() {
+        transfer.lift(ty, &mut values, &mut lifted).map_err(lowering_error)?;
+    }
+    if let Some(ret_ptr) = ret_ptr {
+        transfer.store_tuple(result_tys, ret_ptr, lifted);
+        lifted = vec![];
+    }
+    ret_values(&mut builder, lifted, span);
     let function_id = builder.build()?;
     module_builder.build()?;
     let component_import = MidenAbiImport::new(FunctionType {
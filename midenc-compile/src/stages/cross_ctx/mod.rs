@@ -3,3 +3,5 @@
 mod flat;
 pub(super) mod lift_exports;
 pub(super) mod lower_imports;
+mod resources;
+mod transfer;
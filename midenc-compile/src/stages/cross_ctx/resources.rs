@@ -0,0 +1,559 @@
+//! Handle tables for the resource types defined by a component.
+//!
+//! A handle to a resource is an index into the handle table of its resource type, which is
+//! maintained in the memory of the component defining the resource. Each entry of the table holds
+//! the representation of a resource (the `i32` the component passed to `resource.new`), and its
+//! state: zero if the handle is not (or no longer) valid, otherwise one plus the number of
+//! outstanding borrows of the handle. The first entry of the table, which is never a valid handle,
+//! holds the last handle that was allocated.
+//!
+//! Dropped handles are reused: they form a free list, whose head is held by a separate global
+//! variable (zero if the list is empty), and whose entries hold the next free handle in place of
+//! their representation.
+//!
+//! The handles are passed as-is across contexts, i.e. the other components hold the handles of the
+//! defining component, and drop them by calling its exported `[resource-drop]` function. The
+//! lifetime of the handles passed to, or returned from, the exported functions is checked at the
+//! component boundary, see [super::lift_exports].
+
+use std::collections::BTreeMap;
+
+use midenc_hir::{
+    diagnostics::Severity, AbiParam, ComponentBuilder, ComponentExport, ComponentResource,
+    FunctionType, Immediate, InstBuilder, InterfaceFunctionIdent, Linkage, ModuleFunctionBuilder,
+    Signature, SourceSpan, Type, Value, ASSERT_FAILED_INVALID_HANDLE,
+    ASSERT_FAILED_TOO_MANY_HANDLES,
+};
+use midenc_session::DiagnosticsHandler;
+
+use crate::CompilerResult;
+
+/// The maximum number of handles that can be allocated for a resource type, including the
+/// first, unused, entry of the handle table
+const MAX_HANDLES: u32 = 1024;
+
+/// The size in bytes of an entry of a handle table: the representation and the state
+const ENTRY_SIZE: u32 = 8;
+
+/// Generates the handle table of each resource type defined by the component, along with the
+/// built-in functions used to access it.
+///
+/// Returns the exports of the `[resource-drop]` functions, through which the other components
+/// drop their handles.
+pub fn generate_handle_tables(
+    component_builder: &mut ComponentBuilder<'_>,
+    diagnostics: &DiagnosticsHandler,
+) -> CompilerResult<BTreeMap<InterfaceFunctionIdent, ComponentExport>> {
+    let mut exports = BTreeMap::new();
+    let resources = component_builder.resources().clone();
+    for resource in resources.into_values() {
+        let dtor = match resource.dtor {
+            Some(dtor) => {
+                let sig = component_builder.signature(&dtor).cloned().ok_or_else(|| {
+                    let message = format!(
+                        "Cannot find signature for the destructor {dtor} of the resource {}",
+                        resource.name
+                    );
+                    diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
+                })?;
+                Some((dtor, sig))
+            }
+            None => None,
+        };
+        let table = table_symbol(resource.name);
+        let free = free_symbol(resource.name);
+        let span = SourceSpan::default();
+
+        let mut module_builder = component_builder.module(resource.name.interface.full_name);
+        module_builder
+            .declare_global_variable(
+                &table,
+                Type::Array(Box::new(Type::U32), (2 * MAX_HANDLES) as usize),
+                Linkage::Internal,
+                None,
+                span,
+            )
+            .map_err(|_| {
+                let message = format!("the handle table {table} is already declared");
+                diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
+            })?;
+        module_builder
+            .declare_global_variable(&free, Type::U32, Linkage::Internal, None, span)
+            .map_err(|_| {
+                let message = format!("the free list {free} is already declared");
+                diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
+            })?;
+
+        // [resource-new](rep: i32) -> i32
+        let id = ComponentResource::builtin(resource.name, "new");
+        let mut builder = module_builder.function(id.function, builtin_signature("new"))?;
+        let rep = builder.block_params(builder.current_block())[0];
+        let last_ptr = builder.ins().symbol_addr(&table, Type::Ptr(Box::new(Type::U32)), span);
+        let last = builder.ins().load(last_ptr, span);
+        let free_ptr = builder.ins().symbol_addr(&free, Type::Ptr(Box::new(Type::U32)), span);
+        let head = builder.ins().load(free_ptr, span);
+        // Reuse the head of the free list if there is one, otherwise allocate a new handle
+        let reuse = builder.ins().neq_imm(head, Immediate::U32(0), span);
+        let next = builder.ins().add_imm_checked(last, Immediate::U32(1), span);
+        let in_bounds = builder.ins().lt_imm(next, Immediate::U32(MAX_HANDLES), span);
+        let available = builder.ins().or(reuse, in_bounds, span);
+        builder.ins().assert_with_error(available, ASSERT_FAILED_TOO_MANY_HANDLES, span);
+        let handle = builder.ins().select(reuse, head, next, span);
+        let last = builder.ins().select(reuse, last, next, span);
+        builder.ins().store(last_ptr, last, span);
+        let handle = builder.ins().bitcast(handle, Type::I32, span);
+        let (rep_ptr, state_ptr) = entry(&mut builder, &table, handle, span);
+        // Pop the handle from the free list. An entry which was never allocated holds zero, i.e.
+        // the free list stays empty.
+        let next_free = builder.ins().load(rep_ptr, span);
+        let next_free = builder.ins().bitcast(next_free, Type::U32, span);
+        builder.ins().store(free_ptr, next_free, span);
+        builder.ins().store(rep_ptr, rep, span);
+        let owned = builder.ins().u32(1, span);
+        builder.ins().store(state_ptr, owned, span);
+        builder.ins().ret(Some(handle), span);
+        builder.build()?;
+
+        // [resource-rep](handle: i32) -> i32
+        let id = ComponentResource::builtin(resource.name, "rep");
+        let mut builder = module_builder.function(id.function, builtin_signature("rep"))?;
+        let handle = builder.block_params(builder.current_block())[0];
+        let (rep_ptr, state_ptr) = entry(&mut builder, &table, handle, span);
+        let state = builder.ins().load(state_ptr, span);
+        assert_valid(&mut builder, state, span);
+        let rep = builder.ins().load(rep_ptr, span);
+        builder.ins().ret(Some(rep), span);
+        builder.build()?;
+
+        // [resource-drop](handle: i32)
+        let id = ComponentResource::builtin(resource.name, "drop");
+        let mut builder = module_builder.function(id.function, builtin_signature("drop"))?;
+        let handle = builder.block_params(builder.current_block())[0];
+        let (rep_ptr, state_ptr) = entry(&mut builder, &table, handle, span);
+        let state = builder.ins().load(state_ptr, span);
+        assert_owned(&mut builder, state, span);
+        let dropped = builder.ins().u32(0, span);
+        builder.ins().store(state_ptr, dropped, span);
+        let rep = builder.ins().load(rep_ptr, span);
+        // Push the handle on the free list, linking it to the previous head through the entry
+        let free_ptr = builder.ins().symbol_addr(&free, Type::Ptr(Box::new(Type::U32)), span);
+        let head = builder.ins().load(free_ptr, span);
+        let head = builder.ins().bitcast(head, Type::I32, span);
+        builder.ins().store(rep_ptr, head, span);
+        let handle = builder.ins().bitcast(handle, Type::U32, span);
+        builder.ins().store(free_ptr, handle, span);
+        if let Some((dtor, sig)) = dtor.clone() {
+            if builder.data_flow_graph().get_import(&dtor).is_none() {
+                builder.import_function(dtor.module, dtor.function, sig).map_err(|_| {
+                    let message = format!("{dtor} is already imported with a different signature");
+                    diagnostics.diagnostic(Severity::Error).with_message(message).into_report()
+                })?;
+            }
+            builder.ins().exec(dtor, &[rep], span);
+        }
+        builder.ins().ret(None, span);
+        let drop = builder.build()?;
+
+        // [resource-lend](handle: i32) -> i32, returns the representation of a borrowed handle
+        let id = ComponentResource::builtin(resource.name, "lend");
+        let mut builder = module_builder.function(id.function, builtin_signature("lend"))?;
+        let handle = builder.block_params(builder.current_block())[0];
+        let (rep_ptr, state_ptr) = entry(&mut builder, &table, handle, span);
+        let state = builder.ins().load(state_ptr, span);
+        assert_valid(&mut builder, state, span);
+        let state = builder.ins().add_imm_checked(state, Immediate::U32(1), span);
+        builder.ins().store(state_ptr, state, span);
+        let rep = builder.ins().load(rep_ptr, span);
+        builder.ins().ret(Some(rep), span);
+        builder.build()?;
+
+        // [resource-unlend](handle: i32), ends a borrow started by `[resource-lend]`
+        let id = ComponentResource::builtin(resource.name, "unlend");
+        let mut builder = module_builder.function(id.function, builtin_signature("unlend"))?;
+        let handle = builder.block_params(builder.current_block())[0];
+        let (_, state_ptr) = entry(&mut builder, &table, handle, span);
+        let state = builder.ins().load(state_ptr, span);
+        let borrowed = builder.ins().gt_imm(state, Immediate::U32(1), span);
+        builder.ins().assert_with_error(borrowed, ASSERT_FAILED_INVALID_HANDLE, span);
+        let state = builder.ins().sub_imm_checked(state, Immediate::U32(1), span);
+        builder.ins().store(state_ptr, state, span);
+        builder.ins().ret(None, span);
+        builder.build()?;
+
+        // [resource-own](handle: i32), checks that ownership of a handle can be transferred
+        let id = ComponentResource::builtin(resource.name, "own");
+        let mut builder = module_builder.function(id.function, builtin_signature("own"))?;
+        let handle = builder.block_params(builder.current_block())[0];
+        let (_, state_ptr) = entry(&mut builder, &table, handle, span);
+        let state = builder.ins().load(state_ptr, span);
+        assert_owned(&mut builder, state, span);
+        builder.ins().ret(None, span);
+        builder.build()?;
+
+        module_builder.build()?;
+
+        let name = InterfaceFunctionIdent {
+            interface: resource.name.interface,
+            function: drop.function.as_symbol(),
+        };
+        let export = ComponentExport {
+            function: drop,
+            function_ty: FunctionType {
+                abi: midenc_hir::types::Abi::Canonical,
+                params: vec![Type::I32],
+                results: vec![],
+            },
+            options: midenc_hir::CanonicalOptions {
+                realloc: None,
+                post_return: None,
+            },
+            param_handles: BTreeMap::new(),
+            result_handles: BTreeMap::new(),
+        };
+        exports.insert(name, export);
+    }
+    Ok(exports)
+}
+
+/// Returns the signature of the built-in function `op` of a resource, see
+/// [ComponentResource::builtin]
+pub fn builtin_signature(op: &str) -> Signature {
+    let results = match op {
+        "new" | "rep" | "lend" => vec![AbiParam::new(Type::I32)],
+        _ => vec![],
+    };
+    Signature::new([AbiParam::new(Type::I32)], results)
+}
+
+/// Returns the name of the global variable holding the handle table of the resource `name`
+fn table_symbol(name: InterfaceFunctionIdent) -> String {
+    format!("{}#[resource-table]{}", name.interface.full_name, name.function)
+}
+
+/// Returns the name of the global variable holding the head of the free list of the handles of
+/// the resource `name`
+fn free_symbol(name: InterfaceFunctionIdent) -> String {
+    format!("{}#[resource-free]{}", name.interface.full_name, name.function)
+}
+
+/// Returns pointers to the representation and the state of the entry of the `i32` `handle` in the
+/// handle `table`, trapping if the handle is out of the bounds of the table
+fn entry(
+    builder: &mut ModuleFunctionBuilder<'_>,
+    table: &str,
+    handle: Value,
+    span: SourceSpan,
+) -> (Value, Value) {
+    let handle = builder.ins().bitcast(handle, Type::U32, span);
+    let in_bounds = builder.ins().lt_imm(handle, Immediate::U32(MAX_HANDLES), span);
+    builder.ins().assert_with_error(in_bounds, ASSERT_FAILED_INVALID_HANDLE, span);
+    let base = builder.ins().symbol_addr(table, Type::Ptr(Box::new(Type::U32)), span);
+    let base = builder.ins().ptrtoint(base, Type::U32, span);
+    let offset = builder.ins().mul_imm_checked(handle, Immediate::U32(ENTRY_SIZE), span);
+    let rep = builder.ins().add_checked(base, offset, span);
+    let state = builder.ins().add_imm_checked(rep, Immediate::U32(4), span);
+    let rep = builder.ins().inttoptr(rep, Type::Ptr(Box::new(Type::I32)), span);
+    let state = builder.ins().inttoptr(state, Type::Ptr(Box::new(Type::U32)), span);
+    (rep, state)
+}
+
+/// Traps unless the handle in the given `state` is valid
+fn assert_valid(builder: &mut ModuleFunctionBuilder<'_>, state: Value, span: SourceSpan) {
+    let valid = builder.ins().neq_imm(state, Immediate::U32(0), span);
+    builder.ins().assert_with_error(valid, ASSERT_FAILED_INVALID_HANDLE, span);
+}
+
+/// Traps unless the handle in the given `state` is valid and not borrowed
+fn assert_owned(builder: &mut ModuleFunctionBuilder<'_>, state: Value, span: SourceSpan) {
+    let owned = builder.ins().eq_imm(state, Immediate::U32(1), span);
+    builder.ins().assert_with_error(owned, ASSERT_FAILED_INVALID_HANDLE, span);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use midenc_codegen_masm::{Emulator, MasmCompiler};
+    use midenc_hir::{testing::TestContext, FunctionIdent, ProgramBuilder, Stack};
+    use midenc_session::OutputType;
+
+    use super::*;
+
+    /// The built-in functions of the resource under test, imported into `main`
+    struct Builtins {
+        new: FunctionIdent,
+        rep: FunctionIdent,
+        drop: FunctionIdent,
+        lend: FunctionIdent,
+        unlend: FunctionIdent,
+    }
+
+    impl Builtins {
+        fn new(builder: &mut ModuleFunctionBuilder<'_>, name: InterfaceFunctionIdent) -> Self {
+            let mut import = |op: &str| {
+                let id = ComponentResource::builtin(name, op);
+                builder.import_function(id.module, id.function, builtin_signature(op)).unwrap()
+            };
+            Self {
+                new: import("new"),
+                rep: import("rep"),
+                drop: import("drop"),
+                lend: import("lend"),
+                unlend: import("unlend"),
+            }
+        }
+
+        fn new_handle(&self, builder: &mut ModuleFunctionBuilder<'_>, rep: i32) -> Value {
+            let rep = builder.ins().i32(rep, SourceSpan::default());
+            self.call(builder, self.new, rep).unwrap()
+        }
+
+        fn call(
+            &self,
+            builder: &mut ModuleFunctionBuilder<'_>,
+            builtin: FunctionIdent,
+            handle: Value,
+        ) -> Option<Value> {
+            let call = builder.ins().exec(builtin, &[handle], SourceSpan::default());
+            builder.inst_results(call).first().copied()
+        }
+    }
+
+    /// Runs a program whose `main` function is built by `script`, which returns the `i32` result
+    /// of `main`, against the handle table of a resource, returning `None` if it trapped
+    fn run<F>(script: F) -> Option<u32>
+    where
+        F: FnOnce(&mut ModuleFunctionBuilder<'_>, &Builtins) -> Value,
+    {
+        let mut context = TestContext::default();
+        context.session.options.output_types.insert(OutputType::Masm, None);
+        context.session.options.output_types.insert(OutputType::Mast, None);
+        let diagnostics = &context.session.diagnostics;
+
+        let name = InterfaceFunctionIdent::from_full("miden:test/things@1.0.0", "thing");
+        let mut component_builder = ComponentBuilder::new(diagnostics);
+        component_builder.add_resource(ComponentResource { name, dtor: None });
+        let exports = generate_handle_tables(&mut component_builder, diagnostics).unwrap();
+        let drop = ComponentResource::builtin(name, "drop");
+        assert_eq!(exports.values().map(|export| export.function).collect::<Vec<_>>(), [drop]);
+
+        let mut builder = ProgramBuilder::new(diagnostics);
+        for (_, module) in component_builder.build().to_modules() {
+            builder.add_module(module).unwrap();
+        }
+        let mut mb = builder.module("test");
+        let sig = Signature::new([], [AbiParam::new(Type::I32)]);
+        let mut fb = mb.function("main", sig).unwrap();
+        let builtins = Builtins::new(&mut fb, name);
+        let result = script(&mut fb, &builtins);
+        fb.ins().ret(Some(result), SourceSpan::default());
+        let main = fb.build().unwrap();
+        mb.build().unwrap();
+
+        let program = builder.with_entrypoint(main).link().unwrap();
+        let mut compiler = MasmCompiler::new(&context.session);
+        let program = compiler.compile(program).unwrap().unwrap_executable().freeze();
+
+        let mut emulator = Emulator::default();
+        emulator.load_program(program).unwrap();
+        // A trap is an assertion failure, on which the emulator panics
+        let stack = panic::catch_unwind(AssertUnwindSafe(|| emulator.start())).ok()?.unwrap();
+        stack.peek().map(|result| result.as_int() as u32)
+    }
+
+    /// Emits a loop calling `body` `n` times, returning the result of its last iteration
+    fn repeat<F>(builder: &mut ModuleFunctionBuilder<'_>, n: u32, body: F) -> Value
+    where
+        F: Fn(&mut ModuleFunctionBuilder<'_>) -> Value,
+    {
+        let span = SourceSpan::default();
+        let header = builder.create_block();
+        let i = builder.append_block_param(header, Type::U32, span);
+        let last = builder.append_block_param(header, Type::I32, span);
+        let body_block = builder.create_block();
+        let exit = builder.create_block();
+        let result = builder.append_block_param(exit, Type::I32, span);
+        let zero = builder.ins().u32(0, span);
+        let none = builder.ins().i32(0, span);
+        builder.ins().br(header, &[zero, none], span);
+
+        builder.switch_to_block(header);
+        let more = builder.ins().lt_imm(i, Immediate::U32(n), span);
+        builder.ins().cond_br(more, body_block, &[], exit, &[last], span);
+
+        builder.switch_to_block(body_block);
+        let value = body(builder);
+        let i = builder.ins().add_imm_checked(i, Immediate::U32(1), span);
+        builder.ins().br(header, &[i, value], span);
+
+        builder.switch_to_block(exit);
+        result
+    }
+
+    #[test]
+    fn new_rep_drop() {
+        // Handles are allocated from one
+        let handle = run(|fb, b| {
+            b.new_handle(fb, 10);
+            b.new_handle(fb, 20)
+        });
+        assert_eq!(handle, Some(2));
+
+        let rep = run(|fb, b| {
+            let first = b.new_handle(fb, 10);
+            b.new_handle(fb, 20);
+            b.call(fb, b.rep, first).unwrap()
+        });
+        assert_eq!(rep, Some(10));
+
+        let rep = run(|fb, b| {
+            let first = b.new_handle(fb, 10);
+            let second = b.new_handle(fb, 20);
+            b.call(fb, b.drop, first);
+            b.call(fb, b.rep, second).unwrap()
+        });
+        assert_eq!(rep, Some(20));
+    }
+
+    #[test]
+    fn invalid_handles_trap() {
+        // The handle zero is never valid
+        assert_eq!(run(|fb, b| b.new_handle(fb, 10)), Some(1));
+        let rep = run(|fb, b| {
+            b.new_handle(fb, 10);
+            let zero = fb.ins().i32(0, SourceSpan::default());
+            b.call(fb, b.rep, zero).unwrap()
+        });
+        assert_eq!(rep, None);
+
+        // Neither is a handle out of the bounds of the table
+        let rep = run(|fb, b| {
+            let handle = fb.ins().i32(MAX_HANDLES as i32, SourceSpan::default());
+            b.call(fb, b.rep, handle).unwrap()
+        });
+        assert_eq!(rep, None);
+
+        let rep = run(|fb, b| {
+            let handle = b.new_handle(fb, 10);
+            b.call(fb, b.drop, handle);
+            b.call(fb, b.rep, handle).unwrap()
+        });
+        assert_eq!(rep, None);
+    }
+
+    #[test]
+    fn double_drop_traps() {
+        let result = run(|fb, b| {
+            let handle = b.new_handle(fb, 10);
+            b.call(fb, b.drop, handle);
+            b.call(fb, b.drop, handle);
+            handle
+        });
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn drop_while_borrowed_traps() {
+        let result = run(|fb, b| {
+            let handle = b.new_handle(fb, 10);
+            b.call(fb, b.lend, handle);
+            b.call(fb, b.drop, handle);
+            handle
+        });
+        assert_eq!(result, None);
+
+        // Once all borrows have ended, the handle can be dropped
+        let result = run(|fb, b| {
+            let handle = b.new_handle(fb, 10);
+            let rep = b.call(fb, b.lend, handle).unwrap();
+            b.call(fb, b.lend, handle);
+            b.call(fb, b.unlend, handle);
+            b.call(fb, b.unlend, handle);
+            b.call(fb, b.drop, handle);
+            rep
+        });
+        assert_eq!(result, Some(10));
+    }
+
+    #[test]
+    fn unlend_without_borrow_traps() {
+        let result = run(|fb, b| {
+            let handle = b.new_handle(fb, 10);
+            b.call(fb, b.unlend, handle);
+            handle
+        });
+        assert_eq!(result, None);
+
+        let result = run(|fb, b| {
+            let handle = b.new_handle(fb, 10);
+            b.call(fb, b.lend, handle);
+            b.call(fb, b.unlend, handle);
+            b.call(fb, b.unlend, handle);
+            handle
+        });
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn dropped_handles_are_reused() {
+        // The most recently dropped handle is reused first
+        let handle = run(|fb, b| {
+            let first = b.new_handle(fb, 10);
+            let second = b.new_handle(fb, 20);
+            b.call(fb, b.drop, first);
+            b.call(fb, b.drop, second);
+            b.new_handle(fb, 30)
+        });
+        assert_eq!(handle, Some(2));
+
+        let handle = run(|fb, b| {
+            let first = b.new_handle(fb, 10);
+            let second = b.new_handle(fb, 20);
+            b.call(fb, b.drop, second);
+            b.call(fb, b.drop, first);
+            b.new_handle(fb, 30)
+        });
+        assert_eq!(handle, Some(1));
+
+        // A reused handle refers to its new representation, and the free list is followed
+        let rep = run(|fb, b| {
+            let first = b.new_handle(fb, 10);
+            let second = b.new_handle(fb, 20);
+            b.call(fb, b.drop, first);
+            b.call(fb, b.drop, second);
+            b.new_handle(fb, 30);
+            let first = b.new_handle(fb, 40);
+            b.call(fb, b.rep, first).unwrap()
+        });
+        assert_eq!(rep, Some(40));
+
+        // Once the free list is exhausted, new handles are allocated
+        let handle = run(|fb, b| {
+            let first = b.new_handle(fb, 10);
+            b.new_handle(fb, 20);
+            b.call(fb, b.drop, first);
+            b.new_handle(fb, 30);
+            b.new_handle(fb, 40)
+        });
+        assert_eq!(handle, Some(3));
+    }
+
+    #[test]
+    fn handle_table_capacity() {
+        // Any number of handles can be allocated over time, as long as they are dropped
+        let handle = run(|fb, b| {
+            repeat(fb, 2 * MAX_HANDLES, |fb| {
+                let handle = b.new_handle(fb, 10);
+                b.call(fb, b.drop, handle);
+                handle
+            })
+        });
+        assert_eq!(handle, Some(1));
+
+        // At most `MAX_HANDLES - 1` handles can be live at the same time
+        let handle = run(|fb, b| repeat(fb, MAX_HANDLES - 1, |fb| b.new_handle(fb, 10)));
+        assert_eq!(handle, Some(MAX_HANDLES - 1));
+        let handle = run(|fb, b| repeat(fb, MAX_HANDLES, |fb| b.new_handle(fb, 10)));
+        assert_eq!(handle, None);
+    }
+}
@@ -0,0 +1,964 @@
+//! Conversion of values between the Wasm CABI and the cross-context Miden ABI (Miden CCABI).
+//!
+//! Values are converted between the flattened core Wasm representation (see [super::flat]) used
+//! by the core Wasm functions, and the flattened Miden CCABI representation passed on the operand
+//! stack by the cross-context calls. The two differ only in how lists (and strings) are passed.
+//! Since the caller and the callee do not share memory, the contents of a list are placed in the
+//! advice map by the sender under the commitment to them, which is passed along with the original
+//! pointer and length of the list. The receiver allocates memory for the list in its own context,
+//! and copies the contents from the advice provider, verifying them against the commitment.
+//!
+//! When the elements of a list contain lists themselves, e.g. a `list<string>`, the commitments to
+//! the nested lists are gathered in an auxiliary buffer, which is committed to and transferred in
+//! the same way, with its commitment passed after the one of the list. The receiver then copies
+//! each nested list in a loop over the elements, and updates the pointers to them.
+//!
+//! This module also handles loading and storing of the flattened values from/to linear memory
+//! according to the Canonical ABI memory layout, for params and results that are spilled to
+//! memory when they exceed the limits of the flattening.
+//!
+//! The payload of a union depends on its discriminant, which is only known at runtime, so the
+//! conversions are done for every case of a union, and their results are selected according to
+//! the discriminant. The effects of the conversions of the inactive cases, i.e. transferring a
+//! list or storing to memory, are suppressed: such lists are transferred as empty lists, and
+//! such stores write back the contents of memory unchanged.
+
+use midenc_hir::{
+    AbiParam, Alignable, FunctionIdent, Immediate, InstBuilder, ModuleFunctionBuilder, Signature,
+    SourceSpan, Type, UnionType, Value, ValueList,
+};
+
+use super::flat::{contains_list, flatten_type, flatten_union_payload, list_commitments};
+
+/// The intrinsic used to commit to the contents of a list and place them in the advice map
+const COMMIT_BYTES: &str = "commit_bytes";
+/// The intrinsic used to copy the contents of a list from the advice map to memory
+const PIPE_BYTES: &str = "pipe_bytes";
+/// The module containing the intrinsics above
+const MEM_INTRINSICS: &str = "intrinsics::mem";
+
+/// The alignment of the memory allocated for the lists received from another context, i.e. the
+/// size of a word in bytes.
+///
+/// A list is copied in whole words, so the received list must have the same offset within a word
+/// as the list that was sent, which is guaranteed by allocating on a word boundary, and then
+/// offsetting the pointer accordingly.
+const LIST_ALIGN: u32 = 16;
+
+/// The number of bytes allocated in addition to the contents of a list received from another
+/// context: up to a word before the list for its offset within a word, up to a word after it to
+/// complete its last word, and another word as the copied words are widened to an even number.
+const LIST_PADDING: u32 = 3 * LIST_ALIGN;
+
+/// Returns the size and alignment, in bytes, of the given type in linear memory, according to the
+/// Canonical ABI
+pub fn size_align(ty: &Type) -> (u32, u32) {
+    match ty {
+        Type::Unit => (0, 1),
+        Type::I1 | Type::I8 | Type::U8 => (1, 1),
+        Type::I16 | Type::U16 => (2, 2),
+        Type::I32 | Type::U32 | Type::Felt => (4, 4),
+        Type::I64 | Type::U64 | Type::F64 => (8, 8),
+        Type::I128 | Type::U128 => (16, 8),
+        Type::U256 => (32, 8),
+        Type::Struct(struct_ty) => {
+            let tys = struct_ty.fields().iter().map(|field| &field.ty);
+            tuple_layout(tys).1
+        }
+        Type::Array(elem_ty, len) => {
+            let (size, align) = size_align(elem_ty);
+            (size.next_multiple_of(align) * *len as u32, align)
+        }
+        // A pointer and a length, as in HIR
+        Type::List(_) => (ty.size_in_bytes() as u32, ty.min_alignment() as u32),
+        Type::Union(union_ty) => union_layout(union_ty).1,
+        Type::Unknown | Type::Never | Type::Ptr(_) | Type::NativePtr(..) => {
+            panic!("CanonABI memory layout: unexpected {} type", ty)
+        }
+    }
+}
+
+/// Returns the offsets, in bytes, of the given types laid out in memory as a tuple, along with the
+/// size and alignment of the tuple, according to the Canonical ABI
+pub fn tuple_layout<'a>(tys: impl IntoIterator<Item = &'a Type>) -> (Vec<u32>, (u32, u32)) {
+    let mut offsets = vec![];
+    let mut size = 0;
+    let mut max_align = 1;
+    for ty in tys {
+        let (field_size, field_align) = size_align(ty);
+        size = size.next_multiple_of(field_align);
+        offsets.push(size);
+        size += field_size;
+        max_align = max_align.max(field_align);
+    }
+    (offsets, (size.next_multiple_of(max_align), max_align))
+}
+
+/// Returns the offset, in bytes, of the payload of the given union in memory, along with the size
+/// and alignment of the union, according to the Canonical ABI
+pub fn union_layout(union_ty: &UnionType) -> (u32, (u32, u32)) {
+    let (discriminant_size, discriminant_align) = size_align(union_ty.discriminant());
+    let (payload_size, payload_align) = union_ty
+        .cases()
+        .iter()
+        .map(size_align)
+        .fold((0, 1), |(size, align), (case_size, case_align)| {
+            (size.max(case_size), align.max(case_align))
+        });
+    let payload_offset = discriminant_size.align_up(payload_align);
+    let align = discriminant_align.max(payload_align);
+    (payload_offset, ((payload_offset + payload_size).align_up(align), align))
+}
+
+/// Returns the size, in bytes, of the commitments to the lists contained in a value of `ty`.
+///
+/// When the elements of a list contain lists, the commitments to those lists are stored, element
+/// by element, in an auxiliary buffer, which is transferred like the contents of the list itself.
+/// The commitments of the lists of every case of a union are stored, those of the inactive cases
+/// being the commitments to empty lists.
+fn commitments_size(ty: &Type) -> u32 {
+    match ty {
+        Type::List(elem_ty) => list_commitments(elem_ty) as u32 * 16,
+        Type::Struct(struct_ty) => {
+            struct_ty.fields().iter().map(|field| commitments_size(&field.ty)).sum()
+        }
+        Type::Array(elem_ty, len) => commitments_size(elem_ty) * *len as u32,
+        Type::Union(union_ty) => union_ty.cases().iter().map(commitments_size).sum(),
+        _ => 0,
+    }
+}
+
+/// Generates the code converting values between the Wasm CABI and the Miden CCABI in the body of
+/// a lifting or lowering function
+pub struct Transfer<'a, 'm> {
+    builder: &'a mut ModuleFunctionBuilder<'m>,
+    /// The `cabi_realloc` function of the core Wasm module, used to allocate memory for the
+    /// lists received from another context, and for the params spilled to memory
+    realloc: Option<(FunctionIdent, Signature)>,
+    /// When converting the payload of a case of a union, the `i1` condition under which that case
+    /// is active
+    active: Option<Value>,
+    span: SourceSpan,
+}
+
+impl<'a, 'm> Transfer<'a, 'm> {
+    pub fn new(
+        builder: &'a mut ModuleFunctionBuilder<'m>,
+        realloc: Option<(FunctionIdent, Signature)>,
+        span: SourceSpan,
+    ) -> Self {
+        Self {
+            builder,
+            realloc,
+            active: None,
+            span,
+        }
+    }
+
+    /// Returns the builder of the lifting or lowering function
+    pub fn builder(&mut self) -> &mut ModuleFunctionBuilder<'m> {
+        self.builder
+    }
+
+    /// Converts the flattened Wasm CABI `values` of `ty` into the Miden CCABI values, committing
+    /// to the contents of any lists
+    pub fn lower(
+        &mut self,
+        ty: &Type,
+        values: &mut impl Iterator<Item = Value>,
+        out: &mut Vec<Value>,
+    ) -> Result<(), String> {
+        match ty {
+            Type::List(elem_ty) => {
+                let ptr = values.next().expect("missing list pointer");
+                let len = values.next().expect("missing list length");
+                let len = self.mask_len(len);
+                out.extend(self.lower_list(elem_ty, ptr, len)?);
+                out.extend([ptr, len]);
+            }
+            Type::Struct(struct_ty) => {
+                for field in struct_ty.fields() {
+                    self.lower(&field.ty, values, out)?;
+                }
+            }
+            Type::Array(elem_ty, len) => {
+                for _ in 0..*len {
+                    self.lower(elem_ty, values, out)?;
+                }
+            }
+            Type::Union(union_ty) if contains_list(ty) => {
+                let discriminant = values.next().expect("missing union discriminant");
+                let slot_tys = flatten_union_payload(union_ty)?;
+                let slots = values.by_ref().take(slot_tys.len()).collect::<Vec<_>>();
+                out.push(discriminant);
+                for (index, case) in union_ty.cases().iter().enumerate() {
+                    let case_values = self.unjoin(case, &slots)?;
+                    self.with_case(discriminant, index, |this| {
+                        this.lower(case, &mut case_values.into_iter(), out)
+                    })?;
+                }
+            }
+            ty => out.extend(values.by_ref().take(flatten_type(ty)?.len())),
+        }
+        Ok(())
+    }
+
+    /// Converts the flattened Miden CCABI `values` of `ty` into the Wasm CABI values, copying the
+    /// contents of any lists into the memory of the current context
+    pub fn lift(
+        &mut self,
+        ty: &Type,
+        values: &mut impl Iterator<Item = Value>,
+        out: &mut Vec<Value>,
+    ) -> Result<(), String> {
+        match ty {
+            Type::List(elem_ty) => {
+                let commitments: Vec<Value> =
+                    values.by_ref().take(list_commitments(elem_ty) * 4).collect();
+                assert_eq!(
+                    commitments.len(),
+                    list_commitments(elem_ty) * 4,
+                    "missing list commitment"
+                );
+                let ptr = values.next().expect("missing list pointer");
+                let len = values.next().expect("missing list length");
+                let dest = self.lift_list(elem_ty, commitments, ptr, len)?;
+                out.extend([dest, len]);
+            }
+            Type::Struct(struct_ty) => {
+                for field in struct_ty.fields() {
+                    self.lift(&field.ty, values, out)?;
+                }
+            }
+            Type::Array(elem_ty, len) => {
+                for _ in 0..*len {
+                    self.lift(elem_ty, values, out)?;
+                }
+            }
+            Type::Union(union_ty) if contains_list(ty) => {
+                let discriminant = values.next().expect("missing union discriminant");
+                let slot_tys = flatten_union_payload(union_ty)?;
+                let mut slots = vec![];
+                for (index, case) in union_ty.cases().iter().enumerate() {
+                    let mut case_values = vec![];
+                    self.lift(case, values, &mut case_values)?;
+                    self.join(discriminant, index, &slot_tys, case_values, &mut slots);
+                }
+                out.push(discriminant);
+                out.extend(slots);
+            }
+            ty => out.extend(values.by_ref().take(flatten_type(ty)?.len())),
+        }
+        Ok(())
+    }
+
+    /// Loads the flattened Wasm CABI values of the tuple of `tys` stored at the `i32` pointer `ptr`
+    pub fn load_tuple(&mut self, tys: &[Type], ptr: Value) -> Vec<Value> {
+        let base = self.bitcast_u32(ptr);
+        let (offsets, _) = tuple_layout(tys);
+        let mut out = vec![];
+        for (ty, offset) in tys.iter().zip(offsets) {
+            self.load(base, offset, ty, &mut out);
+        }
+        out
+    }
+
+    /// Stores the flattened Wasm CABI `values` of the tuple of `tys` at the `i32` pointer `ptr`
+    pub fn store_tuple(&mut self, tys: &[Type], ptr: Value, values: Vec<Value>) {
+        let base = self.bitcast_u32(ptr);
+        let (offsets, _) = tuple_layout(tys);
+        let mut values = values.into_iter();
+        for (ty, offset) in tys.iter().zip(offsets) {
+            self.store(base, offset, ty, &mut values);
+        }
+    }
+
+    /// Allocates memory for a tuple of `tys`, returning the `i32` pointer to it
+    pub fn alloc_tuple(&mut self, tys: &[Type]) -> Result<Value, String> {
+        let (_, (size, align)) = tuple_layout(tys);
+        let size = self.builder.ins().u32(size, self.span);
+        let ptr = self.alloc(size, align)?;
+        Ok(self.bitcast_i32(ptr))
+    }
+
+    /// Loads the flattened Wasm CABI values of `ty` stored at `base + offset`
+    pub fn load(&mut self, base: Value, offset: u32, ty: &Type, out: &mut Vec<Value>) {
+        match ty {
+            Type::Unit => (),
+            Type::I1 | Type::U8 | Type::U16 => {
+                // Booleans are stored as a byte
+                let mem_ty = match ty {
+                    Type::I1 => Type::U8,
+                    ty => ty.clone(),
+                };
+                let value = self.load_scalar(base, offset, mem_ty);
+                let value = self.builder.ins().zext(value, Type::U32, self.span);
+                out.push(self.bitcast_i32(value));
+            }
+            Type::I8 | Type::I16 => {
+                let value = self.load_scalar(base, offset, ty.clone());
+                out.push(self.builder.ins().sext(value, Type::I32, self.span));
+            }
+            Type::I32 | Type::U32 => out.push(self.load_scalar(base, offset, Type::I32)),
+            Type::I64 | Type::U64 => out.push(self.load_scalar(base, offset, Type::I64)),
+            Type::Felt | Type::F64 => out.push(self.load_scalar(base, offset, ty.clone())),
+            Type::I128 | Type::U128 | Type::U256 => {
+                for i in 0..ty.size_in_bytes() as u32 / 8 {
+                    out.push(self.load_scalar(base, offset + i * 8, Type::I64));
+                }
+            }
+            Type::Struct(struct_ty) => {
+                let (offsets, _) = tuple_layout(struct_ty.fields().iter().map(|field| &field.ty));
+                for (field, field_offset) in struct_ty.fields().iter().zip(offsets) {
+                    self.load(base, offset + field_offset, &field.ty, out);
+                }
+            }
+            Type::Array(elem_ty, len) => {
+                let (size, align) = size_align(elem_ty);
+                for i in 0..*len as u32 {
+                    self.load(base, offset + i * size.next_multiple_of(align), elem_ty, out);
+                }
+            }
+            Type::List(_) => {
+                out.push(self.load_scalar(base, offset, Type::I32));
+                out.push(self.load_scalar(base, offset + 4, Type::I32));
+            }
+            Type::Union(union_ty) => {
+                let (payload_offset, _) = union_layout(union_ty);
+                self.load(base, offset, union_ty.discriminant(), out);
+                let discriminant = *out.last().unwrap();
+                let slot_tys = flatten_union_payload(union_ty).expect("unexpected type");
+                let mut slots = vec![];
+                for (index, case) in union_ty.cases().iter().enumerate() {
+                    // Loading the payload of an inactive case has no effect, so it is safe to do
+                    // so, and discard the result
+                    let mut case_values = vec![];
+                    self.load(base, offset + payload_offset, case, &mut case_values);
+                    self.join(discriminant, index, &slot_tys, case_values, &mut slots);
+                }
+                out.extend(slots);
+            }
+            Type::Unknown | Type::Never | Type::Ptr(_) | Type::NativePtr(..) => {
+                panic!("CanonABI load: unexpected {} type", ty)
+            }
+        }
+    }
+
+    /// Stores the flattened Wasm CABI `values` of `ty` at `base + offset`
+    pub fn store(
+        &mut self,
+        base: Value,
+        offset: u32,
+        ty: &Type,
+        values: &mut impl Iterator<Item = Value>,
+    ) {
+        match ty {
+            Type::Unit => (),
+            Type::I1 | Type::I8 | Type::U8 | Type::I16 | Type::U16 => {
+                let mem_ty = match ty.size_in_bytes() {
+                    2 => Type::U16,
+                    _ => Type::U8,
+                };
+                let value = self.bitcast_u32(values.next().expect("missing value to store"));
+                let value = self.builder.ins().trunc(value, mem_ty, self.span);
+                self.store_scalar(base, offset, value);
+            }
+            Type::I32
+            | Type::U32
+            | Type::I64
+            | Type::U64
+            | Type::Felt
+            | Type::F64
+            | Type::I128
+            | Type::U128
+            | Type::U256
+            | Type::List(_) => {
+                // These are flattened into 32- or 64-bit values stored one after another
+                let mut offset = offset;
+                for _ in 0..flatten_type(ty).expect("unexpected type").len() {
+                    let value = values.next().expect("missing value to store");
+                    let size = size_align(self.builder.data_flow_graph().value_type(value)).0;
+                    self.store_scalar(base, offset, value);
+                    offset += size;
+                }
+            }
+            Type::Struct(struct_ty) => {
+                let (offsets, _) = tuple_layout(struct_ty.fields().iter().map(|field| &field.ty));
+                for (field, field_offset) in struct_ty.fields().iter().zip(offsets) {
+                    self.store(base, offset + field_offset, &field.ty, values);
+                }
+            }
+            Type::Array(elem_ty, len) => {
+                let (size, align) = size_align(elem_ty);
+                for i in 0..*len as u32 {
+                    self.store(base, offset + i * size.next_multiple_of(align), elem_ty, values);
+                }
+            }
+            Type::Union(union_ty) => {
+                let (payload_offset, _) = union_layout(union_ty);
+                let discriminant = values.next().expect("missing union discriminant");
+                self.store(
+                    base,
+                    offset,
+                    union_ty.discriminant(),
+                    &mut core::iter::once(discriminant),
+                );
+                let slot_tys = flatten_union_payload(union_ty).expect("unexpected type");
+                let slots = values.by_ref().take(slot_tys.len()).collect::<Vec<_>>();
+                for (index, case) in union_ty.cases().iter().enumerate() {
+                    let case_values = self.unjoin(case, &slots).expect("unexpected type");
+                    self.with_case(discriminant, index, |this| {
+                        this.store(
+                            base,
+                            offset + payload_offset,
+                            case,
+                            &mut case_values.into_iter(),
+                        )
+                    });
+                }
+            }
+            Type::Unknown | Type::Never | Type::Ptr(_) | Type::NativePtr(..) => {
+                panic!("CanonABI store: unexpected {} type", ty)
+            }
+        }
+    }
+
+    /// Allocates `size` bytes of memory aligned to `align` in the current context, using the
+    /// `cabi_realloc` function of the core Wasm module
+    pub fn alloc(&mut self, size: Value, align: u32) -> Result<Value, String> {
+        let (realloc, sig) = self.realloc.clone().ok_or_else(|| {
+            "the core Wasm module does not export `cabi_realloc`, which is required to allocate \
+             memory"
+                .to_string()
+        })?;
+        if self.builder.data_flow_graph().get_import(&realloc).is_none() {
+            self.builder
+                .import_function(realloc.module, realloc.function, sig)
+                .map_err(|_| format!("{realloc} is already imported with a different signature"))?;
+        }
+        let zero = self.builder.ins().i32(0, self.span);
+        let align = self.builder.ins().i32(align as i32, self.span);
+        let size = self.bitcast_i32(size);
+        let call = self.builder.ins().exec(realloc, &[zero, zero, align, size], self.span);
+        let ptr = self.builder.first_result(call);
+        Ok(self.bitcast_u32(ptr))
+    }
+
+    /// Reinterprets the given `i32` value as a `u32`
+    pub fn bitcast_u32(&mut self, value: Value) -> Value {
+        self.builder.ins().bitcast(value, Type::U32, self.span)
+    }
+
+    /// Reinterprets the given `u32` value as an `i32`
+    pub fn bitcast_i32(&mut self, value: Value) -> Value {
+        self.builder.ins().bitcast(value, Type::I32, self.span)
+    }
+
+    /// Commits to the contents of the list of `len` elements of `elem_ty` at the `i32` pointer
+    /// `ptr`, placing them in the advice map, and returns the commitments to be passed along with
+    /// the list.
+    ///
+    /// If the elements contain lists, those are committed to as well, and their commitments are
+    /// gathered in an auxiliary buffer, which is committed to in the same way (see
+    /// [commitments_size]).
+    fn lower_list(&mut self, elem_ty: &Type, ptr: Value, len: Value) -> Result<Vec<Value>, String> {
+        let byte_len = self.byte_len(elem_ty, len);
+        let addr = self.bitcast_u32(ptr);
+        let mut commitments = self.commit_bytes(addr, byte_len)?;
+        if contains_list(elem_ty) {
+            let (aux, aux_len) = self.alloc_aux(elem_ty, len)?;
+            let (size, align) = size_align(elem_ty);
+            let aux_size = commitments_size(elem_ty);
+            self.for_each_index(len, |this, index| {
+                let elem = this.elem_addr(addr, index, size.next_multiple_of(align));
+                let elem_aux = this.elem_addr(aux, index, aux_size);
+                this.lower_nested(elem_ty, elem, 0, elem_aux, &mut 0)
+            })?;
+            commitments.extend(self.commit_bytes(aux, aux_len)?);
+        }
+        Ok(commitments)
+    }
+
+    /// Commits to the lists contained in the value of `ty` stored at `base + offset`, storing
+    /// their commitments in the auxiliary buffer at `aux + aux_offset`
+    fn lower_nested(
+        &mut self,
+        ty: &Type,
+        base: Value,
+        offset: u32,
+        aux: Value,
+        aux_offset: &mut u32,
+    ) -> Result<(), String> {
+        match ty {
+            Type::List(elem_ty) => {
+                let ptr = self.load_scalar(base, offset, Type::I32);
+                let len = self.load_scalar(base, offset + 4, Type::I32);
+                let len = self.mask_len(len);
+                let commitments = self.lower_list(elem_ty, ptr, len)?;
+                // The commitments of the lists of inactive cases are those of empty lists, which
+                // is what the receiver expects, so they are stored unconditionally
+                let active = self.active.take();
+                for commitment in commitments {
+                    self.store_scalar(aux, *aux_offset, commitment);
+                    *aux_offset += 4;
+                }
+                self.active = active;
+            }
+            Type::Struct(struct_ty) => {
+                let (offsets, _) = tuple_layout(struct_ty.fields().iter().map(|field| &field.ty));
+                for (field, field_offset) in struct_ty.fields().iter().zip(offsets) {
+                    self.lower_nested(&field.ty, base, offset + field_offset, aux, aux_offset)?;
+                }
+            }
+            Type::Array(elem_ty, len) => {
+                let (size, align) = size_align(elem_ty);
+                for i in 0..*len as u32 {
+                    let elem_offset = offset + i * size.next_multiple_of(align);
+                    self.lower_nested(elem_ty, base, elem_offset, aux, aux_offset)?;
+                }
+            }
+            Type::Union(union_ty) => {
+                let (payload_offset, _) = union_layout(union_ty);
+                let mut discriminant = vec![];
+                self.load(base, offset, union_ty.discriminant(), &mut discriminant);
+                for (index, case) in union_ty.cases().iter().enumerate() {
+                    self.with_case(discriminant[0], index, |this| {
+                        this.lower_nested(case, base, offset + payload_offset, aux, aux_offset)
+                    })?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Allocates memory for the list of `len` elements of `elem_ty` with the `i32` pointer `ptr`
+    /// in the sending context, and copies its contents from the advice provider, verifying them
+    /// against `commitments`. Returns the `i32` pointer to the copy.
+    ///
+    /// If the elements contain lists, those are copied as well, and the pointers to them in the
+    /// copied elements are updated accordingly.
+    fn lift_list(
+        &mut self,
+        elem_ty: &Type,
+        commitments: Vec<Value>,
+        ptr: Value,
+        len: Value,
+    ) -> Result<Value, String> {
+        let byte_len = self.byte_len(elem_ty, len);
+        // Allocate enough memory to place the list at the same offset within a word as the
+        // original list, and to hold all of the copied words
+        let size =
+            self.builder
+                .ins()
+                .add_imm_checked(byte_len, Immediate::U32(LIST_PADDING), self.span);
+        let base = self.alloc(size, LIST_ALIGN)?;
+        let ptr = self.bitcast_u32(ptr);
+        let offset = self.builder.ins().band_imm(ptr, Immediate::U32(LIST_ALIGN - 1), self.span);
+        let dest = self.builder.ins().add_checked(base, offset, self.span);
+        self.pipe_bytes(&commitments[..4], dest, byte_len)?;
+        if contains_list(elem_ty) {
+            // The auxiliary buffer of the sender is word-aligned, see `alloc_aux`
+            let (aux, aux_len) = self.alloc_aux(elem_ty, len)?;
+            self.pipe_bytes(&commitments[4..], aux, aux_len)?;
+            let (size, align) = size_align(elem_ty);
+            let aux_size = commitments_size(elem_ty);
+            self.for_each_index(len, |this, index| {
+                let elem = this.elem_addr(dest, index, size.next_multiple_of(align));
+                let elem_aux = this.elem_addr(aux, index, aux_size);
+                this.lift_nested(elem_ty, elem, 0, elem_aux, &mut 0)
+            })?;
+        }
+        Ok(self.bitcast_i32(dest))
+    }
+
+    /// Copies the lists contained in the value of `ty` stored at `base + offset`, whose
+    /// commitments are read from the auxiliary buffer at `aux + aux_offset`, and updates the
+    /// pointers to them
+    fn lift_nested(
+        &mut self,
+        ty: &Type,
+        base: Value,
+        offset: u32,
+        aux: Value,
+        aux_offset: &mut u32,
+    ) -> Result<(), String> {
+        match ty {
+            Type::List(elem_ty) => {
+                let ptr = self.load_scalar(base, offset, Type::I32);
+                let len = self.load_scalar(base, offset + 4, Type::I32);
+                let len = self.mask_len(len);
+                let mut commitments = vec![];
+                for _ in 0..list_commitments(elem_ty) * 4 {
+                    commitments.push(self.load_scalar(aux, *aux_offset, Type::Felt));
+                    *aux_offset += 4;
+                }
+                let dest = self.lift_list(elem_ty, commitments, ptr, len)?;
+                self.store_scalar(base, offset, dest);
+            }
+            Type::Struct(struct_ty) => {
+                let (offsets, _) = tuple_layout(struct_ty.fields().iter().map(|field| &field.ty));
+                for (field, field_offset) in struct_ty.fields().iter().zip(offsets) {
+                    self.lift_nested(&field.ty, base, offset + field_offset, aux, aux_offset)?;
+                }
+            }
+            Type::Array(elem_ty, len) => {
+                let (size, align) = size_align(elem_ty);
+                for i in 0..*len as u32 {
+                    let elem_offset = offset + i * size.next_multiple_of(align);
+                    self.lift_nested(elem_ty, base, elem_offset, aux, aux_offset)?;
+                }
+            }
+            Type::Union(union_ty) => {
+                let (payload_offset, _) = union_layout(union_ty);
+                let mut discriminant = vec![];
+                self.load(base, offset, union_ty.discriminant(), &mut discriminant);
+                for (index, case) in union_ty.cases().iter().enumerate() {
+                    self.with_case(discriminant[0], index, |this| {
+                        this.lift_nested(case, base, offset + payload_offset, aux, aux_offset)
+                    })?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Returns the `i32` length `len` of a list, or zero if it belongs to an inactive case
+    fn mask_len(&mut self, len: Value) -> Value {
+        // The list of an inactive case is transferred as an empty list
+        match self.active {
+            Some(active) => {
+                let empty = self.builder.ins().i32(0, self.span);
+                self.builder.ins().select(active, len, empty, self.span)
+            }
+            None => len,
+        }
+    }
+
+    /// Allocates the word-aligned auxiliary buffer holding the commitments to the lists contained
+    /// in the `len` elements of `elem_ty`, returning its address and size in bytes
+    fn alloc_aux(&mut self, elem_ty: &Type, len: Value) -> Result<(Value, Value), String> {
+        let len = self.bitcast_u32(len);
+        let aux_len = self.builder.ins().mul_imm_checked(
+            len,
+            Immediate::U32(commitments_size(elem_ty)),
+            self.span,
+        );
+        // The copied words are widened to an even number, see `LIST_PADDING`
+        let size =
+            self.builder
+                .ins()
+                .add_imm_checked(aux_len, Immediate::U32(LIST_PADDING), self.span);
+        let aux = self.alloc(size, LIST_ALIGN)?;
+        Ok((aux, aux_len))
+    }
+
+    /// Returns the address of the element `index` of the array of elements of `size` bytes at
+    /// `addr`
+    fn elem_addr(&mut self, addr: Value, index: Value, size: u32) -> Value {
+        let offset = self.builder.ins().mul_imm_checked(index, Immediate::U32(size), self.span);
+        self.builder.ins().add_checked(addr, offset, self.span)
+    }
+
+    /// Emits a loop running `f` with each `u32` index in `0..len`, where `len` is an `i32`.
+    ///
+    /// On return, the builder is positioned in the block following the loop.
+    fn for_each_index(
+        &mut self,
+        len: Value,
+        mut f: impl FnMut(&mut Self, Value) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let len = self.bitcast_u32(len);
+        let header = self.builder.create_block();
+        let body = self.builder.create_block();
+        let exit = self.builder.create_block();
+        let index = self.builder.append_block_param(header, Type::U32, self.span);
+        let zero = self.builder.ins().u32(0, self.span);
+        self.builder.ins().br(header, &[zero], self.span);
+
+        self.builder.switch_to_block(header);
+        let done = self.builder.ins().gte(index, len, self.span);
+        self.builder.ins().cond_br(done, exit, &[], body, &[], self.span);
+
+        // The lists of an inactive case are empty, so the body is only ever executed for an
+        // active one
+        self.builder.switch_to_block(body);
+        let active = self.active.take();
+        let result = f(self, index);
+        self.active = active;
+        result?;
+        let next = self.builder.ins().add_imm_checked(index, Immediate::U32(1), self.span);
+        self.builder.ins().br(header, &[next], self.span);
+
+        self.builder.switch_to_block(exit);
+        Ok(())
+    }
+
+    /// Commits to the `byte_len` bytes at `addr`, placing them in the advice map, and returns the
+    /// commitment
+    fn commit_bytes(&mut self, addr: Value, byte_len: Value) -> Result<Vec<Value>, String> {
+        let commit_bytes = self.import_intrinsic(
+            COMMIT_BYTES,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [
+                    AbiParam::new(Type::Felt),
+                    AbiParam::new(Type::Felt),
+                    AbiParam::new(Type::Felt),
+                    AbiParam::new(Type::Felt),
+                ],
+            ),
+        )?;
+        let call = self.builder.ins().exec(commit_bytes, &[addr, byte_len], self.span);
+        Ok(self.builder.inst_results(call).to_vec())
+    }
+
+    /// Copies the `byte_len` bytes under `commitment` in the advice map to `dest`
+    fn pipe_bytes(
+        &mut self,
+        commitment: &[Value],
+        dest: Value,
+        byte_len: Value,
+    ) -> Result<(), String> {
+        let pipe_bytes = self.import_intrinsic(
+            PIPE_BYTES,
+            Signature::new(
+                [
+                    AbiParam::new(Type::Felt),
+                    AbiParam::new(Type::Felt),
+                    AbiParam::new(Type::Felt),
+                    AbiParam::new(Type::Felt),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                ],
+                [],
+            ),
+        )?;
+        let mut args = commitment.to_vec();
+        args.extend([dest, byte_len]);
+        self.builder.ins().exec(pipe_bytes, &args, self.span);
+        Ok(())
+    }
+
+    /// Returns the size in bytes of the contents of a list of `len` elements of `elem_ty`
+    fn byte_len(&mut self, elem_ty: &Type, len: Value) -> Value {
+        let (size, align) = size_align(elem_ty);
+        let len = self.bitcast_u32(len);
+        self.builder.ins().mul_imm_checked(
+            len,
+            Immediate::U32(size.next_multiple_of(align)),
+            self.span,
+        )
+    }
+
+    fn load_scalar(&mut self, base: Value, offset: u32, ty: Type) -> Value {
+        let addr = self.addr(base, offset, ty);
+        self.builder.ins().load(addr, self.span)
+    }
+
+    fn store_scalar(&mut self, base: Value, offset: u32, value: Value) {
+        let ty = self.builder.data_flow_graph().value_type(value).clone();
+        let addr = self.addr(base, offset, ty);
+        // The store of an inactive case writes back what is already in memory
+        let value = match self.active {
+            Some(active) => {
+                let current = self.builder.ins().load(addr, self.span);
+                self.builder.ins().select(active, value, current, self.span)
+            }
+            None => value,
+        };
+        self.builder.ins().store(addr, value, self.span);
+    }
+
+    /// Runs `f` with the case `index` of the union with the `i32` `discriminant` marked as active,
+    /// if it is the active case of that union, and all of the enclosing unions
+    fn with_case<R>(
+        &mut self,
+        discriminant: Value,
+        index: usize,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let active = self.is_active(discriminant, index);
+        let enclosing = self.active;
+        self.active = Some(match enclosing {
+            Some(enclosing) => self.builder.ins().and(enclosing, active, self.span),
+            None => active,
+        });
+        let result = f(self);
+        self.active = enclosing;
+        result
+    }
+
+    /// Returns an `i1` which is true if the case `index` is the one identified by the `i32`
+    /// `discriminant`
+    fn is_active(&mut self, discriminant: Value, index: usize) -> Value {
+        let discriminant = self.bitcast_u32(discriminant);
+        self.builder.ins().eq_imm(discriminant, Immediate::U32(index as u32), self.span)
+    }
+
+    /// Places the flattened `values` of the case `index` of a union in the payload `slots` of
+    /// types `slot_tys`, where they are kept if it is the case identified by `discriminant`
+    fn join(
+        &mut self,
+        discriminant: Value,
+        index: usize,
+        slot_tys: &[Type],
+        values: Vec<Value>,
+        slots: &mut Vec<Value>,
+    ) {
+        let active = self.is_active(discriminant, index);
+        for (i, value) in values.into_iter().enumerate() {
+            let slot_ty = &slot_tys[i];
+            // see https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md#flat-lowering
+            let value = match self.builder.data_flow_graph().value_type(value).clone() {
+                ty if &ty == slot_ty => value,
+                Type::I32 if slot_ty == &Type::I64 => {
+                    let value = self.bitcast_u32(value);
+                    let value = self.builder.ins().zext(value, Type::U64, self.span);
+                    self.builder.ins().bitcast(value, Type::I64, self.span)
+                }
+                _ => self.builder.ins().cast(value, slot_ty.clone(), self.span),
+            };
+            match slots.get_mut(i) {
+                Some(slot) => *slot = self.builder.ins().select(active, value, *slot, self.span),
+                None => slots.push(value),
+            }
+        }
+    }
+
+    /// Takes the flattened values of `case` from the payload `slots` of a union
+    fn unjoin(&mut self, case: &Type, slots: &[Value]) -> Result<Vec<Value>, String> {
+        let case_tys = flatten_type(case)?;
+        let mut values = vec![];
+        for (param, slot) in case_tys.into_iter().zip(slots.iter().copied()) {
+            // see https://github.com/WebAssembly/component-model/blob/main/design/mvp/CanonicalABI.md#flat-lifting
+            let value = match self.builder.data_flow_graph().value_type(slot).clone() {
+                ty if ty == param.ty => slot,
+                Type::I64 if param.ty == Type::I32 => {
+                    let slot = self.builder.ins().bitcast(slot, Type::U64, self.span);
+                    let value = self.builder.ins().trunc(slot, Type::U32, self.span);
+                    self.bitcast_i32(value)
+                }
+                _ => self.builder.ins().cast(slot, param.ty, self.span),
+            };
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn addr(&mut self, base: Value, offset: u32, ty: Type) -> Value {
+        let addr = if offset == 0 {
+            base
+        } else {
+            self.builder.ins().add_imm_checked(base, Immediate::U32(offset), self.span)
+        };
+        self.builder.ins().inttoptr(addr, Type::Ptr(Box::new(ty)), self.span)
+    }
+
+    fn import_intrinsic(
+        &mut self,
+        name: &'static str,
+        sig: Signature,
+    ) -> Result<FunctionIdent, String> {
+        self.builder.import_function(MEM_INTRINSICS, name, sig).map_err(|_| {
+            format!("{MEM_INTRINSICS}::{name} is already imported with a different signature")
+        })
+    }
+}
+
+/// Returns `values` from the current function
+pub fn ret_values(builder: &mut ModuleFunctionBuilder<'_>, values: Vec<Value>, span: SourceSpan) {
+    let mut args = ValueList::default();
+    args.extend(values, &mut builder.data_flow_graph_mut().value_lists);
+    builder.ins().Ret(args, span);
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::StructType;
+
+    use super::*;
+
+    fn string() -> Type {
+        Type::List(Box::new(Type::U8))
+    }
+
+    fn option(ty: Type) -> Type {
+        Type::Union(UnionType::new(Type::U8, [Type::Unit, ty]))
+    }
+
+    fn result(ok: Type, err: Type) -> Type {
+        Type::Union(UnionType::new(Type::U8, [ok, err]))
+    }
+
+    fn union_ty(ty: &Type) -> &UnionType {
+        match ty {
+            Type::Union(union_ty) => union_ty,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tuple_layout_pads_fields() {
+        let (offsets, layout) = tuple_layout(&[Type::U8, Type::U32, Type::U16]);
+        assert_eq!(offsets, vec![0, 4, 8]);
+        assert_eq!(layout, (12, 4));
+
+        let (offsets, layout) = tuple_layout(&[Type::U8, Type::U64]);
+        assert_eq!(offsets, vec![0, 8]);
+        assert_eq!(layout, (16, 8));
+
+        let record = Type::Struct(StructType::new([Type::U16, string()]));
+        assert_eq!(size_align(&record), (12, 4));
+    }
+
+    #[test]
+    fn tuple_layout_of_spilled_strings_in_aggregates() {
+        // `record { name: string, id: u32 }`, as stored in the return area
+        let record = Type::Struct(StructType::new([string(), Type::U32]));
+        let (offsets, layout) = tuple_layout(&[record.clone()]);
+        assert_eq!(offsets, vec![0]);
+        assert_eq!(layout, (12, 4));
+
+        // The tuple of spilled params holding such records
+        let (offsets, layout) = tuple_layout(&vec![record.clone(); 6]);
+        assert_eq!(offsets, vec![0, 12, 24, 36, 48, 60]);
+        assert_eq!(layout, (72, 4));
+
+        // `tuple<u8, string, u64>` is padded before the string and the integer
+        let tuple = Type::Struct(StructType::new([Type::U8, string(), Type::U64]));
+        let (offsets, layout) = tuple_layout(&[Type::U8, string(), Type::U64]);
+        assert_eq!(offsets, vec![0, 4, 16]);
+        assert_eq!(layout, (24, 8));
+        assert_eq!(size_align(&tuple), (24, 8));
+
+        // `option<record { name: string, id: u32 }>` and a list of such records
+        let ty = option(record.clone());
+        assert_eq!(union_layout(union_ty(&ty)), (4, (16, 4)));
+        assert_eq!(size_align(&Type::List(Box::new(record))), (8, 4));
+    }
+
+    #[test]
+    fn union_layout_aligns_payload() {
+        let ty = option(Type::U64);
+        assert_eq!(union_layout(union_ty(&ty)), (8, (16, 8)));
+        let ty = result(string(), Type::U32);
+        assert_eq!(union_layout(union_ty(&ty)), (4, (12, 4)));
+        let ty = result(Type::U8, Type::Unit);
+        assert_eq!(union_layout(union_ty(&ty)), (1, (2, 1)));
+        let ty = option(Type::Unit);
+        assert_eq!(union_layout(union_ty(&ty)), (1, (1, 1)));
+    }
+
+    #[test]
+    fn commitments_of_nested_lists() {
+        assert_eq!(commitments_size(&Type::U32), 0);
+        assert_eq!(commitments_size(&string()), 16);
+        // The commitment to the contents of the list, and the one to the commitments of its strings
+        assert_eq!(commitments_size(&Type::List(Box::new(string()))), 32);
+        // Both cases of a union carry commitments, whichever is active
+        assert_eq!(commitments_size(&result(string(), string())), 32);
+        assert_eq!(commitments_size(&option(Type::U32)), 0);
+    }
+}
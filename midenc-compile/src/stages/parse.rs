@@ -58,6 +58,7 @@ impl Stage for ParseStage {
                     session,
                     &WasmTranslationConfig {
                         source_name: name.as_str().to_string().into(),
+                        soft_float: session.options.soft_float,
                         ..Default::default()
                     },
                 ),
@@ -66,6 +67,7 @@ impl Stage for ParseStage {
                     session,
                     &WasmTranslationConfig {
                         source_name: name.as_str().to_string().into(),
+                        soft_float: session.options.soft_float,
                         ..Default::default()
                     },
                 ),
@@ -118,6 +120,7 @@ impl ParseStage {
         let file_name = path.file_stem().unwrap().to_str().unwrap().to_owned();
         let config = wasm::WasmTranslationConfig {
             source_name: file_name.into(),
+            soft_float: session.options.soft_float,
             ..Default::default()
         };
         self.parse_hir_from_wasm_bytes(&bytes, session, &config)
@@ -146,6 +149,7 @@ impl ParseStage {
         let file_name = path.file_stem().unwrap().to_str().unwrap().to_owned();
         let config = WasmTranslationConfig {
             source_name: file_name.into(),
+            soft_float: session.options.soft_float,
             ..Default::default()
         };
         let wasm = wat::parse_file(path).into_diagnostic().wrap_err("failed to parse wat")?;
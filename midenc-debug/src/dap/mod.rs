@@ -0,0 +1,4 @@
+mod protocol;
+mod server;
+
+pub use self::server::DapServer;
@@ -0,0 +1,303 @@
+//! The subset of the [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/specification)
+//! understood by [super::DapServer]
+
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A request sent by the client
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub seq: i64,
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// A message sent to the client
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Message {
+    Response {
+        seq: i64,
+        request_seq: i64,
+        success: bool,
+        command: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(skip_serializing_if = "Value::is_null")]
+        body: Value,
+    },
+    Event {
+        seq: i64,
+        event: String,
+        #[serde(skip_serializing_if = "Value::is_null")]
+        body: Value,
+    },
+}
+
+/// Read the next message from `input`, returning `None` once the client closes the stream
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Request>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(len) = header.strip_prefix("Content-Length:") {
+            content_length = len.trim().parse::<usize>().ok();
+        }
+    }
+    let len = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut content = vec![0; len];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content).map(Some).map_err(io::Error::from)
+}
+
+/// Write `message` to `output`
+pub fn write_message<W: Write>(output: &mut W, message: &Message) -> io::Result<()> {
+    let content = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub supports_configuration_done_request: bool,
+    pub supports_step_back: bool,
+    pub supports_read_memory_request: bool,
+    pub supports_terminate_request: bool,
+    pub supports_conditional_breakpoints: bool,
+    pub supports_hit_conditional_breakpoints: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchArguments {
+    #[serde(default)]
+    pub stop_on_entry: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetBreakpointsArguments {
+    pub source: Source,
+    #[serde(default)]
+    pub breakpoints: Vec<SourceBreakpoint>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceBreakpoint {
+    pub line: u32,
+    #[serde(default)]
+    pub condition: Option<String>,
+    #[serde(default)]
+    pub hit_condition: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Breakpoint {
+    pub id: i64,
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub line: u32,
+    pub source: Source,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Thread {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackTraceArguments {
+    #[serde(default)]
+    pub start_frame: Option<usize>,
+    #[serde(default)]
+    pub levels: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackFrame {
+    pub id: i64,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scope {
+    pub name: String,
+    pub variables_reference: i64,
+    pub expensive: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariablesArguments {
+    pub variables_reference: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    pub variables_reference: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadMemoryArguments {
+    pub memory_reference: String,
+    #[serde(default)]
+    pub offset: i64,
+    pub count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateArguments {
+    pub expression: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoppedEvent {
+    pub reason: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub thread_id: i64,
+    pub all_threads_stopped: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hit_breakpoint_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputEvent {
+    pub category: &'static str,
+    pub output: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn frame(content: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{content}", content.len())
+    }
+
+    #[test]
+    fn read_consecutive_messages() {
+        let input = [
+            frame(r#"{"seq":1,"type":"request","command":"initialize","arguments":{"a":1}}"#),
+            // Other headers are ignored, in any order
+            format!(
+                "Content-Type: application/vscode-jsonrpc; charset=utf-8\r\n{}",
+                frame(r#"{"seq":2,"type":"request","command":"threads"}"#)
+            ),
+        ]
+        .concat();
+        let mut input = Cursor::new(input.into_bytes());
+
+        let request = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(request.seq, 1);
+        assert_eq!(request.command, "initialize");
+        assert_eq!(request.arguments, json!({ "a": 1 }));
+
+        let request = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(request.seq, 2);
+        assert_eq!(request.command, "threads");
+        assert_eq!(request.arguments, Value::Null);
+
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn read_multibyte_content() {
+        // The length is in bytes, not characters
+        let content = r#"{"seq":1,"command":"evaluate","arguments":{"expression":"é"}}"#;
+        let input = format!("{}{}", frame(content), frame(r#"{"seq":2,"command":"next"}"#));
+        let mut input = Cursor::new(input.into_bytes());
+
+        let request = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(request.arguments, json!({ "expression": "é" }));
+        let request = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(request.command, "next");
+    }
+
+    #[test]
+    fn read_invalid_messages() {
+        let mut input = Cursor::new(b"Content-Type: text/plain\r\n\r\n{}".to_vec());
+        let err = read_message(&mut input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut input = Cursor::new(frame(r#"{"seq":1}"#).into_bytes());
+        assert!(read_message(&mut input).is_err());
+
+        // The stream ends before the content does
+        let mut input = Cursor::new(b"Content-Length: 100\r\n\r\n{}".to_vec());
+        assert!(read_message(&mut input).is_err());
+    }
+
+    #[test]
+    fn write_messages() {
+        let mut output = vec![];
+        let response = Message::Response {
+            seq: 1,
+            request_seq: 7,
+            success: true,
+            command: "launch".to_string(),
+            message: None,
+            body: Value::Null,
+        };
+        write_message(&mut output, &response).unwrap();
+        let event = Message::Event {
+            seq: 2,
+            event: "stopped".to_string(),
+            body: json!({ "reason": "entry" }),
+        };
+        write_message(&mut output, &event).unwrap();
+
+        // Fields without a value are omitted
+        let expected = [
+            frame(
+                r#"{"type":"response","seq":1,"request_seq":7,"success":true,"command":"launch"}"#,
+            ),
+            frame(r#"{"type":"event","seq":2,"event":"stopped","body":{"reason":"entry"}}"#),
+        ]
+        .concat();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -0,0 +1,723 @@
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use glob::Pattern;
+use miden_processor::{Felt, RowIndex};
+use midenc_codegen_masm::NativePtr;
+use midenc_session::diagnostics::{IntoDiagnostic, Report};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::protocol::*;
+use crate::{ui::State, BreakpointExpr, BreakpointType, Condition, ReadMemoryExpr};
+
+/// The DAP client debugs a single thread of execution, i.e. the VM
+const THREAD_ID: i64 = 1;
+
+/// The variables reference of the operand stack
+const OPERAND_STACK: i64 = 1;
+const LOCALS: i64 = 2;
+
+/// A server for the Debug Adapter Protocol, which drives the same [State] as the TUI.
+///
+/// Execution only advances in response to requests, so the program is always stopped while the
+/// server waits for the next request from the client.
+pub struct DapServer {
+    state: State,
+    seq: i64,
+    /// Events to send once the response to the current request has been sent
+    events: Vec<Message>,
+    stop_on_entry: bool,
+    terminated: bool,
+    disconnected: bool,
+}
+
+impl DapServer {
+    pub fn new(state: State) -> Self {
+        Self {
+            state,
+            seq: 0,
+            events: vec![],
+            stop_on_entry: false,
+            terminated: false,
+            disconnected: false,
+        }
+    }
+
+    /// Serve requests read from `input`, until the client disconnects
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> Result<(), Report> {
+        while !self.disconnected {
+            let Some(request) = read_message(&mut input).into_diagnostic()? else {
+                break;
+            };
+            log::debug!("received '{}' request", &request.command);
+            let (success, message, body) = match self.handle(&request) {
+                Ok(body) => (true, None, body),
+                Err(err) => (false, Some(err), Value::Null),
+            };
+            let response = Message::Response {
+                seq: self.next_seq(),
+                request_seq: request.seq,
+                success,
+                command: request.command,
+                message,
+                body,
+            };
+            write_message(&mut output, &response).into_diagnostic()?;
+            for event in core::mem::take(&mut self.events) {
+                write_message(&mut output, &event).into_diagnostic()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: &Request) -> Result<Value, String> {
+        match request.command.as_str() {
+            "initialize" => {
+                self.event("initialized", Value::Null);
+                to_body(Capabilities {
+                    supports_configuration_done_request: true,
+                    supports_step_back: true,
+                    supports_read_memory_request: true,
+                    supports_terminate_request: true,
+                    supports_conditional_breakpoints: true,
+                    supports_hit_conditional_breakpoints: true,
+                })
+            }
+            "launch" | "attach" => {
+                let args = arguments::<LaunchArguments>(request).unwrap_or_default();
+                self.stop_on_entry = args.stop_on_entry;
+                Ok(Value::Null)
+            }
+            "setBreakpoints" => self.set_breakpoints(arguments(request)?),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                if self.stop_on_entry {
+                    self.stopped("entry", None, vec![]);
+                } else {
+                    self.resume();
+                }
+                Ok(Value::Null)
+            }
+            "threads" => Ok(json!({
+                "threads": [Thread { id: THREAD_ID, name: "main".to_string() }]
+            })),
+            "stackTrace" => self.stack_trace(arguments(request).unwrap_or_default()),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    Scope {
+                        name: "Locals".to_string(),
+                        variables_reference: LOCALS,
+                        expensive: false,
+                    },
+                    Scope {
+                        name: "Operand Stack".to_string(),
+                        variables_reference: OPERAND_STACK,
+                        expensive: false,
+                    },
+                ]
+            })),
+            "variables" => self.variables(arguments(request)?),
+            "continue" => {
+                self.resume();
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" => self.step(BreakpointType::Next),
+            "stepIn" => self.step(BreakpointType::Step),
+            "stepOut" => self.step(BreakpointType::Finish),
+            "stepBack" => {
+                self.state.reverse_next().map_err(|err| err.to_string())?;
+                self.stopped("step", None, vec![]);
+                Ok(Value::Null)
+            }
+            "reverseContinue" => self.reverse_continue(),
+            "pause" => {
+                self.stopped("pause", None, vec![]);
+                Ok(Value::Null)
+            }
+            "readMemory" => self.read_memory(arguments(request)?),
+            "evaluate" => self.evaluate(arguments(request)?),
+            "disconnect" | "terminate" => {
+                self.disconnected = true;
+                Ok(Value::Null)
+            }
+            command => Err(format!("unsupported request '{command}'")),
+        }
+    }
+
+    fn set_breakpoints(&mut self, args: SetBreakpointsArguments) -> Result<Value, String> {
+        let path = args.source.path.clone().ok_or("expected a source path")?;
+        let pattern = source_pattern(Path::new(&path), &self.state.session.options.current_dir)
+            .map_err(|err| format!("invalid source path: {err}"))?;
+
+        // The client always sends the full set of breakpoints for a source
+        self.state.breakpoints.retain(
+            |bp| !matches!(&bp.ty, BreakpointType::Line { pattern: p, .. } if p == &pattern),
+        );
+        let mut breakpoints = vec![];
+        for bp in args.breakpoints {
+            match source_breakpoint(&bp, pattern.clone()) {
+                Ok(expr) => {
+                    self.state.create_conditional_breakpoint(expr);
+                    let id =
+                        self.state.breakpoints.last().map(|bp| bp.id as i64).unwrap_or_default();
+                    breakpoints.push(Breakpoint {
+                        id,
+                        verified: true,
+                        message: None,
+                        line: bp.line,
+                        source: args.source.clone(),
+                    });
+                }
+                Err(err) => breakpoints.push(Breakpoint {
+                    id: 0,
+                    verified: false,
+                    message: Some(err),
+                    line: bp.line,
+                    source: args.source.clone(),
+                }),
+            }
+        }
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn stack_trace(&self, args: StackTraceArguments) -> Result<Value, String> {
+        let session = &self.state.session;
+        let frames = self.state.executor.callstack.frames();
+        let stack_frames = frames
+            .iter()
+            .rev()
+            .enumerate()
+            .skip(args.start_frame.unwrap_or(0))
+            .take(args.levels.filter(|n| *n > 0).unwrap_or(frames.len()))
+            .map(|(id, frame)| {
+                let name = frame
+                    .procedure(session.name())
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| "<unknown>".to_string());
+                match frame.last_resolved(session) {
+                    Some(loc) => {
+                        let path = loc.source_file.path();
+                        StackFrame {
+                            id: id as i64,
+                            name,
+                            source: Some(Source {
+                                name: path.file_name().map(|name| name.to_string_lossy().into()),
+                                path: Some(path.display().to_string()),
+                            }),
+                            line: loc.line,
+                            column: loc.col,
+                        }
+                    }
+                    None => StackFrame {
+                        id: id as i64,
+                        name,
+                        source: None,
+                        line: 0,
+                        column: 0,
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(json!({ "stackFrames": stack_frames, "totalFrames": frames.len() }))
+    }
+
+    fn variables(&self, args: VariablesArguments) -> Result<Value, String> {
+        let variables = match self.state.executor.last.as_ref() {
+            Some(last) if args.variables_reference == OPERAND_STACK => last
+                .stack
+                .iter()
+                .enumerate()
+                .map(|(i, elem)| Variable {
+                    name: format!("[{i}]"),
+                    value: elem.as_int().to_string(),
+                    variables_reference: 0,
+                })
+                .collect(),
+            _ if args.variables_reference == LOCALS => self
+                .state
+                .variable_names()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|name| Variable {
+                    name: name.to_string(),
+                    value: self
+                        .state
+                        .read_variable(name)
+                        .map(|(_, value)| value)
+                        .unwrap_or_else(|err| err),
+                    variables_reference: 0,
+                })
+                .collect(),
+            _ => vec![],
+        };
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Read `count` bytes of linear memory starting at the byte address `memoryReference + offset`
+    fn read_memory(&self, args: ReadMemoryArguments) -> Result<Value, String> {
+        let base = parse_memory_reference(&args.memory_reference)?;
+        let addr = u32::try_from(base + args.offset)
+            .map_err(|_| "memory reference is out of bounds".to_string())?;
+        let ctx = self.state.executor.current_context;
+        let clk = RowIndex::from(self.state.executor.cycle);
+
+        // Memory is read one 32-bit element at a time, from the element containing `addr`
+        let mut bytes = Vec::with_capacity(args.count as usize + 4);
+        let skip = (addr % 4) as usize;
+        let mut chunk = addr - skip as u32;
+        while bytes.len() < skip + args.count as usize {
+            let ptr = NativePtr::from_ptr(chunk);
+            let elem = self
+                .state
+                .execution_trace
+                .read_memory_element_in_context(ptr.waddr, ptr.index, ctx, clk)
+                .unwrap_or(Felt::new(0));
+            bytes.extend(((elem.as_int() & u32::MAX as u64) as u32).to_be_bytes());
+            chunk = match chunk.checked_add(4) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        let bytes = bytes.get(skip..).unwrap_or_default();
+        let bytes = &bytes[..bytes.len().min(args.count as usize)];
+        Ok(json!({
+            "address": format!("{addr:#x}"),
+            "data": base64_encode(bytes),
+            "unreadableBytes": args.count as usize - bytes.len(),
+        }))
+    }
+
+    /// Evaluate a debugger command, using the same syntax as the TUI command prompt
+    fn evaluate(&self, args: EvaluateArguments) -> Result<Value, String> {
+        let expression = args.expression.trim();
+        let expression = expression.strip_prefix(':').unwrap_or(expression);
+        let result = match expression.split_once(' ') {
+            Some(("r" | "read", rest)) => {
+                self.state.read_memory(&rest.parse::<ReadMemoryExpr>()?)?
+            }
+            Some(("p" | "print", name)) => self.state.print_variable(name.trim())?,
+            // Anything else is the name of a variable, e.g. when hovering over it in the editor
+            None => self.state.read_variable(expression)?.1,
+            _ => return Err(format!("unsupported expression '{expression}'")),
+        };
+        Ok(json!({ "result": result, "variablesReference": 0 }))
+    }
+
+    fn step(&mut self, ty: BreakpointType) -> Result<Value, String> {
+        if !self.state.executor.stopped {
+            self.state.create_breakpoint(ty);
+        }
+        self.resume();
+        Ok(Value::Null)
+    }
+
+    /// Move execution back to the last cycle at which a source breakpoint was hit, or to the start
+    /// of the program if there is no such cycle
+    fn reverse_continue(&mut self) -> Result<Value, String> {
+        let breakpoints = self.state.breakpoints.clone();
+        let session = self.state.session.clone();
+        let rewound = self
+            .state
+            .rewind_to_last(|executor| {
+                let Some(loc) = executor
+                    .callstack
+                    .current_frame()
+                    .and_then(|frame| frame.recent().back())
+                    .and_then(|detail| detail.resolve(&session))
+                else {
+                    return false;
+                };
+                breakpoints.iter().any(|bp| bp.should_break_at(loc))
+            })
+            .map_err(|err| err.to_string())?;
+        if rewound {
+            self.stopped("breakpoint", None, vec![]);
+        } else {
+            self.state.goto_cycle(0).map_err(|err| err.to_string())?;
+            self.stopped("entry", None, vec![]);
+        }
+        Ok(Value::Null)
+    }
+
+    /// Resume execution, and report why it stopped once it does
+    fn resume(&mut self) {
+        if !self.state.executor.stopped {
+            self.state.resume();
+        } else if !self.terminated {
+            // The client was told about the failure, and resumed anyway
+            self.terminate();
+            return;
+        }
+
+        if !self.state.executor.stopped {
+            let hit = self
+                .state
+                .breakpoints_hit
+                .drain(..)
+                .filter(|bp| !bp.is_internal())
+                .map(|bp| bp.id as i64)
+                .collect::<Vec<_>>();
+            let reason = if hit.is_empty() { "step" } else { "breakpoint" };
+            self.stopped(reason, None, hit);
+        } else if let Some(err) = self.state.execution_failed.as_ref() {
+            // Stop at the failure, so the client can inspect the state of the program
+            let description = err.to_string();
+            self.output("stderr", format!("{description}\n"));
+            self.stopped("exception", Some(description), vec![]);
+        } else {
+            self.terminate();
+        }
+    }
+
+    fn terminate(&mut self) {
+        let exit_code = if self.state.execution_failed.is_some() {
+            1
+        } else {
+            0
+        };
+        self.event("exited", json!({ "exitCode": exit_code }));
+        self.event("terminated", Value::Null);
+        self.terminated = true;
+    }
+
+    fn stopped(&mut self, reason: &'static str, description: Option<String>, hit: Vec<i64>) {
+        let body = StoppedEvent {
+            reason,
+            description,
+            thread_id: THREAD_ID,
+            all_threads_stopped: true,
+            hit_breakpoint_ids: hit,
+        };
+        self.event("stopped", serde_json::to_value(body).unwrap_or_default());
+    }
+
+    fn output(&mut self, category: &'static str, output: String) {
+        let body = OutputEvent { category, output };
+        self.event("output", serde_json::to_value(body).unwrap_or_default());
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        let seq = self.next_seq();
+        self.events.push(Message::Event {
+            seq,
+            event: event.to_string(),
+            body,
+        });
+    }
+
+    fn next_seq(&mut self) -> i64 {
+        self.seq += 1;
+        self.seq
+    }
+}
+
+fn arguments<T: serde::de::DeserializeOwned>(request: &Request) -> Result<T, String> {
+    serde_json::from_value(request.arguments.clone())
+        .map_err(|err| format!("invalid arguments for '{}': {err}", &request.command))
+}
+
+fn to_body<T: Serialize>(body: T) -> Result<Value, String> {
+    serde_json::to_value(body).map_err(|err| err.to_string())
+}
+
+/// Returns the breakpoint described by `bp`, a line breakpoint in the file matched by `pattern`
+///
+/// Only hit conditions of the form `N` are supported, i.e. break on the Nth hit.
+fn source_breakpoint(bp: &SourceBreakpoint, pattern: Pattern) -> Result<BreakpointExpr, String> {
+    let condition = bp
+        .condition
+        .as_deref()
+        .filter(|condition| !condition.trim().is_empty())
+        .map(|condition| condition.parse::<Condition>())
+        .transpose()?;
+    let ignore_count = match bp.hit_condition.as_deref().map(str::trim) {
+        None | Some("") => 0,
+        Some(hits) => match hits.parse::<usize>() {
+            Ok(n) if n > 0 => n - 1,
+            _ => {
+                return Err(format!("invalid hit condition '{hits}': expected a positive integer"))
+            }
+        },
+    };
+    Ok(BreakpointExpr {
+        ty: BreakpointType::Line {
+            pattern,
+            line: bp.line,
+        },
+        condition,
+        ignore_count,
+    })
+}
+
+/// Returns the pattern matching the source locations in the file at `path`
+///
+/// Source locations are usually relative to the directory the program was compiled from, so when
+/// `path` is in the working directory, the pattern matches any path ending with its relative path.
+fn source_pattern(path: &Path, cwd: &Path) -> Result<Pattern, glob::PatternError> {
+    match path.strip_prefix(cwd) {
+        Ok(relative) => {
+            Pattern::new(&format!("**/{}", Pattern::escape(&relative.to_string_lossy())))
+        }
+        Err(_) => Pattern::new(&Pattern::escape(&path.to_string_lossy())),
+    }
+}
+
+fn parse_memory_reference(s: &str) -> Result<i64, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => s.parse::<i64>(),
+    }
+    .map_err(|err| format!("invalid memory reference: {err}"))
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - i * 8)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::TestProgram;
+
+    /// A program whose procedures emit the frame events emitted by the compiler
+    const PROGRAM: &str = "\
+proc.add_two
+    trace.240
+    nop
+    push.2
+    add
+    trace.252
+    nop
+end
+
+begin
+    trace.240
+    nop
+    push.1
+    exec.add_two
+    push.3
+    mul
+    trace.252
+    nop
+end
+";
+
+    /// The line of `add` in [PROGRAM]
+    const ADD_LINE: u32 = 5;
+
+    /// Frames each request of `requests`, numbering them from 1
+    fn script(requests: &[(&str, Value)]) -> Vec<u8> {
+        let mut input = vec![];
+        for (seq, (command, arguments)) in requests.iter().enumerate() {
+            let content = json!({
+                "seq": seq + 1,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            })
+            .to_string();
+            write!(input, "Content-Length: {}\r\n\r\n{content}", content.len()).unwrap();
+        }
+        input
+    }
+
+    /// Splits the framed messages of `output`
+    fn messages(output: &[u8]) -> Vec<Value> {
+        let mut output = Cursor::new(output);
+        let mut messages = vec![];
+        loop {
+            let mut header = String::new();
+            if output.read_line(&mut header).unwrap() == 0 {
+                break messages;
+            }
+            let len = header.trim().strip_prefix("Content-Length: ").unwrap().parse().unwrap();
+            output.read_line(&mut header).unwrap();
+            let mut content = vec![0; len];
+            std::io::Read::read_exact(&mut output, &mut content).unwrap();
+            messages.push(serde_json::from_slice(&content).unwrap());
+        }
+    }
+
+    /// Returns the response to the request `request_seq`, followed by the events sent after it
+    fn response(messages: &[Value], request_seq: i64) -> (&Value, Vec<&Value>) {
+        let index = messages
+            .iter()
+            .position(|message| message["request_seq"] == request_seq)
+            .unwrap_or_else(|| panic!("no response to request {request_seq}"));
+        let events = messages[index + 1..]
+            .iter()
+            .take_while(|message| message["type"] == "event")
+            .collect();
+        (&messages[index], events)
+    }
+
+    fn event<'a>(events: &[&'a Value], name: &str) -> &'a Value {
+        events
+            .iter()
+            .find(|event| event["event"] == name)
+            .unwrap_or_else(|| panic!("expected a '{name}' event, got {events:?}"))
+    }
+
+    #[test]
+    fn breakpoint_session() {
+        let program = TestProgram::new("dap_breakpoint_session", PROGRAM);
+        let source = program.source_path.display().to_string();
+        let input = script(&[
+            ("initialize", json!({ "adapterID": "miden" })),
+            ("launch", json!({})),
+            (
+                "setBreakpoints",
+                json!({ "source": { "path": source }, "breakpoints": [{ "line": ADD_LINE }] }),
+            ),
+            ("configurationDone", Value::Null),
+            ("threads", Value::Null),
+            ("stackTrace", json!({ "threadId": THREAD_ID })),
+            ("variables", json!({ "variablesReference": OPERAND_STACK })),
+            ("continue", json!({ "threadId": THREAD_ID })),
+            ("unknownRequest", Value::Null),
+            ("disconnect", Value::Null),
+            // Requests after the client disconnected are not served
+            ("threads", Value::Null),
+        ]);
+        let mut output = vec![];
+        let mut server = DapServer::new(program.state(vec![]));
+        server.run(Cursor::new(input), &mut output).unwrap();
+        let messages = messages(&output);
+
+        // Every message has its own sequence number
+        let seqs = messages.iter().map(|message| message["seq"].as_i64().unwrap());
+        assert!(seqs.clone().zip(seqs.skip(1)).all(|(a, b)| a < b));
+
+        let (initialize, events) = response(&messages, 1);
+        assert_eq!(initialize["success"], true);
+        assert_eq!(initialize["body"]["supportsStepBack"], true);
+        event(&events, "initialized");
+
+        let (set_breakpoints, _) = response(&messages, 3);
+        let breakpoints = set_breakpoints["body"]["breakpoints"].as_array().unwrap();
+        assert_eq!(breakpoints.len(), 1);
+        assert_eq!(breakpoints[0]["verified"], true);
+        assert_eq!(breakpoints[0]["line"], ADD_LINE);
+        let id = breakpoints[0]["id"].clone();
+
+        // Execution stops at the breakpoint
+        let (_, events) = response(&messages, 4);
+        let stopped = event(&events, "stopped");
+        assert_eq!(stopped["body"]["reason"], "breakpoint");
+        assert_eq!(stopped["body"]["hitBreakpointIds"], json!([id]));
+
+        let (threads, _) = response(&messages, 5);
+        assert_eq!(threads["body"]["threads"], json!([{ "id": THREAD_ID, "name": "main" }]));
+
+        // The innermost frame is the procedure containing the breakpoint
+        let (stack_trace, _) = response(&messages, 6);
+        let frames = stack_trace["body"]["stackFrames"].as_array().unwrap();
+        assert!(frames.len() >= 2, "unexpected stack frames: {frames:?}");
+        assert!(frames[0]["name"].as_str().unwrap().contains("add_two"));
+        assert_eq!(frames[0]["line"], ADD_LINE);
+        assert_eq!(frames[0]["source"]["path"], source);
+
+        // `add` has been executed
+        let (variables, _) = response(&messages, 7);
+        let variables = variables["body"]["variables"].as_array().unwrap();
+        assert_eq!(variables[0], json!({ "name": "[0]", "value": "3", "variablesReference": 0 }));
+
+        // The program then runs to completion
+        let (resumed, events) = response(&messages, 8);
+        assert_eq!(resumed["body"]["allThreadsContinued"], true);
+        assert_eq!(event(&events, "exited")["body"]["exitCode"], 0);
+        event(&events, "terminated");
+
+        let (unknown, _) = response(&messages, 9);
+        assert_eq!(unknown["success"], false);
+        assert!(unknown["message"].as_str().unwrap().contains("unsupported request"));
+
+        response(&messages, 10);
+        assert!(messages.iter().all(|message| message["request_seq"] != 11));
+    }
+
+    #[test]
+    fn stop_on_entry_and_step() {
+        let program = TestProgram::new("dap_stop_on_entry_and_step", PROGRAM);
+        let input = script(&[
+            ("initialize", Value::Null),
+            ("launch", json!({ "stopOnEntry": true })),
+            ("configurationDone", Value::Null),
+            ("variables", json!({ "variablesReference": OPERAND_STACK })),
+            ("next", json!({ "threadId": THREAD_ID })),
+            ("variables", json!({ "variablesReference": OPERAND_STACK })),
+            ("disconnect", Value::Null),
+        ]);
+        let mut output = vec![];
+        let mut server = DapServer::new(program.state(vec![Felt::new(5)]));
+        server.run(Cursor::new(input), &mut output).unwrap();
+        let messages = messages(&output);
+
+        let (_, events) = response(&messages, 3);
+        assert_eq!(event(&events, "stopped")["body"]["reason"], "entry");
+
+        // Nothing has been executed yet
+        let (variables, _) = response(&messages, 4);
+        assert!(variables["body"]["variables"].as_array().unwrap().is_empty());
+
+        let (_, events) = response(&messages, 5);
+        assert_eq!(event(&events, "stopped")["body"]["reason"], "step");
+
+        // The program operands are on the stack once execution starts
+        let (variables, _) = response(&messages, 6);
+        let variables = variables["body"]["variables"].as_array().unwrap();
+        assert!(variables.iter().any(|variable| variable["value"] == "5"));
+    }
+
+    #[test]
+    fn source_breakpoint_conditions() {
+        let pattern = Pattern::new("**/test.masm").unwrap();
+        let bp = SourceBreakpoint {
+            line: 3,
+            condition: Some("  ".to_string()),
+            hit_condition: Some("4".to_string()),
+        };
+        let expr = source_breakpoint(&bp, pattern.clone()).unwrap();
+        assert!(expr.condition.is_none());
+        assert_eq!(expr.ignore_count, 3);
+
+        for hits in ["0", "-1", ">= 2"] {
+            let bp = SourceBreakpoint {
+                line: 3,
+                condition: None,
+                hit_condition: Some(hits.to_string()),
+            };
+            assert!(source_breakpoint(&bp, pattern.clone()).is_err(), "{hits}");
+        }
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xff, 0xfe]), "//4=");
+    }
+}
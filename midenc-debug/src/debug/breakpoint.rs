@@ -3,13 +3,19 @@ use std::{ops::Deref, str::FromStr};
 use glob::Pattern;
 use miden_processor::VmState;
 
-use super::ResolvedLocation;
+use super::{Condition, MemoryAccess, ResolvedLocation, WatchExpr};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Breakpoint {
     pub id: u8,
     pub creation_cycle: usize,
     pub ty: BreakpointType,
+    /// If set, the breakpoint is only hit when this condition holds
+    pub condition: Option<Condition>,
+    /// The number of times the breakpoint is triggered without being hit
+    pub ignore_count: usize,
+    /// The number of times the breakpoint was triggered, and its condition held
+    pub hit_count: usize,
 }
 
 impl Default for Breakpoint {
@@ -18,6 +24,9 @@ impl Default for Breakpoint {
             id: 0,
             creation_cycle: 0,
             ty: BreakpointType::Step,
+            condition: None,
+            ignore_count: 0,
+            hit_count: 0,
         }
     }
 }
@@ -66,6 +75,8 @@ pub enum BreakpointType {
     Opcode(miden_core::Operation),
     /// Break when any cycle causes us to push a frame for PROCEDURE on the call stack
     Called(Pattern),
+    /// Break when any cycle accesses the watched range of memory
+    Watch(WatchExpr),
 }
 impl BreakpointType {
     /// Return true if this breakpoint indicates we should break for `current_op`
@@ -95,6 +106,14 @@ impl BreakpointType {
         }
     }
 
+    /// Return true if this breakpoint indicates we should break on `access`
+    pub fn should_break_on(&self, access: &MemoryAccess) -> bool {
+        match self {
+            Self::Watch(expr) => expr.should_break_on(access),
+            _ => false,
+        }
+    }
+
     /// Returns true if this breakpoint is internal to the debugger (i.e. not creatable via :b)
     pub fn is_internal(&self) -> bool {
         matches!(self, BreakpointType::Next | BreakpointType::Step | BreakpointType::Finish)
@@ -113,6 +132,46 @@ impl BreakpointType {
     }
 }
 
+/// A breakpoint as specified via `:break`, i.e. `SPEC [if CONDITION] [ignore N]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakpointExpr {
+    pub ty: BreakpointType,
+    pub condition: Option<Condition>,
+    pub ignore_count: usize,
+}
+impl FromStr for BreakpointExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        // b {spec} [if {condition}] [ignore {n}]
+        let (s, ignore_count) = match s.rsplit_once(" ignore ") {
+            Some((rest, n)) => {
+                let n = n.trim().parse::<usize>().map_err(|err| {
+                    format!("invalid breakpoint expression: could not parse ignore count: {err}")
+                })?;
+                (rest, n)
+            }
+            None => (s, 0),
+        };
+        let (s, condition) = match s.split_once(" if ") {
+            Some((rest, condition)) => (rest, Some(condition.parse::<Condition>()?)),
+            None => (s, None),
+        };
+        let ty = s.parse::<BreakpointType>()?;
+        if ty.is_one_shot() && (condition.is_some() || ignore_count > 0) {
+            return Err("invalid breakpoint expression: cycle breakpoints cannot be conditional"
+                .to_string());
+        }
+        Ok(Self {
+            ty,
+            condition,
+            ignore_count,
+        })
+    }
+}
+
 impl FromStr for BreakpointType {
     type Err = String;
 
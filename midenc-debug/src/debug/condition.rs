@@ -0,0 +1,640 @@
+use std::{
+    fmt,
+    iter::Peekable,
+    str::{CharIndices, FromStr},
+};
+
+use miden_processor::Felt;
+
+use super::{FormatType, ReadMemoryExpr};
+
+/// Provides access to the state of the program when evaluating a [Condition]
+pub trait ConditionEnv {
+    /// Get the element of the operand stack at `index`, where `0` is the top of the stack
+    fn stack_item(&self, index: usize) -> Option<Felt>;
+    /// Evaluate a `read` expression against the memory of the current context
+    fn read_memory(&self, expr: &ReadMemoryExpr) -> Result<String, String>;
+    /// Get the name of the current procedure, if known
+    fn procedure(&self) -> Option<String>;
+}
+
+/// A condition attached to a breakpoint, which must hold for the breakpoint to be hit
+///
+/// Conditions are boolean expressions over the state of the program, e.g.
+/// `s0 == 0x1234 && proc == "my_crate::*"`, see the debugger documentation
+/// for what is supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = s.trim();
+        let mut parser = Parser {
+            source,
+            chars: source.char_indices().peekable(),
+        };
+        let expr = parser.parse_or()?;
+        parser.skip_whitespace();
+        if let Some((pos, _)) = parser.chars.peek() {
+            return Err(format!("invalid condition: unexpected input '{}'", &source[*pos..]));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+}
+
+impl Condition {
+    /// Evaluate this condition against the state of the program provided by `env`
+    pub fn evaluate(&self, env: &impl ConditionEnv) -> Result<bool, String> {
+        self.expr.evaluate(env)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    /// True if the operand is non-zero, or a non-empty string
+    Operand(Operand),
+    Compare {
+        op: CompareOp,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn evaluate(&self, env: &impl ConditionEnv) -> Result<bool, String> {
+        match self {
+            Self::Operand(operand) => operand.evaluate(env).map(|value| value.is_truthy()),
+            Self::Compare { op, lhs, rhs } => op.apply(&lhs.evaluate(env)?, &rhs.evaluate(env)?),
+            Self::Not(expr) => expr.evaluate(env).map(|value| !value),
+            Self::And(lhs, rhs) => Ok(lhs.evaluate(env)? && rhs.evaluate(env)?),
+            Self::Or(lhs, rhs) => Ok(lhs.evaluate(env)? || rhs.evaluate(env)?),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    /// The Nth element of the operand stack, i.e. `sN` or `stack[N]`, where `s0` is the top
+    Stack(usize),
+    /// The value read from memory by a `read` expression, i.e. `mem[ADDR OPTIONS..]`
+    Memory(ReadMemoryExpr),
+    /// The name of the current procedure, i.e. `proc`
+    Procedure,
+    Int(i128),
+    String(String),
+}
+
+impl Operand {
+    fn evaluate(&self, env: &impl ConditionEnv) -> Result<Value, String> {
+        match self {
+            Self::Stack(index) => env
+                .stack_item(*index)
+                .map(|elem| Value::Int(elem.as_int() as i128))
+                .ok_or_else(|| format!("invalid condition: stack index {index} is out of bounds")),
+            Self::Memory(expr) => {
+                let expr = ReadMemoryExpr {
+                    format: FormatType::Decimal,
+                    ..expr.clone()
+                };
+                let value = env.read_memory(&expr)?;
+                Ok(match value.as_str() {
+                    "true" => Value::Int(1),
+                    "false" => Value::Int(0),
+                    _ => value.parse::<i128>().map(Value::Int).unwrap_or(Value::String(value)),
+                })
+            }
+            Self::Procedure => Ok(Value::String(env.procedure().unwrap_or_default())),
+            Self::Int(value) => Ok(Value::Int(*value)),
+            Self::String(value) => Ok(Value::String(value.clone())),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+/// The value of an [Operand], once evaluated
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Int(i128),
+    String(String),
+}
+impl Value {
+    /// Returns true if this value is non-zero, or a non-empty string
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Int(n) => *n != 0,
+            Self::String(s) => !s.is_empty(),
+        }
+    }
+}
+impl CompareOp {
+    /// Compare `lhs` with `rhs`
+    ///
+    /// Strings are equal if they are identical, or if `rhs` is a glob pattern matching `lhs`, as
+    /// with `break in PATTERN`. Only integers can be ordered.
+    fn apply(&self, lhs: &Value, rhs: &Value) -> Result<bool, String> {
+        match (lhs, rhs) {
+            (Value::Int(a), Value::Int(b)) => Ok(match self {
+                Self::Eq => a == b,
+                Self::Ne => a != b,
+                Self::Lt => a < b,
+                Self::Lte => a <= b,
+                Self::Gt => a > b,
+                Self::Gte => a >= b,
+            }),
+            (Value::String(a), Value::String(b)) => {
+                let matches = a == b
+                    || glob::Pattern::new(b).map(|pattern| pattern.matches(a)).unwrap_or(false);
+                match self {
+                    Self::Eq => Ok(matches),
+                    Self::Ne => Ok(!matches),
+                    _ => Err("invalid condition: strings can only be compared for equality".into()),
+                }
+            }
+            _ => Err("invalid condition: cannot compare an integer with a string".into()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    /// Consume `token` if it is next in the input
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let Some(&(pos, _)) = self.chars.peek() else {
+            return false;
+        };
+        if !self.source[pos..].starts_with(token) {
+            return false;
+        }
+        for _ in token.chars() {
+            self.chars.next();
+        }
+        true
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.eat("!=") {
+            return Err("invalid condition: expected operand before '!='".into());
+        }
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.parse_or()?;
+            if !self.eat(")") {
+                return Err("invalid condition: expected ')'".into());
+            }
+            return Ok(expr);
+        }
+        let lhs = self.parse_operand()?;
+        // Two-character operators must be tried first
+        let op = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Lte),
+            (">=", CompareOp::Gte),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| self.eat(token).then_some(op));
+        match op {
+            Some(op) => Ok(Expr::Compare {
+                op,
+                lhs,
+                rhs: self.parse_operand()?,
+            }),
+            None => Ok(Expr::Operand(lhs)),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        self.skip_whitespace();
+        let Some(&(start, c)) = self.chars.peek() else {
+            return Err("invalid condition: unexpected end of expression".into());
+        };
+
+        if c == '"' {
+            self.chars.next();
+            let mut value = String::new();
+            for (_, c) in self.chars.by_ref() {
+                if c == '"' {
+                    return Ok(Operand::String(value));
+                }
+                value.push(c);
+            }
+            return Err("invalid condition: unterminated string".into());
+        }
+
+        if self.eat("mem[") {
+            let Some(&(start, _)) = self.chars.peek() else {
+                return Err("invalid condition: expected ']'".into());
+            };
+            for (end, c) in self.chars.by_ref() {
+                if c == ']' {
+                    let expr = self.source[start..end].parse::<ReadMemoryExpr>()?;
+                    return Ok(Operand::Memory(expr));
+                }
+            }
+            return Err("invalid condition: expected ']'".into());
+        }
+
+        if self.eat("stack[") {
+            let index = self.parse_word()?;
+            if !self.eat("]") {
+                return Err("invalid condition: expected ']'".into());
+            }
+            return parse_stack_index(&index);
+        }
+
+        let word = self.parse_word()?;
+        match word.as_str() {
+            "proc" => Ok(Operand::Procedure),
+            word if word.starts_with('s') && word.len() > 1 => parse_stack_index(&word[1..]),
+            word => parse_int(word).ok_or_else(|| {
+                format!("invalid condition: unrecognized operand '{}'", &self.source[start..])
+            }),
+        }
+    }
+
+    /// Parse a run of alphanumeric characters, e.g. an identifier or integer literal
+    fn parse_word(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let mut word = String::new();
+        if let Some((_, c)) = self.chars.next_if(|(_, c)| *c == '-') {
+            word.push(c);
+        }
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            word.push(c);
+        }
+        if word.is_empty() {
+            match self.chars.peek() {
+                Some((pos, _)) => {
+                    Err(format!("invalid condition: unexpected input '{}'", &self.source[*pos..]))
+                }
+                None => Err("invalid condition: unexpected end of expression".into()),
+            }
+        } else {
+            Ok(word)
+        }
+    }
+}
+
+fn parse_stack_index(index: &str) -> Result<Operand, String> {
+    index
+        .parse::<usize>()
+        .map(Operand::Stack)
+        .map_err(|err| format!("invalid condition: invalid stack index '{index}': {err}"))
+}
+
+fn parse_int(s: &str) -> Option<i128> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::TestProgram, BreakpointExpr};
+
+    /// A [ConditionEnv] with a fixed operand stack and procedure, and whose memory contains the
+    /// value `5` as a `u32`, and `true` as an `i1`
+    struct TestEnv {
+        stack: Vec<u64>,
+        procedure: Option<&'static str>,
+    }
+    impl ConditionEnv for TestEnv {
+        fn stack_item(&self, index: usize) -> Option<Felt> {
+            self.stack.get(index).copied().map(Felt::new)
+        }
+
+        fn read_memory(&self, expr: &ReadMemoryExpr) -> Result<String, String> {
+            assert_eq!(expr.format, FormatType::Decimal);
+            match expr.ty {
+                midenc_hir::Type::U32 => Ok("5".into()),
+                midenc_hir::Type::I1 => Ok("true".into()),
+                _ => Err("unsupported type".into()),
+            }
+        }
+
+        fn procedure(&self) -> Option<String> {
+            self.procedure.map(String::from)
+        }
+    }
+
+    const ENV: TestEnv = TestEnv {
+        stack: vec![],
+        procedure: None,
+    };
+
+    fn parse(source: &str) -> Expr {
+        source.parse::<Condition>().unwrap().expr
+    }
+
+    fn operand(operand: Operand) -> Box<Expr> {
+        Box::new(Expr::Operand(operand))
+    }
+
+    fn evaluate(source: &str, env: &TestEnv) -> Result<bool, String> {
+        source.parse::<Condition>()?.evaluate(env)
+    }
+
+    #[test]
+    fn parse_precedence() {
+        use Operand::Stack;
+
+        // && binds tighter than ||, and both associate to the left
+        assert_eq!(
+            parse("s0 || s1 && s2"),
+            Expr::Or(operand(Stack(0)), Box::new(Expr::And(operand(Stack(1)), operand(Stack(2)))))
+        );
+        assert_eq!(
+            parse("s0 && s1 || s2"),
+            Expr::Or(Box::new(Expr::And(operand(Stack(0)), operand(Stack(1)))), operand(Stack(2)))
+        );
+        assert_eq!(
+            parse("s0 || s1 || s2"),
+            Expr::Or(Box::new(Expr::Or(operand(Stack(0)), operand(Stack(1)))), operand(Stack(2)))
+        );
+        assert_eq!(
+            parse("(s0 || s1) && s2"),
+            Expr::And(Box::new(Expr::Or(operand(Stack(0)), operand(Stack(1)))), operand(Stack(2)))
+        );
+
+        // Comparisons bind tighter than !, && and ||
+        let compare = |index, rhs| Expr::Compare {
+            op: CompareOp::Eq,
+            lhs: Stack(index),
+            rhs: Operand::Int(rhs),
+        };
+        assert_eq!(parse("!s0 == 1"), Expr::Not(Box::new(compare(0, 1))));
+        assert_eq!(
+            parse("s0==1&&stack[1]==-2"),
+            Expr::And(Box::new(compare(0, 1)), Box::new(compare(1, -2)))
+        );
+        assert_eq!(parse("!!s0"), Expr::Not(Box::new(Expr::Not(operand(Stack(0))))));
+    }
+
+    #[test]
+    fn parse_operands() {
+        let compare = |source: &str| match parse(source) {
+            Expr::Compare { op, lhs, rhs } => (op, lhs, rhs),
+            expr => panic!("expected comparison, got {expr:?}"),
+        };
+
+        assert_eq!(compare("s12 != 0x1f"), (CompareOp::Ne, Operand::Stack(12), Operand::Int(31)));
+        assert_eq!(
+            compare("stack[ 3 ] < -0x10"),
+            (CompareOp::Lt, Operand::Stack(3), Operand::Int(-16))
+        );
+        assert_eq!(compare("s0 <= 1"), (CompareOp::Lte, Operand::Stack(0), Operand::Int(1)));
+        assert_eq!(compare("s0 > 1"), (CompareOp::Gt, Operand::Stack(0), Operand::Int(1)));
+        assert_eq!(compare("s0 >= 1"), (CompareOp::Gte, Operand::Stack(0), Operand::Int(1)));
+        assert_eq!(
+            compare(r#"proc == "my_crate::* && s0""#),
+            (CompareOp::Eq, Operand::Procedure, Operand::String("my_crate::* && s0".into()))
+        );
+
+        // Surrounding whitespace is not part of the condition
+        let condition = "  s0 == 1\t".parse::<Condition>().unwrap();
+        assert_eq!(condition.to_string(), "s0 == 1");
+    }
+
+    #[test]
+    fn parse_memory_reads() {
+        let read = |args: &str| args.parse::<ReadMemoryExpr>().unwrap();
+
+        assert_eq!(parse("mem[0x100]"), Expr::Operand(Operand::Memory(read("0x100"))));
+        assert_eq!(
+            parse("mem[ 0x100 -t u32 -m byte ] == 5 || s0"),
+            Expr::Or(
+                Box::new(Expr::Compare {
+                    op: CompareOp::Eq,
+                    lhs: Operand::Memory(read("0x100 -t u32 -m byte")),
+                    rhs: Operand::Int(5),
+                }),
+                operand(Operand::Stack(0))
+            )
+        );
+        assert_eq!(
+            parse("1 == mem[16 -t i1]"),
+            Expr::Compare {
+                op: CompareOp::Eq,
+                lhs: Operand::Int(1),
+                rhs: Operand::Memory(read("16 -t i1")),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |source: &str| match source.parse::<Condition>() {
+            Ok(condition) => panic!("expected '{source}' to be invalid, got {condition:?}"),
+            Err(err) => err,
+        };
+
+        assert_eq!(error(""), "invalid condition: unexpected end of expression");
+        assert_eq!(error("s0 =="), "invalid condition: unexpected end of expression");
+        assert_eq!(error("s0 &&"), "invalid condition: unexpected end of expression");
+        assert_eq!(error("== 1"), "invalid condition: unexpected input '== 1'");
+        assert_eq!(error("!= 1"), "invalid condition: expected operand before '!='");
+        assert_eq!(error("(s0 || s1"), "invalid condition: expected ')'");
+        assert_eq!(error("s0 == 1)"), "invalid condition: unexpected input ')'");
+        assert_eq!(error("s0 == 1 s1"), "invalid condition: unexpected input 's1'");
+        assert_eq!(error(r#"proc == "main"#), "invalid condition: unterminated string");
+        assert_eq!(error("mem[0x100"), "invalid condition: expected ']'");
+        assert_eq!(error("stack[1"), "invalid condition: expected ']'");
+        assert_eq!(error("foo"), "invalid condition: unrecognized operand 'foo'");
+        assert!(error("sx").starts_with("invalid condition: invalid stack index 'x'"));
+        assert!(error("stack[-1]").starts_with("invalid condition: invalid stack index '-1'"));
+        // The arguments of a memory read are validated when parsed
+        error("mem[0x100 -t f32]");
+        error("mem[]");
+    }
+
+    #[test]
+    fn evaluate_stack() {
+        let env = TestEnv {
+            stack: vec![0, 7, u32::MAX as u64 + 1],
+            procedure: None,
+        };
+
+        assert_eq!(evaluate("s0", &env), Ok(false));
+        assert_eq!(evaluate("!s0 && s1", &env), Ok(true));
+        assert_eq!(evaluate("s1 == 7 && stack[1] == 0x7", &env), Ok(true));
+        assert_eq!(evaluate("s1 < 7 || s1 > 7", &env), Ok(false));
+        assert_eq!(evaluate("s1 <= 7 && s1 >= 7 && s1 != 8", &env), Ok(true));
+        // Elements are compared as integers, not truncated to u32
+        assert_eq!(evaluate("s2 == 0x100000000 && s2 > 0", &env), Ok(true));
+        assert_eq!(evaluate("s1 > -1", &env), Ok(true));
+        // The right-hand side of && and || is only evaluated when needed
+        assert_eq!(evaluate("s0 && s3", &env), Ok(false));
+        assert_eq!(evaluate("s1 || s3", &env), Ok(true));
+        assert_eq!(
+            evaluate("s0 || s3", &env),
+            Err("invalid condition: stack index 3 is out of bounds".into())
+        );
+        assert_eq!(
+            evaluate("s0 == \"0\"", &env),
+            Err("invalid condition: cannot compare an integer with a string".into())
+        );
+    }
+
+    #[test]
+    fn evaluate_memory() {
+        assert_eq!(evaluate("mem[0x100 -t u32] == 5", &ENV), Ok(true));
+        // The format requested by the read is ignored, so values can be compared with integers
+        assert_eq!(evaluate("mem[0x100 -t u32 -f hex] > 4", &ENV), Ok(true));
+        // Booleans evaluate to 1 or 0
+        assert_eq!(evaluate("mem[0x100 -t i1]", &ENV), Ok(true));
+        assert_eq!(evaluate("mem[0x100 -t i1] == 1", &ENV), Ok(true));
+        assert_eq!(evaluate("mem[0x100 -t u64]", &ENV), Err("unsupported type".into()));
+    }
+
+    #[test]
+    fn evaluate_procedure() {
+        let env = TestEnv {
+            stack: vec![],
+            procedure: Some("my_crate::foo::bar"),
+        };
+
+        assert_eq!(evaluate(r#"proc == "my_crate::foo::bar""#, &env), Ok(true));
+        assert_eq!(evaluate(r#"proc == "my_crate::*""#, &env), Ok(true));
+        assert_eq!(evaluate(r#"proc != "my_crate::*""#, &env), Ok(false));
+        assert_eq!(evaluate(r#"proc == "other::*""#, &env), Ok(false));
+        assert_eq!(evaluate(r#"proc != "other::*""#, &env), Ok(true));
+        assert_eq!(evaluate("proc", &env), Ok(true));
+        assert_eq!(
+            evaluate(r#"proc < "my_crate""#, &env),
+            Err("invalid condition: strings can only be compared for equality".into())
+        );
+        assert_eq!(
+            evaluate("proc == 1", &env),
+            Err("invalid condition: cannot compare an integer with a string".into())
+        );
+
+        // Without a current procedure, `proc` is the empty string
+        assert_eq!(evaluate("proc", &ENV), Ok(false));
+        assert_eq!(evaluate(r#"proc == "*""#, &ENV), Ok(true));
+    }
+
+    /// A program which increments the top of the stack in `bump`, called four times
+    const PROGRAM: &str = "\
+proc.bump
+    trace.240
+    nop
+    add.1
+    trace.252
+    nop
+end
+
+begin
+    trace.240
+    nop
+    push.0
+    exec.bump
+    exec.bump
+    exec.bump
+    exec.bump
+    trace.252
+    nop
+end
+";
+
+    /// Sets a breakpoint on the line of `add.1` in [PROGRAM], with the condition and ignore count
+    /// given by `options`, and resumes until the program terminates
+    ///
+    /// Returns the value at the top of the stack at each hit.
+    fn hits(options: &str) -> Vec<u64> {
+        let program = TestProgram::new("condition", PROGRAM);
+        let mut state = program.state(vec![]);
+        let expr = format!("**/condition.masm:4 {options}").parse::<BreakpointExpr>().unwrap();
+        state.create_conditional_breakpoint(expr);
+
+        let mut hits = vec![];
+        loop {
+            state.resume();
+            assert!(state.execution_failed.is_none());
+            if state.breakpoints_hit.is_empty() {
+                assert!(state.executor.stopped);
+                break hits;
+            }
+            state.breakpoints_hit.clear();
+            hits.push(state.stack_item(0).unwrap().as_int());
+        }
+    }
+
+    #[test]
+    fn breakpoint_conditions_and_hit_counts() {
+        assert_eq!(hits(""), [1, 2, 3, 4]);
+        assert_eq!(hits("if s0 > 2"), [3, 4]);
+        assert_eq!(hits(r#"if proc == "*bump""#), [1, 2, 3, 4]);
+        assert!(hits(r#"if proc != "*bump""#).is_empty());
+
+        // The first N hits are ignored
+        assert_eq!(hits("ignore 1"), [2, 3, 4]);
+        assert_eq!(hits("ignore 3"), [4]);
+        assert!(hits("ignore 4").is_empty());
+        // Only hits for which the condition holds are counted
+        assert_eq!(hits("if s0 > 1 ignore 2"), [4]);
+        assert_eq!(hits("if s0 == 1 || s0 == 4 ignore 1"), [4]);
+
+        // A condition which cannot be evaluated always stops execution
+        assert_eq!(hits("if s100 == 0"), [1, 2, 3, 4]);
+    }
+}
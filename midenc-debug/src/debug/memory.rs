@@ -133,7 +133,7 @@ impl clap::builder::TypedValueParser for TypeParser {
     }
 }
 
-fn parse_address(s: &str) -> Result<u32, String> {
+pub(super) fn parse_address(s: &str) -> Result<u32, String> {
     if let Some(s) = s.strip_prefix("0x") {
         u32::from_str_radix(s, 16).map_err(|err| format!("invalid memory address: {err}"))
     } else if s.is_empty() {
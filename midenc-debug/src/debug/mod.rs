@@ -1,9 +1,15 @@
 mod breakpoint;
+mod condition;
 mod memory;
 mod stacktrace;
+mod variables;
+mod watchpoint;
 
 pub use self::{
-    breakpoint::{Breakpoint, BreakpointType},
+    breakpoint::{Breakpoint, BreakpointExpr, BreakpointType},
+    condition::{Condition, ConditionEnv},
     memory::{FormatType, MemoryMode, ReadMemoryExpr},
     stacktrace::{CallFrame, CallStack, CurrentFrame, OpDetail, ResolvedLocation, StackTrace},
+    variables::format_variable,
+    watchpoint::{AccessKind, MemoryAccess, WatchExpr, WatchMode},
 };
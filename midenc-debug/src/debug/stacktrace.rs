@@ -9,7 +9,7 @@ use std::{
 };
 
 use miden_core::{debuginfo::Location, AssemblyOp};
-use miden_processor::{Operation, RowIndex, VmState};
+use miden_processor::{Felt, Operation, RowIndex, VmState};
 use midenc_hir::demangle;
 use midenc_session::{
     diagnostics::{SourceFile, SourceSpan},
@@ -24,16 +24,22 @@ struct SpanContext {
     location: Option<Location>,
 }
 
+#[derive(Clone)]
 pub struct CallStack {
     trace_events: Rc<RefCell<BTreeMap<RowIndex, TraceEvent>>>,
+    debug_values: Rc<RefCell<BTreeMap<RowIndex, Vec<(u16, Felt)>>>>,
     contexts: BTreeSet<Rc<str>>,
     frames: Vec<CallFrame>,
     block_stack: Vec<Option<SpanContext>>,
 }
 impl CallStack {
-    pub fn new(trace_events: Rc<RefCell<BTreeMap<RowIndex, TraceEvent>>>) -> Self {
+    pub fn new(
+        trace_events: Rc<RefCell<BTreeMap<RowIndex, TraceEvent>>>,
+        debug_values: Rc<RefCell<BTreeMap<RowIndex, Vec<(u16, Felt)>>>>,
+    ) -> Self {
         Self {
             trace_events,
+            debug_values,
             contexts: BTreeSet::default(),
             frames: vec![],
             block_stack: vec![],
@@ -90,6 +96,13 @@ impl CallStack {
             let popped_frame = self.handle_trace_event(event, procedure.as_ref());
             let is_frame_end = popped_frame.is_some();
 
+            // Record the values of the debug slots reported this cycle in the current frame
+            if let Some(values) = self.debug_values.borrow().get(&state.clk) {
+                if let Some(current_frame) = self.frames.last_mut() {
+                    current_frame.debug_values.extend(values.iter().copied());
+                }
+            }
+
             // These ops we do not record in call frame details
             let ignore = matches!(
                 op,
@@ -245,11 +258,13 @@ impl CallStack {
     }
 }
 
+#[derive(Clone)]
 pub struct CallFrame {
     procedure: Option<Rc<str>>,
     context: VecDeque<OpDetail>,
     display_name: std::cell::OnceCell<Rc<str>>,
     finishing: bool,
+    debug_values: BTreeMap<u16, Felt>,
 }
 impl CallFrame {
     pub fn new(procedure: Option<Rc<str>>) -> Self {
@@ -258,9 +273,20 @@ impl CallFrame {
             context: Default::default(),
             display_name: Default::default(),
             finishing: false,
+            debug_values: Default::default(),
         }
     }
 
+    /// Get the fully-qualified name of the procedure executing in this frame, if known
+    pub fn procedure_path(&self) -> Option<&str> {
+        self.procedure.as_deref()
+    }
+
+    /// Get the most recent value reported for the given debug slot in this frame, if any
+    pub fn debug_value(&self, slot: u16) -> Option<Felt> {
+        self.debug_values.get(&slot).copied()
+    }
+
     pub fn procedure(&self, strip_prefix: &str) -> Option<Rc<str>> {
         self.procedure.as_ref()?;
         let name = self.display_name.get_or_init(|| {
@@ -0,0 +1,302 @@
+use std::fmt::Write;
+
+use miden_processor::{ContextId, RowIndex};
+use midenc_codegen_masm::NativePtr;
+use midenc_hir::{DebugLocation, DebugTypeEncoding, DebugVariable, Type};
+
+use super::CallFrame;
+use crate::ExecutionTrace;
+
+/// The maximum number of bytes displayed for a value of an opaque type, e.g. a struct
+const MAX_OPAQUE_BYTES: u32 = 64;
+
+/// Format the value of `var` in `frame`
+///
+/// Variables located in memory are read from `trace`, under `ctx`, as of cycle `clk`.
+pub fn format_variable(
+    var: &DebugVariable,
+    frame: &CallFrame,
+    trace: &ExecutionTrace,
+    ctx: ContextId,
+    clk: RowIndex,
+) -> Result<String, String> {
+    let slot = || {
+        frame
+            .debug_value(var.slot)
+            .ok_or_else(|| format!("'{}' is not available at this point", &var.name))
+    };
+
+    let mut output = String::new();
+    match var.location {
+        DebugLocation::Value => {
+            write_value(&mut output, var.ty.encoding, var.ty.size, slot()?.as_int(), &[]);
+        }
+        DebugLocation::Memory { offset } => {
+            let addr = u32::try_from((slot()?.as_int() as u32) as i64 + offset)
+                .map_err(|_| format!("'{}' is located outside of linear memory", &var.name))?;
+            let size = match var.ty.encoding {
+                DebugTypeEncoding::Opaque => var.ty.size.min(MAX_OPAQUE_BYTES),
+                _ => var.ty.size,
+            };
+            let bytes = read_bytes(trace, addr, size, ctx, clk)?;
+            let bits = bytes.iter().take(8).fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+            write_value(&mut output, var.ty.encoding, var.ty.size, bits, &bytes);
+        }
+        DebugLocation::Untracked => {
+            return Err(format!(
+                "'{}' is not tracked by the debugger, as values of type '{}' do not fit in a \
+                 field element",
+                &var.name, &var.ty.name
+            ));
+        }
+    }
+    Ok(output)
+}
+
+/// Read `size` bytes of linear memory starting at `addr`, which need not be aligned
+fn read_bytes(
+    trace: &ExecutionTrace,
+    addr: u32,
+    size: u32,
+    ctx: ContextId,
+    clk: RowIndex,
+) -> Result<Vec<u8>, String> {
+    // Reads must start on an element boundary, so read the leading bytes and discard them
+    let leading = addr % 4;
+    let ty = Type::Array(Box::new(Type::U8), (leading + size) as usize);
+    let mut bytes = trace
+        .read_bytes_for_type(NativePtr::from_ptr(addr - leading), &ty, ctx, clk)
+        .map_err(|err| format!("invalid read: {err}"))?;
+    bytes.drain(..leading as usize);
+    Ok(bytes)
+}
+
+/// Write `bits`, the value of a variable of `size` bytes, as described by `encoding`
+///
+/// Values of opaque types are written as the raw `bytes` read from memory, if any.
+fn write_value(
+    output: &mut String,
+    encoding: DebugTypeEncoding,
+    size: u32,
+    bits: u64,
+    bytes: &[u8],
+) {
+    let width = size.clamp(1, 8) * 8;
+    let truncated = if width < 64 {
+        bits & ((1 << width) - 1)
+    } else {
+        bits
+    };
+    match encoding {
+        DebugTypeEncoding::Signed => {
+            let shift = 64 - width;
+            write!(output, "{}", ((truncated << shift) as i64) >> shift).unwrap()
+        }
+        DebugTypeEncoding::Unsigned => write!(output, "{truncated}").unwrap(),
+        DebugTypeEncoding::Boolean => write!(output, "{}", truncated != 0).unwrap(),
+        DebugTypeEncoding::Float if size == 4 => {
+            write!(output, "{}", f32::from_bits(bits as u32)).unwrap()
+        }
+        DebugTypeEncoding::Float if size == 8 => {
+            write!(output, "{}", f64::from_bits(bits)).unwrap()
+        }
+        DebugTypeEncoding::Address => write!(output, "{truncated:#x}").unwrap(),
+        DebugTypeEncoding::Float | DebugTypeEncoding::Opaque if bytes.is_empty() => {
+            write!(output, "{bits:#x}").unwrap()
+        }
+        DebugTypeEncoding::Float | DebugTypeEncoding::Opaque => {
+            output.push('[');
+            for (i, byte) in bytes.iter().enumerate() {
+                if i > 0 {
+                    output.push_str(", ");
+                }
+                write!(output, "{byte:#04x}").unwrap();
+            }
+            if size as usize > bytes.len() {
+                output.push_str(", ..");
+            }
+            output.push(']');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use midenc_hir::{DebugType, FunctionDebugInfo, ProgramDebugInfo};
+
+    use super::*;
+    use crate::{testing::TestProgram, BreakpointType};
+
+    fn format(encoding: DebugTypeEncoding, size: u32, bits: u64, bytes: &[u8]) -> String {
+        let mut output = String::new();
+        write_value(&mut output, encoding, size, bits, bytes);
+        output
+    }
+
+    #[test]
+    fn format_integers() {
+        use DebugTypeEncoding::*;
+
+        assert_eq!(format(Signed, 4, 0xffffffff, &[]), "-1");
+        assert_eq!(format(Signed, 1, 0x80, &[]), "-128");
+        assert_eq!(format(Signed, 2, 0x7fff, &[]), "32767");
+        assert_eq!(format(Signed, 8, u64::MAX, &[]), "-1");
+        assert_eq!(format(Unsigned, 4, 0xffffffff, &[]), "4294967295");
+        assert_eq!(format(Unsigned, 8, u64::MAX, &[]), "18446744073709551615");
+        // Bits beyond the size of the type are ignored
+        assert_eq!(format(Signed, 4, 0x1_0000_0005, &[]), "5");
+        assert_eq!(format(Unsigned, 2, 0x12345, &[]), "9029");
+        assert_eq!(format(Boolean, 1, 0x100, &[]), "false");
+        assert_eq!(format(Boolean, 1, 1, &[]), "true");
+        assert_eq!(format(Address, 4, 0x1000, &[]), "0x1000");
+    }
+
+    #[test]
+    fn format_floats_and_opaque_values() {
+        use DebugTypeEncoding::*;
+
+        assert_eq!(format(Float, 4, 1.5f32.to_bits() as u64, &[]), "1.5");
+        assert_eq!(format(Float, 8, (-2.25f64).to_bits(), &[]), "-2.25");
+        // Floats of other sizes are displayed as their raw bits, or bytes
+        assert_eq!(format(Float, 2, 0x3c00, &[]), "0x3c00");
+        assert_eq!(format(Float, 2, 0x3c00, &[0x3c, 0x00]), "[0x3c, 0x00]");
+        assert_eq!(format(Opaque, 4, 0x2a, &[]), "0x2a");
+        assert_eq!(format(Opaque, 2, 0, &[1, 0xff]), "[0x01, 0xff]");
+        // Values larger than the bytes read are elided
+        assert_eq!(format(Opaque, 3, 0, &[1, 2]), "[0x01, 0x02, ..]");
+    }
+
+    /// A program whose procedure `vars` reports the values of its debug slots, and writes to the
+    /// word at byte address 64
+    const PROGRAM: &str = "\
+proc.vars
+    trace.240
+    nop
+    push.4294967295
+    trace.3674210304
+    drop
+    push.64
+    trace.3674210305
+    drop
+    push.16777731
+    dup.0
+    dup.0
+    dup.0
+    mem_storew.4
+    dropw
+    trace.252
+    nop
+end
+
+begin
+    trace.240
+    nop
+    exec.vars
+    trace.252
+    nop
+end
+";
+
+    /// The line of `dropw` in [PROGRAM], once the word at byte address 64 has been written
+    const DROPW_LINE: u32 = 15;
+
+    fn variable(
+        name: &str,
+        ty: (&str, u32, DebugTypeEncoding),
+        slot: u16,
+        location: DebugLocation,
+    ) -> DebugVariable {
+        let (ty, size, encoding) = ty;
+        DebugVariable {
+            name: name.to_string(),
+            ty: DebugType {
+                name: ty.to_string(),
+                size,
+                encoding,
+            },
+            slot,
+            location,
+        }
+    }
+
+    #[test]
+    fn print_variables() {
+        use DebugLocation::*;
+        use DebugTypeEncoding::*;
+
+        let program = TestProgram::new("variables", PROGRAM);
+        // Slot 0 holds u32::MAX, and slot 1 the byte address 64, i.e. the frame base, at which
+        // each 32-bit element of the word holds 0x01000203
+        let memory = |offset| Memory { offset };
+        program.write_debug_info(&ProgramDebugInfo {
+            functions: vec![FunctionDebugInfo {
+                procedure: "vars".to_string(),
+                variables: vec![
+                    variable("x", ("i32", 4, Signed), 0, Value),
+                    variable("n", ("u32", 4, Unsigned), 0, Value),
+                    variable("p", ("*u8", 4, Address), 0, Value),
+                    variable("m", ("u32", 4, Unsigned), 1, memory(4)),
+                    variable("b", ("bool", 1, Boolean), 1, memory(0)),
+                    variable("h", ("u16", 2, Unsigned), 1, memory(2)),
+                    variable("s", ("[u8; 6]", 6, Opaque), 1, memory(0)),
+                    variable("big", ("[u8; 100]", 100, Opaque), 1, memory(0)),
+                    variable("neg", ("u32", 4, Unsigned), 1, memory(-128)),
+                    variable("w", ("i64", 8, Signed), 2, Untracked),
+                    variable("late", ("u32", 4, Unsigned), 3, Value),
+                ],
+            }],
+        });
+
+        let mut state = program.state(vec![]);
+        assert_eq!(state.print_variable("x"), Err("no function is executing".to_string()));
+
+        state.create_breakpoint(BreakpointType::Line {
+            pattern: glob::Pattern::new("**/variables.masm").unwrap(),
+            line: DROPW_LINE,
+        });
+        state.resume();
+        assert_eq!(state.breakpoints_hit.len(), 1);
+
+        assert_eq!(
+            state.variable_names().unwrap(),
+            ["x", "n", "p", "m", "b", "h", "s", "big", "neg", "w", "late"]
+        );
+        assert_eq!(state.print_variable("x").unwrap(), "x: i32 = -1");
+        assert_eq!(state.print_variable("n").unwrap(), "n: u32 = 4294967295");
+        assert_eq!(state.print_variable("p").unwrap(), "p: *u8 = 0xffffffff");
+        assert_eq!(state.print_variable("m").unwrap(), "m: u32 = 16777731");
+        assert_eq!(state.print_variable("b").unwrap(), "b: bool = true");
+        assert_eq!(state.print_variable("h").unwrap(), "h: u16 = 515");
+        assert_eq!(
+            state.print_variable("s").unwrap(),
+            "s: [u8; 6] = [0x01, 0x00, 0x02, 0x03, 0x01, 0x00]"
+        );
+        // Opaque values are truncated to 64 bytes, the remainder of which are zero
+        let (_, big) = state.read_variable("big").unwrap();
+        assert!(
+            big.starts_with("[0x01, 0x00, 0x02, 0x03, 0x01, 0x00, 0x02, 0x03, 0x01"),
+            "{big}"
+        );
+        assert!(big.ends_with("0x00, 0x00, ..]"), "{big}");
+        assert_eq!(big.matches("0x").count(), MAX_OPAQUE_BYTES as usize);
+
+        assert_eq!(
+            state.print_variable("neg"),
+            Err("'neg' is located outside of linear memory".to_string())
+        );
+        assert_eq!(
+            state.print_variable("w"),
+            Err("'w' is not tracked by the debugger, as values of type 'i64' do not fit in a \
+                 field element"
+                .to_string())
+        );
+        assert_eq!(
+            state.print_variable("late"),
+            Err("'late' is not available at this point".to_string())
+        );
+        assert_eq!(
+            state.print_variable("y"),
+            Err("no variable named 'y' in the current function".to_string())
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use miden_core::Operation;
+use miden_processor::{ContextId, Felt};
+use midenc_codegen_masm::NativePtr;
+
+use super::memory::parse_address;
+
+/// A watchpoint on a range of bytes of linear memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchExpr {
+    /// The byte address of the first byte watched
+    pub addr: u32,
+    /// The number of bytes watched
+    pub len: u32,
+    /// The kind of accesses to break on
+    pub mode: WatchMode,
+}
+impl WatchExpr {
+    /// The number of bytes watched when no length is given, i.e. a 32-bit value
+    pub const DEFAULT_LEN: u32 = 4;
+
+    /// Returns the range of element addresses covering the watched bytes
+    ///
+    /// Each element of a word holds a 32-bit chunk of the byte-addressable space, so the element
+    /// address of a byte is derived from its [NativePtr], just like [super::ReadMemoryExpr] does.
+    pub fn elements(&self) -> Range<u64> {
+        let element_of = |ptr: NativePtr| (ptr.waddr as u64 * 4) + ptr.index as u64;
+        let start = NativePtr::from_ptr(self.addr);
+        let end = NativePtr::from_ptr(self.addr.saturating_add(self.len.max(1) - 1));
+        element_of(start)..(element_of(end) + 1)
+    }
+
+    /// Return true if `access` touches any of the watched bytes, in a way this watchpoint is
+    /// interested in
+    pub fn should_break_on(&self, access: &MemoryAccess) -> bool {
+        if !self.mode.matches(access.kind) {
+            return false;
+        }
+        let watched = self.elements();
+        access.elements.start < watched.end && watched.start < access.elements.end
+    }
+}
+impl fmt::Display for WatchExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = self.addr as u64 + self.len as u64;
+        write!(f, "{:#x}..{end:#x} ({})", self.addr, self.mode)
+    }
+}
+impl FromStr for WatchExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // watch {addr} [{len}] [read|write|rw]
+        let mut args = s.split_whitespace();
+        let addr = args
+            .next()
+            .ok_or_else(|| "invalid watch expression: expected memory address".to_string())
+            .and_then(parse_address)?;
+        let mut len = None;
+        let mut mode = None;
+        for arg in args {
+            if mode.is_some() {
+                return Err(format!("invalid watch expression: unexpected argument '{arg}'"));
+            }
+            if len.is_none() {
+                if let Ok(n) = parse_address(arg) {
+                    if n == 0 {
+                        return Err("invalid watch expression: length must be non-zero".into());
+                    }
+                    len = Some(n);
+                    continue;
+                }
+            }
+            mode = Some(arg.parse::<WatchMode>()?);
+        }
+        let len = len.unwrap_or(Self::DEFAULT_LEN);
+        // The range may end at the last byte of the address space
+        if addr.checked_add(len - 1).is_none() {
+            return Err("invalid watch expression: range exceeds the address space".into());
+        }
+        Ok(Self {
+            addr,
+            len,
+            mode: mode.unwrap_or_default(),
+        })
+    }
+}
+
+/// The kind of memory accesses which trigger a watchpoint
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WatchMode {
+    Read,
+    #[default]
+    Write,
+    ReadWrite,
+}
+impl WatchMode {
+    /// Return true if an access of the given kind triggers a watchpoint in this mode
+    pub fn matches(&self, kind: AccessKind) -> bool {
+        matches!(
+            (self, kind),
+            (Self::ReadWrite, _)
+                | (Self::Read, AccessKind::Read)
+                | (Self::Write, AccessKind::Write)
+        )
+    }
+}
+impl fmt::Display for WatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => f.write_str("read"),
+            Self::Write => f.write_str("write"),
+            Self::ReadWrite => f.write_str("rw"),
+        }
+    }
+}
+impl FromStr for WatchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "r" | "read" => Ok(Self::Read),
+            "w" | "write" => Ok(Self::Write),
+            "rw" | "readwrite" | "read-write" => Ok(Self::ReadWrite),
+            _ => Err(format!("invalid watch mode '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A range of memory accessed by an operation executed by the VM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAccess {
+    /// The context whose memory was accessed
+    pub ctx: ContextId,
+    pub kind: AccessKind,
+    /// The element addresses accessed, i.e. `word address * 4 + element index`
+    pub elements: Range<u64>,
+}
+impl MemoryAccess {
+    /// Returns the memory accessed by `op` in `ctx`, given the operand stack as it was before `op`
+    /// was executed, or `None` if `op` does not access memory.
+    pub fn of(op: &Operation, ctx: ContextId, stack: &[Felt]) -> Option<Self> {
+        let (kind, operand, len) = match op {
+            // Only the first element of the word is loaded/stored
+            Operation::MLoad => (AccessKind::Read, 0, 1),
+            Operation::MStore => (AccessKind::Write, 0, 1),
+            Operation::MLoadW => (AccessKind::Read, 0, 4),
+            Operation::MStoreW => (AccessKind::Write, 0, 4),
+            // Two consecutive words, starting at the address in the 13th element of the stack
+            Operation::MStream => (AccessKind::Read, 12, 8),
+            Operation::Pipe => (AccessKind::Write, 12, 8),
+            _ => return None,
+        };
+        let start = stack.get(operand)?.as_int() * 4;
+        Some(Self {
+            ctx,
+            kind,
+            elements: start..(start + len),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch(addr: u32, len: u32, mode: WatchMode) -> WatchExpr {
+        WatchExpr { addr, len, mode }
+    }
+
+    fn access(kind: AccessKind, elements: Range<u64>) -> MemoryAccess {
+        MemoryAccess {
+            ctx: ContextId::root(),
+            kind,
+            elements,
+        }
+    }
+
+    #[test]
+    fn parse_watch_expr() {
+        use WatchMode::*;
+
+        let parse = |s: &str| s.parse::<WatchExpr>().unwrap();
+        assert_eq!(parse("0x100"), watch(0x100, 4, Write));
+        assert_eq!(parse("256"), watch(0x100, 4, Write));
+        assert_eq!(parse("0x100 8"), watch(0x100, 8, Write));
+        assert_eq!(parse("0x100 0x10"), watch(0x100, 16, Write));
+        assert_eq!(parse("0x100 read"), watch(0x100, 4, Read));
+        assert_eq!(parse("0x100 r"), watch(0x100, 4, Read));
+        assert_eq!(parse("0x100 write"), watch(0x100, 4, Write));
+        assert_eq!(parse("0x100 w"), watch(0x100, 4, Write));
+        assert_eq!(parse("0x100 rw"), watch(0x100, 4, ReadWrite));
+        assert_eq!(parse("0x100 readwrite"), watch(0x100, 4, ReadWrite));
+        assert_eq!(parse("0x100 read-write"), watch(0x100, 4, ReadWrite));
+        assert_eq!(parse("0x100 1 read"), watch(0x100, 1, Read));
+        assert_eq!(parse("  0x100\t2  rw "), watch(0x100, 2, ReadWrite));
+
+        // Ranges may extend up to, and including, the last byte of the address space
+        assert_eq!(parse("0xffffffff 1"), watch(u32::MAX, 1, Write));
+        assert_eq!(parse("0xfffffffc"), watch(0xfffffffc, 4, Write));
+        assert_eq!(parse("0 0xffffffff rw"), watch(0, u32::MAX, ReadWrite));
+    }
+
+    #[test]
+    fn parse_invalid_watch_expr() {
+        let error = |s: &str| s.parse::<WatchExpr>().unwrap_err();
+        assert_eq!(error(""), "invalid watch expression: expected memory address");
+        assert!(error("foo").starts_with("invalid memory address"));
+        assert!(error("read").starts_with("invalid memory address"));
+        assert_eq!(error("0x100 0"), "invalid watch expression: length must be non-zero");
+        assert_eq!(error("0x100 bogus"), "invalid watch mode 'bogus'");
+        // The length must precede the mode, and only one of each may be given
+        assert_eq!(error("0x100 read 8"), "invalid watch expression: unexpected argument '8'");
+        assert_eq!(error("0x100 8 9"), "invalid watch mode '9'");
+        assert_eq!(error("0x100 8 rw w"), "invalid watch expression: unexpected argument 'w'");
+        // Lengths which are not valid addresses are not mistaken for modes
+        assert_eq!(error("0x100 0x100000000"), "invalid watch mode '0x100000000'");
+
+        let overflow = "invalid watch expression: range exceeds the address space";
+        assert_eq!(error("0xffffffff"), overflow);
+        assert_eq!(error("0xffffffff 2"), overflow);
+        assert_eq!(error("0xfffffffd rw"), overflow);
+        assert_eq!(error("2 0xffffffff"), overflow);
+    }
+
+    #[test]
+    fn display_watch_expr() {
+        assert_eq!(watch(0x100, 8, WatchMode::Read).to_string(), "0x100..0x108 (read)");
+        assert_eq!(
+            watch(u32::MAX, 1, WatchMode::ReadWrite).to_string(),
+            "0xffffffff..0x100000000 (rw)"
+        );
+    }
+
+    #[test]
+    fn watched_elements() {
+        // Each element covers 4 bytes, and each word 4 elements, i.e. 16 bytes
+        assert_eq!(watch(0, 1, WatchMode::Write).elements(), 0..1);
+        assert_eq!(watch(0, 4, WatchMode::Write).elements(), 0..1);
+        assert_eq!(watch(0, 5, WatchMode::Write).elements(), 0..2);
+        assert_eq!(watch(3, 2, WatchMode::Write).elements(), 0..2);
+        assert_eq!(watch(16, 16, WatchMode::Write).elements(), 4..8);
+        assert_eq!(watch(15, 2, WatchMode::Write).elements(), 3..5);
+        assert_eq!(watch(18, 4, WatchMode::Write).elements(), 4..6);
+        assert_eq!(watch(u32::MAX, 1, WatchMode::Write).elements(), 0x3fffffff..0x40000000);
+        // A zero length is treated as watching a single byte
+        assert_eq!(watch(17, 0, WatchMode::Write).elements(), 4..5);
+    }
+
+    #[test]
+    fn break_on_element_boundaries() {
+        use AccessKind::Write;
+
+        // Bytes 16..20, i.e. the first element of word 1
+        let expr = watch(16, 4, WatchMode::Write);
+        assert!(expr.should_break_on(&access(Write, 4..5)));
+        assert!(!expr.should_break_on(&access(Write, 3..4)));
+        assert!(!expr.should_break_on(&access(Write, 5..6)));
+
+        // Bytes 18..22 straddle the first and second elements of word 1
+        let expr = watch(18, 4, WatchMode::Write);
+        assert!(!expr.should_break_on(&access(Write, 3..4)));
+        assert!(expr.should_break_on(&access(Write, 4..5)));
+        assert!(expr.should_break_on(&access(Write, 5..6)));
+        assert!(!expr.should_break_on(&access(Write, 6..7)));
+    }
+
+    #[test]
+    fn break_on_word_boundaries() {
+        use AccessKind::Write;
+
+        let word = |waddr: u64| access(Write, (waddr * 4)..(waddr * 4 + 4));
+
+        // The last element of word 0, and the first of word 1
+        let expr = watch(12, 8, WatchMode::Write);
+        assert!(expr.should_break_on(&word(0)));
+        assert!(expr.should_break_on(&word(1)));
+        assert!(!expr.should_break_on(&word(2)));
+
+        // Exactly word 1
+        let expr = watch(16, 16, WatchMode::Write);
+        assert!(!expr.should_break_on(&word(0)));
+        assert!(expr.should_break_on(&word(1)));
+        assert!(!expr.should_break_on(&word(2)));
+
+        // Accesses of two words, as done by `mstream` and `pipe`
+        assert!(expr.should_break_on(&access(Write, 0..8)));
+        assert!(expr.should_break_on(&access(Write, 4..12)));
+        assert!(!expr.should_break_on(&access(Write, 8..16)));
+    }
+
+    #[test]
+    fn break_on_access_kind() {
+        use AccessKind::{Read, Write};
+
+        let expr = watch(0, 4, WatchMode::Read);
+        assert!(expr.should_break_on(&access(Read, 0..1)));
+        assert!(!expr.should_break_on(&access(Write, 0..1)));
+
+        let expr = watch(0, 4, WatchMode::Write);
+        assert!(!expr.should_break_on(&access(Read, 0..1)));
+        assert!(expr.should_break_on(&access(Write, 0..1)));
+
+        let expr = watch(0, 4, WatchMode::ReadWrite);
+        assert!(expr.should_break_on(&access(Read, 0..1)));
+        assert!(expr.should_break_on(&access(Write, 0..1)));
+    }
+
+    #[test]
+    fn memory_access_of_op() {
+        let ctx = ContextId::root();
+        let stack = (0..16).map(|n| Felt::new(n + 1)).collect::<Vec<_>>();
+
+        let of = |op| MemoryAccess::of(&op, ctx, &stack);
+        assert_eq!(of(Operation::MLoad), Some(access(AccessKind::Read, 4..5)));
+        assert_eq!(of(Operation::MStore), Some(access(AccessKind::Write, 4..5)));
+        assert_eq!(of(Operation::MLoadW), Some(access(AccessKind::Read, 4..8)));
+        assert_eq!(of(Operation::MStoreW), Some(access(AccessKind::Write, 4..8)));
+        assert_eq!(of(Operation::MStream), Some(access(AccessKind::Read, 52..60)));
+        assert_eq!(of(Operation::Pipe), Some(access(AccessKind::Write, 52..60)));
+        assert_eq!(of(Operation::Add), None);
+
+        // The address operand must be on the stack
+        assert_eq!(MemoryAccess::of(&Operation::MStream, ctx, &stack[..12]), None);
+    }
+}
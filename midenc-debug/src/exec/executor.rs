@@ -22,7 +22,7 @@ use midenc_session::{
     LinkLibrary, Session, BASE, STDLIB,
 };
 
-use super::{DebugExecutor, DebuggerHost, ExecutionTrace, TraceEvent};
+use super::{state::Snapshot, DebugExecutor, DebuggerHost, ExecutionTrace, TraceEvent};
 use crate::{debug::CallStack, felt::PopFromStack, TestFelt};
 
 /// The [Executor] is responsible for executing a program with the Miden VM.
@@ -146,14 +146,20 @@ impl Executor {
         host.register_assert_failed_tracer(move |clk, event| {
             assertion_events.borrow_mut().insert(clk, event);
         });
+        let debug_values: Rc<RefCell<BTreeMap<RowIndex, Vec<(u16, Felt)>>>> =
+            Rc::new(Default::default());
+        let reported_values = Rc::clone(&debug_values);
+        host.register_debug_value_handler(move |clk, slot, value| {
+            reported_values.borrow_mut().entry(clk).or_default().push((slot, value));
+        });
 
         let mut process = Process::new_debug(program.kernel().clone(), self.stack);
         let process_state: ProcessState = (&process).into();
         let root_context = process_state.ctx();
         let result = process.execute(program, &mut host);
         let mut iter = VmStateIterator::new(process, result.clone());
-        let mut callstack = CallStack::new(trace_events);
-        DebugExecutor {
+        let mut callstack = CallStack::new(trace_events, debug_values);
+        let mut executor = DebugExecutor {
             iter,
             result,
             contexts: Default::default(),
@@ -162,9 +168,15 @@ impl Executor {
             callstack,
             recent: VecDeque::with_capacity(5),
             last: None,
+            last_access: None,
             cycle: 0,
             stopped: false,
-        }
+            snapshots: vec![],
+            first: None,
+            pending: None,
+        };
+        executor.snapshots.push(Snapshot::of(&executor));
+        executor
     }
 
     /// Execute the given program until termination, producing a trace
@@ -2,11 +2,11 @@ use std::{collections::BTreeMap, sync::Arc};
 
 use miden_core::crypto::hash::RpoDigest;
 use miden_processor::{
-    AdviceExtractor, AdviceInjector, AdviceProvider, ExecutionError, Host, HostResponse,
+    AdviceExtractor, AdviceInjector, AdviceProvider, ExecutionError, Felt, Host, HostResponse,
     MastForest, MastForestStore, MemAdviceProvider, MemMastForestStore, ProcessState, RowIndex,
 };
 
-use super::{TraceEvent, TraceHandler};
+use super::{DebugValueHandler, TraceEvent, TraceHandler};
 
 /// This is an implementation of [Host] which is essentially [miden_processor::DefaultHost],
 /// but extended with additional functionality for debugging, in particular it manages trace
@@ -17,6 +17,7 @@ pub struct DebuggerHost {
     store: MemMastForestStore,
     tracing_callbacks: BTreeMap<u32, Vec<Box<TraceHandler>>>,
     on_assert_failed: Option<Box<TraceHandler>>,
+    on_debug_value: Option<Box<DebugValueHandler>>,
 }
 impl DebuggerHost {
     /// Construct a new instance of [DebuggerHost] with the given advice provider.
@@ -26,6 +27,7 @@ impl DebuggerHost {
             store: Default::default(),
             tracing_callbacks: Default::default(),
             on_assert_failed: None,
+            on_debug_value: None,
         }
     }
 
@@ -49,6 +51,15 @@ impl DebuggerHost {
         self.on_assert_failed = Some(Box::new(callback));
     }
 
+    /// Register a handler to be called when the value of a debug slot is reported, with the value
+    /// found on top of the operand stack
+    pub fn register_debug_value_handler<F>(&mut self, callback: F)
+    where
+        F: FnMut(RowIndex, u16, Felt) + 'static,
+    {
+        self.on_debug_value = Some(Box::new(callback));
+    }
+
     /// Load `forest` into the MAST store for this host
     pub fn load_mast_forest(&mut self, forest: Arc<MastForest>) {
         self.store.insert(forest);
@@ -90,6 +101,11 @@ impl Host for DebuggerHost {
                 handler(clk, event);
             }
         }
+        if let TraceEvent::DebugValue(slot) = event {
+            if let Some(handler) = self.on_debug_value.as_mut() {
+                handler(clk, slot, process.get_stack_item(0));
+            }
+        }
         Ok(HostResponse::None)
     }
 
@@ -104,6 +120,21 @@ impl Host for DebuggerHost {
                  for that use"
                     .to_string(),
             ),
+            midenc_hir::ASSERT_FAILED_INVALID_HANDLE => Some(
+                "invalid resource handle: the handle was dropped, or is still borrowed".to_string(),
+            ),
+            midenc_hir::ASSERT_FAILED_TOO_MANY_HANDLES => {
+                Some("too many resources: the resource handle table is full".to_string())
+            }
+            midenc_hir::ASSERT_FAILED_UNSUPPORTED_F64 => Some(
+                "unsupported floating point operation: the operation has no soft-float \
+                 implementation"
+                    .to_string(),
+            ),
+            code if code >= midenc_hir::ASSERT_FAILED_EXCEPTION => Some(format!(
+                "uncaught exception: an exception with tag {} was thrown",
+                code - midenc_hir::ASSERT_FAILED_EXCEPTION
+            )),
             _ => None,
         };
         ExecutionError::FailedAssertion {
@@ -7,5 +7,5 @@ pub use self::{
     executor::Executor,
     host::DebuggerHost,
     state::{Chiplets, DebugExecutor},
-    trace::{ExecutionTrace, TraceEvent, TraceHandler},
+    trace::{DebugValueHandler, ExecutionTrace, TraceEvent, TraceHandler},
 };
@@ -6,7 +6,7 @@ use miden_processor::{
 };
 
 use super::ExecutionTrace;
-use crate::{CallFrame, CallStack, TestFelt};
+use crate::{CallFrame, CallStack, MemoryAccess, TestFelt};
 
 /// A special version of [crate::Executor] which provides finer-grained control over execution,
 /// and captures a ton of information about the program being executed, so as to make it possible
@@ -31,13 +31,50 @@ pub struct DebugExecutor {
     pub recent: VecDeque<Operation>,
     /// The most recent [VmState] produced by the [VmStateIterator]
     pub last: Option<VmState>,
+    /// The memory accessed by the operation executed in the most recent cycle, if any
+    pub last_access: Option<MemoryAccess>,
     /// The current clock cycle
     pub cycle: usize,
     /// Whether or not execution has terminated
     pub stopped: bool,
+    /// Snapshots of the executor, taken every [Self::SNAPSHOT_INTERVAL] cycles, in cycle order
+    pub(crate) snapshots: Vec<Snapshot>,
+    /// The state of the first cycle, which [VmStateIterator::back] cannot return to
+    pub(crate) first: Option<VmState>,
+    /// A state to be executed in place of the next state produced by the iterator
+    pub(crate) pending: Option<VmState>,
+}
+
+/// The state of a [DebugExecutor] at a given cycle, other than that of its [VmStateIterator]
+#[derive(Clone)]
+pub(crate) struct Snapshot {
+    cycle: usize,
+    contexts: BTreeSet<ContextId>,
+    current_context: ContextId,
+    callstack: CallStack,
+    recent: VecDeque<Operation>,
+    last: Option<VmState>,
+    last_access: Option<MemoryAccess>,
+}
+impl Snapshot {
+    pub(crate) fn of(executor: &DebugExecutor) -> Self {
+        Self {
+            cycle: executor.cycle,
+            contexts: executor.contexts.clone(),
+            current_context: executor.current_context,
+            callstack: executor.callstack.clone(),
+            recent: executor.recent.clone(),
+            last: executor.last.clone(),
+            last_access: executor.last_access.clone(),
+        }
+    }
 }
 
 impl DebugExecutor {
+    /// The number of cycles between each snapshot of the executor taken while stepping, which
+    /// bounds the number of cycles re-executed by [Self::rewind]
+    pub const SNAPSHOT_INTERVAL: usize = 1000;
+
     /// Advance the program state by one cycle.
     ///
     /// If the program has already reached its termination state, it returns the same result
@@ -48,9 +85,16 @@ impl DebugExecutor {
         if self.stopped {
             return self.result.as_ref().map(|_| None).map_err(|err| err.clone());
         }
-        match self.iter.next() {
+        let next = match self.pending.take() {
+            Some(state) => Some(Ok(state)),
+            None => self.iter.next(),
+        };
+        match next {
             Some(Ok(state)) => {
                 self.cycle += 1;
+                if self.cycle == 1 {
+                    self.first = Some(state.clone());
+                }
                 if self.current_context != state.ctx {
                     self.contexts.insert(state.ctx);
                     self.current_context = state.ctx;
@@ -65,8 +109,20 @@ impl DebugExecutor {
 
                 let exited = self.callstack.next(&state);
 
+                // The operand stack of the previous state holds the operands of `state.op`
+                self.last_access = match (state.op.as_ref(), self.last.as_ref()) {
+                    (Some(op), Some(prev)) => MemoryAccess::of(op, state.ctx, &prev.stack),
+                    _ => None,
+                };
+
                 self.last = Some(state);
 
+                if self.cycle % Self::SNAPSHOT_INTERVAL == 0
+                    && self.snapshots.last().is_some_and(|snapshot| snapshot.cycle < self.cycle)
+                {
+                    self.snapshots.push(Snapshot::of(self));
+                }
+
                 Ok(exited)
             }
             Some(Err(err)) => {
@@ -74,12 +130,58 @@ impl DebugExecutor {
                 Err(err)
             }
             None => {
+                // The iterator only produces an error once, so if execution failed, and we've
+                // rewound since, the error must be reported from the result
                 self.stopped = true;
-                Ok(None)
+                self.result.as_ref().map(|_| None).map_err(|err| err.clone())
             }
         }
     }
 
+    /// Move execution back to the most recent snapshot taken at or before `cycle`, which must not
+    /// be after the current cycle, so that it can be stepped forward to `cycle`.
+    ///
+    /// The states of all cycles are computed by the VM up front, so the [VmStateIterator] is
+    /// walked back to the snapshot rather than re-executing the program from the start.
+    pub fn rewind(&mut self, cycle: usize) {
+        assert!(cycle <= self.cycle, "cannot rewind to cycle {cycle} from cycle {}", self.cycle);
+        let index = self.snapshots.partition_point(|snapshot| snapshot.cycle <= cycle) - 1;
+        let snapshot = self.snapshots[index].clone();
+        if snapshot.cycle == self.cycle {
+            return;
+        }
+
+        // Walk back until the iterator next produces the state following the snapshot. Each call
+        // to `back` produces the state preceding the one the iterator would produce next, but
+        // never the state of the first cycle, so that one is replayed from `first` instead.
+        for _ in snapshot.cycle.max(1)..self.cycle {
+            self.iter.back();
+        }
+        self.pending = if snapshot.cycle == 0 {
+            self.first.clone()
+        } else {
+            None
+        };
+
+        let Snapshot {
+            cycle,
+            contexts,
+            current_context,
+            callstack,
+            recent,
+            last,
+            last_access,
+        } = snapshot;
+        self.cycle = cycle;
+        self.contexts = contexts;
+        self.current_context = current_context;
+        self.callstack = callstack;
+        self.recent = recent;
+        self.last = last;
+        self.last_access = last_access;
+        self.stopped = false;
+    }
+
     /// Consume the [DebugExecutor], converting it into an [ExecutionTrace] at the current cycle.
     pub fn into_execution_trace(self) -> ExecutionTrace {
         let last_cycle = self.cycle;
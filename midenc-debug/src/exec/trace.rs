@@ -21,6 +21,9 @@ use crate::{debug::CallStack, felt::PopFromStack, DebuggerHost, TestFelt};
 /// A callback to be executed when a [TraceEvent] occurs at a given clock cycle
 pub type TraceHandler = dyn FnMut(RowIndex, TraceEvent);
 
+/// A callback to be executed when the value of a debug slot is reported at a given clock cycle
+pub type DebugValueHandler = dyn FnMut(RowIndex, u16, Felt);
+
 /// Occurs when an attempt to read memory of the VM fails
 #[derive(Debug, thiserror::Error)]
 pub enum MemoryReadError {
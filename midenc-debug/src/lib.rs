@@ -3,10 +3,13 @@
 
 mod cli;
 mod config;
+mod dap;
 mod debug;
 mod exec;
 mod felt;
 mod logger;
+#[cfg(test)]
+mod testing;
 mod ui;
 
 use std::rc::Rc;
@@ -37,6 +40,17 @@ pub fn run(
     rt.block_on(async move { start_ui(inputs, args, session, logger).await })
 }
 
+/// Run the debugger as a Debug Adapter Protocol server, communicating with the client over stdio
+pub fn run_dap(
+    inputs: Option<DebuggerConfig>,
+    args: Vec<miden_processor::Felt>,
+    session: Rc<Session>,
+) -> ExecutionResult<()> {
+    let state = ui::State::from_inputs(inputs, args, session)?;
+    let mut server = dap::DapServer::new(state);
+    server.run(std::io::stdin().lock(), std::io::stdout().lock())
+}
+
 pub fn run_noninteractively(
     inputs: Option<DebuggerConfig>,
     args: Vec<miden_processor::Felt>,
@@ -0,0 +1,91 @@
+//! Helpers for testing the debugger against small programs written in Miden Assembly
+
+use std::{path::PathBuf, rc::Rc, sync::Arc};
+
+use miden_assembly::{
+    ast::{Module, ModuleKind},
+    Assembler, LibraryNamespace, LibraryPath,
+};
+use miden_processor::Felt;
+use midenc_hir::ProgramDebugInfo;
+use midenc_session::{
+    diagnostics::DefaultSourceManager, FileType, InputFile, InputType, Options, Session,
+};
+
+use crate::ui::State;
+
+/// A program assembled, with debug information, from the Miden Assembly source `source`, and
+/// written as a package to a directory of its own, named after `name`.
+pub struct TestProgram {
+    /// The directory containing the source file and the package
+    pub dir: PathBuf,
+    /// The path to the source file of the program
+    pub source_path: PathBuf,
+    /// The path to the package of the program
+    pub package_path: PathBuf,
+    pub session: Rc<Session>,
+}
+
+impl TestProgram {
+    pub fn new(name: &str, source: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("midenc-debug-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source_path = dir.join(format!("{name}.masm"));
+        std::fs::write(&source_path, source).unwrap();
+        let package_path = dir.join(format!("{name}.masp"));
+
+        let mut options = Options::default();
+        options.current_dir = dir.clone();
+        let session = Session::new(
+            [InputFile::new(FileType::Masp, InputType::Real(package_path.clone()))],
+            None,
+            None,
+            dir.clone(),
+            options,
+            None,
+            Arc::new(DefaultSourceManager::default()),
+        );
+
+        let path = LibraryPath::from(LibraryNamespace::Exec);
+        let module = Module::parser(ModuleKind::Executable)
+            .parse_file(path, &source_path, &session.source_manager)
+            .unwrap();
+        let program = Assembler::new(session.source_manager.clone())
+            .with_debug_mode(true)
+            .assemble_program(module)
+            .unwrap();
+        let package = miden_package::Package {
+            name: name.to_string(),
+            mast: miden_package::MastArtifact::Executable(Arc::new(program)),
+            manifest: miden_package::PackageManifest {
+                exports: Default::default(),
+                dependencies: vec![],
+            },
+        };
+        std::fs::write(&package_path, package.write_to_bytes().unwrap()).unwrap();
+
+        Self {
+            dir,
+            source_path,
+            package_path,
+            session: Rc::new(session),
+        }
+    }
+
+    /// Writes `debug_info` to the `.dbg` file the debugger loads along with the package
+    pub fn write_debug_info(&self, debug_info: &ProgramDebugInfo) {
+        let content = toml::to_string_pretty(debug_info).unwrap();
+        std::fs::write(self.package_path.with_extension("dbg"), content).unwrap();
+    }
+
+    /// Returns the debugger state for a run of the program with the operands `args`
+    pub fn state(&self, args: Vec<Felt>) -> State {
+        State::from_inputs(None, args, self.session.clone()).unwrap()
+    }
+}
+
+impl Drop for TestProgram {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
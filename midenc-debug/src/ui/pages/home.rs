@@ -19,7 +19,7 @@ use crate::{
         state::{InputMode, State},
         tui::EventResponse,
     },
-    Breakpoint, BreakpointType, ReadMemoryExpr,
+    Breakpoint, BreakpointExpr, BreakpointType, ReadMemoryExpr, WatchExpr,
 };
 
 #[derive(Default)]
@@ -119,9 +119,9 @@ impl Page for Home {
                 // Dispatch commands of the form: CMD [ARGS..]
                 match args.split_once(' ') {
                     Some((cmd, rest)) => match cmd.trim() {
-                        "b" | "break" | "breakpoint" => match rest.parse::<BreakpointType>() {
-                            Ok(ty) => {
-                                state.create_breakpoint(ty);
+                        "b" | "break" | "breakpoint" => match rest.parse::<BreakpointExpr>() {
+                            Ok(expr) => {
+                                state.create_conditional_breakpoint(expr);
                                 actions.push(Some(Action::TimedStatusLine(
                                     "breakpoint created".to_string(),
                                     1,
@@ -131,6 +131,59 @@ impl Page for Home {
                                 actions.push(Some(Action::TimedStatusLine(err, 5)));
                             }
                         },
+                        "w" | "watch" => match rest.parse::<WatchExpr>() {
+                            Ok(expr) => {
+                                state.create_breakpoint(BreakpointType::Watch(expr));
+                                actions.push(Some(Action::TimedStatusLine(
+                                    "watchpoint created".to_string(),
+                                    1,
+                                )));
+                            }
+                            Err(err) => {
+                                actions.push(Some(Action::TimedStatusLine(err, 5)));
+                            }
+                        },
+                        "ignore" => {
+                            let parsed = rest.trim().split_once(' ').and_then(|(id, n)| {
+                                Some((id.parse::<u8>().ok()?, n.trim().parse::<usize>().ok()?))
+                            });
+                            match parsed {
+                                Some((id, n)) if state.ignore_breakpoint(id, n) => {
+                                    actions.push(Some(Action::TimedStatusLine(
+                                        format!("breakpoint {id} will be ignored {n} times"),
+                                        1,
+                                    )));
+                                }
+                                Some((id, _)) => actions.push(Some(Action::TimedStatusLine(
+                                    format!("no breakpoint with id {id}"),
+                                    5,
+                                ))),
+                                None => actions.push(Some(Action::TimedStatusLine(
+                                    "invalid ignore command: expected 'ignore ID N'".into(),
+                                    5,
+                                ))),
+                            }
+                        }
+                        "goto" | "goto-cycle" => match rest.trim().parse::<usize>() {
+                            Ok(cycle) => match state.goto_cycle(cycle) {
+                                Ok(_) => {
+                                    for pane in self.panes.iter_mut() {
+                                        actions.push(pane.update(Action::Update, state)?);
+                                    }
+                                }
+                                Err(err) => {
+                                    actions.push(Some(Action::TimedStatusLine(err.to_string(), 5)));
+                                }
+                            },
+                            Err(err) => actions.push(Some(Action::TimedStatusLine(
+                                format!("invalid cycle: {err}"),
+                                5,
+                            ))),
+                        },
+                        "p" | "print" => match state.print_variable(rest.trim()) {
+                            Ok(result) => actions.push(Some(Action::StatusLine(result))),
+                            Err(err) => actions.push(Some(Action::TimedStatusLine(err, 5))),
+                        },
                         "r" | "read" => match rest.parse::<ReadMemoryExpr>() {
                             Ok(expr) => match state.read_memory(&expr) {
                                 Ok(result) => actions.push(Some(Action::StatusLine(result))),
@@ -145,12 +198,43 @@ impl Page for Home {
                     },
                     None => match args.trim() {
                         "q" | "quit" => actions.push(Some(Action::Quit)),
+                        "p" | "print" => match state.variable_names() {
+                            Ok(names) if names.is_empty() => actions.push(Some(
+                                Action::TimedStatusLine("no variables in scope".into(), 3),
+                            )),
+                            Ok(names) => {
+                                actions.push(Some(Action::StatusLine(names.join(", "))));
+                            }
+                            Err(err) => actions.push(Some(Action::TimedStatusLine(err, 5))),
+                        },
                         "reload" => {
                             actions.push(Some(Action::Reload));
                         }
                         "debug" => {
                             actions.push(Some(Action::ShowDebug));
                         }
+                        cmd @ ("rs" | "reverse-step" | "rn" | "reverse-next" | "rf"
+                        | "reverse-finish") => {
+                            let rewound = match cmd {
+                                "rs" | "reverse-step" => state.reverse_step(),
+                                "rn" | "reverse-next" => state.reverse_next(),
+                                _ => state.reverse_finish(),
+                            };
+                            match rewound {
+                                Ok(true) => {
+                                    for pane in self.panes.iter_mut() {
+                                        actions.push(pane.update(Action::Update, state)?);
+                                    }
+                                }
+                                Ok(false) => actions.push(Some(Action::TimedStatusLine(
+                                    "cannot go back any further".into(),
+                                    3,
+                                ))),
+                                Err(err) => {
+                                    actions.push(Some(Action::TimedStatusLine(err.to_string(), 5)));
+                                }
+                            }
+                        }
                         invalid => {
                             log::debug!("unknown command: '{invalid}'");
                             actions.push(Some(Action::TimedStatusLine("unknown command".into(), 1)))
@@ -164,137 +248,10 @@ impl Page for Home {
                 }
             }
             Action::Continue => {
-                let start_cycle = state.executor.cycle;
-                let mut breakpoints = core::mem::take(&mut state.breakpoints);
-                state.stopped = false;
-                let stopped = loop {
-                    // If stepping the program results in the program terminating succesfully, stop
-                    if state.executor.stopped {
-                        break true;
-                    }
-
-                    let mut consume_most_recent_finish = false;
-                    match state.executor.step() {
-                        Ok(Some(exited)) if exited.should_break_on_exit() => {
-                            consume_most_recent_finish = true;
-                        }
-                        Ok(_) => (),
-                        Err(err) => {
-                            // Execution terminated with an error
-                            state.execution_failed = Some(err);
-                            break true;
-                        }
-                    }
-
-                    if breakpoints.is_empty() {
-                        // No breakpoint management needed, keep executing
-                        continue;
-                    }
-
-                    let (op, is_op_boundary, proc, loc) = match state.executor.last.as_ref() {
-                        Some(last_state) => {
-                            let op = last_state.op;
-                            let is_boundary = last_state
-                                .asmop
-                                .as_ref()
-                                .map(|info| info.cycle_idx() == 1)
-                                .unwrap_or(false);
-                            let (proc, loc) = match state.executor.callstack.current_frame() {
-                                Some(frame) => {
-                                    let loc = frame
-                                        .recent()
-                                        .back()
-                                        .and_then(|detail| detail.resolve(&state.session))
-                                        .cloned();
-                                    (frame.procedure(state.session.name()), loc)
-                                }
-                                None => (None, None),
-                            };
-                            (op, is_boundary, proc, loc)
-                        }
-                        None => (None, false, None, None),
-                    };
-
-                    // Remove all breakpoints triggered at this cycle
-                    let current_cycle = state.executor.cycle;
-                    let cycles_stepped = current_cycle - start_cycle;
-                    breakpoints.retain_mut(|bp| {
-                        if let Some(n) = bp.cycles_to_skip(current_cycle) {
-                            if cycles_stepped >= n {
-                                let retained = !bp.is_one_shot();
-                                if retained {
-                                    state.breakpoints_hit.push(bp.clone());
-                                } else {
-                                    state.breakpoints_hit.push(core::mem::take(bp));
-                                }
-                                return retained;
-                            } else {
-                                return true;
-                            }
-                        }
-
-                        if cycles_stepped > 0
-                            && is_op_boundary
-                            && matches!(&bp.ty, BreakpointType::Next)
-                        {
-                            state.breakpoints_hit.push(core::mem::take(bp));
-                            return false;
-                        }
-
-                        if let Some(loc) = loc.as_ref() {
-                            if bp.should_break_at(loc) {
-                                let retained = !bp.is_one_shot();
-                                if retained {
-                                    state.breakpoints_hit.push(bp.clone());
-                                } else {
-                                    state.breakpoints_hit.push(core::mem::take(bp));
-                                }
-                                return retained;
-                            }
-                        }
-
-                        if let Some(proc) = proc.as_deref() {
-                            if bp.should_break_in(proc) {
-                                let retained = !bp.is_one_shot();
-                                if retained {
-                                    state.breakpoints_hit.push(bp.clone());
-                                } else {
-                                    state.breakpoints_hit.push(core::mem::take(bp));
-                                }
-                                return retained;
-                            }
-                        }
-
-                        true
-                    });
-
-                    if consume_most_recent_finish {
-                        if let Some(id) = breakpoints.iter().rev().find_map(|bp| {
-                            if matches!(bp.ty, BreakpointType::Finish) {
-                                Some(bp.id)
-                            } else {
-                                None
-                            }
-                        }) {
-                            breakpoints.retain(|bp| bp.id != id);
-                            break true;
-                        }
-                    }
-
-                    if !state.breakpoints_hit.is_empty() {
-                        break true;
-                    }
-                };
-
-                // Restore the breakpoints state
-                state.breakpoints = breakpoints;
-
-                // Ensure that if we yield to the runtime, that we resume executing when
-                // resumed, unless we specifically stopped for a breakpoint or other condition
-                state.stopped = stopped;
+                state.resume();
 
                 // Report program termination to the user
-                if stopped && state.executor.stopped {
+                if state.stopped && state.executor.stopped {
                     if let Some(err) = state.execution_failed.as_ref() {
                         actions.push(Some(Action::StatusLine(err.to_string())));
                     } else {
@@ -106,7 +106,8 @@ impl Pane for BreakpointsPane {
                 self.init(state)?;
             }
             Action::Update => {
-                if self.breakpoint_cycle < state.executor.cycle {
+                // The cycle moves backwards when rewinding execution, which also discards hits
+                if self.breakpoint_cycle != state.executor.cycle {
                     self.breakpoints_hit.clear();
                     self.breakpoints_hit.append(&mut state.breakpoints_hit);
                     if let Some(prev) = self.breakpoint_selected {
@@ -172,7 +173,7 @@ impl Pane for BreakpointsPane {
                 } else {
                     Span::styled("", Style::default())
                 };
-                let line = match &bp.ty {
+                let mut line = match &bp.ty {
                     BreakpointType::Next | BreakpointType::Step | BreakpointType::Finish => {
                         unreachable!()
                     }
@@ -207,7 +208,21 @@ impl Pane for BreakpointsPane {
                         Span::styled("opcode:", yellow),
                         Span::styled(format!("{op}"), gray),
                     ]),
+                    BreakpointType::Watch(ref expr) => Line::from(vec![
+                        gutter,
+                        Span::styled("watch:", yellow),
+                        Span::styled(format!("{expr}"), gray),
+                    ]),
                 };
+                if let Some(condition) = bp.condition.as_ref() {
+                    line.spans.push(Span::styled(" if ", yellow));
+                    line.spans.push(Span::styled(format!("{condition}"), gray));
+                }
+                if bp.ignore_count > bp.hit_count {
+                    line.spans.push(Span::styled(" ignore ", yellow));
+                    line.spans
+                        .push(Span::styled(format!("{}", bp.ignore_count - bp.hit_count), gray));
+                }
                 if is_hit {
                     line.patch_style(Style::default().add_modifier(Modifier::BOLD))
                 } else {
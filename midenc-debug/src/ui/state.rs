@@ -3,17 +3,20 @@ use std::{rc::Rc, sync::Arc};
 use miden_assembly::Library;
 use miden_core::{utils::Deserializable, FieldElement};
 use miden_processor::{Felt, Program, StackInputs};
+use midenc_hir::{DebugVariable, FunctionDebugInfo, ProgramDebugInfo};
 use midenc_session::{
     diagnostics::{IntoDiagnostic, Report, SourceSpan, Span, WrapErr},
     InputType, Session,
 };
 
 use crate::{
-    Breakpoint, BreakpointType, DebugExecutor, DebuggerConfig, ExecutionTrace, ReadMemoryExpr,
+    Breakpoint, BreakpointExpr, BreakpointType, ConditionEnv, DebugExecutor, DebuggerConfig,
+    ExecutionTrace, ReadMemoryExpr,
 };
 
 pub struct State {
     pub package: Arc<miden_package::Package>,
+    pub debug_info: ProgramDebugInfo,
     pub inputs: DebuggerConfig,
     pub executor: DebugExecutor,
     pub execution_trace: ExecutionTrace,
@@ -48,6 +51,7 @@ impl State {
         }
         let args = inputs.inputs.iter().copied().rev().collect::<Vec<_>>();
         let package = load_package(&session)?;
+        let debug_info = load_debug_info(&session);
 
         let mut executor = crate::Executor::for_package(&package.clone(), args.clone(), &session)?;
         executor.with_advice_inputs(inputs.advice_inputs.clone());
@@ -73,6 +77,7 @@ impl State {
 
         Ok(Self {
             package,
+            debug_info,
             inputs,
             executor,
             execution_trace,
@@ -92,14 +97,8 @@ impl State {
         let package = load_package(&self.session)?;
         let args = self.inputs.inputs.iter().copied().rev().collect::<Vec<_>>();
 
-        let mut executor = crate::Executor::for_package(&package, args.clone(), &self.session)?;
-        executor.with_advice_inputs(self.inputs.advice_inputs.clone());
-        for link_library in self.session.options.link_libraries.iter() {
-            let lib = link_library.load(&self.session)?;
-            executor.with_library(&lib);
-        }
+        let executor = new_debug_executor(&package, &self.inputs, &self.session)?;
         let program = package.unwrap_program();
-        let executor = executor.into_debug(&program, &self.session);
 
         // Execute the program until it terminates to capture a full trace for use during debugging
         let mut trace_executor = crate::Executor::for_package(&package, args, &self.session)?;
@@ -111,6 +110,7 @@ impl State {
         let execution_trace = trace_executor.capture_trace(&program, &self.session);
 
         self.package = package;
+        self.debug_info = load_debug_info(&self.session);
         self.executor = executor;
         self.execution_trace = execution_trace;
         self.execution_failed = None;
@@ -120,12 +120,241 @@ impl State {
         self.next_breakpoint_id = 0;
         self.stopped = true;
         for bp in breakpoints {
-            self.create_breakpoint(bp.ty);
+            self.create_conditional_breakpoint(BreakpointExpr {
+                ty: bp.ty,
+                condition: bp.condition,
+                ignore_count: bp.ignore_count,
+            });
+        }
+        Ok(())
+    }
+
+    /// Resumes execution until a breakpoint is hit, or the program terminates
+    ///
+    /// The breakpoints hit are recorded in `breakpoints_hit`, and one-shot breakpoints are removed.
+    pub fn resume(&mut self) {
+        let start_cycle = self.executor.cycle;
+        let mut breakpoints = core::mem::take(&mut self.breakpoints);
+        self.stopped = false;
+        let stopped = loop {
+            // If stepping the program results in the program terminating succesfully, stop
+            if self.executor.stopped {
+                break true;
+            }
+
+            let mut consume_most_recent_finish = false;
+            match self.executor.step() {
+                Ok(Some(exited)) if exited.should_break_on_exit() => {
+                    consume_most_recent_finish = true;
+                }
+                Ok(_) => (),
+                Err(err) => {
+                    // Execution terminated with an error
+                    self.execution_failed = Some(err);
+                    break true;
+                }
+            }
+
+            if breakpoints.is_empty() {
+                // No breakpoint management needed, keep executing
+                continue;
+            }
+
+            let (op, is_op_boundary, proc, loc) = match self.executor.last.as_ref() {
+                Some(last_state) => {
+                    let op = last_state.op;
+                    let is_boundary = last_state
+                        .asmop
+                        .as_ref()
+                        .map(|info| info.cycle_idx() == 1)
+                        .unwrap_or(false);
+                    let (proc, loc) = match self.executor.callstack.current_frame() {
+                        Some(frame) => {
+                            let loc = frame
+                                .recent()
+                                .back()
+                                .and_then(|detail| detail.resolve(&self.session))
+                                .cloned();
+                            (frame.procedure(self.session.name()), loc)
+                        }
+                        None => (None, None),
+                    };
+                    (op, is_boundary, proc, loc)
+                }
+                None => (None, false, None, None),
+            };
+
+            let access = self.executor.last_access.clone();
+
+            // Remove all breakpoints triggered at this cycle
+            let current_cycle = self.executor.cycle;
+            let cycles_stepped = current_cycle - start_cycle;
+            let mut hits = vec![];
+            breakpoints.retain_mut(|bp| {
+                let triggered = match bp.cycles_to_skip(current_cycle) {
+                    Some(n) => cycles_stepped >= n,
+                    None => {
+                        (cycles_stepped > 0
+                            && is_op_boundary
+                            && matches!(&bp.ty, BreakpointType::Next))
+                            || loc.as_ref().is_some_and(|loc| bp.should_break_at(loc))
+                            || access.as_ref().is_some_and(|access| bp.should_break_on(access))
+                            || proc.as_deref().is_some_and(|proc| bp.should_break_in(proc))
+                    }
+                };
+                if !triggered {
+                    return true;
+                }
+
+                if let Some(condition) = bp.condition.as_ref() {
+                    // A condition which cannot be evaluated stops execution, so it can be fixed
+                    match condition.evaluate(&*self) {
+                        Ok(true) => (),
+                        Ok(false) => return true,
+                        Err(err) => log::warn!("breakpoint {} condition failed: {err}", bp.id),
+                    }
+                }
+                bp.hit_count += 1;
+                if bp.hit_count <= bp.ignore_count {
+                    return true;
+                }
+
+                let retained = !bp.is_one_shot();
+                if retained {
+                    hits.push(bp.clone());
+                } else {
+                    hits.push(core::mem::take(bp));
+                }
+                retained
+            });
+            self.breakpoints_hit.append(&mut hits);
+
+            if consume_most_recent_finish {
+                if let Some(id) = breakpoints.iter().rev().find_map(|bp| {
+                    if matches!(bp.ty, BreakpointType::Finish) {
+                        Some(bp.id)
+                    } else {
+                        None
+                    }
+                }) {
+                    breakpoints.retain(|bp| bp.id != id);
+                    break true;
+                }
+            }
+
+            if !self.breakpoints_hit.is_empty() {
+                break true;
+            }
+        };
+
+        // Restore the breakpoints state
+        self.breakpoints = breakpoints;
+
+        // Ensure that if we yield to the runtime, that we resume executing when
+        // resumed, unless we specifically stopped for a breakpoint or other condition
+        self.stopped = stopped;
+    }
+
+    /// Moves execution to `cycle`
+    ///
+    /// The VM only executes forward, so moving to an earlier cycle rewinds the executor to the
+    /// nearest snapshot preceding `cycle`, and executes forward from there, reconstructing the call
+    /// stack, operand stack and memory as they were at `cycle`. If execution terminates before
+    /// reaching `cycle`, it stops at the last cycle.
+    pub fn goto_cycle(&mut self, cycle: usize) -> Result<(), Report> {
+        if cycle < self.executor.cycle {
+            log::debug!("rewinding from cycle {} to cycle {cycle}", self.executor.cycle);
+            self.executor.rewind(cycle);
+            self.execution_failed = None;
+        }
+        while self.executor.cycle < cycle && !self.executor.stopped {
+            if let Err(err) = self.executor.step() {
+                self.execution_failed = Some(err);
+                break;
+            }
         }
+        self.breakpoints_hit.clear();
+        self.stopped = true;
         Ok(())
     }
 
+    /// Moves execution back one cycle
+    pub fn reverse_step(&mut self) -> Result<bool, Report> {
+        if self.executor.cycle == 0 {
+            return Ok(false);
+        }
+        self.goto_cycle(self.executor.cycle - 1)?;
+        Ok(true)
+    }
+
+    /// Moves execution back to the first cycle of the previous instruction, i.e. the reverse of
+    /// [BreakpointType::Next]
+    pub fn reverse_next(&mut self) -> Result<bool, Report> {
+        self.rewind_to_last(|executor| {
+            executor
+                .last
+                .as_ref()
+                .and_then(|state| state.asmop.as_ref())
+                .is_some_and(|info| info.cycle_idx() == 1)
+        })
+    }
+
+    /// Moves execution back to the last cycle before the current call frame was entered, i.e. the
+    /// reverse of [BreakpointType::Finish]
+    pub fn reverse_finish(&mut self) -> Result<bool, Report> {
+        let depth = self.executor.callstack.frames().len();
+        self.rewind_to_last(|executor| executor.callstack.frames().len() < depth)
+    }
+
+    /// Moves execution back to the last cycle before the current one for which `predicate` holds.
+    ///
+    /// Returns false, and leaves execution at the current cycle, if there is no such cycle.
+    pub fn rewind_to_last<F>(&mut self, mut predicate: F) -> Result<bool, Report>
+    where
+        F: FnMut(&DebugExecutor) -> bool,
+    {
+        // Search the cycles between each snapshot and the start of the previously searched range,
+        // starting from the most recent, so only the cycles after the one found are re-executed
+        let current_cycle = self.executor.cycle;
+        let mut end = current_cycle;
+        while end > 1 {
+            self.executor.rewind(end - 2);
+            let start = self.executor.cycle;
+            let mut found = None;
+            while self.executor.cycle + 1 < end && !self.executor.stopped {
+                if self.executor.step().is_err() {
+                    break;
+                }
+                if predicate(&self.executor) {
+                    found = Some(self.executor.cycle);
+                }
+            }
+            if let Some(cycle) = found {
+                self.goto_cycle(cycle)?;
+                return Ok(true);
+            }
+            end = start + 1;
+        }
+        self.goto_cycle(current_cycle)?;
+        Ok(false)
+    }
+
     pub fn create_breakpoint(&mut self, ty: BreakpointType) {
+        self.create_conditional_breakpoint(BreakpointExpr {
+            ty,
+            condition: None,
+            ignore_count: 0,
+        });
+    }
+
+    /// Creates a breakpoint which is only hit when its condition holds, once it has been
+    /// triggered more than `ignore_count` times
+    pub fn create_conditional_breakpoint(&mut self, expr: BreakpointExpr) {
+        let BreakpointExpr {
+            ty,
+            condition,
+            ignore_count,
+        } = expr;
         let id = self.next_breakpoint_id();
         let creation_cycle = self.executor.cycle;
         log::trace!("created breakpoint with id {id} at cycle {creation_cycle}");
@@ -138,9 +367,25 @@ impl State {
             id,
             creation_cycle,
             ty,
+            condition,
+            ignore_count,
+            hit_count: 0,
         });
     }
 
+    /// Ignores the next `n` times the breakpoint `id` is hit
+    ///
+    /// Returns false if there is no such breakpoint.
+    pub fn ignore_breakpoint(&mut self, id: u8, n: usize) -> bool {
+        match self.breakpoints.iter_mut().find(|bp| bp.id == id) {
+            Some(bp) => {
+                bp.ignore_count = bp.hit_count + n;
+                true
+            }
+            None => false,
+        }
+    }
+
     fn next_breakpoint_id(&mut self) -> u8 {
         let mut candidate = self.next_breakpoint_id;
         let mut initial = candidate;
@@ -264,6 +509,96 @@ impl State {
     }
 }
 
+impl State {
+    /// Format the source-level variable `name` of the current function, as `name: type = value`
+    pub fn print_variable(&self, name: &str) -> Result<String, String> {
+        let (var, value) = self.read_variable(name)?;
+        Ok(format!("{}: {} = {value}", &var.name, &var.ty.name))
+    }
+
+    /// Read the value of the source-level variable `name` of the current function
+    pub fn read_variable(&self, name: &str) -> Result<(&DebugVariable, String), String> {
+        let (frame, function) = self.current_function_debug_info()?;
+        let var = function
+            .variables
+            .iter()
+            .rev()
+            .find(|var| var.name == name)
+            .ok_or_else(|| format!("no variable named '{name}' in the current function"))?;
+        let cycle = miden_processor::RowIndex::from(self.executor.cycle);
+        let value = crate::format_variable(
+            var,
+            frame,
+            &self.execution_trace,
+            self.executor.current_context,
+            cycle,
+        )?;
+        Ok((var, value))
+    }
+
+    /// Get the names of the source-level variables of the current function
+    pub fn variable_names(&self) -> Result<Vec<&str>, String> {
+        let (_, function) = self.current_function_debug_info()?;
+        let mut names = Vec::<&str>::with_capacity(function.variables.len());
+        for var in function.variables.iter() {
+            if !names.contains(&var.name.as_str()) {
+                names.push(var.name.as_str());
+            }
+        }
+        Ok(names)
+    }
+
+    fn current_function_debug_info(
+        &self,
+    ) -> Result<(&crate::CallFrame, &FunctionDebugInfo), String> {
+        let frame = self
+            .executor
+            .callstack
+            .current_frame()
+            .ok_or_else(|| "no function is executing".to_string())?;
+        let procedure = frame
+            .procedure_path()
+            .ok_or_else(|| "the current procedure is unknown".to_string())?;
+        let function = self
+            .debug_info
+            .function(procedure)
+            .ok_or_else(|| format!("no debug information is available for '{procedure}'"))?;
+        Ok((frame, function))
+    }
+}
+
+impl ConditionEnv for State {
+    fn stack_item(&self, index: usize) -> Option<Felt> {
+        self.executor.last.as_ref()?.stack.get(index).copied()
+    }
+
+    fn read_memory(&self, expr: &ReadMemoryExpr) -> Result<String, String> {
+        State::read_memory(self, expr)
+    }
+
+    fn procedure(&self) -> Option<String> {
+        let frame = self.executor.callstack.current_frame()?;
+        frame.procedure(self.session.name()).map(|name| name.to_string())
+    }
+}
+
+/// Creates a [DebugExecutor] for `package`, which has not executed any cycle yet
+fn new_debug_executor(
+    package: &Arc<miden_package::Package>,
+    inputs: &DebuggerConfig,
+    session: &Session,
+) -> Result<DebugExecutor, Report> {
+    let args = inputs.inputs.iter().copied().rev().collect::<Vec<_>>();
+    let mut executor = crate::Executor::for_package(package, args, session)?;
+    executor.with_advice_inputs(inputs.advice_inputs.clone());
+    for link_library in session.options.link_libraries.iter() {
+        let lib = link_library.load(session)?;
+        executor.with_library(&lib);
+    }
+    let program = package.unwrap_program();
+    Ok(executor.into_debug(&program, session))
+}
+
 fn load_package(session: &Session) -> Result<Arc<miden_package::Package>, Report> {
     let package = match &session.inputs[0].file {
         InputType::Real(ref path) => {
@@ -289,3 +624,163 @@ fn load_package(session: &Session) -> Result<Arc<miden_package::Package>, Report
         Ok(package)
     }
 }
+
+/// Load the debug information emitted by the compiler next to the input package, if any
+fn load_debug_info(session: &Session) -> ProgramDebugInfo {
+    let InputType::Real(ref path) = session.inputs[0].file else {
+        return ProgramDebugInfo::default();
+    };
+    let path = path.with_extension("dbg");
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return ProgramDebugInfo::default();
+    };
+    toml::from_str(&content).unwrap_or_else(|err| {
+        log::warn!("failed to parse debug information from {}: {err}", path.display());
+        ProgramDebugInfo::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_core::Word;
+    use miden_processor::RowIndex;
+
+    use super::*;
+    use crate::testing::TestProgram;
+
+    /// A program which runs for several snapshot intervals, calling a procedure which writes to
+    /// memory on each iteration of a loop
+    const PROGRAM: &str = "\
+proc.store
+    trace.240
+    nop
+    dup.0
+    mem_store.100
+    dup.0
+    push.3
+    mul
+    mem_store.101
+    trace.252
+    nop
+end
+
+begin
+    trace.240
+    nop
+    push.400
+    dup.0
+    neq.0
+    while.true
+        exec.store
+        sub.1
+        dup.0
+        neq.0
+    end
+    drop
+    trace.252
+    nop
+end
+";
+
+    /// The state of the VM at the current cycle of `state`
+    #[derive(Debug, PartialEq)]
+    struct Observed {
+        cycle: usize,
+        clk: Option<RowIndex>,
+        stack: Vec<Felt>,
+        memory: Vec<(u64, Word)>,
+        frames: Vec<Option<String>>,
+    }
+
+    fn observe(state: &State) -> Observed {
+        let last = state.executor.last.as_ref();
+        Observed {
+            cycle: state.executor.cycle,
+            clk: last.map(|last| last.clk),
+            stack: last.map(|last| last.stack.clone()).unwrap_or_default(),
+            memory: last.map(|last| last.memory.clone()).unwrap_or_default(),
+            frames: state
+                .executor
+                .callstack
+                .frames()
+                .iter()
+                .map(|frame| frame.procedure_path().map(String::from))
+                .collect(),
+        }
+    }
+
+    /// Returns the state at `cycle`, as observed by executing the program forward only
+    fn observe_fresh(program: &TestProgram, cycle: usize) -> Observed {
+        let mut state = program.state(vec![]);
+        state.goto_cycle(cycle).unwrap();
+        observe(&state)
+    }
+
+    #[test]
+    fn goto_earlier_cycle() {
+        let program = TestProgram::new("goto", PROGRAM);
+        let mut state = program.state(vec![]);
+        state.goto_cycle(usize::MAX).unwrap();
+        assert!(state.execution_failed.is_none());
+        let last_cycle = state.executor.cycle;
+        assert!(last_cycle > 4 * DebugExecutor::SNAPSHOT_INTERVAL, "{last_cycle}");
+
+        let interval = DebugExecutor::SNAPSHOT_INTERVAL;
+        let moves = [
+            (last_cycle, 3),
+            (4, 3),
+            (2, 1),
+            (1, 0),
+            (3 * interval + 7, 2 * interval + 500),
+            (2 * interval + 1, 2 * interval),
+            (2 * interval, 2 * interval - 1),
+            (last_cycle, interval),
+            (interval, 0),
+        ];
+        for (m, n) in moves {
+            state.goto_cycle(m).unwrap();
+            assert_eq!(observe(&state), observe_fresh(&program, m), "at {m}");
+            state.goto_cycle(n).unwrap();
+            assert_eq!(observe(&state), observe_fresh(&program, n), "from {m} to {n}");
+            assert!(!state.executor.stopped);
+        }
+
+        // Once rewound, execution proceeds to the same end
+        state.goto_cycle(usize::MAX).unwrap();
+        assert_eq!(state.executor.cycle, last_cycle);
+        assert!(state.execution_failed.is_none());
+        let memory = observe(&state).memory;
+        assert!(memory.iter().any(|(addr, word)| *addr == 100 && word[0] == Felt::new(1)));
+    }
+
+    #[test]
+    fn reverse_step_matches_forward_run() {
+        let program = TestProgram::new("reverse-step", PROGRAM);
+        let mut state = program.state(vec![]);
+        let start = DebugExecutor::SNAPSHOT_INTERVAL + 3;
+        state.goto_cycle(start).unwrap();
+        for cycle in (start - 6..start).rev() {
+            assert!(state.reverse_step().unwrap());
+            assert_eq!(observe(&state), observe_fresh(&program, cycle), "at {cycle}");
+        }
+    }
+
+    #[test]
+    fn rewind_past_failure() {
+        let program =
+            TestProgram::new("rewind-failure", "begin\n    push.1\n    push.0\n    assert\nend\n");
+        let mut state = program.state(vec![]);
+        state.goto_cycle(usize::MAX).unwrap();
+        assert!(state.execution_failed.is_some());
+        let last_cycle = state.executor.cycle;
+
+        state.goto_cycle(1).unwrap();
+        assert!(state.execution_failed.is_none());
+        assert_eq!(observe(&state), observe_fresh(&program, 1));
+
+        // The failure is reported again when execution reaches it
+        state.goto_cycle(usize::MAX).unwrap();
+        assert!(state.execution_failed.is_some());
+        assert_eq!(state.executor.cycle, last_cycle);
+    }
+}
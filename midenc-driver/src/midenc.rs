@@ -96,6 +96,11 @@ enum Commands {
         /// NOTE: These arguments will override any stack values provided via --inputs
         #[arg(last(true), value_name = "ARGV")]
         args: Vec<debugger::Felt>,
+        /// Instead of starting the TUI, serve the Debug Adapter Protocol over stdin/stdout
+        ///
+        /// This allows debugging the program from any editor which supports DAP.
+        #[arg(long, default_value_t = false)]
+        dap: bool,
         #[command(flatten)]
         options: debugger::Debugger,
     },
@@ -181,6 +186,7 @@ impl Midenc {
                 input,
                 inputs,
                 args,
+                dap,
                 mut options,
             } => {
                 if options.working_dir.is_none() {
@@ -188,7 +194,15 @@ impl Midenc {
                 }
                 let session = options.into_session(vec![input], emitter);
                 let args = args.into_iter().map(|felt| felt.0).collect();
-                debugger::run(inputs, args, Rc::new(session), logger)
+                if dap {
+                    // The protocol is spoken over stdout, so logs must go elsewhere, i.e. stderr
+                    log::set_boxed_logger(logger)
+                        .unwrap_or_else(|err| panic!("failed to install logger: {err}"));
+                    log::set_max_level(filter);
+                    debugger::run_dap(inputs, args, Rc::new(session))
+                } else {
+                    debugger::run(inputs, args, Rc::new(session), logger)
+                }
             }
         }
     }
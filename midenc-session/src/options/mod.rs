@@ -43,6 +43,8 @@ pub struct Options {
     pub link_only: bool,
     /// Generate Miden Assembly from the inputs without the linker
     pub no_link: bool,
+    /// Lower 64-bit floating point operations to calls into the soft-float intrinsics
+    pub soft_float: bool,
     /// Print CFG to stdout after each pass
     pub print_cfg_after_all: bool,
     /// Print CFG to stdout each time the named passes are applied
@@ -102,6 +104,7 @@ impl Options {
             analyze_only: false,
             link_only: false,
             no_link: false,
+            soft_float: false,
             save_temps: false,
             print_cfg_after_all: false,
             print_cfg_after_pass: vec![],
@@ -187,7 +190,7 @@ impl Options {
 }
 
 /// This enum describes the degree to which compiled programs will be optimized
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(clap::ValueEnum))]
 pub enum OptLevel {
     /// No optimizations at all
@@ -204,6 +207,19 @@ pub enum OptLevel {
     /// Only optimizations which reduce code size are applied
     SizeMin,
 }
+impl OptLevel {
+    /// Returns true if any optimizations should be applied at this level
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, Self::None)
+    }
+
+    /// Returns true if optimizations at this level should favor code size over runtime performance
+    #[inline]
+    pub fn prefers_size(&self) -> bool {
+        matches!(self, Self::Size | Self::SizeMin)
+    }
+}
 
 /// This enum describes what type of debugging information to emit in compiled programs
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
@@ -158,6 +158,24 @@ impl RustcTest {
     }
 }
 
+/// Configuration for tests which use as input, a core Wasm module in the WebAssembly text format
+pub struct WatTest {
+    name: Cow<'static, str>,
+    source_code: Cow<'static, str>,
+}
+impl WatTest {
+    /// Construct a new WebAssembly text input with the given name and source code content
+    pub fn new(
+        name: impl Into<Cow<'static, str>>,
+        source_code: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            source_code: source_code.into(),
+        }
+    }
+}
+
 /// The various types of input artifacts that can be used to drive compiler tests
 pub enum CompilerTestInputType {
     /// A project that uses `cargo miden build` to produce a Wasm module to use as input
@@ -166,6 +184,8 @@ pub enum CompilerTestInputType {
     Cargo(CargoTest),
     /// A project that uses `rustc` to produce a core Wasm module to use as input
     Rustc(RustcTest),
+    /// A core Wasm module written in the WebAssembly text format, used as input directly
+    Wat(WatTest),
 }
 impl From<CargoTest> for CompilerTestInputType {
     fn from(config: CargoTest) -> Self {
@@ -177,6 +197,11 @@ impl From<RustcTest> for CompilerTestInputType {
         Self::Rustc(config)
     }
 }
+impl From<WatTest> for CompilerTestInputType {
+    fn from(config: WatTest) -> Self {
+        Self::Wat(config)
+    }
+}
 
 /// [CompilerTestBuilder] is used to obtain a [CompilerTest], and subsequently run that test.
 ///
@@ -224,11 +249,13 @@ impl CompilerTestBuilder {
             CompilerTestInputType::Cargo(ref mut config) => config.entrypoint.take(),
             CompilerTestInputType::Rustc(_) => Some("__main".into()),
             CompilerTestInputType::CargoMiden(ref mut config) => config.entrypoint.take(),
+            CompilerTestInputType::Wat(_) => None,
         };
         let name = match source {
             CompilerTestInputType::Cargo(ref mut config) => config.name.as_ref(),
             CompilerTestInputType::Rustc(ref mut config) => config.name.as_ref(),
             CompilerTestInputType::CargoMiden(ref mut config) => config.name.as_ref(),
+            CompilerTestInputType::Wat(ref mut config) => config.name.as_ref(),
         };
         let entrypoint = entrypoint.as_deref().map(|entry| FunctionIdent {
             module: Ident::with_empty_span(Symbol::intern(name)),
@@ -327,6 +354,24 @@ impl CompilerTestBuilder {
     /// Consume the builder, invoke any tools required to obtain the inputs for the test, and if
     /// successful, return a [CompilerTest], ready for evaluation.
     pub fn build(self) -> CompilerTest {
+        // WebAssembly text is assembled directly, there is nothing to compile
+        if let CompilerTestInputType::Wat(ref config) = self.source {
+            let working_dir = std::env::temp_dir().join(config.name.as_ref());
+            fs::create_dir_all(&working_dir).unwrap();
+            let output_file = working_dir.join(config.name.as_ref()).with_extension("wasm");
+            let wasm = wat::parse_str(config.source_code.as_ref())
+                .unwrap_or_else(|err| panic!("invalid WebAssembly text: {err}"));
+            fs::write(&output_file, wasm).unwrap();
+            let inputs = vec![InputFile::from_path(output_file).unwrap()];
+            return CompilerTest {
+                config: self.config,
+                session: default_session(inputs, &self.midenc_flags),
+                artifact_name: config.name.clone(),
+                entrypoint: self.entrypoint,
+                ..Default::default()
+            };
+        }
+
         // Set up the command used to compile the test inputs (typically Rust -> Wasm)
         let mut command = match self.source {
             CompilerTestInputType::CargoMiden(_) => {
@@ -340,6 +385,7 @@ impl CompilerTestBuilder {
                 cmd
             }
             CompilerTestInputType::Rustc(_) => Command::new("rustc"),
+            CompilerTestInputType::Wat(_) => unreachable!(),
         };
 
         // Extract the directory in which source code is presumed to exist (or will be placed)
@@ -354,6 +400,7 @@ impl CompilerTestBuilder {
                 .as_deref()
                 .map(Cow::Borrowed)
                 .unwrap_or_else(|| Cow::Owned(std::env::temp_dir())),
+            CompilerTestInputType::Wat(_) => unreachable!(),
         };
 
         // Cargo-based source types share a lot of configuration in common
@@ -564,6 +611,7 @@ impl CompilerTestBuilder {
                     ..Default::default()
                 }
             }
+            CompilerTestInputType::Wat(_) => unreachable!(),
         }
     }
 }
@@ -634,6 +682,22 @@ impl CompilerTestBuilder {
         CompilerTestBuilder::new(config)
     }
 
+    /// Set the WebAssembly text of a core Wasm module to compile, whose exported `entrypoint`
+    /// function is used as the entrypoint of the program
+    pub fn wat_module(
+        name: impl Into<Cow<'static, str>>,
+        wat: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        let name = name.into();
+        let module_name = Ident::with_empty_span(Symbol::intern(&name));
+        let mut builder = CompilerTestBuilder::new(WatTest::new(name, wat));
+        builder.with_entrypoint(FunctionIdent {
+            module: module_name,
+            function: Ident::with_empty_span(Symbol::intern("entrypoint")),
+        });
+        builder
+    }
+
     /// Set the Rust source code to compile
     pub fn rust_source_program(rust_source: impl Into<Cow<'static, str>>) -> Self {
         let rust_source = rust_source.into();
@@ -971,6 +1035,14 @@ impl CompilerTest {
             .build()
     }
 
+    /// Set the WebAssembly text of a core Wasm module to compile
+    pub fn wat_module(
+        name: impl Into<Cow<'static, str>>,
+        wat: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        CompilerTestBuilder::wat_module(name, wat).build()
+    }
+
     /// Set the Rust source code to compile
     pub fn rust_source_program(rust_source: impl Into<Cow<'static, str>>) -> Self {
         CompilerTestBuilder::rust_source_program(rust_source).build()
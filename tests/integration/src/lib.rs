@@ -8,8 +8,12 @@ mod cargo_proj;
 mod compiler_test;
 mod exec_emulator;
 
-pub use compiler_test::{default_session, CargoTest, CompilerTest, CompilerTestBuilder, RustcTest};
+pub use compiler_test::{
+    default_session, CargoTest, CompilerTest, CompilerTestBuilder, RustcTest, WatTest,
+};
 pub use exec_emulator::execute_emulator;
 
 #[cfg(test)]
 mod rust_masm_tests;
+#[cfg(test)]
+mod wat_masm_tests;
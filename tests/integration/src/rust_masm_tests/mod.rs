@@ -15,6 +15,7 @@ mod apps;
 mod instructions;
 mod intrinsics;
 mod rust_sdk;
+mod soft_float;
 mod types;
 
 pub fn run_masm_vs_rust<T>(
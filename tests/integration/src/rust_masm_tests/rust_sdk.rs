@@ -1,4 +1,9 @@
-use std::{collections::BTreeMap, env, path::PathBuf, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use expect_test::expect_file;
 use miden_core::crypto::hash::RpoDigest;
@@ -150,36 +155,39 @@ fn rust_sdk_cross_ctx_account() {
     }));
 }
 
-#[test]
-fn rust_sdk_cross_ctx_note() {
-    // Build cross-ctx-account package
+/// Builds the account package `account` (in `rust-apps-wasm/rust-sdk`) for the cross-context
+/// note `note`, returning the path to the account package
+fn build_cross_ctx_account(account: &str, note: &str) -> PathBuf {
     let args: Vec<String> = [
         "cargo",
         "miden",
         "build",
         "--manifest-path",
-        "../rust-apps-wasm/rust-sdk/cross-ctx-account/Cargo.toml",
+        &format!("../rust-apps-wasm/rust-sdk/{account}/Cargo.toml"),
         "--release",
         // Use the target dir of this test's cargo project to avoid issues running tests in parallel
         // i.e. avoid using the same target dir as the basic-wallet test (see above)
         "--target-dir",
-        "../rust-apps-wasm/rust-sdk/cross-ctx-note/target",
+        &format!("../rust-apps-wasm/rust-sdk/{note}/target"),
     ]
     .iter()
     .map(|s| s.to_string())
     .collect();
     // dbg!(env::current_dir().unwrap().display());
     let outputs = cargo_miden::run(args.into_iter(), cargo_miden::OutputType::Masm)
-        .expect("Failed to compile the cross-ctx-account package for cross-ctx-note");
-    let masp_path: PathBuf = outputs.first().unwrap().clone();
-    // dbg!(&masp_path);
+        .unwrap_or_else(|err| panic!("Failed to compile the {account} package for {note}: {err}"));
+    outputs.first().unwrap().clone()
+}
 
+/// Compiles the cross-context note `note` (in `rust-apps-wasm/rust-sdk`), linked against the
+/// account package at `masp_path`
+fn cross_ctx_note_test(note: &str, masp_path: &Path) -> CompilerTest {
     let _ = env_logger::builder().is_test(true).try_init();
 
     let config = WasmTranslationConfig::default();
 
     let mut builder = CompilerTestBuilder::rust_source_cargo_miden(
-        "../rust-apps-wasm/rust-sdk/cross-ctx-note",
+        format!("../rust-apps-wasm/rust-sdk/{note}"),
         config,
         [
             "-l".into(),
@@ -187,25 +195,24 @@ fn rust_sdk_cross_ctx_note() {
             "-l".into(),
             "base".into(),
             "--link-library".into(),
-            masp_path.clone().into_os_string().into_string().unwrap().into(),
+            masp_path.to_str().unwrap().to_string().into(),
         ],
     );
     builder.with_entrypoint(FunctionIdent {
         // module: Ident::new(Symbol::intern("miden:base/note-script@1.0.0"), SourceSpan::default()),
-        module: Ident::new(Symbol::intern("cross_ctx_note"), SourceSpan::default()),
+        module: Ident::new(Symbol::intern(&note.replace('-', "_")), SourceSpan::default()),
         // function: Ident::new(Symbol::intern("note-script"), SourceSpan::default()),
         function: Ident::new(
             Symbol::intern("miden:base/note-script@1.0.0#note-script"),
             SourceSpan::default(),
         ),
     });
-    let mut test = builder.build();
-    let artifact_name = test.artifact_name().to_string();
-    test.expect_wasm(expect_file![format!("../../expected/rust_sdk/{artifact_name}.wat")]);
-    test.expect_ir(expect_file![format!("../../expected/rust_sdk/{artifact_name}.hir")]);
-    test.expect_masm(expect_file![format!("../../expected/rust_sdk/{artifact_name}.masm")]);
+    builder.build()
+}
 
-    // Run it in the VM (output is checked via assert_eq in the note code)
+/// Runs the compiled cross-context note of `test` in the VM, along with the account package at
+/// `masp_path`. The output is checked via the assertions in the note code.
+fn execute_cross_ctx_note(test: &mut CompilerTest, masp_path: &Path) {
     let package = test.compiled_package();
 
     let mut exec = Executor::new(vec![]);
@@ -216,5 +223,28 @@ fn rust_sdk_cross_ctx_note() {
         .add(account_package.digest(), account_package.into());
     exec.with_dependencies(&package.manifest.dependencies).unwrap();
 
-    let trace = exec.execute(&package.unwrap_program(), &test.session);
+    exec.execute(&package.unwrap_program(), &test.session);
+}
+
+#[test]
+fn rust_sdk_cross_ctx_note() {
+    let masp_path = build_cross_ctx_account("cross-ctx-account", "cross-ctx-note");
+    let mut test = cross_ctx_note_test("cross-ctx-note", &masp_path);
+    let artifact_name = test.artifact_name().to_string();
+    test.expect_wasm(expect_file![format!("../../expected/rust_sdk/{artifact_name}.wat")]);
+    test.expect_ir(expect_file![format!("../../expected/rust_sdk/{artifact_name}.hir")]);
+    test.expect_masm(expect_file![format!("../../expected/rust_sdk/{artifact_name}.masm")]);
+
+    // Run it in the VM (output is checked via assert_eq in the note code)
+    execute_cross_ctx_note(&mut test, &masp_path);
+}
+
+/// Passes strings, results carrying strings, lists of lists (`list<list<u8>>` and
+/// `list<string>`, including empty outer and inner lists), and records holding strings to and
+/// from another context
+#[test]
+fn rust_sdk_cross_ctx_strings_and_results() {
+    let masp_path = build_cross_ctx_account("cross-ctx-abi-account", "cross-ctx-abi-note");
+    let mut test = cross_ctx_note_test("cross-ctx-abi-note", &masp_path);
+    execute_cross_ctx_note(&mut test, &masp_path);
 }
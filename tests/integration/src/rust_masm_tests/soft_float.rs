@@ -0,0 +1,292 @@
+//! Semantic tests for the soft-float implementation of 64-bit floating point arithmetic, which
+//! compare the results of the compiled code against the host implementation of IEEE 754.
+//!
+//! Floats are passed to and from the compiled code as their raw bits, so that every bit pattern,
+//! i.e. NaNs, infinities, subnormals, and signed zeroes, can be exercised.
+
+use std::borrow::Cow;
+
+use midenc_debug::PushToStack;
+use proptest::{
+    prelude::*,
+    test_runner::{TestError, TestRunner},
+};
+
+use super::run_masm_vs_rust;
+use crate::CompilerTest;
+
+/// Any operation producing a NaN produces the canonical quiet NaN
+const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+const SIGN_BIT: u64 = 1 << 63;
+const MANTISSA_MASK: u64 = (1 << 52) - 1;
+
+fn soft_float_flags() -> [Cow<'static, str>; 2] {
+    ["-C".into(), "soft-float".into()]
+}
+
+/// Get the bits of `x` that the soft-float implementation is expected to produce
+fn canonicalize(x: f64) -> u64 {
+    if x.is_nan() {
+        CANONICAL_NAN
+    } else {
+        x.to_bits()
+    }
+}
+
+/// Floats which are likely to expose mistakes in the handling of edge cases
+fn special_values() -> Vec<u64> {
+    let mut values = [
+        0.0,
+        -0.0,
+        1.0,
+        -1.0,
+        0.5,
+        1.5,
+        2.5,
+        -2.5,
+        f64::EPSILON,
+        f64::MIN_POSITIVE,
+        -f64::MIN_POSITIVE,
+        f64::MAX,
+        f64::MIN,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NAN,
+        1e300,
+        1e-300,
+        9007199254740992.0,    // 2^53
+        9223372036854775808.0, // 2^63
+        -9223372036854775808.0,
+        18446744073709551616.0, // 2^64
+        4294967295.0,
+        4294967296.0,
+        2147483648.0,
+        -2147483648.0,
+        -2147483649.0,
+    ]
+    .map(f64::to_bits)
+    .to_vec();
+    values.extend([
+        // smallest and largest subnormals
+        1,
+        MANTISSA_MASK,
+        SIGN_BIT | 1,
+        SIGN_BIT | MANTISSA_MASK,
+        // a signaling NaN, and a negative quiet NaN with a payload
+        0x7ff0_0000_0000_0001,
+        0xfff8_0000_0000_beef,
+    ]);
+    values
+}
+
+/// Generate the bits of a float, biased towards edge cases
+fn f64_bits() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        prop::sample::select(special_values()),
+        any::<u64>(),
+        // subnormals of either sign
+        (any::<bool>(), 1u64..=MANTISSA_MASK)
+            .prop_map(|(neg, mantissa)| ((neg as u64) << 63) | mantissa),
+        // normal floats close enough in magnitude to one another for the operands to interact
+        (any::<bool>(), 1000u64..1050, any::<u64>()).prop_map(|(neg, exp, mantissa)| {
+            ((neg as u64) << 63) | (exp << 52) | (mantissa & MANTISSA_MASK)
+        }),
+    ]
+}
+
+/// Check every value produced by `strategy` against the host implementation via `test`
+fn check<S, F>(strategy: S, test: F)
+where
+    S: Strategy,
+    S::Value: core::fmt::Debug,
+    F: Fn(S::Value) -> Result<(), TestCaseError>,
+{
+    let res = TestRunner::default().run(&strategy, test);
+    match res {
+        Err(TestError::Fail(_, value)) => {
+            panic!("Found minimal(shrinked) failing case: {:?}", value);
+        }
+        Ok(_) => (),
+        _ => panic!("Unexpected test result: {:?}", res),
+    }
+}
+
+/// Compiles an arithmetic operation on two floats, and checks it against the host for all kinds
+/// of operands
+macro_rules! test_arith_op {
+    ($name:ident, $op:tt) => {
+        #[test]
+        fn $name() {
+            let op_str = stringify!($op);
+            let main_fn = format!(
+                "(a: u64, b: u64) -> u64 {{ (f64::from_bits(a) {op_str} f64::from_bits(b)).to_bits() }}"
+            );
+            let mut test = CompilerTest::rust_fn_body(&main_fn, soft_float_flags());
+            let package = test.compiled_package();
+
+            check((f64_bits(), f64_bits()), move |(a, b)| {
+                let rs_out = canonicalize(f64::from_bits(a) $op f64::from_bits(b));
+                let mut args = Vec::<midenc_hir::Felt>::default();
+                PushToStack::try_push(&b, &mut args);
+                PushToStack::try_push(&a, &mut args);
+                run_masm_vs_rust(rs_out, &package, &args, &test.session)
+            });
+        }
+    };
+}
+
+/// Compiles a comparison of two floats, and checks it against the host for all kinds of operands
+macro_rules! test_cmp_op {
+    ($name:ident, $op:tt) => {
+        #[test]
+        fn $name() {
+            let op_str = stringify!($op);
+            let main_fn =
+                format!("(a: u64, b: u64) -> bool {{ f64::from_bits(a) {op_str} f64::from_bits(b) }}");
+            let mut test = CompilerTest::rust_fn_body(&main_fn, soft_float_flags());
+            let package = test.compiled_package();
+
+            check((f64_bits(), f64_bits()), move |(a, b)| {
+                let rs_out = f64::from_bits(a) $op f64::from_bits(b);
+                let mut args = Vec::<midenc_hir::Felt>::default();
+                PushToStack::try_push(&b, &mut args);
+                PushToStack::try_push(&a, &mut args);
+                run_masm_vs_rust(rs_out, &package, &args, &test.session)
+            });
+        }
+    };
+}
+
+/// Compiles a conversion of a float to the integral type `$ty`, which saturates and maps NaN to
+/// zero, and checks it against the host for all kinds of floats
+macro_rules! test_float_to_int {
+    ($name:ident, $ty:ty) => {
+        #[test]
+        fn $name() {
+            let ty_str = stringify!($ty);
+            let main_fn = format!("(a: u64) -> {ty_str} {{ f64::from_bits(a) as {ty_str} }}");
+            let mut test = CompilerTest::rust_fn_body(&main_fn, soft_float_flags());
+            let package = test.compiled_package();
+
+            check(f64_bits(), move |a| {
+                let rs_out = f64::from_bits(a) as $ty;
+                let mut args = Vec::<midenc_hir::Felt>::default();
+                PushToStack::try_push(&a, &mut args);
+                run_masm_vs_rust(rs_out, &package, &args, &test.session)
+            });
+        }
+    };
+}
+
+/// Compiles a conversion of the integral type `$ty` to a float, and checks it against the host
+/// for all values of `$ty`
+macro_rules! test_int_to_float {
+    ($name:ident, $ty:ty) => {
+        #[test]
+        fn $name() {
+            let ty_str = stringify!($ty);
+            let main_fn = format!("(a: {ty_str}) -> u64 {{ (a as f64).to_bits() }}");
+            let mut test = CompilerTest::rust_fn_body(&main_fn, soft_float_flags());
+            let package = test.compiled_package();
+
+            let values = prop_oneof![
+                any::<$ty>(),
+                Just(<$ty>::MIN),
+                Just(<$ty>::MAX),
+                Just(0 as $ty),
+                // odd values, most of which are not exactly representable when 64 bits wide
+                (0..=<$ty>::MAX).prop_map(|a| a | 1),
+            ];
+            check(values, move |a| {
+                let rs_out = (a as f64).to_bits();
+                let mut args = Vec::<midenc_hir::Felt>::default();
+                PushToStack::try_push(&a, &mut args);
+                run_masm_vs_rust(rs_out, &package, &args, &test.session)
+            });
+        }
+    };
+}
+
+/// Compiles an operation which only manipulates the sign bit of a float, `|x, y| expr`, and
+/// checks it against the host for all kinds of operands. NaNs are not canonicalized by these.
+macro_rules! test_sign_op {
+    ($name:ident, |$x:ident, $y:ident| $expr:expr) => {
+        #[test]
+        #[allow(unused_variables)]
+        fn $name() {
+            let expr_str = stringify!($expr);
+            let main_fn = format!(
+                "(a: u64, b: u64) -> u64 {{ let {x} = f64::from_bits(a); let {y} = \
+                 f64::from_bits(b); ({expr_str}).to_bits() }}",
+                x = stringify!($x),
+                y = stringify!($y),
+            );
+            let mut test = CompilerTest::rust_fn_body(&main_fn, soft_float_flags());
+            let package = test.compiled_package();
+
+            check((f64_bits(), f64_bits()), move |(a, b)| {
+                let ($x, $y) = (f64::from_bits(a), f64::from_bits(b));
+                let rs_out = ($expr).to_bits();
+                let mut args = Vec::<midenc_hir::Felt>::default();
+                PushToStack::try_push(&b, &mut args);
+                PushToStack::try_push(&a, &mut args);
+                run_masm_vs_rust(rs_out, &package, &args, &test.session)
+            });
+        }
+    };
+}
+
+/// Compiles a rounding of a float to an integral value via the Wasm intrinsic `$wasm_fn`, and
+/// checks it against `$host_fn` for all kinds of floats
+macro_rules! test_rounding_op {
+    ($name:ident, $wasm_fn:ident, $host_fn:expr) => {
+        #[test]
+        fn $name() {
+            let wasm_fn = stringify!($wasm_fn);
+            let main_fn = format!(
+                "(a: u64) -> u64 {{ core::arch::wasm32::{wasm_fn}(f64::from_bits(a)).to_bits() }}"
+            );
+            let mut test = CompilerTest::rust_fn_body(&main_fn, soft_float_flags());
+            let package = test.compiled_package();
+
+            check(f64_bits(), move |a| {
+                let rs_out = canonicalize($host_fn(f64::from_bits(a)));
+                let mut args = Vec::<midenc_hir::Felt>::default();
+                PushToStack::try_push(&a, &mut args);
+                run_masm_vs_rust(rs_out, &package, &args, &test.session)
+            });
+        }
+    };
+}
+
+test_arith_op!(f64_add, +);
+test_arith_op!(f64_sub, -);
+test_arith_op!(f64_mul, *);
+test_arith_op!(f64_div, /);
+
+test_cmp_op!(f64_eq, ==);
+test_cmp_op!(f64_ne, !=);
+test_cmp_op!(f64_lt, <);
+test_cmp_op!(f64_le, <=);
+test_cmp_op!(f64_gt, >);
+test_cmp_op!(f64_ge, >=);
+
+test_float_to_int!(f64_to_i32, i32);
+test_float_to_int!(f64_to_u32, u32);
+test_float_to_int!(f64_to_i64, i64);
+test_float_to_int!(f64_to_u64, u64);
+
+test_int_to_float!(i32_to_f64, i32);
+test_int_to_float!(u32_to_f64, u32);
+test_int_to_float!(i64_to_f64, i64);
+test_int_to_float!(u64_to_f64, u64);
+
+test_sign_op!(f64_neg, |x, y| -x);
+test_sign_op!(f64_abs, |x, y| x.abs());
+test_sign_op!(f64_copysign, |x, y| x.copysign(y));
+
+test_rounding_op!(f64_trunc, f64_trunc, f64::trunc);
+test_rounding_op!(f64_floor, f64_floor, f64::floor);
+test_rounding_op!(f64_ceil, f64_ceil, f64::ceil);
+test_rounding_op!(f64_nearest, f64_nearest, f64::round_ties_even);
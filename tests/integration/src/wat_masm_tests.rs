@@ -0,0 +1,38 @@
+//! Semantic tests for Wasm features which are not produced by the Rust compiler, and so are
+//! written in the WebAssembly text format.
+
+use midenc_debug::Executor;
+use midenc_hir::Felt;
+
+use crate::CompilerTest;
+
+/// Test that a passive data segment can be copied into linear memory once it has been grown, and
+/// that copying from it traps once it has been dropped
+#[test]
+fn memory_init_after_grow() {
+    let wat = r#"
+        (module
+            (memory 1)
+            (data $greeting "hello, world")
+            (func $entrypoint (export "entrypoint") (param $drop i32) (result i32)
+                (if (i32.eq (memory.grow (i32.const 2)) (i32.const -1))
+                    (then unreachable))
+                (if (local.get $drop)
+                    (then data.drop $greeting))
+                ;; copy "world" to address 0x100
+                (memory.init $greeting (i32.const 0x100) (i32.const 7) (i32.const 5))
+                (i32.load (i32.const 0x100))
+            )
+        )"#;
+    let mut test = CompilerTest::wat_module("memory_init_after_grow", wat);
+    let package = test.compiled_package();
+    let program = package.unwrap_program();
+
+    let exec = Executor::for_package(&package, vec![Felt::new(0)], &test.session).unwrap();
+    let output: u32 = exec.execute_into(&program, &test.session);
+    assert_eq!(output, u32::from_le_bytes(*b"worl"));
+
+    let exec = Executor::for_package(&package, vec![Felt::new(1)], &test.session).unwrap();
+    let trapped = exec.into_debug(&program, &test.session).any(|step| step.is_err());
+    assert!(trapped, "expected memory.init of a dropped segment to trap");
+}
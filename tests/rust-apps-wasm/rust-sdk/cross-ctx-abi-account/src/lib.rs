@@ -0,0 +1,62 @@
+// Do not link against libstd (i.e. anything defined in `std::`)
+#![no_std]
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+// Global allocator to use heap memory in no-std environment
+#[global_allocator]
+static ALLOC: miden::BumpAlloc = miden::BumpAlloc::new();
+
+// Required for no-std crates
+#[panic_handler]
+fn my_panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+use bindings::exports::miden::cross_ctx_abi_account::*;
+
+bindings::export!(MyAccount with_types_in bindings);
+
+mod bindings;
+
+struct MyAccount;
+
+impl strings::Guest for MyAccount {
+    fn shout(input: String) -> String {
+        input.to_ascii_uppercase()
+    }
+
+    fn shout_result(input: Result<String, u32>) -> Result<String, u32> {
+        match input {
+            Ok(input) => Ok(input.to_ascii_uppercase()),
+            Err(code) => Err(code + 1),
+        }
+    }
+}
+
+impl lists::Guest for MyAccount {
+    fn reverse_all(input: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        input
+            .into_iter()
+            .rev()
+            .map(|mut bytes| {
+                bytes.reverse();
+                bytes
+            })
+            .collect()
+    }
+
+    fn shout_all(input: Vec<String>) -> Vec<String> {
+        input.iter().map(|s| s.to_ascii_uppercase()).collect()
+    }
+}
+
+impl records::Guest for MyAccount {
+    fn shout_entry(input: records::Entry) -> records::Entry {
+        records::Entry {
+            name: input.name.to_ascii_uppercase(),
+            id: input.id + 1,
+        }
+    }
+}
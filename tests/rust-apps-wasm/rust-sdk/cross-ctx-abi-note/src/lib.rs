@@ -0,0 +1,89 @@
+// Do not link against libstd (i.e. anything defined in `std::`)
+#![no_std]
+
+extern crate alloc;
+use alloc::{string::String, vec, vec::Vec};
+
+// Global allocator to use heap memory in no-std environment
+#[global_allocator]
+static ALLOC: miden::BumpAlloc = miden::BumpAlloc::new();
+
+// Required for no-std crates
+#[panic_handler]
+fn my_panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+bindings::export!(MyNote with_types_in bindings);
+
+mod bindings;
+
+use bindings::{
+    exports::miden::base::note_script::Guest,
+    miden::cross_ctx_abi_account::{
+        lists::{reverse_all, shout_all},
+        records::{shout_entry, Entry},
+        strings::{shout, shout_result},
+    },
+};
+use miden::*;
+
+/// Fails the execution unless `cond` holds (a panic would loop forever)
+fn assert_true(cond: bool) {
+    assert(Felt::from(cond as u32));
+}
+
+struct MyNote;
+
+impl Guest for MyNote {
+    fn note_script() {
+        // The string is longer than a word, and its length is not a multiple of a word
+        let output = shout("hello from the note script");
+        assert_true(output == "HELLO FROM THE NOTE SCRIPT");
+
+        let output = shout("");
+        assert_true(output.is_empty());
+
+        match shout_result(Ok("ok, account")) {
+            Ok(output) => assert_true(output == "OK, ACCOUNT"),
+            Err(_) => assert_true(false),
+        }
+
+        match shout_result(Err(41)) {
+            Ok(_) => assert_true(false),
+            Err(code) => assert_eq(Felt::from(code), felt!(42)),
+        }
+
+        // The inner lists are empty, shorter than a word, and longer than a word
+        let input: [Vec<u8>; 3] = [vec![1, 2, 3], vec![], vec![4, 5, 6, 7, 8, 9, 10, 11, 12]];
+        let output = reverse_all(&input);
+        let expected: [Vec<u8>; 3] = [vec![12, 11, 10, 9, 8, 7, 6, 5, 4], vec![], vec![3, 2, 1]];
+        assert_true(output == expected);
+
+        let output = reverse_all(&[vec![], vec![]]);
+        assert_true(output.len() == 2 && output.iter().all(|bytes| bytes.is_empty()));
+
+        let output = reverse_all(&[]);
+        assert_true(output.is_empty());
+
+        let input = [
+            String::from("hello"),
+            String::new(),
+            String::from("a string longer than a word"),
+        ];
+        let output = shout_all(&input);
+        assert_true(output == ["HELLO", "", "A STRING LONGER THAN A WORD"]);
+
+        let output = shout_all(&[]);
+        assert_true(output.is_empty());
+
+        // The record is passed in flattened form, and returned via the return area
+        let input = Entry {
+            name: String::from("account name"),
+            id: 7,
+        };
+        let output = shout_entry(&input);
+        assert_true(output.name == "ACCOUNT NAME");
+        assert_eq(Felt::from(output.id), felt!(8));
+    }
+}
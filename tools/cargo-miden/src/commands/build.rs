@@ -0,0 +1,96 @@
+use anyhow::anyhow;
+use clap::{Args, ValueEnum};
+use midenc_session::OutputType;
+
+/// The kinds of artifacts `cargo miden build` can place in `target/miden/<profile>`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum EmitType {
+    /// Miden Assembly text
+    Masm,
+    /// Miden IR text
+    Hir,
+    /// A Miden package in binary form
+    Masp,
+}
+
+impl From<EmitType> for OutputType {
+    fn from(ty: EmitType) -> Self {
+        match ty {
+            EmitType::Masm => OutputType::Masm,
+            EmitType::Hir => OutputType::Hir,
+            EmitType::Masp => OutputType::Masp,
+        }
+    }
+}
+
+/// Compile the current package to a Miden package (`target/miden/<profile>/<name>.masp`)
+#[derive(Args)]
+#[clap(disable_version_flag = true)]
+pub struct BuildCommand {
+    /// Build artifacts in release mode, with optimizations
+    #[clap(long, short = 'r')]
+    pub release: bool,
+    /// The artifacts to emit, as a comma-separated list [default: masp]
+    #[clap(long, value_delimiter = ',')]
+    pub emit: Vec<EmitType>,
+    /// Additional arguments passed verbatim to `cargo build`
+    //
+    // The first argument unknown to `cargo miden build` starts these, so any `--emit` given after
+    // it is captured here as well, see `BuildCommand::take_trailing_emit`
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub cargo_args: Vec<String>,
+}
+
+impl BuildCommand {
+    /// Moves the `--emit` options found among the cargo arguments (up to a `--`, if any) to
+    /// [BuildCommand::emit], so that they can be given in any position
+    pub fn take_trailing_emit(&mut self) -> anyhow::Result<()> {
+        let mut cargo_args = Vec::with_capacity(self.cargo_args.len());
+        let mut args = core::mem::take(&mut self.cargo_args).into_iter();
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "--" => {
+                    cargo_args.push(arg);
+                    cargo_args.extend(args.by_ref());
+                    break;
+                }
+                "--emit" => {
+                    args.next().ok_or_else(|| anyhow!("a value is required for '--emit <EMIT>'"))?
+                }
+                _ => match arg.strip_prefix("--emit=") {
+                    Some(value) => value.to_string(),
+                    None => {
+                        cargo_args.push(arg);
+                        continue;
+                    }
+                },
+            };
+            for ty in value.split(',') {
+                let ty = EmitType::from_str(ty, true)
+                    .map_err(|err| anyhow!("invalid value '{ty}' for '--emit <EMIT>': {err}"))?;
+                self.emit.push(ty);
+            }
+        }
+        self.cargo_args = cargo_args;
+        Ok(())
+    }
+
+    /// Returns the artifacts to emit, i.e. a Miden package unless specified otherwise
+    pub fn emit(&self) -> Vec<EmitType> {
+        if self.emit.is_empty() {
+            vec![EmitType::Masp]
+        } else {
+            self.emit.clone()
+        }
+    }
+
+    /// Returns the arguments of the `cargo build` invocation, starting with the subcommand
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
+        if self.release {
+            args.push("--release".to_string());
+        }
+        args.extend(self.cargo_args.iter().cloned());
+        args
+    }
+}
@@ -1,3 +1,5 @@
+mod build;
 mod new_project;
 
+pub use build::*;
 pub use new_project::*;
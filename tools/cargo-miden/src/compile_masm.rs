@@ -9,11 +9,18 @@ use midenc_session::{
     InputFile, OutputType,
 };
 
+use crate::commands::EmitType;
+
+/// Compiles the given Wasm file with `midenc`, placing the `emit` artifacts in `output_folder`.
+///
+/// Returns the paths of the emitted artifacts, in the order they were requested, or an error if
+/// any of them was not emitted.
 pub fn wasm_to_masm(
     wasm_file_path: &Path,
     output_folder: &Path,
     is_bin: bool,
-) -> Result<PathBuf, Report> {
+    emit: &[EmitType],
+) -> Result<Vec<PathBuf>, Report> {
     if !output_folder.exists() {
         return Err(Report::msg(format!(
             "MASM output folder '{}' does not exist.",
@@ -28,21 +35,51 @@ pub fn wasm_to_masm(
     let input = InputFile::from_path(wasm_file_path)
         .into_diagnostic()
         .wrap_err("Invalid input file")?;
-    let output_file = output_folder
-        .join(wasm_file_path.file_stem().expect("invalid wasm file path: no file stem"))
-        .with_extension(OutputType::Masp.extension());
+    let stem = wasm_file_path.file_stem().expect("invalid wasm file path: no file stem");
+    let output_file = output_folder.join(stem).with_extension(OutputType::Masp.extension());
     let project_type = if is_bin { "--exe" } else { "--lib" };
-    let args: Vec<&std::ffi::OsStr> = vec![
+    // Intermediate artifacts are written to a temporary directory unless a path is given, so
+    // direct all of them to the output folder
+    let output_types = emit
+        .iter()
+        .map(|ty| {
+            let ty = OutputType::from(*ty);
+            if ty.is_intermediate() {
+                format!("{ty}={}", output_folder.display())
+            } else {
+                ty.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut args: Vec<&std::ffi::OsStr> = vec![
         "--output-dir".as_ref(),
         output_folder.as_os_str(),
-        "-o".as_ref(),
-        output_file.as_os_str(),
         project_type.as_ref(),
         "--verbose".as_ref(),
         "--target".as_ref(),
         "rollup".as_ref(),
+        "--emit".as_ref(),
+        output_types.as_ref(),
     ];
+    if emit.contains(&EmitType::Masp) {
+        args.push("-o".as_ref());
+        args.push(output_file.as_os_str());
+    }
     let session = Rc::new(Compiler::new_session([input], None, args));
     midenc_compile::compile(session.clone())?;
-    Ok(output_file)
+
+    let mut outputs = Vec::with_capacity(emit.len());
+    for ty in emit {
+        let ty = OutputType::from(*ty);
+        let output = output_folder.join(stem).with_extension(ty.extension());
+        if !output.exists() {
+            return Err(Report::msg(format!(
+                "The requested '{ty}' artifact was not emitted to '{}'",
+                output.display()
+            )));
+        }
+        outputs.push(output);
+    }
+    Ok(outputs)
 }
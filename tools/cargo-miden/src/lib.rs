@@ -10,7 +10,7 @@ use cargo_component_core::{
     terminal::{Color, Terminal, Verbosity},
 };
 use clap::{CommandFactory, Parser};
-use commands::NewCommand;
+use commands::{BuildCommand, EmitType, NewCommand};
 use compile_masm::wasm_to_masm;
 use non_component::run_cargo_command_for_non_component;
 
@@ -26,7 +26,7 @@ fn version() -> &'static str {
 /// The list of commands that are built-in to `cargo-miden`.
 const BUILTIN_COMMANDS: &[&str] = &[
     "miden", // for indirection via `cargo miden`
-    "new",
+    "new", "build",
 ];
 
 /// The list of commands that are explicitly unsupported by `cargo-miden`.
@@ -37,7 +37,7 @@ const UNSUPPORTED_COMMANDS: &[&str] =
     &["install", "login", "logout", "owner", "package", "search", "uninstall"];
 
 const AFTER_HELP: &str = "Unrecognized subcommands will be passed to cargo verbatim
-     and the artifacts will be processed afterwards (e.g. `build` command compiles MASM).
+     and the resulting Wasm artifacts will be compiled to MASM afterwards.
      \nSee `cargo help` for more information on available cargo commands.";
 
 /// Cargo integration for Miden
@@ -61,6 +61,7 @@ enum CargoMiden {
 #[derive(Parser)]
 enum Command {
     New(NewCommand),
+    Build(BuildCommand),
 }
 
 fn detect_subcommand<I, T>(args: I) -> Option<String>
@@ -113,6 +114,16 @@ where
             match CargoMiden::parse_from(args.clone()) {
                 CargoMiden::Miden(cmd) | CargoMiden::Command(cmd) => match cmd {
                     Command::New(cmd) => vec![cmd.exec()?],
+                    Command::Build(mut cmd) => {
+                        cmd.take_trailing_emit()?;
+                        let emit = cmd.emit();
+                        run_cargo_command_and_compile(
+                            cmd.cargo_args(),
+                            Some("build"),
+                            build_output_type,
+                            &emit,
+                        )?
+                    }
                 },
             }
         }
@@ -139,145 +150,187 @@ where
         _ => {
             // Not a built-in command, run the cargo command
             let args = args.into_iter().skip_while(|arg| arg == "miden").collect::<Vec<_>>();
-            let cargo_args = CargoArguments::parse_from(args.clone().into_iter())?;
-            // dbg!(&cargo_args);
-            let cache_dir = std::env::var(CACHE_DIR_ENV_VAR).map(PathBuf::from).ok();
-            let config_file = std::env::var(CONFIG_FILE_ENV_VAR).map(PathBuf::from).ok();
-            let config = Config::new(
-                Terminal::new(
-                    if cargo_args.quiet {
-                        Verbosity::Quiet
-                    } else {
-                        match cargo_args.verbose {
-                            0 => Verbosity::Normal,
-                            _ => Verbosity::Verbose,
-                        }
-                    },
-                    cargo_args.color.unwrap_or_default(),
-                ),
-                config_file,
-            )?;
-            let metadata = load_metadata(cargo_args.manifest_path.as_deref())?;
-            let mut packages = load_component_metadata(
-                &metadata,
-                cargo_args.packages.iter(),
-                cargo_args.workspace,
-            )?;
+            run_cargo_command_and_compile(
+                args,
+                subcommand.as_deref(),
+                build_output_type,
+                &[EmitType::Masp],
+            )?
+        }
+    };
+    Ok(outputs)
+}
 
-            if packages.is_empty() {
-                bail!(
-                    "manifest `{path}` contains no package or the workspace has no members",
-                    path = metadata.workspace_root.join("Cargo.toml")
-                );
-            }
+/// Runs the cargo command given by `args` (starting with the cargo subcommand) and, if
+/// `build_output_type` is [OutputType::Masm], compiles the resulting Wasm artifacts with `midenc`,
+/// placing the requested `emit` outputs in `target/miden/<profile>`.
+fn run_cargo_command_and_compile(
+    args: Vec<String>,
+    subcommand: Option<&str>,
+    build_output_type: OutputType,
+    emit: &[EmitType],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let cargo_args = CargoArguments::parse_from(args.clone().into_iter())?;
+    let profile_dir = profile_dir_name(&args, cargo_args.release)?;
+    // dbg!(&cargo_args);
+    let cache_dir = std::env::var(CACHE_DIR_ENV_VAR).map(PathBuf::from).ok();
+    let config_file = std::env::var(CONFIG_FILE_ENV_VAR).map(PathBuf::from).ok();
+    let config = Config::new(
+        Terminal::new(
+            if cargo_args.quiet {
+                Verbosity::Quiet
+            } else {
+                match cargo_args.verbose {
+                    0 => Verbosity::Normal,
+                    _ => Verbosity::Verbose,
+                }
+            },
+            cargo_args.color.unwrap_or_default(),
+        ),
+        config_file,
+    )?;
+    let metadata = load_metadata(cargo_args.manifest_path.as_deref())?;
+    let mut packages =
+        load_component_metadata(&metadata, cargo_args.packages.iter(), cargo_args.workspace)?;
 
-            for package in packages.iter_mut() {
-                package.metadata.section.bindings.with = [
-                    ("miden:base/core-types@1.0.0/felt", "miden::Felt"),
-                    ("miden:base/core-types@1.0.0/word", "miden::Word"),
-                    ("miden:base/core-types@1.0.0/core-asset", "miden::CoreAsset"),
-                    ("miden:base/core-types@1.0.0/account-id", "miden::AccountId"),
-                    ("miden:base/core-types@1.0.0/tag", "miden::Tag"),
-                    ("miden:base/core-types@1.0.0/note-type", "miden::NoteType"),
-                    ("miden:base/core-types@1.0.0/recipient", "miden::Recipient"),
-                ]
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect();
-                // skip functions that are provided by the Miden SDK and/or intrinsics
-                // only function names (no CM path)
-                package.metadata.section.bindings.skip = vec![
-                    // Our function names can clash with user's function names leading to
-                    // skipping the bindings generation of the user's function names
-                    // see https://github.com/0xPolygonMiden/compiler/issues/341
-                    "remove-asset",
-                    "create-note",
-                    "heap-base",
-                    "hash-one-to-one",
-                    "hash-two-to-one",
-                    "add-asset",
-                    "add",
-                    "unchecked-from-u64",
-                ]
-                .into_iter()
-                .map(|s| s.to_string())
-                .collect();
-            }
+    if packages.is_empty() {
+        bail!(
+            "manifest `{path}` contains no package or the workspace has no members",
+            path = metadata.workspace_root.join("Cargo.toml")
+        );
+    }
 
-            let mut spawn_args: Vec<_> = args.into_iter().collect();
-            spawn_args.extend_from_slice(
-                &[
-                    "-Z",
-                    // compile std as part of crate graph compilation
-                    // https://doc.rust-lang.org/cargo/reference/unstable.html#build-std
-                    // to abort on panic below
-                    "build-std=std,core,alloc,panic_abort",
-                    "-Z",
-                    // abort on panic without message formatting (core::fmt uses call_indirect)
-                    "build-std-features=panic_immediate_abort",
-                ]
-                .map(|s| s.to_string()),
-            );
+    for package in packages.iter_mut() {
+        package.metadata.section.bindings.with = [
+            ("miden:base/core-types@1.0.0/felt", "miden::Felt"),
+            ("miden:base/core-types@1.0.0/word", "miden::Word"),
+            ("miden:base/core-types@1.0.0/core-asset", "miden::CoreAsset"),
+            ("miden:base/core-types@1.0.0/account-id", "miden::AccountId"),
+            ("miden:base/core-types@1.0.0/tag", "miden::Tag"),
+            ("miden:base/core-types@1.0.0/note-type", "miden::NoteType"),
+            ("miden:base/core-types@1.0.0/recipient", "miden::Recipient"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        // skip functions that are provided by the Miden SDK and/or intrinsics
+        // only function names (no CM path)
+        package.metadata.section.bindings.skip = vec![
+            // Our function names can clash with user's function names leading to
+            // skipping the bindings generation of the user's function names
+            // see https://github.com/0xPolygonMiden/compiler/issues/341
+            "remove-asset",
+            "create-note",
+            "heap-base",
+            "hash-one-to-one",
+            "hash-two-to-one",
+            "add-asset",
+            "add",
+            "unchecked-from-u64",
+        ]
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect();
+    }
+
+    let mut spawn_args: Vec<_> = args.into_iter().collect();
+    spawn_args.extend_from_slice(
+        &[
+            "-Z",
+            // compile std as part of crate graph compilation
+            // https://doc.rust-lang.org/cargo/reference/unstable.html#build-std
+            // to abort on panic below
+            "build-std=std,core,alloc,panic_abort",
+            "-Z",
+            // abort on panic without message formatting (core::fmt uses call_indirect)
+            "build-std-features=panic_immediate_abort",
+        ]
+        .map(|s| s.to_string()),
+    );
 
-            let env_vars =
-                vec![("RUSTFLAGS".to_string(), "-C target-feature=+bulk-memory".to_string())]
-                    .into_iter()
-                    .collect();
+    let env_vars = vec![("RUSTFLAGS".to_string(), "-C target-feature=+bulk-memory".to_string())]
+        .into_iter()
+        .collect();
 
-            let mut builder = tokio::runtime::Builder::new_current_thread();
-            let rt = builder.enable_all().build()?;
-            // dbg!(&packages);
-            let mut wasm_outputs = rt.block_on(async {
-                let client = config.client(cache_dir, cargo_args.offline).await?;
-                run_cargo_command(
-                    client,
-                    &config,
-                    &metadata,
-                    &packages,
-                    subcommand.as_deref(),
-                    &cargo_args,
-                    &spawn_args,
-                    &env_vars,
-                )
-                .await
-            })?;
-            // dbg!(&wasm_outputs);
-            if wasm_outputs.is_empty() {
-                // crates that don't have a WIT component are ignored by the
-                // `cargo-component` run_cargo_command and return no outputs.
-                // Build them with our own version of run_cargo_command
-                wasm_outputs = run_cargo_command_for_non_component(
-                    &config,
-                    subcommand.as_deref(),
-                    &cargo_args,
-                    &spawn_args,
-                    &env_vars,
-                )?;
+    let mut builder = tokio::runtime::Builder::new_current_thread();
+    let rt = builder.enable_all().build()?;
+    // dbg!(&packages);
+    let mut wasm_outputs = rt.block_on(async {
+        let client = config.client(cache_dir, cargo_args.offline).await?;
+        run_cargo_command(
+            client,
+            &config,
+            &metadata,
+            &packages,
+            subcommand,
+            &cargo_args,
+            &spawn_args,
+            &env_vars,
+        )
+        .await
+    })?;
+    // dbg!(&wasm_outputs);
+    if wasm_outputs.is_empty() {
+        // crates that don't have a WIT component are ignored by the
+        // `cargo-component` run_cargo_command and return no outputs.
+        // Build them with our own version of run_cargo_command
+        wasm_outputs = run_cargo_command_for_non_component(
+            &config,
+            subcommand,
+            &cargo_args,
+            &spawn_args,
+            &env_vars,
+        )?;
+    }
+    let outputs = match build_output_type {
+        OutputType::Wasm => wasm_outputs,
+        OutputType::Masm => {
+            let miden_out_dir = metadata.target_directory.join("miden").join(&profile_dir);
+            if !miden_out_dir.exists() {
+                std::fs::create_dir_all(&miden_out_dir)?;
             }
-            match build_output_type {
-                OutputType::Wasm => wasm_outputs,
-                OutputType::Masm => {
-                    let miden_out_dir =
-                        metadata.target_directory.join("miden").join(if cargo_args.release {
-                            "release"
-                        } else {
-                            "debug"
-                        });
-                    if !miden_out_dir.exists() {
-                        std::fs::create_dir_all(&miden_out_dir)?;
-                    }
 
-                    let mut outputs = Vec::new();
-                    for wasm in wasm_outputs {
-                        let is_bin = false;
-                        let output = wasm_to_masm(&wasm, miden_out_dir.as_std_path(), is_bin)
-                            .map_err(|e| anyhow::anyhow!("{e}"))?;
-                        outputs.push(output);
-                    }
-                    outputs
-                }
+            let mut outputs = Vec::new();
+            for wasm in wasm_outputs {
+                let is_bin = false;
+                let output = wasm_to_masm(&wasm, miden_out_dir.as_std_path(), is_bin, emit)
+                    .map_err(|e| anyhow::anyhow!("{e}"))?;
+                outputs.extend(output);
             }
+            outputs
         }
     };
     Ok(outputs)
 }
+
+/// Returns the name of the directory in which cargo places the artifacts built with the profile
+/// given by `--profile` in `args` (up to a `--`, if any), or by `release` otherwise.
+///
+/// This follows cargo, i.e. the `dev` and `test` profiles map to `debug`, and `bench` to `release`.
+fn profile_dir_name(args: &[String], release: bool) -> anyhow::Result<String> {
+    let mut profile = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => break,
+            "--profile" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("a value is required for '--profile <NAME>'"))?;
+                profile = Some(value.as_str());
+            }
+            _ => {
+                if let Some(value) = arg.strip_prefix("--profile=") {
+                    profile = Some(value);
+                }
+            }
+        }
+    }
+    Ok(match profile {
+        Some("dev" | "test") => "debug".to_string(),
+        Some("release" | "bench") => "release".to_string(),
+        Some(name) => name.to_string(),
+        None if release => "release".to_string(),
+        None => "debug".to_string(),
+    })
+}
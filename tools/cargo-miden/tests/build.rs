@@ -73,6 +73,42 @@ fn build_new_project_from_template() {
     assert!(expected_masm_path.to_str().unwrap().contains("/release/"));
     assert!(expected_masm_path.metadata().unwrap().len() > 0);
 
+    // the output directory follows the profile given by `--profile`
+    let args = ["cargo", "miden", "build", "--profile", "release"]
+        .iter()
+        .map(|s| s.to_string());
+    let outputs = run(args, OutputType::Masm).expect("Failed to compile with --profile release");
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].to_str().unwrap().contains("/miden/release/"));
+    let args = ["cargo", "miden", "build", "--profile=dev"].iter().map(|s| s.to_string());
+    let outputs = run(args, OutputType::Masm).expect("Failed to compile with --profile=dev");
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].to_str().unwrap().contains("/miden/debug/"));
+
+    // emit intermediate artifacts instead of the package
+    let args = ["cargo", "miden", "build", "--release", "--emit", "masm,hir"]
+        .iter()
+        .map(|s| s.to_string());
+    let outputs = run(args, OutputType::Masm).expect("Failed to emit MASM and HIR");
+    let extensions = outputs
+        .iter()
+        .map(|path| path.extension().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(extensions, ["masm", "hir"]);
+    for output in outputs.iter() {
+        dbg!(output);
+        assert!(output.to_str().unwrap().contains("/release/"));
+        assert!(output.metadata().unwrap().len() > 0);
+    }
+
+    // `--emit` is recognized after the arguments passed through to cargo as well
+    let args = ["cargo", "miden", "build", "--release", "--quiet", "--emit=hir"]
+        .iter()
+        .map(|s| s.to_string());
+    let outputs = run(args, OutputType::Masm).expect("Failed to emit HIR");
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].extension().unwrap(), "hir");
+
     env::set_current_dir(restore_dir).unwrap();
     fs::remove_dir_all(new_project_path).unwrap();
 }